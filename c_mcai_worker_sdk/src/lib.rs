@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err)]
+
 mod constants;
 #[cfg(feature = "media")]
 mod media;
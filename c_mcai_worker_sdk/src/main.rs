@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err)]
+
 use c_mcai_worker_sdk::worker::CWorkerEvent;
 use mcai_worker_sdk::start_worker;
 
@@ -22,11 +22,12 @@ pub struct CWorkerParameters {
 fn get_instance_type_from_parameter_type(parameter_type: &ParameterType) -> InstanceType {
   match parameter_type {
     ParameterType::String => InstanceType::String,
+    ParameterType::ArrayOfObjects => InstanceType::Array,
     ParameterType::ArrayOfStrings => InstanceType::Array,
     ParameterType::Boolean => InstanceType::Boolean,
     ParameterType::Credential => InstanceType::String,
     ParameterType::Integer => InstanceType::Integer,
-    ParameterType::Requirements => InstanceType::Array,
+    ParameterType::Requirements => InstanceType::Object,
   }
 }
 
@@ -111,6 +112,10 @@ pub fn test_get_instance_type_from_parameter() {
     InstanceType::String,
     get_instance_type_from_parameter_type(&ParameterType::String)
   );
+  assert_eq!(
+    InstanceType::Array,
+    get_instance_type_from_parameter_type(&ParameterType::ArrayOfObjects)
+  );
   assert_eq!(
     InstanceType::Array,
     get_instance_type_from_parameter_type(&ParameterType::ArrayOfStrings)
@@ -128,7 +133,7 @@ pub fn test_get_instance_type_from_parameter() {
     get_instance_type_from_parameter_type(&ParameterType::Integer)
   );
   assert_eq!(
-    InstanceType::Array,
+    InstanceType::Object,
     get_instance_type_from_parameter_type(&ParameterType::Requirements)
   );
 }
@@ -2,7 +2,9 @@
 use std::sync::{mpsc::Sender, Arc, Mutex};
 
 use mcai_worker_sdk::{
-  debug, job::JobResult, McaiChannel, MessageError, MessageEvent, Result, Version,
+  debug,
+  job::{JobContext, JobResult},
+  McaiChannel, MessageError, MessageEvent, Result, Version,
 };
 #[cfg(feature = "media")]
 use mcai_worker_sdk::{FormatContext, ProcessFrame, ProcessResult, StreamDescriptor};
@@ -86,6 +88,7 @@ impl MessageEvent<CWorkerParameters> for CWorkerEvent {
     job_result: JobResult,
     stream_index: usize,
     process_frame: ProcessFrame,
+    _context: JobContext,
   ) -> Result<ProcessResult> {
     call_worker_process_frame(job_result, stream_index, process_frame)
   }
@@ -110,6 +113,7 @@ impl MessageEvent<CWorkerParameters> for CWorkerEvent {
     channel: Option<McaiChannel>,
     parameters: CWorkerParameters,
     job_result: JobResult,
+    _context: JobContext,
   ) -> Result<JobResult> {
     debug!("Process job: {}", job_result.get_job_id());
     let process_return = call_worker_process(job_result.clone(), parameters, channel)?;
@@ -291,7 +295,7 @@ pub fn call_worker_process_frame(
       ProcessFrame::EbuTtmlLive(_ebu_ttml_live) => {
         return Err(MessageError::NotImplemented());
       }
-      ProcessFrame::Data(_) => {
+      ProcessFrame::Data(_) | ProcessFrame::TypedData { .. } | ProcessFrame::BitmapSubtitle(_) => {
         return Err(MessageError::NotImplemented());
       }
     };
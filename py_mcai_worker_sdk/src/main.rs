@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err)]
+
 #[macro_use]
 extern crate serde_derive;
 
@@ -12,7 +14,7 @@ use pyo3::{prelude::*, types::*};
 
 use mcai_worker_sdk::{
   info,
-  job::{JobResult, JobStatus},
+  job::{JobContext, JobResult, JobStatus},
   publish_job_progression, start_worker,
   worker::{Parameter, ParameterType},
   McaiChannel, MessageError, MessageEvent, Result, Version,
@@ -358,6 +360,7 @@ impl MessageEvent<PythonWorkerParameters> for PythonWorkerEvent {
     job_result: JobResult,
     stream_index: usize,
     process_frame: ProcessFrame,
+    _context: JobContext,
   ) -> Result<ProcessResult> {
     let gil = Python::acquire_gil();
     let (py, python_module) = get_python_module(&gil)?;
@@ -399,7 +402,9 @@ impl MessageEvent<PythonWorkerParameters> for PythonWorkerEvent {
 
         Ok(ProcessResult::new_json(&response.to_string()))
       }
-      ProcessFrame::Data(_data) => Err(MessageError::NotImplemented()),
+      ProcessFrame::Data(_) | ProcessFrame::TypedData { .. } | ProcessFrame::BitmapSubtitle(_) => {
+        Err(MessageError::NotImplemented())
+      }
     }
   }
 
@@ -427,6 +432,7 @@ impl MessageEvent<PythonWorkerParameters> for PythonWorkerEvent {
     channel: Option<McaiChannel>,
     parameters: PythonWorkerParameters,
     mut job_result: JobResult,
+    _context: JobContext,
   ) -> Result<JobResult> {
     let gil = Python::acquire_gil();
     let (py, python_module) = get_python_module(&gil)?;
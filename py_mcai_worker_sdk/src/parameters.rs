@@ -68,6 +68,7 @@ impl JsonSchema for PythonWorkerParameters {
 fn get_instance_type_from_parameter_type(parameter_type: &ParameterType) -> InstanceType {
   match parameter_type {
     ParameterType::String => InstanceType::String,
+    ParameterType::ArrayOfObjects => InstanceType::Array,
     ParameterType::ArrayOfStrings => InstanceType::Array,
     ParameterType::Boolean => InstanceType::Boolean,
     ParameterType::Credential => InstanceType::String,
@@ -155,6 +156,10 @@ pub fn test_get_instance_type_from_parameter() {
     InstanceType::String,
     get_instance_type_from_parameter_type(&ParameterType::String)
   );
+  assert_eq!(
+    InstanceType::Array,
+    get_instance_type_from_parameter_type(&ParameterType::ArrayOfObjects)
+  );
   assert_eq!(
     InstanceType::Array,
     get_instance_type_from_parameter_type(&ParameterType::ArrayOfStrings)
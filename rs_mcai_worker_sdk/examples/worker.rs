@@ -2,7 +2,7 @@
 extern crate serde_derive;
 
 use mcai_worker_sdk::{
-  job::{JobResult, JobStatus},
+  job::{JobContext, JobResult, JobStatus},
   publish_job_progression, McaiChannel, MessageError, MessageEvent, Result,
 };
 use schemars::JsonSchema;
@@ -81,6 +81,8 @@ Do no use in production, just for developments."#
           let filters = vec![VideoFilter::Resize(Scaling {
             width: Some(200),
             height: Some(70),
+            algorithm: Some("bilinear".to_string()),
+            full_chroma: None,
           })];
           stream_descriptors.push(StreamDescriptor::new_video(stream_index as usize, filters))
         }
@@ -114,6 +116,7 @@ Do no use in production, just for developments."#
     job_result: JobResult,
     _stream_index: usize,
     frame: ProcessFrame,
+    _context: JobContext,
   ) -> Result<ProcessResult> {
     match frame {
       ProcessFrame::AudioVideo(frame) => {
@@ -170,8 +173,10 @@ Do no use in production, just for developments."#
     channel: Option<McaiChannel>,
     parameters: WorkerParameters,
     job_result: JobResult,
+    context: JobContext,
   ) -> Result<JobResult> {
     publish_job_progression(channel.clone(), job_result.get_job_id(), 50)?;
+    context.check_cancelled()?;
 
     match parameters.action {
       Some(action_label) => match action_label.as_str() {
@@ -0,0 +1,86 @@
+//! Publication of orders after a delay, without holding the consumer
+//!
+//! The order is published on a `<queue>.delay.<milliseconds>` queue, where it expires after the delay
+//! and is dead-lettered to its exchange and routing key. The delay queues are deleted by the broker once unused.
+
+use super::queue_description::QueueDescription;
+use crate::McaiChannel;
+use lapin::{options::BasicPublishOptions, BasicProperties};
+use std::convert::TryFrom;
+
+/// Duration in milliseconds during which an unused delay queue is kept after the delay
+const DELAY_QUEUE_EXPIRATION: u64 = 60000;
+
+/// Publish the order on its exchange and routing key once the delay in milliseconds has elapsed
+pub(crate) fn publish_delayed(
+  channel: &McaiChannel,
+  exchange: &str,
+  routing_key: &str,
+  data: Vec<u8>,
+  properties: BasicProperties,
+  delay: u64,
+) -> Result<(), String> {
+  if delay == 0 {
+    return publish(channel, exchange, routing_key, data, properties);
+  }
+
+  let expires = i32::try_from(delay.saturating_add(DELAY_QUEUE_EXPIRATION))
+    .map_err(|_| format!("Unable to delay the order by {} ms", delay))?;
+
+  let delay_queue = QueueDescription {
+    name: get_delay_queue_name(routing_key, delay),
+    durable: true,
+    auto_delete: false,
+    dead_letter_exchange: Some(exchange.to_string()),
+    dead_letter_routing_key: Some(routing_key.to_string()),
+    max_priority: None,
+    message_ttl: None,
+    expires: Some(expires),
+  };
+  delay_queue.declare(channel);
+
+  // the expiration is removed by the broker once the order is dead-lettered
+  let properties = properties.with_expiration(delay.to_string().into());
+  publish(channel, "", &delay_queue.name, data, properties)
+}
+
+fn publish(
+  channel: &McaiChannel,
+  exchange: &str,
+  routing_key: &str,
+  data: Vec<u8>,
+  properties: BasicProperties,
+) -> Result<(), String> {
+  channel
+    .basic_publish(
+      exchange,
+      routing_key,
+      BasicPublishOptions::default(),
+      data,
+      properties,
+    )
+    .wait()
+    .map(|_| ())
+    .map_err(|error| error.to_string())
+}
+
+fn get_delay_queue_name(routing_key: &str, delay: u64) -> String {
+  format!("{}.delay.{}", routing_key, delay)
+}
+
+/// Queues holding the orders temporarily, not counted as deaths of the orders
+pub(crate) fn is_holding_queue(queue_name: &str) -> bool {
+  queue_name.contains(".delay.") || queue_name.contains(".lease.")
+}
+
+#[test]
+pub fn test_delay_queue() {
+  assert_eq!(
+    "job_transfer.delay.5000",
+    get_delay_queue_name("job_transfer", 5000)
+  );
+  assert!(is_holding_queue("job_transfer.delay.5000"));
+  assert!(is_holding_queue("job_transfer.lease.e9b7f6c4a8d2"));
+  assert!(!is_holding_queue("job_transfer"));
+  assert!(!is_holding_queue("job_delayed"));
+}
@@ -0,0 +1,106 @@
+use amq_protocol_uri::AMQPUri;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Timeout of the TCP connection checking an endpoint is reachable
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Broker endpoints, the active one is kept until it becomes unreachable
+#[derive(Debug)]
+pub struct BrokerFailover {
+  uris: Vec<AMQPUri>,
+  active: usize,
+}
+
+impl BrokerFailover {
+  pub fn new(uris: Vec<AMQPUri>) -> Self {
+    BrokerFailover { uris, active: 0 }
+  }
+
+  /// Select the endpoint to connect to, starting from the active one
+  ///
+  /// When no endpoint is reachable, the active one is returned to be retried.
+  pub fn select(&mut self) -> AMQPUri {
+    let count = self.uris.len();
+    let reachable = (0..count)
+      .map(|offset| (self.active + offset) % count)
+      .find(|index| is_reachable(&self.uris[*index]));
+
+    match reachable {
+      Some(index) if index != self.active => {
+        warn!(
+          "AMQP broker {} is unreachable, fail over to {}",
+          get_endpoint(&self.uris[self.active]),
+          get_endpoint(&self.uris[index])
+        );
+        self.active = index;
+      }
+      Some(_) => {}
+      None => warn!("No AMQP broker is reachable"),
+    }
+
+    self.uris[self.active].clone()
+  }
+
+  /// Move to the next endpoint, when the connection to the active one failed
+  pub fn fail_over(&mut self) {
+    self.active = (self.active + 1) % self.uris.len();
+  }
+
+  pub fn get_active_endpoint(&self) -> String {
+    get_endpoint(&self.uris[self.active])
+  }
+}
+
+fn get_endpoint(uri: &AMQPUri) -> String {
+  format!("{}:{}", uri.authority.host, uri.authority.port)
+}
+
+fn is_reachable(uri: &AMQPUri) -> bool {
+  (uri.authority.host.as_str(), uri.authority.port)
+    .to_socket_addrs()
+    .map(|mut addresses| {
+      addresses.any(|address| TcpStream::connect_timeout(&address, HEALTH_CHECK_TIMEOUT).is_ok())
+    })
+    .unwrap_or(false)
+}
+
+#[test]
+pub fn test_broker_failover() {
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+  let reachable_port = listener.local_addr().unwrap().port();
+  let unreachable_port = {
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    closed.local_addr().unwrap().port()
+  };
+
+  let get_uri = |port: u16| {
+    let mut uri = AMQPUri::default();
+    uri.authority.host = "127.0.0.1".to_string();
+    uri.authority.port = port;
+    uri
+  };
+
+  let mut failover = BrokerFailover::new(vec![
+    get_uri(unreachable_port),
+    get_uri(reachable_port),
+    get_uri(unreachable_port),
+  ]);
+  assert_eq!(reachable_port, failover.select().authority.port);
+  assert_eq!(
+    format!("127.0.0.1:{}", reachable_port),
+    failover.get_active_endpoint()
+  );
+
+  failover.fail_over();
+  assert_eq!(
+    unreachable_port,
+    failover.uris[failover.active].authority.port
+  );
+  // wraps around to the reachable endpoint
+  assert_eq!(reachable_port, failover.select().authority.port);
+
+  drop(listener);
+  let mut failover = BrokerFailover::new(vec![get_uri(unreachable_port)]);
+  assert_eq!(unreachable_port, failover.select().authority.port);
+}
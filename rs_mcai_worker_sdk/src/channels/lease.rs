@@ -0,0 +1,326 @@
+//! Renewal of the deliveries of long-running jobs
+//!
+//! Brokers redeliver an order whose delivery is not acknowledged in time
+//! (like the `consumer_timeout` of RabbitMQ), even if its job is still in progress.
+//! While a job runs, its delivery is renewed periodically: the order is published on a lease queue
+//! dedicated to the job, got back as a new delivery, and the previous delivery is acknowledged.
+//!
+//! The new delivery keeps the exchange, routing key and properties of the order, as if delivered by the job queue.
+//!
+//! If the worker is lost, the pending copy of the order expires and is dead-lettered to the job queue.
+//! A copy published but not got back is purged, the job still running with the previous delivery.
+
+use super::queue_description::QueueDescription;
+use crate::McaiChannel;
+use lapin::{
+  message::Delivery,
+  options::{BasicAckOptions, BasicGetOptions, BasicPublishOptions, QueuePurgeOptions},
+};
+use std::convert::TryFrom;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Time to live in milliseconds of the copy of the order not yet got back, or released by a lost worker
+const LEASE_EXPIRATION: u64 = 10000;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Delivery of an order in progress, renewed until released
+pub(crate) struct DeliveryLease {
+  stopped: Arc<AtomicBool>,
+  renewal: Option<JoinHandle<Delivery>>,
+  delivery: Option<Delivery>,
+}
+
+impl DeliveryLease {
+  /// Renew the delivery every `interval` seconds, not renewed without interval
+  pub(crate) fn start(channel: McaiChannel, delivery: Delivery, interval: Option<u64>) -> Self {
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let interval = match interval.filter(|interval| *interval > 0) {
+      Some(interval) => Duration::from_secs(interval),
+      None => {
+        return DeliveryLease {
+          stopped,
+          renewal: None,
+          delivery: Some(delivery),
+        }
+      }
+    };
+
+    let renewal_stopped = stopped.clone();
+    let renewal = thread::spawn(move || {
+      let lease_queue = LeaseQueue::new(&delivery.routing_key.to_string(), interval);
+      let mut delivery = delivery;
+      let mut renewed = Instant::now();
+
+      while !renewal_stopped.load(Ordering::SeqCst) {
+        if renewed.elapsed() >= interval {
+          delivery = lease_queue.renew(&channel, delivery);
+          renewed = Instant::now();
+        }
+        thread::sleep(POLL_INTERVAL);
+      }
+      delivery
+    });
+
+    DeliveryLease {
+      stopped,
+      renewal: Some(renewal),
+      delivery: None,
+    }
+  }
+
+  /// Stop the renewal, the returned delivery is the one to acknowledge
+  pub(crate) fn release(mut self) -> Delivery {
+    self.stopped.store(true, Ordering::SeqCst);
+
+    match (self.renewal.take(), self.delivery.take()) {
+      (Some(renewal), _) => renewal
+        .join()
+        .expect("The renewal of the delivery should not panic"),
+      (None, Some(delivery)) => delivery,
+      (None, None) => unreachable!(),
+    }
+  }
+}
+
+struct LeaseQueue {
+  name: String,
+  job_queue: String,
+  /// Duration in milliseconds without renewal after which the queue is deleted
+  expires: u64,
+}
+
+impl LeaseQueue {
+  fn new(job_queue: &str, interval: Duration) -> Self {
+    LeaseQueue {
+      name: get_lease_queue_name(job_queue, &Uuid::new_v4().to_string()),
+      job_queue: job_queue.to_string(),
+      expires: (interval.as_millis() as u64 * 3).max(LEASE_EXPIRATION * 6),
+    }
+  }
+
+  /// Replace the delivery by a new one, the delivery is kept if it can not be renewed
+  fn renew(&self, channel: &McaiChannel, delivery: Delivery) -> Delivery {
+    match self.get_new_delivery(channel, &delivery) {
+      Ok(new_delivery) => {
+        if let Err(error) = channel
+          .basic_ack(delivery.delivery_tag, BasicAckOptions::default())
+          .wait()
+        {
+          error!("Unable to acknowledge the renewed delivery: {:?}", error);
+        }
+        debug!("Delivery renewed on queue {}", self.name);
+        new_delivery
+      }
+      Err(error) => {
+        warn!("Unable to renew the delivery: {}", error);
+        delivery
+      }
+    }
+  }
+
+  fn get_new_delivery(
+    &self,
+    channel: &McaiChannel,
+    delivery: &Delivery,
+  ) -> Result<Delivery, String> {
+    let expires = i32::try_from(self.expires)
+      .map_err(|_| format!("Invalid expiration of the lease queue: {} ms", self.expires))?;
+    let lease_queue = QueueDescription {
+      name: self.name.clone(),
+      durable: true,
+      auto_delete: false,
+      dead_letter_exchange: Some("".to_string()),
+      dead_letter_routing_key: Some(self.job_queue.clone()),
+      max_priority: None,
+      message_ttl: None,
+      expires: Some(expires),
+    };
+    lease_queue.declare(channel);
+
+    let properties = delivery
+      .properties
+      .clone()
+      .with_expiration(LEASE_EXPIRATION.to_string().into());
+
+    channel
+      .basic_publish(
+        "",
+        &self.name,
+        BasicPublishOptions::default(),
+        delivery.data.clone(),
+        properties,
+      )
+      .wait()
+      .map_err(|error| error.to_string())?;
+
+    let leased_delivery = get_leased_delivery(
+      &self.name,
+      || {
+        channel
+          .basic_get(&self.name, BasicGetOptions::default())
+          .wait()
+          .map(|message| message.map(|message| message.delivery))
+          .map_err(|error| error.to_string())
+      },
+      || {
+        channel
+          .queue_purge(&self.name, QueuePurgeOptions::default())
+          .wait()
+          .map(|_| ())
+          .map_err(|error| error.to_string())
+      },
+    )?;
+    Ok(restore_delivery(delivery, leased_delivery))
+  }
+}
+
+/// Get back the copy of the order, purged if it can not be got back:
+/// otherwise it would expire and be dead-lettered to the job queue while the job is in progress
+fn get_leased_delivery<G, P>(queue_name: &str, get: G, purge: P) -> Result<Delivery, String>
+where
+  G: FnOnce() -> Result<Option<Delivery>, String>,
+  P: FnOnce() -> Result<(), String>,
+{
+  let error = match get() {
+    Ok(Some(delivery)) => return Ok(delivery),
+    Ok(None) => format!("The lease queue {} is empty", queue_name),
+    Err(error) => error,
+  };
+
+  if let Err(purge_error) = purge() {
+    error!(
+      "Unable to purge the lease queue {}, the order may be processed again: {}",
+      queue_name, purge_error
+    );
+  }
+  Err(error)
+}
+
+/// Delivery got from the lease queue, with the exchange, routing key and properties (without the lease expiration)
+/// of the original delivery, used to publish the result or to requeue the order
+fn restore_delivery(delivery: &Delivery, leased_delivery: Delivery) -> Delivery {
+  Delivery {
+    exchange: delivery.exchange.clone(),
+    routing_key: delivery.routing_key.clone(),
+    properties: delivery.properties.clone(),
+    ..leased_delivery
+  }
+}
+
+fn get_lease_queue_name(job_queue: &str, lease_id: &str) -> String {
+  format!("{}.lease.{}", job_queue, lease_id)
+}
+
+#[test]
+pub fn test_lease_queue() {
+  assert_eq!(
+    "job_transfer.lease.e9b7f6c4a8d2",
+    get_lease_queue_name("job_transfer", "e9b7f6c4a8d2")
+  );
+
+  let lease_queue = LeaseQueue::new("job_transfer", Duration::from_secs(600));
+  assert_eq!("job_transfer", lease_queue.job_queue);
+  assert_eq!(1_800_000, lease_queue.expires);
+
+  let lease_queue = LeaseQueue::new("job_transfer", Duration::from_secs(1));
+  assert_eq!(60_000, lease_queue.expires);
+}
+
+#[test]
+pub fn test_restore_delivery() {
+  use amq_protocol_types::{AMQPValue, FieldTable};
+  use lapin::BasicProperties;
+
+  let mut headers = FieldTable::default();
+  headers.insert("x-retry-count".into(), AMQPValue::LongLongInt(1));
+  let properties = BasicProperties::default()
+    .with_priority(5)
+    .with_headers(headers);
+
+  let delivery = Delivery {
+    delivery_tag: 1,
+    exchange: "job".into(),
+    routing_key: "job_transfer".into(),
+    redelivered: false,
+    properties: properties.clone(),
+    data: b"{}".to_vec(),
+  };
+  let leased_delivery = Delivery {
+    delivery_tag: 2,
+    exchange: "".into(),
+    routing_key: "job_transfer.lease.e9b7f6c4a8d2".into(),
+    redelivered: false,
+    properties: properties.with_expiration(LEASE_EXPIRATION.to_string().into()),
+    data: b"{}".to_vec(),
+  };
+
+  // the order is released or requeued to the job queue, not to the lease queue
+  let delivery = restore_delivery(&delivery, leased_delivery);
+  assert_eq!(2, delivery.delivery_tag);
+  assert_eq!("job", delivery.exchange.as_str());
+  assert_eq!("job_transfer", delivery.routing_key.as_str());
+  assert_eq!(&None, delivery.properties.expiration());
+  assert_eq!(&Some(5), delivery.properties.priority());
+  assert!(delivery.properties.headers().is_some());
+}
+
+#[test]
+pub fn test_get_leased_delivery() {
+  use std::cell::Cell;
+
+  let queue_name = "job_transfer.lease.e9b7f6c4a8d2";
+  let leased_delivery = Delivery {
+    delivery_tag: 2,
+    exchange: "".into(),
+    routing_key: queue_name.into(),
+    redelivered: false,
+    properties: lapin::BasicProperties::default(),
+    data: b"{}".to_vec(),
+  };
+
+  let purged = Cell::new(false);
+  let result = get_leased_delivery(
+    queue_name,
+    || Ok(Some(leased_delivery.clone())),
+    || {
+      purged.set(true);
+      Ok(())
+    },
+  );
+  assert_eq!(2, result.unwrap().delivery_tag);
+  assert!(!purged.get());
+
+  // the copy of the order not got back is not dead-lettered to the job queue
+  let result = get_leased_delivery(
+    queue_name,
+    || Err("channel closed".to_string()),
+    || {
+      purged.set(true);
+      Ok(())
+    },
+  );
+  assert_eq!(Some("channel closed".to_string()), result.err());
+  assert!(purged.replace(false));
+
+  let result = get_leased_delivery(
+    queue_name,
+    || Ok(None),
+    || {
+      purged.set(true);
+      Err("channel closed".to_string())
+    },
+  );
+  assert_eq!(
+    Some("The lease queue job_transfer.lease.e9b7f6c4a8d2 is empty".to_string()),
+    result.err()
+  );
+  assert!(purged.get());
+}
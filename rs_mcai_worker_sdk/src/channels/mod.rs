@@ -1,7 +1,11 @@
 mod bind_description;
+pub(crate) mod delayed;
 mod exchange_description;
+pub mod failover;
+pub(crate) mod lease;
 mod queue_description;
 
+use crate::config::{get_heartbeat_exchange, get_job_events_exchange};
 use crate::worker::WorkerConfiguration;
 use bind_description::BindDescription;
 use exchange_description::ExchangeDescription;
@@ -23,9 +27,9 @@ static QUEUE_NAME_WORKER_DISCOVERY: &str = "worker_discovery";
 pub fn declare_consumer_channel(
   conn: &Connection,
   worker_configuration: &WorkerConfiguration,
+  prefetch_count: u16,
 ) -> Channel {
   let channel = conn.create_channel().wait().unwrap();
-  let prefetch_count = 1;
 
   info!("Initialise Exchanges and Queues");
   set_qos(&channel, prefetch_count);
@@ -54,6 +58,24 @@ pub fn declare_consumer_channel(
   };
   response_exchange.declare(&channel);
 
+  if let Some(job_events_exchange) = get_job_events_exchange() {
+    let job_events_exchange = ExchangeDescription {
+      name: job_events_exchange,
+      kind: ExchangeKind::Topic,
+      alternate_exchange: None,
+    };
+    job_events_exchange.declare(&channel);
+  }
+
+  if let Some(heartbeat_exchange) = get_heartbeat_exchange() {
+    let heartbeat_exchange = ExchangeDescription {
+      name: heartbeat_exchange,
+      kind: ExchangeKind::Topic,
+      alternate_exchange: None,
+    };
+    heartbeat_exchange.declare(&channel);
+  }
+
   let delayed_queue = QueueDescription {
     name: EXCHANGE_NAME_DELAYED.to_string(),
     durable: true,
@@ -62,6 +84,7 @@ pub fn declare_consumer_channel(
     dead_letter_routing_key: None,
     max_priority: None,
     message_ttl: Some(5000),
+    expires: None,
   };
   delayed_queue.declare(&channel);
 
@@ -88,6 +111,7 @@ pub fn declare_consumer_channel(
     dead_letter_routing_key: None,
     max_priority: None,
     message_ttl: None,
+    expires: None,
   };
   direct_messaging_queue.declare(&channel);
 
@@ -135,6 +159,7 @@ pub fn declare_consumer_channel(
     dead_letter_routing_key: Some(QUEUE_NAME_WORKER_DISCOVERY.to_string()),
     max_priority: None,
     message_ttl: None,
+    expires: None,
   };
   worker_discovery_queue.declare(&channel);
 
@@ -164,6 +189,7 @@ pub fn declare_consumer_channel(
     dead_letter_routing_key: Some(worker_configuration.get_queue_name()),
     max_priority: Some(100),
     message_ttl: None,
+    expires: None,
   };
   job_queue.declare(&channel);
 
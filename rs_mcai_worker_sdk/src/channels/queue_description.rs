@@ -9,6 +9,7 @@ pub struct QueueDescription {
   pub dead_letter_routing_key: Option<String>,
   pub max_priority: Option<i16>,
   pub message_ttl: Option<i16>,
+  pub expires: Option<i32>,
 }
 
 impl QueueDescription {
@@ -52,6 +53,10 @@ impl QueueDescription {
     if let Some(message_ttl) = &self.message_ttl {
       queue_fields.insert("x-message-ttl".into(), AMQPValue::ShortInt(*message_ttl));
     }
+
+    if let Some(expires) = &self.expires {
+      queue_fields.insert("x-expires".into(), AMQPValue::LongInt(*expires));
+    }
     queue_fields
   }
 }
@@ -65,6 +70,7 @@ pub fn test_queue_description() {
   let dead_letter_routing_key = Some("dead_letter_routing_key".to_string());
   let max_priority = Some(1000);
   let message_ttl = Some(123);
+  let expires = Some(60000);
 
   let queue_description = QueueDescription {
     name,
@@ -74,6 +80,7 @@ pub fn test_queue_description() {
     dead_letter_routing_key: dead_letter_routing_key.clone(),
     max_priority: max_priority.clone(),
     message_ttl: message_ttl.clone(),
+    expires,
   };
 
   let field_table = queue_description.get_field_table();
@@ -82,6 +89,7 @@ pub fn test_queue_description() {
   assert!(tree_map.contains_key("x-dead-letter-routing-key"));
   assert!(tree_map.contains_key("x-max-priority"));
   assert!(tree_map.contains_key("x-message-ttl"));
+  assert!(tree_map.contains_key("x-expires"));
 
   assert_eq!(
     &AMQPValue::LongString(dead_letter_exchange.unwrap().into()),
@@ -99,4 +107,8 @@ pub fn test_queue_description() {
     &AMQPValue::ShortInt(message_ttl.unwrap().into()),
     tree_map.get("x-message-ttl").unwrap()
   );
+  assert_eq!(
+    &AMQPValue::LongInt(expires.unwrap()),
+    tree_map.get("x-expires").unwrap()
+  );
 }
@@ -1,15 +1,111 @@
 use amq_protocol_uri::{AMQPAuthority, AMQPScheme, AMQPUri, AMQPUserInfo};
+use std::collections::BTreeMap;
 use std::env;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Values applied with `SdkConfig::apply`, taking precedence over the environment variables
+static CONFIG_OVERRIDES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
 
 macro_rules! get_env_value {
   ($key:expr, $default:expr) => {
-    match env::var($key) {
-      Ok(value) => value,
+    match get_config_value($key) {
+      Some(value) => value,
       _ => $default.to_string(),
     }
   };
 }
 
+/// Programmatic configuration of the SDK, the environment variables remain the fallback
+///
+/// The keys are the environment variables of the runtime configuration,
+/// the values are applied to the whole process.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SdkConfig {
+  values: BTreeMap<String, String>,
+}
+
+impl SdkConfig {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Set any variable of the runtime configuration, e.g. `JOB_TIMEOUT`
+  pub fn with_value(mut self, key: &str, value: &str) -> Self {
+    self.values.insert(key.to_string(), value.to_string());
+    self
+  }
+
+  pub fn with_amqp_hostname(self, hostname: &str) -> Self {
+    self.with_value("AMQP_HOSTNAME", hostname)
+  }
+
+  pub fn with_amqp_port(self, port: u16) -> Self {
+    self.with_value("AMQP_PORT", &port.to_string())
+  }
+
+  pub fn with_amqp_tls(self, tls: bool) -> Self {
+    self.with_value("AMQP_TLS", &tls.to_string())
+  }
+
+  pub fn with_amqp_username(self, username: &str) -> Self {
+    self.with_value("AMQP_USERNAME", username)
+  }
+
+  pub fn with_amqp_password(self, password: &str) -> Self {
+    self.with_value("AMQP_PASSWORD", password)
+  }
+
+  pub fn with_amqp_vhost(self, vhost: &str) -> Self {
+    self.with_value("AMQP_VHOST", vhost)
+  }
+
+  pub fn with_amqp_queue(self, queue: &str) -> Self {
+    self.with_value("AMQP_QUEUE", queue)
+  }
+
+  pub fn with_backend_hostname(self, hostname: &str) -> Self {
+    self.with_value("BACKEND_HOSTNAME", hostname)
+  }
+
+  pub fn with_backend_username(self, username: &str) -> Self {
+    self.with_value("BACKEND_USERNAME", username)
+  }
+
+  pub fn with_backend_password(self, password: &str) -> Self {
+    self.with_value("BACKEND_PASSWORD", password)
+  }
+
+  pub fn with_max_concurrent_jobs(self, max_concurrent_jobs: u16) -> Self {
+    self.with_value("MAX_CONCURRENT_JOBS", &max_concurrent_jobs.to_string())
+  }
+
+  pub fn with_job_timeout(self, timeout: u64) -> Self {
+    self.with_value("JOB_TIMEOUT", &timeout.to_string())
+  }
+
+  pub fn get_value(&self, key: &str) -> Option<&String> {
+    self.values.get(key)
+  }
+
+  /// Override the environment variables with the values of this configuration
+  pub fn apply(&self) {
+    CONFIG_OVERRIDES
+      .write()
+      .unwrap()
+      .extend(self.values.clone());
+  }
+}
+
+fn get_config_value(key: &str) -> Option<String> {
+  CONFIG_OVERRIDES
+    .read()
+    .unwrap()
+    .get(key)
+    .cloned()
+    .or_else(|| env::var(key).ok())
+}
+
 fn get_amqp_tls() -> bool {
   let value = get_env_value!("AMQP_TLS", "true");
   matches!(value.as_str(), "true" | "1" | "True" | "TRUE")
@@ -43,6 +139,547 @@ pub fn get_amqp_queue() -> String {
   get_env_value!("AMQP_QUEUE", "job_undefined")
 }
 
+pub fn get_max_concurrent_jobs() -> u16 {
+  let value = get_env_value!("MAX_CONCURRENT_JOBS", "1");
+  match value.parse::<u16>() {
+    Ok(value) if value > 0 => value,
+    _ => 1,
+  }
+}
+
+/// Maximum number of jobs processed at a time by the worker
+pub fn get_effective_max_concurrent_jobs() -> u16 {
+  clamp_max_concurrent_jobs(get_max_concurrent_jobs())
+}
+
+/// Media processing relies on a per-job state in the worker, jobs are processed one at a time
+pub(crate) fn clamp_max_concurrent_jobs(max_concurrent_jobs: u16) -> u16 {
+  if cfg!(feature = "media") {
+    1
+  } else {
+    max_concurrent_jobs
+  }
+}
+
+pub fn get_job_timeout() -> Option<u64> {
+  get_config_value("JOB_TIMEOUT").and_then(|value| value.parse::<u64>().ok())
+}
+
+/// Cron expressions of the windows in which jobs are consumed, separated by `;`
+pub fn get_processing_windows() -> Option<String> {
+  get_config_value("PROCESSING_WINDOWS")
+}
+
+/// Topic exchange on which the state transitions of the jobs are published
+pub fn get_job_events_exchange() -> Option<String> {
+  get_config_value("JOB_EVENTS_EXCHANGE")
+}
+
+/// Topic exchange on which the heartbeats of the instance are published
+pub fn get_heartbeat_exchange() -> Option<String> {
+  get_config_value("HEARTBEAT_EXCHANGE").filter(|exchange| !exchange.is_empty())
+}
+
+/// Interval between two heartbeats in seconds
+pub fn get_heartbeat_interval() -> u64 {
+  let value = get_env_value!("HEARTBEAT_INTERVAL", "30");
+  match value.parse::<u64>() {
+    Ok(value) if value > 0 => value,
+    _ => 30,
+  }
+}
+
+/// Endpoint of the backend on which the worker is registered on startup
+pub fn get_worker_registration_url() -> Option<String> {
+  get_config_value("WORKER_REGISTRATION_URL").filter(|url| !url.is_empty())
+}
+
+/// Prefix of the configuration variables of the instance parameters,
+/// not to read the unrelated variables of the environment
+const INSTANCE_PARAMETER_PREFIX: &str = "INSTANCE_PARAMETER_";
+
+/// Configuration variable of an instance parameter, like `INSTANCE_PARAMETER_MODEL_PATH` for `model_path`
+pub(crate) fn get_instance_parameter_key(identifier: &str) -> String {
+  format!("{}{}", INSTANCE_PARAMETER_PREFIX, identifier.to_uppercase())
+}
+
+/// Value of an instance parameter, set in its configuration variable
+pub(crate) fn get_instance_parameter(identifier: &str) -> Option<String> {
+  get_config_value(&get_instance_parameter_key(identifier))
+}
+
+/// Human-readable name of the instance, for the consumer tags and the logs
+pub fn get_instance_name_override() -> Option<String> {
+  get_config_value("INSTANCE_NAME").filter(|instance_name| !instance_name.is_empty())
+}
+
+/// Identifier of the instance, taking precedence over the detected one
+pub fn get_instance_id_override() -> Option<String> {
+  get_config_value("INSTANCE_ID").filter(|instance_id| !instance_id.is_empty())
+}
+
+/// Name of the Kubernetes pod, set with the downward API
+pub fn get_pod_name() -> Option<String> {
+  get_config_value("POD_NAME").filter(|pod_name| !pod_name.is_empty())
+}
+
+/// UID of the Kubernetes pod, set with the downward API
+pub fn get_pod_uid() -> Option<String> {
+  get_config_value("POD_UID").filter(|pod_uid| !pod_uid.is_empty())
+}
+
+pub fn is_kubernetes_pod() -> bool {
+  get_config_value("KUBERNETES_SERVICE_HOST").is_some()
+}
+
+/// Free-form labels of the worker, comma separated `key=value` pairs like `team=ingest,gpu=true`
+pub fn get_worker_labels() -> BTreeMap<String, String> {
+  get_config_value("WORKER_LABELS")
+    .map(|labels| {
+      labels
+        .split(',')
+        .map(|label| label.split_once('=').unwrap_or((label, "")))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Duration in seconds during which the description published on request is cached
+pub fn get_describe_cache_ttl() -> u64 {
+  let value = get_env_value!("DESCRIBE_CACHE_TTL", "60");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 60,
+  }
+}
+
+/// Print the worker description and exit
+pub fn is_describe_enabled() -> bool {
+  let value = get_env_value!("DESCRIBE", "false");
+  value == "1" || value.to_lowercase() == "true"
+}
+
+/// Run the self-test of the worker, print its diagnostic and exit
+pub fn get_self_test() -> bool {
+  let value = get_env_value!("SELF_TEST", "false");
+  value == "1" || value.to_lowercase() == "true"
+}
+
+/// Format of the description printed with `DESCRIBE=1`: `json`, `json-compact`, `yaml` or `schema-only`
+pub fn get_describe_format() -> String {
+  get_env_value!("DESCRIBE_FORMAT", "json")
+}
+
+/// Format of the log lines: `text` or `json`, one JSON object per line
+pub fn get_log_format() -> String {
+  get_env_value!("LOG_FORMAT", "text")
+}
+
+/// Capture of the log records of the jobs: `none`, `queue` to publish them on the `job_logs` queue,
+/// or `result` to add them to the job results
+pub fn get_job_log_capture() -> String {
+  get_env_value!("JOB_LOG_CAPTURE", "none")
+}
+
+/// Maximum number of log records captured per job, the oldest ones are dropped
+pub fn get_job_log_capture_size() -> usize {
+  let value = get_env_value!("JOB_LOG_CAPTURE_SIZE", "200");
+  match value.parse::<usize>() {
+    Ok(value) if value > 0 => value,
+    _ => 200,
+  }
+}
+
+/// Base URL of the OTLP/HTTP collector receiving the spans
+#[cfg(feature = "otel")]
+pub fn get_otlp_endpoint() -> Option<String> {
+  get_config_value("OTEL_EXPORTER_OTLP_ENDPOINT").filter(|endpoint| !endpoint.is_empty())
+}
+
+/// Service of the exported spans, the worker name by default
+#[cfg(feature = "otel")]
+pub fn get_otlp_service_name() -> Option<String> {
+  get_config_value("OTEL_SERVICE_NAME").filter(|service_name| !service_name.is_empty())
+}
+
+/// Queue on which the description is published on request, unless the request has a `reply_to` queue
+pub fn get_describe_response_queue() -> String {
+  get_env_value!("DESCRIBE_RESPONSE_QUEUE", "worker_discovery")
+}
+
+/// Number of orders prefetched to find the re-runs, 0 to process the orders in the queue order
+pub fn get_rerun_lookahead() -> u16 {
+  let value = get_env_value!("RERUN_LOOKAHEAD", "0");
+  match value.parse::<u16>() {
+    Ok(value) => value,
+    _ => 0,
+  }
+}
+
+pub fn get_job_timeout_policy() -> String {
+  get_env_value!("JOB_TIMEOUT_POLICY", "ack")
+}
+
+/// Number of requeues of a timed out order with the `requeue` policy, before it is dead-lettered
+pub fn get_job_timeout_max_requeues() -> u32 {
+  let value = get_env_value!("JOB_TIMEOUT_MAX_REQUEUES", "3");
+  value.parse::<u32>().unwrap_or(3)
+}
+
+pub fn get_requirements_requeue_policy() -> String {
+  get_env_value!("REQUIREMENTS_REQUEUE_POLICY", "reject")
+}
+
+/// Timeout in seconds of the HEAD request checking a required URL
+pub fn get_requirements_url_timeout() -> u64 {
+  let value = get_env_value!("REQUIREMENTS_URL_TIMEOUT", "5");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 5,
+  }
+}
+
+/// Resolution above which decoded images are downscaled, as `<width>x<height>`
+#[cfg(feature = "media")]
+pub fn get_max_video_resolution() -> Option<(u32, u32)> {
+  get_config_value("MAX_VIDEO_RESOLUTION").and_then(|value| parse_resolution(&value))
+}
+
+/// Resolution above which sources are rejected, as `<width>x<height>`
+#[cfg(feature = "media")]
+pub fn get_max_source_resolution() -> (u32, u32) {
+  let value = get_env_value!("MAX_SOURCE_RESOLUTION", "16384x16384");
+  parse_resolution(&value).unwrap_or((16384, 16384))
+}
+
+/// Read bandwidth in bytes per second shared by the sources of all the jobs
+#[cfg(feature = "media")]
+pub fn get_source_max_bandwidth() -> Option<u64> {
+  get_config_value("SOURCE_MAX_BANDWIDTH").and_then(|value| value.parse::<u64>().ok())
+}
+
+#[cfg(feature = "media")]
+fn parse_resolution(value: &str) -> Option<(u32, u32)> {
+  let mut dimensions = value
+    .split('x')
+    .map(|dimension| dimension.trim().parse::<u32>());
+  match (dimensions.next(), dimensions.next(), dimensions.next()) {
+    (Some(Ok(width)), Some(Ok(height)), None) if width > 0 && height > 0 => Some((width, height)),
+    _ => None,
+  }
+}
+
+pub fn get_transient_max_retries() -> u32 {
+  let value = get_env_value!("TRANSIENT_MAX_RETRIES", "3");
+  match value.parse::<u32>() {
+    Ok(value) => value,
+    _ => 3,
+  }
+}
+
+pub fn get_transient_retry_delay() -> u64 {
+  let value = get_env_value!("TRANSIENT_RETRY_DELAY", "1000");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 1000,
+  }
+}
+
+pub fn get_init_max_retries() -> u32 {
+  let value = get_env_value!("INIT_MAX_RETRIES", "0");
+  match value.parse::<u32>() {
+    Ok(value) => value,
+    _ => 0,
+  }
+}
+
+pub fn get_init_retry_delay() -> u64 {
+  let value = get_env_value!("INIT_RETRY_DELAY", "5000");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 5000,
+  }
+}
+
+pub fn get_watchdog_timeout() -> Option<u64> {
+  get_config_value("WATCHDOG_TIMEOUT")
+    .and_then(|value| value.parse::<u64>().ok())
+    .filter(|timeout| *timeout > 0)
+}
+
+pub fn get_watchdog_restart() -> bool {
+  let value = get_env_value!("WATCHDOG_RESTART", "false");
+  matches!(value.as_str(), "true" | "1" | "True" | "TRUE")
+}
+
+/// Run the shutdown hooks and exit on `SIGTERM` and `SIGINT`, after the previously installed handlers
+pub fn get_handle_termination_signals() -> bool {
+  let value = get_env_value!("HANDLE_TERMINATION_SIGNALS", "false");
+  matches!(value.as_str(), "true" | "1" | "True" | "TRUE")
+}
+
+/// Inject latency, failures and delayed publishes, for staging environments only
+pub fn get_chaos_mode() -> bool {
+  let value = get_env_value!("CHAOS_MODE", "false");
+  matches!(value.as_str(), "true" | "1" | "True" | "TRUE")
+}
+
+fn get_probability(key: &str) -> f64 {
+  get_config_value(key)
+    .and_then(|value| value.parse::<f64>().ok())
+    .map(|probability| probability.clamp(0.0, 1.0))
+    .unwrap_or(0.0)
+}
+
+pub fn get_chaos_latency_probability() -> f64 {
+  get_probability("CHAOS_LATENCY_PROBABILITY")
+}
+
+pub fn get_chaos_latency_max() -> u64 {
+  let value = get_env_value!("CHAOS_LATENCY_MAX", "5000");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 5000,
+  }
+}
+
+pub fn get_chaos_failure_probability() -> f64 {
+  get_probability("CHAOS_FAILURE_PROBABILITY")
+}
+
+pub fn get_chaos_transient_probability() -> f64 {
+  get_probability("CHAOS_TRANSIENT_PROBABILITY")
+}
+
+pub fn get_chaos_publish_delay_probability() -> f64 {
+  get_probability("CHAOS_PUBLISH_DELAY_PROBABILITY")
+}
+
+pub fn get_chaos_publish_delay_max() -> u64 {
+  let value = get_env_value!("CHAOS_PUBLISH_DELAY_MAX", "5000");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 5000,
+  }
+}
+
+pub fn get_workspace_root() -> PathBuf {
+  get_config_value("WORKSPACE_ROOT")
+    .map(PathBuf::from)
+    .unwrap_or_else(env::temp_dir)
+}
+
+pub fn get_workspace_quota() -> Option<u64> {
+  get_config_value("WORKSPACE_QUOTA").and_then(|value| value.parse::<u64>().ok())
+}
+
+/// One minute load average per processor above which new orders are requeued
+pub fn get_admission_max_cpu_load() -> Option<f64> {
+  get_config_value("ADMISSION_MAX_CPU_LOAD").and_then(|value| value.parse::<f64>().ok())
+}
+
+/// Available memory in bytes below which new orders are requeued
+pub fn get_admission_min_free_memory() -> Option<u64> {
+  get_config_value("ADMISSION_MIN_FREE_MEMORY").and_then(|value| value.parse::<u64>().ok())
+}
+
+/// Available space in bytes of the workspace disk below which new orders are requeued
+pub fn get_admission_min_free_disk() -> Option<u64> {
+  get_config_value("ADMISSION_MIN_FREE_DISK").and_then(|value| value.parse::<u64>().ok())
+}
+
+/// Delay in milliseconds before requeueing an order whose resources are locked by another worker
+pub fn get_claim_requeue_delay() -> u64 {
+  let value = get_env_value!("CLAIM_REQUEUE_DELAY", "5000");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 5000,
+  }
+}
+
+/// Interval in seconds between two renewals of the delivery of an order in progress
+pub fn get_delivery_lease_renewal_interval() -> Option<u64> {
+  get_config_value("DELIVERY_LEASE_RENEWAL_INTERVAL").and_then(|value| value.parse::<u64>().ok())
+}
+
+/// Delay in milliseconds before an order whose requirements are not met is published again behind the other orders
+pub fn get_requirements_requeue_delay() -> u64 {
+  let value = get_env_value!("REQUIREMENTS_REQUEUE_DELAY", "10000");
+  value.parse::<u64>().unwrap_or(10000)
+}
+
+pub fn get_admission_requeue_delay() -> u64 {
+  let value = get_env_value!("ADMISSION_REQUEUE_DELAY", "5000");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 5000,
+  }
+}
+
+pub fn get_http_client_timeout() -> Option<u64> {
+  get_config_value("HTTP_CLIENT_TIMEOUT").and_then(|value| value.parse::<u64>().ok())
+}
+
+pub fn get_http_client_proxy() -> Option<String> {
+  get_config_value("HTTP_CLIENT_PROXY")
+}
+
+pub fn get_http_client_ca_certificate() -> Option<PathBuf> {
+  get_config_value("HTTP_CLIENT_CA_CERTIFICATE").map(PathBuf::from)
+}
+
+pub fn get_http_client_accept_invalid_certificates() -> bool {
+  let value = get_env_value!("HTTP_CLIENT_ACCEPT_INVALID_CERTIFICATES", "false");
+  matches!(value.as_str(), "true" | "1" | "True" | "TRUE")
+}
+
+pub fn get_http_client_max_retries() -> u32 {
+  let value = get_env_value!("HTTP_CLIENT_MAX_RETRIES", "3");
+  match value.parse::<u32>() {
+    Ok(value) => value,
+    _ => 3,
+  }
+}
+
+pub fn get_http_client_retry_delay() -> u64 {
+  let value = get_env_value!("HTTP_CLIENT_RETRY_DELAY", "500");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 500,
+  }
+}
+
+/// Maximum number of requests per second to a host
+pub fn get_http_client_rate_limit() -> Option<f64> {
+  get_config_value("HTTP_CLIENT_RATE_LIMIT")
+    .and_then(|value| value.parse::<f64>().ok())
+    .filter(|rate_limit| *rate_limit > 0.0)
+}
+
+/// Prefix of the environment variables readable through the `ENVIRONMENT` store, none to read any variable
+pub fn get_environment_store_prefix() -> Option<String> {
+  get_config_value("ENVIRONMENT_STORE_PREFIX").filter(|prefix| !prefix.is_empty())
+}
+
+/// Environment variables the `{env:NAME}` placeholders of the parameters can expand, comma separated, none by default
+pub fn get_parameter_template_variables() -> Vec<String> {
+  get_config_value("PARAMETER_TEMPLATE_VARIABLES")
+    .map(|variables| {
+      variables
+        .split(',')
+        .map(|variable| variable.trim().to_string())
+        .filter(|variable| !variable.is_empty())
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// URLs the `{"@ref": "<url>"}` parameters can be downloaded from, comma separated prefixes, none by default
+pub fn get_parameter_reference_allowed_urls() -> Vec<String> {
+  get_config_value("PARAMETER_REFERENCE_ALLOWED_URLS")
+    .map(|urls| {
+      urls
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Maximum size in bytes of the content downloaded for the `{"@ref": "<url>"}` parameters
+pub fn get_parameter_reference_max_size() -> u64 {
+  let value = get_env_value!("PARAMETER_REFERENCE_MAX_SIZE", "67108864");
+  match value.parse::<u64>() {
+    Ok(value) => value,
+    _ => 67_108_864,
+  }
+}
+
+/// Directory of the secret files read through the `FILE` store
+pub fn get_file_store_path() -> String {
+  get_env_value!("FILE_STORE_PATH", "/run/secrets")
+}
+
+/// Address of the HashiCorp Vault server of the `HASHICORP_VAULT` store
+pub fn get_hashicorp_vault_hostname() -> String {
+  get_env_value!("HASHICORP_VAULT_HOSTNAME", "http://127.0.0.1:8200")
+}
+
+/// Mount path of the KV v2 secrets engine
+pub fn get_hashicorp_vault_mount() -> String {
+  get_env_value!("HASHICORP_VAULT_MOUNT", "secret")
+}
+
+pub fn get_hashicorp_vault_token() -> Option<String> {
+  get_config_value("HASHICORP_VAULT_TOKEN").filter(|token| !token.is_empty())
+}
+
+pub fn get_hashicorp_vault_role_id() -> Option<String> {
+  get_config_value("HASHICORP_VAULT_ROLE_ID").filter(|role_id| !role_id.is_empty())
+}
+
+pub fn get_hashicorp_vault_secret_id() -> Option<String> {
+  get_config_value("HASHICORP_VAULT_SECRET_ID")
+}
+
+/// Region of the `AWS_SECRETS_MANAGER` store
+pub fn get_aws_secrets_manager_region() -> Option<String> {
+  get_config_value("AWS_SECRETS_MANAGER_REGION")
+    .or_else(|| get_config_value("AWS_REGION"))
+    .or_else(|| get_config_value("AWS_DEFAULT_REGION"))
+    .filter(|region| !region.is_empty())
+}
+
+/// Endpoint of AWS Secrets Manager, instead of the endpoint of the region
+pub fn get_aws_secrets_manager_endpoint() -> Option<String> {
+  get_config_value("AWS_SECRETS_MANAGER_ENDPOINT").filter(|endpoint| !endpoint.is_empty())
+}
+
+pub fn get_aws_sts_endpoint() -> String {
+  get_env_value!("AWS_STS_ENDPOINT", "https://sts.amazonaws.com")
+}
+
+pub fn get_aws_access_key_id() -> Option<String> {
+  get_config_value("AWS_ACCESS_KEY_ID").filter(|key| !key.is_empty())
+}
+
+pub fn get_aws_secret_access_key() -> Option<String> {
+  get_config_value("AWS_SECRET_ACCESS_KEY").filter(|key| !key.is_empty())
+}
+
+pub fn get_aws_session_token() -> Option<String> {
+  get_config_value("AWS_SESSION_TOKEN").filter(|token| !token.is_empty())
+}
+
+/// Role assumed with the web identity token, as set up by EKS for the service accounts
+pub fn get_aws_role_arn() -> Option<String> {
+  get_config_value("AWS_ROLE_ARN").filter(|role_arn| !role_arn.is_empty())
+}
+
+pub fn get_aws_web_identity_token_file() -> Option<String> {
+  get_config_value("AWS_WEB_IDENTITY_TOKEN_FILE").filter(|path| !path.is_empty())
+}
+
+/// Project of the secrets of the `GCP_SECRET_MANAGER` store, the project of the instance by default
+pub fn get_gcp_secret_manager_project() -> Option<String> {
+  get_config_value("GCP_SECRET_MANAGER_PROJECT").filter(|project| !project.is_empty())
+}
+
+pub fn get_gcp_secret_manager_endpoint() -> String {
+  get_env_value!(
+    "GCP_SECRET_MANAGER_ENDPOINT",
+    "https://secretmanager.googleapis.com"
+  )
+}
+
+/// Metadata server giving the access token of the workload identity
+pub fn get_gcp_metadata_hostname() -> String {
+  get_env_value!("GCP_METADATA_HOSTNAME", "http://metadata.google.internal")
+}
+
 pub fn get_store_hostname(store_code: &str) -> String {
   get_env_value!(
     &format!("{}_HOSTNAME", store_code),
@@ -50,6 +687,12 @@ pub fn get_store_hostname(store_code: &str) -> String {
   )
 }
 
+/// A backend store is known once its hostname is configured, the `BACKEND` store having a default one
+pub fn is_store_configured(store_code: &str) -> bool {
+  store_code.eq_ignore_ascii_case("BACKEND")
+    || get_config_value(&format!("{}_HOSTNAME", store_code)).is_some()
+}
+
 pub fn get_store_username(store_code: &str) -> String {
   get_env_value!(&format!("{}_USERNAME", store_code), "")
 }
@@ -58,10 +701,64 @@ pub fn get_store_password(store_code: &str) -> String {
   get_env_value!(&format!("{}_PASSWORD", store_code), "")
 }
 
-pub fn get_amqp_uri() -> AMQPUri {
-  let amqp_tls = get_amqp_tls();
-  let amqp_hostname = get_amqp_hostname();
+pub fn get_order_signature_key() -> Option<String> {
+  get_config_value("ORDER_SIGNATURE_KEY")
+}
+
+pub fn get_response_encryption_key() -> Option<String> {
+  get_config_value("RESPONSE_ENCRYPTION_KEY")
+}
+
+/// Broker endpoints, as comma separated `host` or `host:port` in `AMQP_HOSTNAME`
+fn get_amqp_endpoints() -> Vec<(String, u16)> {
   let amqp_port = get_amqp_port();
+  let endpoints: Vec<(String, u16)> = get_amqp_hostname()
+    .split(',')
+    .map(|endpoint| endpoint.trim())
+    .filter(|endpoint| !endpoint.is_empty())
+    .map(|endpoint| match split_amqp_endpoint(endpoint) {
+      (host, Some(port)) => match port.parse::<u16>() {
+        Ok(port) => (host, port),
+        _ => (endpoint.to_string(), amqp_port),
+      },
+      (host, None) => (host, amqp_port),
+    })
+    .collect();
+
+  if endpoints.is_empty() {
+    vec![("127.0.0.1".to_string(), amqp_port)]
+  } else {
+    endpoints
+  }
+}
+
+/// Host and port of a broker endpoint, the IPv6 addresses being bracketed like `[::1]:5672`
+fn split_amqp_endpoint(endpoint: &str) -> (String, Option<&str>) {
+  if endpoint.starts_with('[') {
+    return match endpoint.split_once(']') {
+      Some((host, "")) => (format!("{}]", host), None),
+      Some((host, port)) => (
+        format!("{}]", host),
+        Some(port.strip_prefix(':').unwrap_or(port)),
+      ),
+      None => (endpoint.to_string(), None),
+    };
+  }
+
+  // an IPv6 address without brackets has no port
+  if endpoint.matches(':').count() > 1 {
+    return (format!("[{}]", endpoint), None);
+  }
+
+  match endpoint.rsplit_once(':') {
+    Some((host, port)) => (host.to_string(), Some(port)),
+    None => (endpoint.to_string(), None),
+  }
+}
+
+pub fn get_amqp_uris() -> Vec<AMQPUri> {
+  let amqp_tls = get_amqp_tls();
+  let amqp_endpoints = get_amqp_endpoints();
   let amqp_username = get_amqp_username();
   let amqp_password = get_amqp_password();
   let amqp_vhost = get_amqp_vhost();
@@ -69,8 +766,10 @@ pub fn get_amqp_uri() -> AMQPUri {
 
   info!("Start connection with configuration:");
   info!("AMQP TLS: {}", amqp_tls);
-  info!("AMQP HOSTNAME: {}", amqp_hostname);
-  info!("AMQP PORT: {}", amqp_port);
+  for (amqp_hostname, amqp_port) in &amqp_endpoints {
+    info!("AMQP HOSTNAME: {}", amqp_hostname);
+    info!("AMQP PORT: {}", amqp_port);
+  }
   info!("AMQP USERNAME: {}", amqp_username);
   info!("AMQP VIRTUAL HOST: {}", amqp_vhost);
   info!("AMQP QUEUE: {}", amqp_queue);
@@ -81,32 +780,292 @@ pub fn get_amqp_uri() -> AMQPUri {
     AMQPScheme::AMQP
   };
 
-  AMQPUri {
-    scheme,
-    authority: AMQPAuthority {
-      userinfo: AMQPUserInfo {
-        username: amqp_username,
-        password: amqp_password,
+  amqp_endpoints
+    .into_iter()
+    .map(|(amqp_hostname, amqp_port)| AMQPUri {
+      scheme: scheme.clone(),
+      authority: AMQPAuthority {
+        userinfo: AMQPUserInfo {
+          username: amqp_username.clone(),
+          password: amqp_password.clone(),
+        },
+        host: amqp_hostname,
+        port: amqp_port,
       },
-      host: amqp_hostname,
-      port: amqp_port,
-    },
-    vhost: amqp_vhost,
-    query: Default::default(),
-  }
+      vhost: amqp_vhost.clone(),
+      query: Default::default(),
+    })
+    .collect()
 }
 
 pub fn get_source_orders() -> Option<Vec<String>> {
-  env::var("SOURCE_ORDERS")
-    .map(|source_orders| {
-      Some(
-        source_orders
-          .split(':')
-          .map(|path| path.to_string())
-          .collect(),
-      )
-    })
-    .unwrap_or(None)
+  get_config_value("SOURCE_ORDERS").map(|source_orders| {
+    source_orders
+      .split(':')
+      .map(|path| path.to_string())
+      .collect()
+  })
+}
+
+const BOOLEAN_KEYS: &[&str] = &[
+  "AMQP_TLS",
+  "WATCHDOG_RESTART",
+  "HANDLE_TERMINATION_SIGNALS",
+  "CHAOS_MODE",
+  "HTTP_CLIENT_ACCEPT_INVALID_CERTIFICATES",
+];
+
+const POSITIVE_INTEGER_KEYS: &[&str] = &[
+  "MAX_CONCURRENT_JOBS",
+  "HEARTBEAT_INTERVAL",
+  "JOB_LOG_CAPTURE_SIZE",
+];
+
+const INTEGER_KEYS: &[&str] = &[
+  "JOB_TIMEOUT",
+  "JOB_TIMEOUT_MAX_REQUEUES",
+  "DESCRIBE_CACHE_TTL",
+  "RERUN_LOOKAHEAD",
+  "REQUIREMENTS_URL_TIMEOUT",
+  "SOURCE_MAX_BANDWIDTH",
+  "TRANSIENT_MAX_RETRIES",
+  "TRANSIENT_RETRY_DELAY",
+  "INIT_MAX_RETRIES",
+  "INIT_RETRY_DELAY",
+  "WATCHDOG_TIMEOUT",
+  "CHAOS_LATENCY_MAX",
+  "CHAOS_PUBLISH_DELAY_MAX",
+  "WORKSPACE_QUOTA",
+  "ADMISSION_MIN_FREE_MEMORY",
+  "ADMISSION_MIN_FREE_DISK",
+  "CLAIM_REQUEUE_DELAY",
+  "DELIVERY_LEASE_RENEWAL_INTERVAL",
+  "ADMISSION_REQUEUE_DELAY",
+  "REQUIREMENTS_REQUEUE_DELAY",
+  "HTTP_CLIENT_TIMEOUT",
+  "HTTP_CLIENT_MAX_RETRIES",
+  "HTTP_CLIENT_RETRY_DELAY",
+  "PARAMETER_REFERENCE_MAX_SIZE",
+];
+
+const PROBABILITY_KEYS: &[&str] = &[
+  "CHAOS_LATENCY_PROBABILITY",
+  "CHAOS_FAILURE_PROBABILITY",
+  "CHAOS_TRANSIENT_PROBABILITY",
+  "CHAOS_PUBLISH_DELAY_PROBABILITY",
+];
+
+const CHOICE_KEYS: &[(&str, &[&str])] = &[
+  ("JOB_TIMEOUT_POLICY", &["ack", "requeue", "dead_letter"]),
+  ("REQUIREMENTS_REQUEUE_POLICY", &["reject", "back_of_queue"]),
+  (
+    "DESCRIBE_FORMAT",
+    &["json", "json-compact", "yaml", "schema-only"],
+  ),
+  ("LOG_FORMAT", &["text", "json"]),
+  ("JOB_LOG_CAPTURE", &["none", "queue", "result"]),
+];
+
+const URL_KEYS: &[&str] = &[
+  "BACKEND_HOSTNAME",
+  "HASHICORP_VAULT_HOSTNAME",
+  "GCP_METADATA_HOSTNAME",
+  "AWS_STS_ENDPOINT",
+  "AWS_SECRETS_MANAGER_ENDPOINT",
+  "HTTP_CLIENT_PROXY",
+  "WORKER_REGISTRATION_URL",
+  "OTEL_EXPORTER_OTLP_ENDPOINT",
+];
+
+/// Length of the consumer tags, less the `status_` prefix of the direct messaging one
+const MAX_INSTANCE_NAME_LENGTH: usize = 248;
+
+/// Every invalid value of the runtime configuration, instead of the silent fallback on the defaults
+pub fn validate() -> Result<(), Vec<String>> {
+  let mut keys: Vec<String> = env::vars().map(|(key, _)| key).collect();
+  keys.extend(CONFIG_OVERRIDES.read().unwrap().keys().cloned());
+  keys.sort();
+  keys.dedup();
+
+  let errors = check_configuration(&keys, get_config_value);
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+/// The unset values are valid, the defaults apply
+fn check_configuration<F: Fn(&str) -> Option<String>>(
+  keys: &[String],
+  get_value: F,
+) -> Vec<String> {
+  let mut errors = vec![];
+
+  if let Some(value) = get_value("AMQP_PORT") {
+    if value.parse::<u16>().map(|port| port == 0).unwrap_or(true) {
+      errors.push(format!("AMQP_PORT: invalid port {:?}", value));
+    }
+  }
+
+  if let Some(value) = get_value("AMQP_HOSTNAME") {
+    for endpoint in value.split(',').map(str::trim) {
+      if endpoint.is_empty() {
+        errors.push(format!("AMQP_HOSTNAME: empty host in {:?}", value));
+      } else if let (_, Some(port)) = split_amqp_endpoint(endpoint) {
+        if port.parse::<u16>().map(|port| port == 0).unwrap_or(true) {
+          errors.push(format!("AMQP_HOSTNAME: invalid port in {:?}", endpoint));
+        }
+      }
+    }
+  }
+
+  for key in BOOLEAN_KEYS {
+    if let Some(value) = get_value(key) {
+      if !matches!(
+        value.as_str(),
+        "true" | "1" | "True" | "TRUE" | "false" | "0" | "False" | "FALSE"
+      ) {
+        errors.push(format!("{}: expected a boolean, got {:?}", key, value));
+      }
+    }
+  }
+
+  for key in POSITIVE_INTEGER_KEYS {
+    if let Some(value) = get_value(key) {
+      if value.parse::<u64>().map(|value| value == 0).unwrap_or(true) {
+        errors.push(format!(
+          "{}: expected a positive integer, got {:?}",
+          key, value
+        ));
+      }
+    }
+  }
+
+  for key in INTEGER_KEYS {
+    if let Some(value) = get_value(key) {
+      if value.parse::<u64>().is_err() {
+        errors.push(format!("{}: expected an integer, got {:?}", key, value));
+      }
+    }
+  }
+
+  for key in PROBABILITY_KEYS {
+    if let Some(value) = get_value(key) {
+      if !value
+        .parse::<f64>()
+        .map(|probability| (0.0..=1.0).contains(&probability))
+        .unwrap_or(false)
+      {
+        errors.push(format!(
+          "{}: expected a probability between 0 and 1, got {:?}",
+          key, value
+        ));
+      }
+    }
+  }
+
+  for key in &["HTTP_CLIENT_RATE_LIMIT", "ADMISSION_MAX_CPU_LOAD"] {
+    if let Some(value) = get_value(key) {
+      if value
+        .parse::<f64>()
+        .map(|value| value <= 0.0)
+        .unwrap_or(true)
+      {
+        errors.push(format!(
+          "{}: expected a positive number, got {:?}",
+          key, value
+        ));
+      }
+    }
+  }
+
+  for (key, choices) in CHOICE_KEYS {
+    if let Some(value) = get_value(key) {
+      if !choices.contains(&value.as_str()) {
+        errors.push(format!(
+          "{}: expected one of {}, got {:?}",
+          key,
+          choices.join(", "),
+          value
+        ));
+      }
+    }
+  }
+
+  // the hostnames of the HTTP services, like the backend stores configured with their credentials,
+  // the other `*_HOSTNAME` variables of the environment being unrelated to the SDK
+  let store_keys = keys.iter().filter_map(|key| {
+    let store_code = key.strip_suffix("_HOSTNAME")?;
+    let has_credentials = [
+      format!("{}_USERNAME", store_code),
+      format!("{}_PASSWORD", store_code),
+    ]
+    .iter()
+    .any(|credential_key| keys.contains(credential_key));
+    if !store_code.is_empty()
+      && store_code != "AMQP"
+      && has_credentials
+      && !URL_KEYS.contains(&key.as_str())
+    {
+      Some(key.as_str())
+    } else {
+      None
+    }
+  });
+  let url_keys = URL_KEYS.iter().cloned().chain(store_keys);
+  for key in url_keys {
+    if let Some(value) = get_value(key) {
+      match reqwest::Url::parse(&value) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        Ok(url) => errors.push(format!(
+          "{}: unsupported scheme {:?}, expected HTTP(S)",
+          key,
+          url.scheme()
+        )),
+        Err(error) => errors.push(format!("{}: malformed URL {:?}: {}", key, value, error)),
+      }
+    }
+  }
+
+  #[cfg(feature = "media")]
+  for key in &["MAX_VIDEO_RESOLUTION", "MAX_SOURCE_RESOLUTION"] {
+    if let Some(value) = get_value(key) {
+      if parse_resolution(&value).is_none() {
+        errors.push(format!(
+          "{}: expected <width>x<height>, got {:?}",
+          key, value
+        ));
+      }
+    }
+  }
+
+  // the consumer tags are AMQP short strings, of 255 bytes at most with their prefix
+  if let Some(value) = get_value("INSTANCE_NAME") {
+    if value.len() > MAX_INSTANCE_NAME_LENGTH {
+      errors.push(format!(
+        "INSTANCE_NAME: longer than {} bytes",
+        MAX_INSTANCE_NAME_LENGTH
+      ));
+    }
+  }
+
+  if let Some(value) = get_value("PROCESSING_WINDOWS") {
+    if let Err(error) = crate::worker::processing_window::ProcessingWindows::new(&value) {
+      errors.push(format!("PROCESSING_WINDOWS: {:?}", error));
+    }
+  }
+
+  if let Some(path) = get_value("HTTP_CLIENT_CA_CERTIFICATE") {
+    if !PathBuf::from(&path).is_file() {
+      errors.push(format!(
+        "HTTP_CLIENT_CA_CERTIFICATE: file {:?} not found",
+        path
+      ));
+    }
+  }
+
+  errors
 }
 
 #[test]
@@ -118,6 +1077,13 @@ fn configuration() {
   assert!(get_amqp_password() == "guest".to_string());
   assert!(get_amqp_vhost() == "/".to_string());
   assert!(get_amqp_queue() == "job_undefined".to_string());
+  assert!(get_max_concurrent_jobs() == 1);
+  assert!(get_transient_max_retries() == 3);
+  assert!(get_transient_retry_delay() == 1000);
+  assert!(get_init_max_retries() == 0);
+  assert!(get_init_retry_delay() == 5000);
+  assert!(get_workspace_root() == env::temp_dir());
+  assert!(get_workspace_quota() == None);
   assert!(get_store_hostname("BACKEND") == "http://127.0.0.1:4000/api".to_string());
   assert!(get_store_username("BACKEND") == "".to_string());
   assert!(get_store_password("BACKEND") == "".to_string());
@@ -126,4 +1092,116 @@ fn configuration() {
   assert!(get_amqp_tls() == false);
   env::set_var("AMQP_PORT", "BAD_VALUE");
   assert!(get_amqp_port() == 5672);
+  env::set_var("MAX_CONCURRENT_JOBS", "4");
+  assert!(get_max_concurrent_jobs() == 4);
+  assert!(get_effective_max_concurrent_jobs() == if cfg!(feature = "media") { 1 } else { 4 });
+  env::set_var("MAX_CONCURRENT_JOBS", "0");
+  assert!(get_max_concurrent_jobs() == 1);
+}
+
+#[test]
+#[cfg(feature = "media")]
+fn resolution() {
+  assert_eq!(None, get_max_video_resolution());
+  assert_eq!((16384, 16384), get_max_source_resolution());
+
+  assert_eq!(Some((3840, 2160)), parse_resolution("3840x2160"));
+  assert_eq!(None, parse_resolution("3840"));
+  assert_eq!(None, parse_resolution("0x2160"));
+  assert_eq!(None, parse_resolution("3840x2160x1"));
+  assert_eq!(None, parse_resolution("widthxheight"));
+}
+
+#[test]
+fn sdk_config() {
+  assert_eq!(None, get_config_value("SDK_CONFIG_TEST"));
+
+  let config = SdkConfig::new()
+    .with_value("SDK_CONFIG_TEST", "configured")
+    .with_amqp_port(5671)
+    .with_amqp_tls(false);
+  assert_eq!(Some(&"5671".to_string()), config.get_value("AMQP_PORT"));
+  assert_eq!(Some(&"false".to_string()), config.get_value("AMQP_TLS"));
+
+  env::set_var("SDK_CONFIG_TEST_FALLBACK", "environment");
+  SdkConfig::new()
+    .with_value("SDK_CONFIG_TEST", "configured")
+    .apply();
+  assert_eq!("configured", get_env_value!("SDK_CONFIG_TEST", "default"));
+  assert_eq!(
+    "environment",
+    get_env_value!("SDK_CONFIG_TEST_FALLBACK", "default")
+  );
+  assert_eq!(
+    "default",
+    get_env_value!("SDK_CONFIG_TEST_MISSING", "default")
+  );
+}
+
+#[test]
+fn configuration_validation() {
+  let instance_name = "transcoder-".repeat(25);
+  let values: BTreeMap<&str, &str> = vec![
+    ("AMQP_PORT", "BAD_VALUE"),
+    (
+      "AMQP_HOSTNAME",
+      "rabbitmq-0:5672,rabbitmq-1:port,[::1]:5672,[fd00::2],fd00::3,[fd00::4]:0",
+    ),
+    ("AMQP_TLS", "yes"),
+    ("MAX_CONCURRENT_JOBS", "0"),
+    ("JOB_TIMEOUT", "3600"),
+    ("HTTP_CLIENT_RATE_LIMIT", "2.5"),
+    ("CHAOS_FAILURE_PROBABILITY", "2"),
+    ("JOB_TIMEOUT_POLICY", "retry"),
+    ("BACKEND_HOSTNAME", "backend:4000/api"),
+    ("MEDIA_HOSTNAME", "https://media.example.com/api"),
+    ("MEDIA_USERNAME", "media"),
+    ("ASSETS_HOSTNAME", "assets:4000"),
+    ("ASSETS_PASSWORD", "secret"),
+    ("POSTGRES_SERVICE_HOSTNAME", "postgres:5432"),
+    ("INSTANCE_NAME", &instance_name),
+  ]
+  .into_iter()
+  .collect();
+  let keys: Vec<String> = values.keys().map(|key| key.to_string()).collect();
+
+  let errors = check_configuration(&keys, |key| values.get(key).map(|value| value.to_string()));
+  assert_eq!(
+    vec![
+      "AMQP_PORT: invalid port \"BAD_VALUE\"",
+      "AMQP_HOSTNAME: invalid port in \"rabbitmq-1:port\"",
+      "AMQP_HOSTNAME: invalid port in \"[fd00::4]:0\"",
+      "AMQP_TLS: expected a boolean, got \"yes\"",
+      "MAX_CONCURRENT_JOBS: expected a positive integer, got \"0\"",
+      "CHAOS_FAILURE_PROBABILITY: expected a probability between 0 and 1, got \"2\"",
+      "JOB_TIMEOUT_POLICY: expected one of ack, requeue, dead_letter, got \"retry\"",
+      "BACKEND_HOSTNAME: unsupported scheme \"backend\", expected HTTP(S)",
+      "ASSETS_HOSTNAME: unsupported scheme \"assets\", expected HTTP(S)",
+      "INSTANCE_NAME: longer than 248 bytes",
+    ],
+    errors
+  );
+
+  assert!(check_configuration(&[], |_| None).is_empty());
+}
+
+#[test]
+fn amqp_endpoint_split() {
+  assert_eq!(
+    ("rabbitmq".to_string(), Some("5672")),
+    split_amqp_endpoint("rabbitmq:5672")
+  );
+  assert_eq!(
+    ("rabbitmq".to_string(), None),
+    split_amqp_endpoint("rabbitmq")
+  );
+  assert_eq!(
+    ("[::1]".to_string(), Some("5672")),
+    split_amqp_endpoint("[::1]:5672")
+  );
+  assert_eq!(("[::1]".to_string(), None), split_amqp_endpoint("[::1]"));
+  assert_eq!(
+    ("[fd00::2]".to_string(), None),
+    split_amqp_endpoint("fd00::2")
+  );
 }
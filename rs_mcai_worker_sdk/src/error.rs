@@ -2,10 +2,13 @@ use crate::job::{JobResult, JobStatus};
 
 /// Internal error status to manage process errors
 #[derive(Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum MessageError {
   RuntimeError(String),
   ParameterValueError(String),
   ProcessingError(JobResult),
+  /// Temporary failure, the order is retried with backoff before publishing the error
+  Transient(JobResult),
   RequirementsError(String),
   NotImplemented(),
 }
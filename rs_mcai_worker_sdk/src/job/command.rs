@@ -0,0 +1,339 @@
+//! Execution of external programs (ffmpeg, imagemagick...) by the jobs
+//!
+//! The output lines of the program are written in the job log, and can be parsed into progressions.
+//! The last lines of its outputs are kept, up to `with_max_output_size` bytes per output.
+//! The program is killed when the job is cancelled, its timeout is reached or the job fails,
+//! and an exit code other than 0 and the accepted ones fails the job with its last error lines.
+//!
+//! ```ignore
+//! let output = Command::new("ffmpeg")
+//!   .with_args(&["-i", &source_path, &destination_path])
+//!   .with_timeout(Duration::from_secs(3600))
+//!   .with_progression(|line| parse_ffmpeg_progression(line))
+//!   .run(channel, &context)?;
+//! ```
+
+use crate::job::{JobContext, JobResult, JobStatus};
+use crate::{McaiChannel, MessageError, Result};
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{self, Child, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval between two checks of the cancellation and the timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of error lines reported when the program fails
+const ERROR_LINES: usize = 20;
+
+/// Maximum number of bytes kept of each output by default
+const MAX_OUTPUT_SIZE: usize = 1024 * 1024;
+
+type ProgressionParser = Box<dyn Fn(&str) -> Option<u8>>;
+
+enum OutputLine {
+  Stdout(String),
+  Stderr(String),
+}
+
+pub struct Command {
+  program: String,
+  command: process::Command,
+  has_current_dir: bool,
+  timeout: Option<Duration>,
+  progression: Option<ProgressionParser>,
+  accepted_exit_codes: Vec<i32>,
+  max_output_size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandOutput {
+  pub stdout: String,
+  pub stderr: String,
+  /// None if the program was terminated by a signal
+  pub exit_code: Option<i32>,
+}
+
+/// Running program, killed if the job fails before it exits
+struct ChildGuard {
+  child: Child,
+  exited: bool,
+}
+
+impl ChildGuard {
+  fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+    let status = self.child.try_wait()?;
+    self.exited = status.is_some();
+    Ok(status)
+  }
+}
+
+impl Drop for ChildGuard {
+  fn drop(&mut self) {
+    if !self.exited {
+      let _ = self.child.kill();
+      let _ = self.child.wait();
+    }
+  }
+}
+
+impl Command {
+  pub fn new(program: &str) -> Self {
+    Command {
+      program: program.to_string(),
+      command: process::Command::new(program),
+      has_current_dir: false,
+      timeout: None,
+      progression: None,
+      accepted_exit_codes: vec![],
+      max_output_size: MAX_OUTPUT_SIZE,
+    }
+  }
+
+  pub fn with_arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+    self.command.arg(arg);
+    self
+  }
+
+  pub fn with_args<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(mut self, args: I) -> Self {
+    self.command.args(args);
+    self
+  }
+
+  pub fn with_env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+    self.command.env(key, value);
+    self
+  }
+
+  /// Working directory of the program (default: the job workspace)
+  pub fn with_current_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+    self.command.current_dir(path);
+    self.has_current_dir = true;
+    self
+  }
+
+  /// Maximum duration of the program, killed once reached
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// Parse the progression of the job, from 0 to 100, from the output lines
+  pub fn with_progression<F: 'static + Fn(&str) -> Option<u8>>(mut self, parser: F) -> Self {
+    self.progression = Some(Box::new(parser));
+    self
+  }
+
+  /// Exit codes other than 0 not failing the job, like `1` for `grep` without match
+  pub fn with_accepted_exit_codes(mut self, exit_codes: &[i32]) -> Self {
+    self.accepted_exit_codes = exit_codes.to_vec();
+    self
+  }
+
+  /// Maximum number of bytes kept of each output, the first lines are dropped beyond (default: 1 MiB)
+  pub fn with_max_output_size(mut self, max_output_size: usize) -> Self {
+    self.max_output_size = max_output_size;
+    self
+  }
+
+  /// Run the program until it exits, the job is failed if its exit code is not accepted
+  pub fn run(
+    mut self,
+    channel: Option<McaiChannel>,
+    context: &JobContext,
+  ) -> Result<CommandOutput> {
+    let job_id = context.get_job_id();
+
+    if !self.has_current_dir {
+      if let Some(workspace) = context.get_workspace() {
+        self.command.current_dir(workspace.get_path());
+      }
+    }
+
+    info!(target: &job_id.to_string(), "Run {:?}", self.command);
+    let child = self
+      .command
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|error| {
+        get_error(
+          job_id,
+          &format!("Unable to run {}: {}", self.program, error),
+        )
+      })?;
+    let mut child = ChildGuard {
+      child,
+      exited: false,
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    read_lines(
+      child.child.stdout.take(),
+      OutputLine::Stdout,
+      sender.clone(),
+    );
+    read_lines(child.child.stderr.take(), OutputLine::Stderr, sender);
+
+    let started = Instant::now();
+    let mut output = CommandOutput {
+      stdout: String::new(),
+      stderr: String::new(),
+      exit_code: None,
+    };
+    let mut last_progression = None;
+
+    let status = loop {
+      match receiver.recv_timeout(POLL_INTERVAL) {
+        Ok(line) => {
+          context.heartbeat();
+
+          let (line, buffer) = match line {
+            OutputLine::Stdout(line) => (line, &mut output.stdout),
+            OutputLine::Stderr(line) => (line, &mut output.stderr),
+          };
+          let line = line.trim_end();
+          append_line(buffer, line, self.max_output_size);
+          info!(target: &job_id.to_string(), "{}: {}", self.program, line);
+
+          // the program is killed by its guard if the progression can not be published
+          let progression = self.progression.as_ref().and_then(|parser| parser(line));
+          if progression.is_some() && progression != last_progression {
+            last_progression = progression;
+            crate::message::publish_job_progression(
+              channel.clone(),
+              job_id,
+              progression.unwrap_or_default().min(100),
+            )?;
+          }
+        }
+        Err(RecvTimeoutError::Timeout) => {}
+        // the outputs are closed, the program is exiting
+        Err(RecvTimeoutError::Disconnected) => {
+          if let Some(status) = child.try_wait().map_err(|error| {
+            get_error(
+              job_id,
+              &format!("Unable to wait for {}: {}", self.program, error),
+            )
+          })? {
+            break status;
+          }
+          thread::sleep(POLL_INTERVAL);
+        }
+      }
+
+      // the program is killed by its guard on cancellation and timeout
+      context.check_cancelled()?;
+
+      if let Some(timeout) = self.timeout {
+        if started.elapsed() > timeout {
+          return Err(get_error(
+            job_id,
+            &format!(
+              "{} timed out after {} seconds",
+              self.program,
+              timeout.as_secs()
+            ),
+          ));
+        }
+      }
+    };
+
+    check_status(
+      job_id,
+      &self.program,
+      status,
+      &self.accepted_exit_codes,
+      &output.stderr,
+    )?;
+    output.exit_code = status.code();
+    Ok(output)
+  }
+}
+
+fn read_lines<R: 'static + Read + Send, F: 'static + Fn(String) -> OutputLine + Send>(
+  reader: Option<R>,
+  output_line: F,
+  sender: Sender<OutputLine>,
+) {
+  if let Some(reader) = reader {
+    thread::spawn(move || {
+      for line in BufReader::new(reader).lines().map_while(|line| line.ok()) {
+        if sender.send(output_line(line)).is_err() {
+          break;
+        }
+      }
+    });
+  }
+}
+
+/// Append the line to the output, dropping its first lines beyond the maximum size
+fn append_line(output: &mut String, line: &str, max_output_size: usize) {
+  output.push_str(line);
+  output.push('\n');
+
+  if output.len() > max_output_size {
+    let excess = output.len() - max_output_size;
+    // the output is cut after the end of line preceding the excess, if any
+    let start = output.as_bytes()[excess - 1..]
+      .iter()
+      .position(|byte| *byte == b'\n')
+      .map(|position| excess + position)
+      .unwrap_or_else(|| output.len());
+    output.drain(..start);
+  }
+}
+
+fn check_status(
+  job_id: u64,
+  program: &str,
+  status: ExitStatus,
+  accepted_exit_codes: &[i32],
+  stderr: &str,
+) -> Result<()> {
+  let accepted = status
+    .code()
+    .map(|code| accepted_exit_codes.contains(&code))
+    .unwrap_or(false);
+  if status.success() || accepted {
+    return Ok(());
+  }
+
+  let exit = match status.code() {
+    Some(code) => format!("exited with code {}", code),
+    None => "was terminated by a signal".to_string(),
+  };
+
+  let lines: Vec<&str> = stderr.lines().collect();
+  let error_lines = lines[lines.len().saturating_sub(ERROR_LINES)..].join("\n");
+
+  Err(get_error(
+    job_id,
+    &format!("{} {}: {}", program, exit, error_lines),
+  ))
+}
+
+fn get_error(job_id: u64, message: &str) -> MessageError {
+  let job_result = JobResult::new(job_id)
+    .with_status(JobStatus::Error)
+    .with_message(message);
+  MessageError::ProcessingError(job_result)
+}
+
+#[test]
+pub fn test_append_line() {
+  let mut output = String::new();
+  append_line(&mut output, "first", 13);
+  append_line(&mut output, "second", 13);
+  assert_eq!("first\nsecond\n", output);
+
+  append_line(&mut output, "third", 13);
+  assert_eq!("second\nthird\n", output);
+
+  append_line(&mut output, "a line longer than the maximum", 13);
+  assert_eq!("", output);
+}
@@ -0,0 +1,118 @@
+use crate::ParameterValue;
+use std::time::Instant;
+
+/// Parameter of the result of a completed job, with the resources it used
+pub const EXECUTION_METRICS_PARAMETER: &str = "execution_metrics";
+
+/// Resources used by a job, attached to its result for the performance dashboards of the orchestrator
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionMetrics {
+  /// Wall-clock duration of the processing, in seconds
+  pub wall_clock_time: f64,
+  /// CPU time (user and system) of the worker process during the processing, in seconds
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cpu_time: Option<f64>,
+  /// Peak resident memory of the worker process, in bytes
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub peak_rss: Option<u64>,
+  /// Frames of the first stream processed (`media` feature)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub frames_processed: Option<u64>,
+}
+
+impl ParameterValue for ExecutionMetrics {
+  fn get_type_as_string() -> String {
+    "execution_metrics".to_string()
+  }
+}
+
+/// Measure of the resources used by a job since its processing started
+pub(crate) struct ExecutionRecorder {
+  started: Instant,
+  cpu_time: Option<f64>,
+}
+
+impl ExecutionRecorder {
+  pub(crate) fn start() -> Self {
+    ExecutionRecorder {
+      started: Instant::now(),
+      cpu_time: get_cpu_time(),
+    }
+  }
+
+  pub(crate) fn finish(&self, frames_processed: Option<u64>) -> ExecutionMetrics {
+    let cpu_time = match (self.cpu_time, get_cpu_time()) {
+      (Some(started), Some(finished)) => Some((finished - started).max(0.0)),
+      _ => None,
+    };
+
+    ExecutionMetrics {
+      wall_clock_time: self.started.elapsed().as_secs_f64(),
+      cpu_time,
+      peak_rss: get_peak_rss(),
+      frames_processed,
+    }
+  }
+}
+
+#[cfg(unix)]
+fn get_resource_usage() -> Option<libc::rusage> {
+  let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+  if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+    return None;
+  }
+  Some(unsafe { usage.assume_init() })
+}
+
+#[cfg(unix)]
+fn get_cpu_time() -> Option<f64> {
+  let to_seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+  get_resource_usage().map(|usage| to_seconds(usage.ru_utime) + to_seconds(usage.ru_stime))
+}
+
+/// The peak resident memory is given in kilobytes, in bytes on macOS
+#[cfg(unix)]
+fn get_peak_rss() -> Option<u64> {
+  let max_rss = get_resource_usage()?.ru_maxrss.max(0) as u64;
+  if cfg!(target_os = "macos") {
+    Some(max_rss)
+  } else {
+    Some(max_rss * 1024)
+  }
+}
+
+#[cfg(not(unix))]
+fn get_cpu_time() -> Option<f64> {
+  None
+}
+
+#[cfg(not(unix))]
+fn get_peak_rss() -> Option<u64> {
+  None
+}
+
+#[test]
+pub fn test_execution_recorder() {
+  let recorder = ExecutionRecorder::start();
+  let mut value: u64 = 0;
+  for index in 0..1_000_000u64 {
+    value = value.wrapping_add(index * index);
+  }
+  assert!(value > 0);
+
+  let metrics = recorder.finish(Some(25));
+  assert!(metrics.wall_clock_time > 0.0);
+  assert_eq!(Some(25), metrics.frames_processed);
+  #[cfg(unix)]
+  {
+    assert!(metrics.cpu_time.unwrap() >= 0.0);
+    assert!(metrics.peak_rss.unwrap() > 0);
+  }
+
+  let json = serde_json::to_value(ExecutionMetrics {
+    wall_clock_time: 1.5,
+    ..Default::default()
+  })
+  .unwrap();
+  assert_eq!(json!({"wall_clock_time": 1.5}), json);
+}
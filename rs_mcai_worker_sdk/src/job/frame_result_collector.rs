@@ -0,0 +1,207 @@
+use crate::job::JobResult;
+use crate::parameter::frame_result::{
+  FrameAggregate, FrameAggregates, FrameResult, FrameResults, Histogram,
+};
+use serde_json::Value;
+
+type FrameResultSink = Box<dyn Fn(&FrameResult) + Send>;
+
+/// Aggregation of a numeric field of the frame result payloads
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregation {
+  field: String,
+  stream_index: Option<usize>,
+  histogram: Option<(f64, f64, usize)>,
+}
+
+impl Aggregation {
+  /// Field of the payload, as a key or a JSON pointer (`/loudness/momentary`)
+  pub fn new(field: &str) -> Self {
+    Aggregation {
+      field: field.to_string(),
+      stream_index: None,
+      histogram: None,
+    }
+  }
+
+  /// Only aggregate the results of the stream
+  pub fn for_stream(mut self, stream_index: usize) -> Self {
+    self.stream_index = Some(stream_index);
+    self
+  }
+
+  /// Count the values in bins between `min` and `max`, the values out of range are counted in the first or last bin
+  pub fn with_histogram(mut self, min: f64, max: f64, bins: usize) -> Self {
+    self.histogram = Some((min, max, bins.max(1)));
+    self
+  }
+
+  fn get_value(&self, frame_result: &FrameResult) -> Option<f64> {
+    if self
+      .stream_index
+      .map(|stream_index| stream_index != frame_result.stream_index)
+      .unwrap_or(false)
+    {
+      return None;
+    }
+
+    let value = if self.field.starts_with('/') {
+      frame_result.payload.pointer(&self.field)
+    } else {
+      frame_result.payload.get(&self.field)
+    };
+    value.and_then(Value::as_f64)
+  }
+}
+
+struct AggregationState {
+  name: String,
+  aggregation: Aggregation,
+  count: u64,
+  min: f64,
+  max: f64,
+  sum: f64,
+  bins: Vec<u64>,
+}
+
+impl AggregationState {
+  fn new(name: &str, aggregation: Aggregation) -> Self {
+    let bins = aggregation
+      .histogram
+      .map(|(_, _, bins)| vec![0; bins])
+      .unwrap_or_default();
+
+    AggregationState {
+      name: name.to_string(),
+      aggregation,
+      count: 0,
+      min: f64::INFINITY,
+      max: f64::NEG_INFINITY,
+      sum: 0.0,
+      bins,
+    }
+  }
+
+  fn push(&mut self, frame_result: &FrameResult) {
+    let value = match self.aggregation.get_value(frame_result) {
+      Some(value) => value,
+      None => return,
+    };
+
+    self.count += 1;
+    self.min = self.min.min(value);
+    self.max = self.max.max(value);
+    self.sum += value;
+
+    if let Some((min, max, bins)) = self.aggregation.histogram {
+      let position = (value - min) / (max - min) * bins as f64;
+      let bin = (position.max(0.0) as usize).min(bins - 1);
+      self.bins[bin] += 1;
+    }
+  }
+
+  fn get_aggregate(&self) -> FrameAggregate {
+    if self.count == 0 {
+      return FrameAggregate::default();
+    }
+
+    FrameAggregate {
+      count: self.count,
+      min: self.min,
+      max: self.max,
+      mean: self.sum / self.count as f64,
+      histogram: self.aggregation.histogram.map(|(min, max, _)| Histogram {
+        min,
+        max,
+        bins: self.bins.clone(),
+      }),
+    }
+  }
+}
+
+/// Accumulation of the frame results of an analysis job
+///
+/// The results are buffered and aggregated, then attached to the job result:
+///
+/// ```ignore
+/// let mut frame_results = FrameResultCollector::new()
+///   .with_aggregation("loudness", Aggregation::new("loudness").with_histogram(-70.0, 0.0, 14));
+///
+/// // for each analysed frame
+/// frame_results.push(FrameResult::new(stream_index, frame.get_pts(), &json!({"loudness": loudness})));
+///
+/// // once the stream is processed
+/// let job_result = frame_results.attach(job_result)?;
+/// ```
+#[derive(Default)]
+pub struct FrameResultCollector {
+  frames: FrameResults,
+  discard_frames: bool,
+  aggregations: Vec<AggregationState>,
+  sink: Option<FrameResultSink>,
+}
+
+impl FrameResultCollector {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_aggregation(mut self, name: &str, aggregation: Aggregation) -> Self {
+    self
+      .aggregations
+      .push(AggregationState::new(name, aggregation));
+    self
+  }
+
+  /// Only keep the aggregates, for long streams
+  pub fn without_frames(mut self) -> Self {
+    self.discard_frames = true;
+    self
+  }
+
+  /// Stream each frame result as soon as it is pushed, like to the `response_sender` of a media worker
+  pub fn with_sink<F: 'static + Fn(&FrameResult) + Send>(mut self, sink: F) -> Self {
+    self.sink = Some(Box::new(sink));
+    self
+  }
+
+  pub fn push(&mut self, frame_result: FrameResult) {
+    if let Some(sink) = &self.sink {
+      sink(&frame_result);
+    }
+
+    for aggregation in &mut self.aggregations {
+      aggregation.push(&frame_result);
+    }
+
+    if !self.discard_frames {
+      self.frames.push(frame_result);
+    }
+  }
+
+  pub fn get_frames(&self) -> &FrameResults {
+    &self.frames
+  }
+
+  pub fn get_aggregates(&self) -> FrameAggregates {
+    self
+      .aggregations
+      .iter()
+      .map(|aggregation| (aggregation.name.clone(), aggregation.get_aggregate()))
+      .collect()
+  }
+
+  /// Add the buffered frames (`frame_results`) and the aggregates (`frame_aggregates`) to the job result
+  pub fn attach(&self, job_result: JobResult) -> Result<JobResult, String> {
+    let job_result = if self.discard_frames {
+      job_result
+    } else {
+      job_result.with_json("frame_results", &self.frames)?
+    };
+
+    if self.aggregations.is_empty() {
+      return Ok(job_result);
+    }
+    job_result.with_json("frame_aggregates", &self.get_aggregates())
+  }
+}
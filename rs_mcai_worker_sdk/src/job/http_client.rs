@@ -0,0 +1,180 @@
+//! HTTP client for the external APIs called by the jobs
+//!
+//! The client is configured from the worker configuration (timeout, proxy, TLS).
+//! Requests are rate limited per host across all the jobs of the worker,
+//! and retried on connection errors, `429 Too Many Requests` and server errors.
+
+use crate::config::*;
+use crate::job::{JobResult, JobStatus};
+use crate::{MessageError, Result};
+use reqwest::{
+  blocking::{Client, RequestBuilder, Response},
+  header::RETRY_AFTER,
+  redirect, Certificate, Proxy, StatusCode,
+};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Next instant a request can be sent to each host
+static RATE_LIMITS: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+
+/// Retry-After delays longer than this are not waited for
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+  job_id: u64,
+  client: Client,
+  max_retries: u32,
+  retry_delay: u64,
+  rate_limit: Option<f64>,
+}
+
+impl HttpClient {
+  pub fn new(job_id: u64) -> Result<Self> {
+    HttpClient::with_redirect_policy(job_id, redirect::Policy::default())
+  }
+
+  /// Client following only the redirects accepted by the policy
+  pub(crate) fn with_redirect_policy(job_id: u64, policy: redirect::Policy) -> Result<Self> {
+    let mut builder = Client::builder()
+      .danger_accept_invalid_certs(get_http_client_accept_invalid_certificates())
+      .redirect(policy);
+
+    if let Some(timeout) = get_http_client_timeout() {
+      builder = builder.timeout(Duration::from_secs(timeout));
+    }
+
+    if let Some(proxy) = get_http_client_proxy() {
+      let proxy = Proxy::all(&proxy)
+        .map_err(|error| MessageError::RuntimeError(format!("Invalid HTTP proxy: {}", error)))?;
+      builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = get_http_client_ca_certificate() {
+      let certificate = std::fs::read(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| Certificate::from_pem(&content).map_err(|error| error.to_string()))
+        .map_err(|error| {
+          MessageError::RuntimeError(format!("Invalid CA certificate {:?}: {}", path, error))
+        })?;
+      builder = builder.add_root_certificate(certificate);
+    }
+
+    let client = builder.build().map_err(|error| {
+      MessageError::RuntimeError(format!("Unable to build the HTTP client: {}", error))
+    })?;
+
+    Ok(HttpClient {
+      job_id,
+      client,
+      max_retries: get_http_client_max_retries(),
+      retry_delay: get_http_client_retry_delay(),
+      rate_limit: get_http_client_rate_limit(),
+    })
+  }
+
+  /// Underlying client, its requests are neither rate limited nor retried
+  pub fn get_client(&self) -> &Client {
+    &self.client
+  }
+
+  pub fn get(&self, url: &str) -> Result<Response> {
+    self.send(|client| client.get(url))
+  }
+
+  /// Send the request built by the closure, called again for each retry
+  ///
+  /// Once the retries are exhausted, a `Transient` error is returned for the order to be retried later.
+  /// Other responses are returned whatever their status.
+  pub fn send<F: Fn(&Client) -> RequestBuilder>(&self, build_request: F) -> Result<Response> {
+    let mut retry = 0;
+
+    loop {
+      let request = build_request(&self.client)
+        .build()
+        .map_err(|error| MessageError::RuntimeError(format!("Invalid HTTP request: {}", error)))?;
+
+      if let (Some(host), Some(rate_limit)) = (request.url().host_str(), self.rate_limit) {
+        wait_rate_limit(host, rate_limit);
+      }
+
+      let url = request.url().to_string();
+      let (failure, retry_after) = match self.client.execute(request) {
+        Ok(response) if is_retryable(response.status()) => {
+          let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .filter(|retry_after| *retry_after <= MAX_RETRY_AFTER);
+          (format!("status {}", response.status()), retry_after)
+        }
+        Ok(response) => return Ok(response),
+        Err(error) => (error.to_string(), None),
+      };
+
+      if retry >= self.max_retries {
+        let job_result = JobResult::new(self.job_id)
+          .with_status(JobStatus::Error)
+          .with_message(&format!(
+            "HTTP request to {} failed after {} retries: {}",
+            url, retry, failure
+          ));
+        return Err(MessageError::Transient(job_result));
+      }
+
+      let delay = retry_after.unwrap_or_else(|| {
+        Duration::from_millis(self.retry_delay.saturating_mul(1 << retry.min(16)))
+      });
+      retry += 1;
+      warn!(target: &self.job_id.to_string(), "HTTP request to {} failed ({}), retry {}/{} in {} ms", url, failure, retry, self.max_retries, delay.as_millis());
+      thread::sleep(delay);
+    }
+  }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+  status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Reserve the next slot of the host, waiting until it is reached
+fn wait_rate_limit(host: &str, rate_limit: f64) {
+  let slot = reserve_slot(host, rate_limit);
+  let now = Instant::now();
+  if slot > now {
+    thread::sleep(slot - now);
+  }
+}
+
+fn reserve_slot(host: &str, rate_limit: f64) -> Instant {
+  let interval = Duration::from_secs_f64(1.0 / rate_limit);
+  let now = Instant::now();
+
+  let mut rate_limits = RATE_LIMITS.lock().unwrap();
+  let slot = rate_limits
+    .get(host)
+    .map(|next| (*next).max(now))
+    .unwrap_or(now);
+  rate_limits.insert(host.to_string(), slot + interval);
+  slot
+}
+
+#[test]
+pub fn test_http_client_rate_limit() {
+  let host = "rate-limit.test";
+  let first = reserve_slot(host, 10.0);
+  let second = reserve_slot(host, 10.0);
+  let third = reserve_slot(host, 10.0);
+
+  assert_eq!(Duration::from_millis(100), second - first);
+  assert_eq!(Duration::from_millis(100), third - second);
+  assert!(reserve_slot("other-host.test", 10.0) < second);
+
+  assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+  assert!(is_retryable(StatusCode::BAD_GATEWAY));
+  assert!(!is_retryable(StatusCode::NOT_FOUND));
+}
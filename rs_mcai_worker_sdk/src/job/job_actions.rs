@@ -0,0 +1,89 @@
+use crate::job::{Job, JobContext, JobResult};
+use crate::worker::parameter_schema_for;
+use crate::{McaiChannel, MessageError, Result};
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+type ActionHandler<ME> =
+  Box<dyn Fn(&ME, Option<McaiChannel>, &Job, JobResult, JobContext) -> Result<JobResult>>;
+
+/// Dispatch table of a worker, processing the orders by their `action`
+///
+/// Each action has its own parameters, parsed from the order:
+///
+/// ```ignore
+/// fn get_actions(&self) -> JobActions<Self> {
+///   JobActions::new()
+///     .with_action("thumbnail", WorkerEvent::process_thumbnail)
+///     .with_action("checksum", WorkerEvent::process_checksum)
+/// }
+/// ```
+pub struct JobActions<ME> {
+  handlers: BTreeMap<String, ActionHandler<ME>>,
+  schemas: BTreeMap<String, RootSchema>,
+}
+
+impl<ME> Default for JobActions<ME> {
+  fn default() -> Self {
+    JobActions {
+      handlers: BTreeMap::new(),
+      schemas: BTreeMap::new(),
+    }
+  }
+}
+
+impl<ME> JobActions<ME> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_action<
+    P: DeserializeOwned + JsonSchema,
+    F: 'static + Fn(&ME, Option<McaiChannel>, P, JobResult, JobContext) -> Result<JobResult>,
+  >(
+    mut self,
+    name: &str,
+    handler: F,
+  ) -> Self {
+    let handler = move |message_event: &ME,
+                        channel: Option<McaiChannel>,
+                        job: &Job,
+                        job_result: JobResult,
+                        context: JobContext| {
+      let parameters: P = job.get_validated_parameters()?;
+      handler(message_event, channel, parameters, job_result, context)
+    };
+
+    self.handlers.insert(name.to_string(), Box::new(handler));
+    self
+      .schemas
+      .insert(name.to_string(), parameter_schema_for::<P>());
+    self
+  }
+
+  pub fn contains(&self, name: &str) -> bool {
+    self.handlers.contains_key(name)
+  }
+
+  /// Parameter schemas of the actions, published in the worker description
+  pub fn get_schemas(&self) -> BTreeMap<String, RootSchema> {
+    self.schemas.clone()
+  }
+
+  pub fn process(
+    &self,
+    name: &str,
+    message_event: &ME,
+    channel: Option<McaiChannel>,
+    job: &Job,
+    job_result: JobResult,
+    context: JobContext,
+  ) -> Result<JobResult> {
+    let handler = self.handlers.get(name).ok_or_else(|| {
+      MessageError::ParameterValueError(format!("Unknown job action: {:?}", name))
+    })?;
+
+    handler(message_event, channel, job, job_result, context)
+  }
+}
@@ -0,0 +1,54 @@
+use schemars::JsonSchema;
+
+/// File produced by a job, declared in its result for the workflow engine
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct JobArtifact {
+  pub name: String,
+  /// Path or URL of the file
+  pub location: String,
+  /// MIME type, like `video/mp4`
+  pub content_type: String,
+  /// Checksum of the content, as `<algorithm>:<hex digest>` like `sha256:9f86d0...`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub checksum: Option<String>,
+}
+
+impl JobArtifact {
+  pub fn new(name: &str, location: &str, content_type: &str, checksum: Option<&str>) -> Self {
+    JobArtifact {
+      name: name.to_string(),
+      location: location.to_string(),
+      content_type: content_type.to_string(),
+      checksum: checksum.map(str::to_string),
+    }
+  }
+
+  /// Whether the artifact is a remote file, otherwise a path of the worker
+  pub fn is_url(&self) -> bool {
+    self.location.contains("://")
+  }
+}
+
+#[test]
+pub fn test_job_artifact() {
+  let artifact = JobArtifact::new(
+    "proxy",
+    "s3://bucket/proxy.mp4",
+    "video/mp4",
+    Some("sha256:9f86d081"),
+  );
+  assert!(artifact.is_url());
+  assert_eq!(
+    json!({
+      "name": "proxy",
+      "location": "s3://bucket/proxy.mp4",
+      "content_type": "video/mp4",
+      "checksum": "sha256:9f86d081"
+    }),
+    json!(artifact)
+  );
+
+  let artifact = JobArtifact::new("thumbnail", "/data/thumbnail.jpg", "image/jpeg", None);
+  assert!(!artifact.is_url());
+  assert_eq!(None, json!(artifact).get("checksum"));
+}
@@ -0,0 +1,76 @@
+use crate::job::{upgrade, Job, JobResult, JobStatus};
+use crate::parameter::ParameterValue;
+use crate::{MessageError, Result};
+
+/// Order containing several jobs, processed sequentially within a single message
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobBatch {
+  pub batch_id: u64,
+  pub jobs: Vec<Job>,
+}
+
+impl JobBatch {
+  pub fn new(message: &str) -> Result<Self> {
+    let parsed: std::result::Result<JobBatch, _> = serde_json::from_str(message);
+    let batch = parsed
+      .map_err(|e| MessageError::RuntimeError(format!("unable to parse input message: {:?}", e)))?;
+
+    Ok(JobBatch {
+      jobs: batch
+        .jobs
+        .into_iter()
+        .map(upgrade)
+        .collect::<Result<Vec<Job>>>()?,
+      ..batch
+    })
+  }
+
+  pub fn check_requirements(&self) -> Result<()> {
+    for job in &self.jobs {
+      job.check_requirements()?;
+    }
+    Ok(())
+  }
+
+  /// Aggregate the per-job results into the batch result.
+  ///
+  /// The batch is completed only if every job has been completed or skipped,
+  /// with warnings if any job has been completed with warnings, and skipped if every job has been skipped.
+  /// The batch is in error if the per-job results can not be added to it.
+  pub fn aggregate(&self, job_results: Vec<JobResult>) -> Result<JobResult> {
+    let has_status =
+      |status: JobStatus| move |job_result: &JobResult| job_result.get_status() == &status;
+    let status = if !job_results
+      .iter()
+      .all(|job_result| job_result.get_status().is_successful())
+    {
+      JobStatus::Error
+    } else if !job_results.is_empty() && job_results.iter().all(has_status(JobStatus::Skipped)) {
+      JobStatus::Skipped
+    } else if job_results
+      .iter()
+      .any(has_status(JobStatus::CompletedWithWarnings))
+    {
+      JobStatus::CompletedWithWarnings
+    } else {
+      JobStatus::Completed
+    };
+
+    JobResult::new(self.batch_id)
+      .with_status(status)
+      .with_json("job_results", &job_results)
+      .map_err(|error| {
+        MessageError::ProcessingError(
+          JobResult::new(self.batch_id)
+            .with_status(JobStatus::Error)
+            .with_message(&error),
+        )
+      })
+  }
+}
+
+impl ParameterValue for Vec<JobResult> {
+  fn get_type_as_string() -> String {
+    "array_of_job_results".to_string()
+  }
+}
@@ -0,0 +1,52 @@
+/// Outcome of the claim of a job, before processing it
+pub enum JobClaim {
+  /// The job is processed, holding the lease until its result is published
+  Acquired(JobLease),
+  /// The resources of the job are locked by another worker, the order is requeued after a delay
+  Contended(String),
+}
+
+/// External lock or lease acquired on the resources of a job, released once dropped
+#[derive(Default)]
+pub struct JobLease {
+  release: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl JobLease {
+  /// Lease without resource to release
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Function releasing the lock, called once the result of the job is published
+  pub fn with_release<F: 'static + FnOnce() + Send>(mut self, release: F) -> Self {
+    self.release = Some(Box::new(release));
+    self
+  }
+}
+
+impl Drop for JobLease {
+  fn drop(&mut self) {
+    if let Some(release) = self.release.take() {
+      release();
+    }
+  }
+}
+
+#[test]
+pub fn test_job_lease() {
+  use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  };
+
+  let released = Arc::new(AtomicBool::new(false));
+  let lease_released = released.clone();
+  let claim = JobClaim::Acquired(
+    JobLease::new().with_release(move || lease_released.store(true, Ordering::SeqCst)),
+  );
+
+  assert!(!released.load(Ordering::SeqCst));
+  drop(claim);
+  assert!(released.load(Ordering::SeqCst));
+}
@@ -0,0 +1,320 @@
+use crate::job::{
+  add_running_jobs, HttpClient, JobMetrics, JobResult, JobStatus, JobWorkspace, ProcessingMetrics,
+};
+use crate::message::trace_context;
+use crate::worker::watchdog;
+use crate::{MessageError, Result};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{
+  atomic::{AtomicBool, AtomicU64, Ordering},
+  Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+
+/// Context of a job in progress, given to the worker to poll for the job cancellation,
+/// to save checkpoints to resume from and to access its workspace
+#[derive(Clone, Debug, Default)]
+pub struct JobContext {
+  job_id: u64,
+  cancelled: Arc<AtomicBool>,
+  checkpoint: Arc<Mutex<Option<Value>>>,
+  workspace: Option<Arc<JobWorkspace>>,
+  metrics: Arc<JobMetrics>,
+  start_instant: Option<Instant>,
+}
+
+impl JobContext {
+  pub fn new(job_id: u64) -> Self {
+    JobContext {
+      job_id,
+      cancelled: Arc::new(AtomicBool::new(false)),
+      checkpoint: Arc::new(Mutex::new(None)),
+      workspace: None,
+      metrics: Arc::new(JobMetrics::default()),
+      start_instant: Some(Instant::now()),
+    }
+  }
+
+  pub(crate) fn with_workspace(mut self, workspace: Arc<JobWorkspace>) -> Self {
+    self.workspace = Some(workspace);
+    self
+  }
+
+  /// Context of a job within a batch, cancelled with the batch
+  pub(crate) fn for_job(&self, job_id: u64) -> Self {
+    JobContext {
+      job_id,
+      cancelled: self.cancelled.clone(),
+      checkpoint: Arc::new(Mutex::new(None)),
+      workspace: None,
+      metrics: Arc::new(JobMetrics::default()),
+      start_instant: Some(Instant::now()),
+    }
+  }
+
+  pub fn get_job_id(&self) -> u64 {
+    self.job_id
+  }
+
+  /// Scratch directory of the job, removed once the job is processed
+  pub fn get_workspace(&self) -> Option<&JobWorkspace> {
+    self.workspace.as_deref()
+  }
+
+  /// Client for the external APIs, rate limited per host and retrying failed requests
+  pub fn get_http_client(&self) -> Result<HttpClient> {
+    HttpClient::new(self.job_id)
+  }
+
+  /// State of the internal queues of the SDK, to adapt the processing under pressure
+  pub fn get_metrics(&self) -> ProcessingMetrics {
+    self.metrics.get_metrics()
+  }
+
+  #[cfg(feature = "media")]
+  pub(crate) fn get_job_metrics(&self) -> &JobMetrics {
+    &self.metrics
+  }
+
+  /// W3C `traceparent` of the order, to continue its distributed trace in the outgoing calls
+  pub fn get_trace_context(&self) -> Option<String> {
+    trace_context::get_trace_context(self.job_id)
+  }
+
+  /// Time since the start of the job
+  pub fn get_elapsed(&self) -> Duration {
+    self
+      .start_instant
+      .map(|start_instant| start_instant.elapsed())
+      .unwrap_or_default()
+  }
+
+  /// Signal the job is alive to the watchdog, for long steps without progression
+  pub fn heartbeat(&self) {
+    watchdog::beat(self.job_id);
+  }
+
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::SeqCst);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::SeqCst)
+  }
+
+  /// Returns a processing error if the job has been cancelled, to abort it with `?`
+  pub fn check_cancelled(&self) -> Result<()> {
+    if !self.is_cancelled() {
+      return Ok(());
+    }
+
+    let job_result = JobResult::new(self.job_id)
+      .with_status(JobStatus::Error)
+      .with_message("Job cancelled");
+    let job_result = job_result
+      .clone()
+      .with_json("error_code", &"cancelled".to_string())
+      .unwrap_or(job_result);
+
+    Err(MessageError::ProcessingError(job_result))
+  }
+
+  /// Checkpoint saved by a previous run of the order, or during this one
+  pub fn get_checkpoint<T: DeserializeOwned>(&self) -> Option<T> {
+    self
+      .get_checkpoint_value()
+      .and_then(|checkpoint| serde_json::from_value(checkpoint).ok())
+  }
+
+  /// Save the progress of the job, included in the order if it is requeued
+  pub fn set_checkpoint<T: Serialize>(&self, checkpoint: &T) -> Result<()> {
+    let checkpoint = serde_json::to_value(checkpoint)
+      .map_err(|error| MessageError::RuntimeError(format!("Invalid checkpoint: {}", error)))?;
+    self.set_checkpoint_value(Some(checkpoint));
+    Ok(())
+  }
+
+  pub(crate) fn get_checkpoint_value(&self) -> Option<Value> {
+    self.checkpoint.lock().unwrap().clone()
+  }
+
+  pub(crate) fn set_checkpoint_value(&self, checkpoint: Option<Value>) {
+    *self.checkpoint.lock().unwrap() = checkpoint;
+  }
+}
+
+/// Job in progress, reported on the status requests
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunningJob {
+  pub job_id: u64,
+  /// Last progression published, in percent
+  pub progression: Option<u8>,
+  /// Duration since the start of the job in seconds
+  pub elapsed: f64,
+}
+
+/// Last job of the worker ended in error, reported on the status requests
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct JobError {
+  pub job_id: u64,
+  pub message: String,
+  pub datetime: DateTime<Utc>,
+}
+
+/// Contexts of the jobs in progress on the worker
+#[derive(Clone, Debug, Default)]
+pub struct RunningJobs {
+  contexts: Arc<Mutex<HashMap<u64, JobContext>>>,
+  last_error: Arc<Mutex<Option<JobError>>>,
+  processed_jobs: Arc<AtomicU64>,
+}
+
+impl RunningJobs {
+  pub fn start(&self, job_id: u64) -> JobContext {
+    let context = JobContext::new(job_id);
+    if self
+      .contexts
+      .lock()
+      .unwrap()
+      .insert(job_id, context.clone())
+      .is_none()
+    {
+      add_running_jobs(1);
+    }
+    context
+  }
+
+  pub fn finish(&self, job_id: u64) {
+    if self.contexts.lock().unwrap().remove(&job_id).is_some() {
+      add_running_jobs(-1);
+      self.processed_jobs.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  /// Number of jobs processed by the worker since its start, completed or not
+  pub fn get_processed_jobs(&self) -> u64 {
+    self.processed_jobs.load(Ordering::SeqCst)
+  }
+
+  /// Number of jobs in progress
+  pub fn count(&self) -> usize {
+    self.contexts.lock().unwrap().len()
+  }
+
+  /// Cancel the job if it is in progress, returns whether it was found
+  pub fn cancel(&self, job_id: u64) -> bool {
+    match self.contexts.lock().unwrap().get(&job_id) {
+      Some(context) => {
+        context.cancel();
+        true
+      }
+      None => false,
+    }
+  }
+
+  pub fn get_checkpoint(&self, job_id: u64) -> Option<Value> {
+    self
+      .contexts
+      .lock()
+      .unwrap()
+      .get(&job_id)
+      .and_then(|context| context.get_checkpoint_value())
+  }
+
+  /// Jobs in progress, by identifier
+  pub fn get_running_jobs(&self) -> Vec<RunningJob> {
+    let mut running_jobs: Vec<RunningJob> = self
+      .contexts
+      .lock()
+      .unwrap()
+      .values()
+      .map(|context| RunningJob {
+        job_id: context.get_job_id(),
+        progression: watchdog::get_progression(context.get_job_id()),
+        elapsed: context.get_elapsed().as_secs_f64(),
+      })
+      .collect();
+    running_jobs.sort_by_key(|running_job| running_job.job_id);
+    running_jobs
+  }
+
+  pub(crate) fn set_last_error(&self, job_id: u64, message: &str) {
+    *self.last_error.lock().unwrap() = Some(JobError {
+      job_id,
+      message: message.to_string(),
+      datetime: Utc::now(),
+    });
+  }
+
+  pub fn get_last_error(&self) -> Option<JobError> {
+    self.last_error.lock().unwrap().clone()
+  }
+}
+
+#[test]
+pub fn test_running_jobs_cancel() {
+  let running_jobs = RunningJobs::default();
+  let context = running_jobs.start(123);
+
+  assert!(context.check_cancelled().is_ok());
+  assert!(!running_jobs.cancel(456));
+  assert!(running_jobs.cancel(123));
+  assert!(context.is_cancelled());
+
+  match context.check_cancelled() {
+    Err(MessageError::ProcessingError(job_result)) => {
+      assert_eq!(123, job_result.get_job_id());
+      assert_eq!(&JobStatus::Error, job_result.get_status());
+    }
+    _ => panic!("the job should be cancelled"),
+  }
+
+  assert_eq!(1, running_jobs.count());
+  running_jobs.finish(123);
+  assert!(!running_jobs.cancel(123));
+  assert_eq!(0, running_jobs.count());
+  assert_eq!(1, running_jobs.get_processed_jobs());
+}
+
+#[test]
+pub fn test_job_context_checkpoint() {
+  let running_jobs = RunningJobs::default();
+  let context = running_jobs.start(123);
+  assert_eq!(None, context.get_checkpoint::<u32>());
+  assert_eq!(None, running_jobs.get_checkpoint(123));
+
+  context.set_checkpoint(&4u32).unwrap();
+  assert_eq!(Some(4), context.get_checkpoint::<u32>());
+  assert_eq!(None, context.get_checkpoint::<String>());
+  assert_eq!(Some(json!(4)), running_jobs.get_checkpoint(123));
+
+  assert_eq!(None, context.for_job(456).get_checkpoint::<u32>());
+}
+
+#[test]
+pub fn test_running_jobs_status() {
+  let running_jobs = RunningJobs::default();
+  running_jobs.start(789);
+  running_jobs.start(788);
+  watchdog::start(789);
+  watchdog::beat_progression(789, 42);
+
+  let jobs = running_jobs.get_running_jobs();
+  assert_eq!(
+    vec![(788, None), (789, Some(42))],
+    jobs
+      .iter()
+      .map(|job| (job.job_id, job.progression))
+      .collect::<Vec<_>>()
+  );
+  watchdog::finish(789);
+
+  assert_eq!(None, running_jobs.get_last_error());
+  running_jobs.set_last_error(788, "Source not found");
+  let last_error = running_jobs.clone().get_last_error().unwrap();
+  assert_eq!(788, last_error.job_id);
+  assert_eq!("Source not found", last_error.message);
+}
@@ -0,0 +1,90 @@
+use crate::worker::instance::get_instance_id;
+use chrono::prelude::*;
+use schemars::JsonSchema;
+
+/// States of the processing of an order, in their order of occurrence
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub enum JobState {
+  #[serde(rename = "received")]
+  Received,
+  #[serde(rename = "validated")]
+  Validated,
+  #[serde(rename = "initializing")]
+  Initializing,
+  #[serde(rename = "processing")]
+  Processing,
+  #[serde(rename = "publishing")]
+  Publishing,
+  #[serde(rename = "completed")]
+  Completed,
+  #[serde(rename = "error")]
+  Error,
+}
+
+impl JobState {
+  /// Routing key of the events of the state
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      JobState::Received => "received",
+      JobState::Validated => "validated",
+      JobState::Initializing => "initializing",
+      JobState::Processing => "processing",
+      JobState::Publishing => "publishing",
+      JobState::Completed => "completed",
+      JobState::Error => "error",
+    }
+  }
+}
+
+/// Transition of the processing of an order to a new state
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JobEvent {
+  #[schemars(with = "String")]
+  datetime: DateTime<Utc>,
+  docker_container_id: String,
+  job_id: u64,
+  state: JobState,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  message: Option<String>,
+}
+
+impl JobEvent {
+  pub fn new(job_id: u64, state: JobState) -> Self {
+    JobEvent {
+      datetime: Utc::now(),
+      docker_container_id: get_instance_id(),
+      job_id,
+      state,
+      message: None,
+    }
+  }
+
+  /// Details of the transition, like the error of the job
+  pub fn with_message(mut self, message: &str) -> Self {
+    self.message = Some(message.to_string());
+    self
+  }
+
+  pub fn get_job_id(&self) -> u64 {
+    self.job_id
+  }
+
+  pub fn get_state(&self) -> JobState {
+    self.state
+  }
+}
+
+#[test]
+pub fn test_job_event() {
+  let job_event = JobEvent::new(123, JobState::Processing);
+  assert_eq!(123, job_event.get_job_id());
+  assert_eq!(JobState::Processing, job_event.get_state());
+
+  let json = json!(job_event);
+  assert_eq!(Some(&json!("processing")), json.get("state"));
+  assert!(json.get("message").is_none());
+
+  let json = json!(JobEvent::new(123, JobState::Error).with_message("invalid parameter"));
+  assert_eq!(Some(&json!("error")), json.get("state"));
+  assert_eq!(Some(&json!("invalid parameter")), json.get("message"));
+}
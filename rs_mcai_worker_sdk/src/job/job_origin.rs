@@ -0,0 +1,90 @@
+//! Origin of the responses of the jobs, for the orchestrator to audit which binary produced which result
+//!
+//! The completed, error and progression messages carry the version of the SDK,
+//! and the name and version of the worker processing the job while its order is processed.
+
+use crate::worker::built_info;
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Name and version of the worker processing each job in progress
+static JOB_ORIGINS: RwLock<BTreeMap<u64, (String, String)>> = RwLock::new(BTreeMap::new());
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WorkerOrigin {
+  #[serde(default)]
+  sdk_version: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  worker_name: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  worker_version: Option<String>,
+}
+
+impl WorkerOrigin {
+  /// Origin of the responses of the job, without worker if the job is not in progress
+  pub(crate) fn for_job(job_id: u64) -> Self {
+    let worker = JOB_ORIGINS.read().unwrap().get(&job_id).cloned();
+
+    WorkerOrigin {
+      sdk_version: built_info::PKG_VERSION.to_string(),
+      worker_name: worker.as_ref().map(|(name, _)| name.clone()),
+      worker_version: worker.map(|(_, version)| version),
+    }
+  }
+
+  pub fn get_sdk_version(&self) -> &str {
+    &self.sdk_version
+  }
+
+  pub fn get_worker_name(&self) -> Option<&str> {
+    self.worker_name.as_deref()
+  }
+
+  pub fn get_worker_version(&self) -> Option<&str> {
+    self.worker_version.as_deref()
+  }
+}
+
+/// Worker of a job, registered until the order is processed
+pub(crate) struct JobOrigin {
+  job_id: Option<u64>,
+}
+
+impl JobOrigin {
+  pub(crate) fn new(job_id: Option<u64>, worker_name: String, worker_version: String) -> Self {
+    if let Some(job_id) = job_id {
+      JOB_ORIGINS
+        .write()
+        .unwrap()
+        .insert(job_id, (worker_name, worker_version));
+    }
+    JobOrigin { job_id }
+  }
+}
+
+impl Drop for JobOrigin {
+  fn drop(&mut self) {
+    if let Some(job_id) = self.job_id {
+      JOB_ORIGINS.write().unwrap().remove(&job_id);
+    }
+  }
+}
+
+#[test]
+pub fn test_job_origin() {
+  let job_origin = JobOrigin::new(Some(9456), "transcoder".to_string(), "1.4.0".to_string());
+
+  let origin = WorkerOrigin::for_job(9456);
+  assert_eq!(built_info::PKG_VERSION, origin.get_sdk_version());
+  assert_eq!(Some("transcoder"), origin.get_worker_name());
+  assert_eq!(Some("1.4.0"), origin.get_worker_version());
+
+  drop(job_origin);
+  let origin = WorkerOrigin::for_job(9456);
+  assert_eq!(None, origin.get_worker_name());
+  assert_eq!(
+    json!({ "sdk_version": built_info::PKG_VERSION }),
+    json!(origin)
+  );
+}
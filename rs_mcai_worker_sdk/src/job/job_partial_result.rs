@@ -0,0 +1,52 @@
+use crate::worker::instance::get_instance_id;
+use chrono::prelude::*;
+use schemars::JsonSchema;
+use serde_json::Value;
+
+/// Intermediate result of a job, published before its final result
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JobPartialResult {
+  #[schemars(with = "String")]
+  datetime: DateTime<Utc>,
+  docker_container_id: String,
+  job_id: u64,
+  /// Position of the result among the partial results of the job, from 0
+  index: u64,
+  result: Value,
+}
+
+impl JobPartialResult {
+  pub fn new(job_id: u64, index: u64, result: Value) -> Self {
+    JobPartialResult {
+      datetime: Utc::now(),
+      docker_container_id: get_instance_id(),
+      job_id,
+      index,
+      result,
+    }
+  }
+
+  pub fn get_job_id(&self) -> u64 {
+    self.job_id
+  }
+
+  pub fn get_index(&self) -> u64 {
+    self.index
+  }
+
+  pub fn get_result(&self) -> &Value {
+    &self.result
+  }
+}
+
+#[test]
+pub fn test_job_partial_result() {
+  let partial_result = JobPartialResult::new(123, 2, json!({"path": "/data/a.mxf"}));
+  assert_eq!(123, partial_result.get_job_id());
+  assert_eq!(2, partial_result.get_index());
+
+  let json = json!(partial_result);
+  assert_eq!(Some(&json!(2)), json.get("index"));
+  assert_eq!(Some(&json!({"path": "/data/a.mxf"})), json.get("result"));
+  assert!(json.get("docker_container_id").is_some());
+}
@@ -1,23 +1,114 @@
-use crate::worker::docker::get_instance_id;
+use super::job_origin::WorkerOrigin;
+use crate::worker::instance::get_instance_id;
 use chrono::prelude::*;
+use schemars::JsonSchema;
+use serde_json::Value;
+use std::time::Duration;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Progress of the current step of a job, beyond its percentage
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProgressionDetails {
+  /// Label of the step, like `downloading` or `encoding`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  step: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  items_done: Option<u64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  items_total: Option<u64>,
+}
+
+impl ProgressionDetails {
+  pub fn new() -> Self {
+    ProgressionDetails::default()
+  }
+
+  pub fn with_step(mut self, step: &str) -> Self {
+    self.step = Some(step.to_string());
+    self
+  }
+
+  /// Items processed, like the files of a batch or the frames of a media
+  pub fn with_items(mut self, items_done: u64, items_total: u64) -> Self {
+    self.items_done = Some(items_done);
+    self.items_total = Some(items_total);
+    self
+  }
+
+  /// Part of the job done, from the items if any, from the progression otherwise
+  fn get_ratio(&self, progression: u8) -> f64 {
+    match (self.items_done, self.items_total) {
+      (Some(items_done), Some(items_total)) if items_total > 0 => {
+        (items_done as f64 / items_total as f64).min(1.0)
+      }
+      _ => f64::from(progression.min(100)) / 100.0,
+    }
+  }
+
+  /// Remaining time of the job, assuming the rest is processed at the same pace
+  pub(crate) fn estimate_remaining(&self, progression: u8, elapsed: Duration) -> Option<Duration> {
+    let ratio = self.get_ratio(progression);
+    if ratio <= 0.0 || elapsed.is_zero() {
+      return None;
+    }
+    Some(elapsed.mul_f64((1.0 - ratio) / ratio))
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JobProgression {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  checkpoint: Option<Value>,
+  #[schemars(with = "String")]
   datetime: DateTime<Utc>,
+  #[serde(flatten)]
+  details: ProgressionDetails,
   docker_container_id: String,
+  /// Estimated remaining time of the job, in seconds
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  eta: Option<u64>,
   job_id: u64,
+  #[serde(flatten)]
+  origin: WorkerOrigin,
   progression: u8,
 }
 
 impl JobProgression {
   pub fn new(job_id: u64, progression: u8) -> Self {
     JobProgression {
+      checkpoint: None,
       datetime: Utc::now(),
-      docker_container_id: get_instance_id("/proc/self/cgroup"),
+      details: ProgressionDetails::default(),
+      docker_container_id: get_instance_id(),
+      eta: None,
       job_id,
+      origin: WorkerOrigin::for_job(job_id),
       progression,
     }
   }
+
+  /// Checkpoint to be stored with the job, to resume it when its order is delivered again
+  pub fn with_checkpoint(mut self, checkpoint: Value) -> Self {
+    self.checkpoint = Some(checkpoint);
+    self
+  }
+
+  pub fn with_details(mut self, details: ProgressionDetails) -> Self {
+    self.details = details;
+    self
+  }
+
+  pub fn with_eta(mut self, remaining: Duration) -> Self {
+    self.eta = Some(remaining.as_secs());
+    self
+  }
+
+  pub fn get_job_id(&self) -> u64 {
+    self.job_id
+  }
+
+  pub fn get_progression(&self) -> u8 {
+    self.progression
+  }
 }
 
 #[test]
@@ -37,3 +128,62 @@ pub fn test_job_progression() {
   );
   assert!(!job_progression.docker_container_id.is_empty());
 }
+
+#[test]
+pub fn test_job_progression_with_checkpoint() {
+  let job_progression = JobProgression::new(123, 50);
+  assert!(json!(job_progression).get("checkpoint").is_none());
+
+  let job_progression = job_progression.with_checkpoint(json!({"segment": 12}));
+  assert_eq!(
+    Some(&json!({"segment": 12})),
+    json!(job_progression).get("checkpoint")
+  );
+}
+
+#[test]
+pub fn test_job_progression_with_details() {
+  let job_progression = JobProgression::new(123, 50);
+  let payload = json!(job_progression);
+  assert!(payload.get("step").is_none());
+  assert!(payload.get("eta").is_none());
+
+  let details = ProgressionDetails::new()
+    .with_step("encoding")
+    .with_items(3, 12);
+  let job_progression = JobProgression::new(123, 25)
+    .with_details(details)
+    .with_eta(Duration::from_millis(90_500));
+  let payload = json!(job_progression);
+  assert_eq!(Some(&json!("encoding")), payload.get("step"));
+  assert_eq!(Some(&json!(3)), payload.get("items_done"));
+  assert_eq!(Some(&json!(12)), payload.get("items_total"));
+  assert_eq!(Some(&json!(90)), payload.get("eta"));
+}
+
+#[test]
+pub fn test_estimate_remaining() {
+  let elapsed = Duration::from_secs(60);
+  let details = ProgressionDetails::new();
+  assert_eq!(None, details.estimate_remaining(0, elapsed));
+  assert_eq!(
+    Some(Duration::from_secs(180)),
+    details.estimate_remaining(25, elapsed)
+  );
+  assert_eq!(
+    Some(Duration::from_secs(0)),
+    details.estimate_remaining(100, elapsed)
+  );
+  assert_eq!(None, details.estimate_remaining(50, Duration::default()));
+
+  let details = details.with_items(1, 3);
+  assert_eq!(
+    Some(Duration::from_secs(120)),
+    details.estimate_remaining(25, elapsed)
+  );
+  let details = ProgressionDetails::new().with_items(0, 0);
+  assert_eq!(
+    Some(Duration::from_secs(60)),
+    details.estimate_remaining(50, elapsed)
+  );
+}
@@ -1,19 +1,34 @@
+use super::job_artifact::JobArtifact;
+use super::job_origin::WorkerOrigin;
 use super::job_status::JobStatus;
 use crate::job::Job;
 use crate::parameter::container::ParametersContainer;
 use crate::parameter::Parameter;
 use crate::parameter::ParameterValue;
 use reqwest::Error;
+use schemars::JsonSchema;
 use serde::Serialize;
+use serde_json::Value;
 use std::time::Instant;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Result parameter listing the non-fatal issues of a job completed with warnings
+const WARNINGS_PARAMETER: &str = "warnings";
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JobResult {
+  /// Files produced by the job
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  artifacts: Vec<JobArtifact>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  checkpoint: Option<Value>,
   destination_paths: Vec<String>,
   execution_duration: f64,
   job_id: u64,
+  #[serde(flatten)]
+  origin: WorkerOrigin,
   parameters: Vec<Parameter>,
   #[serde(skip_serializing, skip_deserializing, default = "default_instant")]
+  #[schemars(skip)]
   start_instant: Instant,
   status: JobStatus,
 }
@@ -25,21 +40,59 @@ fn default_instant() -> Instant {
 impl JobResult {
   pub fn new(job_id: u64) -> JobResult {
     JobResult {
+      artifacts: vec![],
+      checkpoint: None,
       destination_paths: vec![],
       execution_duration: 0.0,
       job_id,
+      origin: WorkerOrigin::for_job(job_id),
       parameters: vec![],
       start_instant: Instant::now(),
       status: JobStatus::default(),
     }
   }
 
+  /// Set the status, a completed job with warnings being `CompletedWithWarnings`
   pub fn with_status(mut self, status: JobStatus) -> Self {
     self.update_execution_duration();
-    self.status = status;
+    self.status = match status {
+      JobStatus::Completed if !self.get_warnings().is_empty() => JobStatus::CompletedWithWarnings,
+      status => status,
+    };
     self
   }
 
+  /// Add a non-fatal issue to the `warnings` parameter, a completed job becoming completed with warnings
+  pub fn with_warning(mut self, warning: &str) -> Self {
+    let warning = Value::String(warning.to_string());
+    match self
+      .parameters
+      .iter_mut()
+      .find(|parameter| parameter.id == WARNINGS_PARAMETER)
+    {
+      Some(Parameter {
+        value: Some(Value::Array(warnings)),
+        ..
+      }) => warnings.push(warning),
+      _ => self.parameters.push(Parameter {
+        id: WARNINGS_PARAMETER.to_string(),
+        kind: Vec::<String>::get_type_as_string(),
+        store: None,
+        default: None,
+        value: Some(Value::Array(vec![warning])),
+      }),
+    }
+    if self.status == JobStatus::Completed {
+      self.status = JobStatus::CompletedWithWarnings;
+    }
+    self
+  }
+
+  /// Skip the job, with the reason as message
+  pub fn skipped(self, reason: &str) -> Self {
+    self.with_status(JobStatus::Skipped).with_message(reason)
+  }
+
   pub fn with_error(mut self, error: Error) -> Self {
     self.update_execution_duration();
     self.parameters.push(Parameter {
@@ -73,24 +126,61 @@ impl JobResult {
     self
   }
 
+  /// Add a parameter with the JSON value of the payload, objects and arrays are not embedded as strings
+  ///
+  /// An untyped payload is set as a `json` parameter with a `serde_json::Value`, like `json!({"faces": 3})`.
   pub fn with_json<T>(mut self, id: &str, serializable: &T) -> Result<Self, String>
   where
     T: Serialize + ParameterValue + Sized,
   {
+    let value = serde_json::to_value(serializable)
+      .map_err(|error| format!("Cannot serialize parameter '{}': {}", id, error))?;
+
     self.parameters.push(Parameter {
       id: id.to_string(),
       kind: T::get_type_as_string(),
       store: None,
       default: None,
-      value: serde_json::to_value(serializable).ok(),
+      value: Some(value),
     });
     Ok(self)
   }
 
+  /// Declare a file produced by the job, by its path or URL
+  pub fn with_artifact(
+    self,
+    name: &str,
+    location: &str,
+    content_type: &str,
+    checksum: Option<&str>,
+  ) -> Self {
+    let artifact = JobArtifact::new(name, location, content_type, checksum);
+    self.with_artifacts(&mut vec![artifact])
+  }
+
+  pub fn with_artifacts(mut self, artifacts: &mut Vec<JobArtifact>) -> Self {
+    self.artifacts.append(artifacts);
+    self
+  }
+
+  /// Checkpoint included in the order when it is retried, to resume the processing from it
+  pub fn with_checkpoint<T: Serialize>(mut self, checkpoint: &T) -> Self {
+    self.checkpoint = serde_json::to_value(checkpoint).ok();
+    self
+  }
+
+  pub fn get_checkpoint(&self) -> Option<&Value> {
+    self.checkpoint.as_ref()
+  }
+
   pub fn get_job_id(&self) -> u64 {
     self.job_id
   }
 
+  pub fn get_origin(&self) -> &WorkerOrigin {
+    &self.origin
+  }
+
   pub fn get_str_job_id(&self) -> String {
     self.job_id.to_string()
   }
@@ -99,6 +189,12 @@ impl JobResult {
     &self.status
   }
 
+  pub fn get_warnings(&self) -> Vec<String> {
+    self
+      .get_parameter::<Vec<String>>(WARNINGS_PARAMETER)
+      .unwrap_or_default()
+  }
+
   pub fn get_execution_duration(&self) -> f64 {
     self.execution_duration
   }
@@ -111,6 +207,10 @@ impl JobResult {
     &self.destination_paths
   }
 
+  pub fn get_artifacts(&self) -> &[JobArtifact] {
+    &self.artifacts
+  }
+
   pub fn update_execution_duration(&mut self) {
     self.execution_duration = self.start_instant.elapsed().as_secs_f64();
   }
@@ -140,5 +240,6 @@ impl PartialEq for JobResult {
       && self.status == other.status
       && self.parameters == other.parameters
       && self.destination_paths == other.destination_paths
+      && self.get_artifacts() == other.get_artifacts()
   }
 }
@@ -1,13 +1,31 @@
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+use schemars::JsonSchema;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum JobStatus {
   #[serde(rename = "unknown")]
   Unknown,
   #[serde(rename = "completed")]
   Completed,
+  /// Nothing to do for the job, e.g. its input has already been processed
+  #[serde(rename = "skipped")]
+  Skipped,
+  /// Completed with non-fatal issues, listed in the warnings of the result
+  #[serde(rename = "completed_with_warnings")]
+  CompletedWithWarnings,
   #[serde(rename = "error")]
   Error,
 }
 
+impl JobStatus {
+  /// Status of the results published on the completed queue
+  pub fn is_successful(&self) -> bool {
+    matches!(
+      self,
+      JobStatus::Completed | JobStatus::Skipped | JobStatus::CompletedWithWarnings
+    )
+  }
+}
+
 impl Default for JobStatus {
   fn default() -> Self {
     JobStatus::Unknown
@@ -20,6 +38,10 @@ pub fn test_job_status_json() {
   assert_eq!("\"unknown\"", &json);
   let json = serde_json::to_string(&JobStatus::Completed).unwrap();
   assert_eq!("\"completed\"", &json);
+  let json = serde_json::to_string(&JobStatus::Skipped).unwrap();
+  assert_eq!("\"skipped\"", &json);
+  let json = serde_json::to_string(&JobStatus::CompletedWithWarnings).unwrap();
+  assert_eq!("\"completed_with_warnings\"", &json);
   let json = serde_json::to_string(&JobStatus::Error).unwrap();
   assert_eq!("\"error\"", &json);
 }
@@ -0,0 +1,138 @@
+use crate::config::{get_workspace_quota, get_workspace_root};
+use crate::job::{JobResult, JobStatus};
+use crate::{MessageError, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Scratch directory of a job, removed once the job is processed
+#[derive(Debug)]
+pub struct JobWorkspace {
+  job_id: u64,
+  path: PathBuf,
+  /// Maximum size of the directory content in bytes
+  quota: Option<u64>,
+}
+
+impl JobWorkspace {
+  /// Create the workspace in the configured root directory
+  pub fn new(job_id: u64) -> Result<Self> {
+    Self::create(&get_workspace_root(), job_id, get_workspace_quota())
+  }
+
+  pub(crate) fn create(root: &Path, job_id: u64, quota: Option<u64>) -> Result<Self> {
+    let path = root.join(format!("mcai_job_{}_{}", job_id, std::process::id()));
+
+    // leftover of a previous run of the job
+    if path.exists() {
+      let _ = fs::remove_dir_all(&path);
+    }
+
+    fs::create_dir_all(&path).map_err(|error| {
+      MessageError::RuntimeError(format!(
+        "Unable to create job workspace {:?}: {}",
+        path, error
+      ))
+    })?;
+
+    Ok(JobWorkspace {
+      job_id,
+      path,
+      quota,
+    })
+  }
+
+  pub fn get_path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn get_quota(&self) -> Option<u64> {
+    self.quota
+  }
+
+  /// Size of the directory content in bytes
+  pub fn get_used_space(&self) -> u64 {
+    get_directory_size(&self.path)
+  }
+
+  /// Returns a processing error if the content exceeds the quota, to abort the job with `?`
+  pub fn check_quota(&self) -> Result<()> {
+    let quota = match self.quota {
+      Some(quota) => quota,
+      None => return Ok(()),
+    };
+
+    let used_space = self.get_used_space();
+    if used_space <= quota {
+      return Ok(());
+    }
+
+    let job_result = JobResult::new(self.job_id)
+      .with_status(JobStatus::Error)
+      .with_message(&format!(
+        "Job workspace quota exceeded: {} bytes used, {} allowed",
+        used_space, quota
+      ));
+    let job_result = job_result
+      .clone()
+      .with_json("error_code", &"workspace_quota_exceeded".to_string())
+      .unwrap_or(job_result);
+
+    Err(MessageError::ProcessingError(job_result))
+  }
+
+  /// Remove the directory and its content
+  pub fn cleanup(&self) {
+    if self.path.exists() {
+      if let Err(error) = fs::remove_dir_all(&self.path) {
+        warn!(target: &self.job_id.to_string(), "Unable to remove job workspace {:?}: {}", self.path, error);
+      }
+    }
+  }
+}
+
+impl Drop for JobWorkspace {
+  fn drop(&mut self) {
+    self.cleanup();
+  }
+}
+
+fn get_directory_size(path: &Path) -> u64 {
+  fs::read_dir(path)
+    .map(|entries| {
+      entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+          Ok(metadata) if metadata.is_dir() => get_directory_size(&entry.path()),
+          Ok(metadata) => metadata.len(),
+          Err(_) => 0,
+        })
+        .sum()
+    })
+    .unwrap_or(0)
+}
+
+#[test]
+pub fn test_job_workspace() {
+  let root = std::env::temp_dir();
+  let workspace = JobWorkspace::create(&root, 987_654, Some(10)).unwrap();
+  let path = workspace.get_path().to_path_buf();
+  assert!(path.is_dir());
+  assert!(path.starts_with(&root));
+
+  fs::create_dir(path.join("segments")).unwrap();
+  fs::write(path.join("segments").join("1.ts"), b"12345678").unwrap();
+  assert_eq!(8, workspace.get_used_space());
+  assert!(workspace.check_quota().is_ok());
+
+  fs::write(path.join("2.ts"), b"12345678").unwrap();
+  match workspace.check_quota() {
+    Err(MessageError::ProcessingError(job_result)) => {
+      assert_eq!(987_654, job_result.get_job_id());
+      assert_eq!(&JobStatus::Error, job_result.get_status());
+    }
+    _ => panic!("the quota should be exceeded"),
+  }
+
+  drop(workspace);
+  assert!(!path.exists());
+}
@@ -1,25 +1,95 @@
 //! Module to manage Job
 
-use crate::{parameter::container::ParametersContainer, MessageError, Parameter, Requirement};
+use crate::{
+  parameter::container::ParametersContainer, MessageError, Parameter, ParameterValue, Requirement,
+};
 use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::path::Path;
 
+pub mod command;
+mod execution_metrics;
+mod frame_result_collector;
+mod http_client;
+mod job_actions;
+mod job_artifact;
+mod job_batch;
+mod job_claim;
+mod job_context;
+mod job_event;
+mod job_origin;
+mod job_partial_result;
 mod job_progression;
 mod job_result;
 mod job_status;
+mod job_workspace;
+mod processing_metrics;
+mod requirements;
+mod result_sender;
+mod schema_version;
 
-use crate::parameter::store::request_value;
+use crate::logger::LOG_LEVEL_PARAMETER;
+use crate::message::admission::check_disk_space;
+use crate::parameter::{
+  credential::ScopedValue,
+  defaults::apply_schema_defaults,
+  parse_array_of_objects, reference,
+  secret::{is_secret_parameter, register_secret_value, with_job_secrets},
+  store::request_value,
+  template::{get_template_parameters, ParameterTemplate},
+  validation::{get_declared_parameters, validate, Violation, VALIDATION_ERRORS_PARAMETER},
+};
+use crate::worker::parameter_schema_for;
 use crate::Result;
-pub use job_progression::JobProgression;
+pub(crate) use execution_metrics::ExecutionRecorder;
+pub use execution_metrics::{ExecutionMetrics, EXECUTION_METRICS_PARAMETER};
+pub use frame_result_collector::{Aggregation, FrameResultCollector};
+pub use http_client::HttpClient;
+pub use job_actions::JobActions;
+pub use job_artifact::JobArtifact;
+pub use job_batch::JobBatch;
+pub use job_claim::{JobClaim, JobLease};
+pub use job_context::{JobContext, JobError, RunningJob, RunningJobs};
+pub use job_event::{JobEvent, JobState};
+pub(crate) use job_origin::JobOrigin;
+pub use job_origin::WorkerOrigin;
+pub use job_partial_result::JobPartialResult;
+pub use job_progression::{JobProgression, ProgressionDetails};
 pub use job_result::JobResult;
 pub use job_status::JobStatus;
-use serde::de::DeserializeOwned;
+pub use job_workspace::JobWorkspace;
+#[cfg(feature = "media")]
+pub(crate) use processing_metrics::update_gauge;
+pub use processing_metrics::ProcessingMetrics;
+pub(crate) use processing_metrics::{
+  add_pending_orders, add_running_jobs, JobMetrics, PendingPublish,
+};
+pub use result_sender::ResultSender;
+pub(crate) use schema_version::upgrade;
+pub use schema_version::ORDER_SCHEMA_VERSION;
+use schemars::{schema::RootSchema, JsonSchema};
+use serde::de::{value::MapDeserializer, DeserializeOwned};
 use serde::Deserialize;
 
+/// Job parameter carrying the checkpoint to resume the order from, as a JSON string
+pub const CHECKPOINT_PARAMETER: &str = "sdk_checkpoint";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Job {
+  /// Version of the format of the order, `1` if not set, the order being upgraded to the current format once parsed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub schema_version: Option<u32>,
   pub job_id: u64,
+  /// Handler of the order among the actions of the worker, the worker `process` if none
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub action: Option<String>,
   pub parameters: Vec<Parameter>,
+  /// Priority of the order, set as AMQP priority of the orders published again by the worker
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub priority: Option<i64>,
+  /// W3C `traceparent` of the distributed trace of the order
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub trace_context: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -53,19 +123,70 @@ pub struct ValueResponseBody {
 
 impl Job {
   pub fn new(message: &str) -> Result<Self> {
+    schema_version::check_message_schema_version(message)?;
     let parsed: std::result::Result<Job, _> = serde_json::from_str(message);
     parsed
       .map_err(|e| MessageError::RuntimeError(format!("unable to parse input message: {:?}", e)))
+      .and_then(upgrade)
   }
 
   pub fn get_parameters<P: Sized + DeserializeOwned>(&self) -> Result<P> {
+    self.deserialize_parameters(None, false)
+  }
+
+  /// Parameters of the job, the missing ones taking the default value declared in the schema of `P`
+  pub fn get_parameters_with_defaults<P: Sized + DeserializeOwned + JsonSchema>(
+    &self,
+  ) -> Result<P> {
+    self.deserialize_parameters(Some(&parameter_schema_for::<P>()), false)
+  }
+
+  /// Parameters of the job validated against the schema of `P`,
+  /// the missing ones taking the default value declared in the schema
+  pub fn get_validated_parameters<P: Sized + DeserializeOwned + JsonSchema>(&self) -> Result<P> {
+    self.deserialize_parameters(Some(&parameter_schema_for::<P>()), true)
+  }
+
+  /// The values resolved from the stores, and the secret ones, are masked while the job is in progress
+  fn deserialize_parameters<P: Sized + DeserializeOwned>(
+    &self,
+    schema: Option<&RootSchema>,
+    validated: bool,
+  ) -> Result<P> {
+    with_job_secrets(self.job_id, || {
+      self.deserialize_job_parameters(schema, validated)
+    })
+  }
+
+  fn deserialize_job_parameters<P: Sized + DeserializeOwned>(
+    &self,
+    schema: Option<&RootSchema>,
+    validated: bool,
+  ) -> Result<P> {
     let mut parameters = Map::<String, Value>::new();
+    let mut stores = HashMap::new();
+    let template = ParameterTemplate::new(self.job_id);
+    let template_parameters = schema.map(get_template_parameters).unwrap_or_default();
     for parameter in &self.parameters {
       if let Some(value) = parameter
         .value
         .clone()
         .or_else(|| parameter.default.clone())
       {
+        let value = reference::resolve(self.job_id, value)?;
+        let value = parse_array_of_objects(&parameter.kind, value)?;
+        // the keys of the credentials are used as is
+        let value =
+          if template_parameters.contains(parameter.id.as_str()) && parameter.store.is_none() {
+            template.expand(value).map_err(|error| {
+              MessageError::ParameterValueError(format!(
+                "Cannot expand parameter '{}': {}",
+                parameter.id, error
+              ))
+            })?
+          } else {
+            value
+          };
         let value = if let Some(store_code) = &parameter.store {
           debug!(
             "Retrieve credential value {} from store {}",
@@ -73,24 +194,43 @@ impl Job {
             store_code
           );
 
-          if let Value::String(credential_key) = value {
-            request_value(&credential_key, &store_code)
-              .map_err(|e| MessageError::ParameterValueError(format!("{:?}", e)))
-          } else {
-            Err(MessageError::ParameterValueError(format!(
+          match value {
+            Value::String(credential_key) => request_value(&credential_key, &store_code)
+              .map_err(|e| MessageError::ParameterValueError(format!("{:?}", e))),
+            // the credential fields of objects are resolved with the store of the parameter
+            Value::Object(_) | Value::Array(_) => {
+              stores.insert(parameter.id.clone(), store_code.clone());
+              Ok(value)
+            }
+            _ => Err(MessageError::ParameterValueError(format!(
               "Cannot handle credential type for {:?}",
               value
-            )))
+            ))),
           }?
         } else {
           value
         };
+        if is_secret_parameter(&parameter.id) {
+          register_secret_value(&value);
+        }
         parameters.insert(parameter.id.clone(), value);
       }
     }
+
+    if let Some(schema) = schema {
+      apply_schema_defaults(schema, &mut parameters);
+      if validated {
+        self.check_parameters(schema, &parameters)?;
+      }
+    }
+
+    let scoped_parameters = parameters.clone().into_iter().map(|(id, value)| {
+      let store = stores.remove(&id);
+      (id, ScopedValue { value, store })
+    });
     let parameters = serde_json::Value::Object(parameters);
 
-    serde_json::from_value(parameters.clone()).map_err(|error| {
+    P::deserialize(MapDeserializer::new(scoped_parameters)).map_err(|error: serde_json::Error| {
       MessageError::ParameterValueError(format!(
         "Cannot get parameters from {:?}: {:?}",
         parameters, error
@@ -98,6 +238,42 @@ impl Job {
     })
   }
 
+  /// Reject the job with every violation of the schema, published as `validation_errors` in the job result
+  fn check_parameters(&self, schema: &RootSchema, parameters: &Map<String, Value>) -> Result<()> {
+    let violations = validate(schema, &Value::Object(parameters.clone()));
+    if violations.is_empty() {
+      return Ok(());
+    }
+
+    let message = violations
+      .iter()
+      .map(Violation::to_string)
+      .collect::<Vec<String>>()
+      .join(", ");
+    let job_result = JobResult::new(self.job_id)
+      .with_status(JobStatus::Error)
+      .with_message(&format!("Invalid parameters: {}", message))
+      .with_json(VALIDATION_ERRORS_PARAMETER, &violations)
+      .map_err(MessageError::RuntimeError)?;
+    Err(MessageError::ProcessingError(job_result))
+  }
+
+  /// Parameters of the order not declared in the schema, the SDK parameters (`requirements`, `log_level`, `sdk_*`) apart
+  pub fn get_unknown_parameters(&self, schema: &RootSchema) -> Vec<String> {
+    let declared_parameters = match get_declared_parameters(schema) {
+      Some(declared_parameters) => declared_parameters,
+      None => return vec![],
+    };
+
+    self
+      .parameters
+      .iter()
+      .map(|parameter| parameter.id.as_str())
+      .filter(|id| !declared_parameters.contains(id) && !is_sdk_parameter(id))
+      .map(str::to_string)
+      .collect()
+  }
+
   pub fn check_requirements(&self) -> Result<()> {
     if let Ok(requirements) = self.get_parameter::<Requirement>("requirements") {
       if let Some(paths) = requirements.paths {
@@ -111,13 +287,71 @@ impl Job {
           }
         }
       }
+      for file in requirements.files.iter().flatten() {
+        requirements::check_file(file)?;
+      }
+      for disk_space in requirements.free_disk_space.iter().flatten() {
+        check_disk_space(disk_space).map_err(|reason| {
+          MessageError::RequirementsError(format!("Warning: Not enough disk space, {}", reason))
+        })?;
+      }
+      for result in requirements.results.iter().flatten() {
+        requirements::check_result(&self.parameters, result)?;
+      }
+      for url in requirements.urls.iter().flatten() {
+        requirements::check_url(self.job_id, url)?;
+      }
     }
     Ok(())
   }
+
+  /// Check the `worker_version` requirement of the order against the version of the worker
+  pub fn check_worker_version(&self, version: &semver::Version) -> Result<()> {
+    match self
+      .get_parameter::<Requirement>("requirements")
+      .ok()
+      .and_then(|requirements| requirements.worker_version)
+    {
+      Some(requirement) => requirements::check_worker_version(&requirement, version),
+      None => Ok(()),
+    }
+  }
+
+  /// Checkpoint saved by a previous run of the order
+  pub fn get_checkpoint(&self) -> Option<Value> {
+    self
+      .get_parameter::<String>(CHECKPOINT_PARAMETER)
+      .ok()
+      .and_then(|checkpoint| serde_json::from_str(&checkpoint).ok())
+  }
+
+  /// Set the checkpoint parameter, replacing the previous one
+  pub fn with_checkpoint(mut self, checkpoint: &Value) -> Self {
+    self
+      .parameters
+      .retain(|parameter| parameter.id != CHECKPOINT_PARAMETER);
+    self.parameters.push(Parameter {
+      id: CHECKPOINT_PARAMETER.to_string(),
+      kind: String::get_type_as_string(),
+      store: None,
+      default: None,
+      value: Some(Value::String(checkpoint.to_string())),
+    });
+    self
+  }
+}
+
+fn is_sdk_parameter(id: &str) -> bool {
+  id.starts_with("sdk_") || id == "requirements" || id == LOG_LEVEL_PARAMETER
 }
 
 impl ParametersContainer for Job {
   fn get_parameters(&self) -> &Vec<Parameter> {
     &self.parameters
   }
+
+  /// The references are downloaded when the parameter is read
+  fn resolve_value(&self, value: Value) -> Result<Value> {
+    reference::resolve(self.job_id, value)
+  }
 }
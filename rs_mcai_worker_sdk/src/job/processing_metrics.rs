@@ -0,0 +1,174 @@
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  Arc, Mutex,
+};
+use std::time::Duration;
+
+/// Weight of the last decoded frame in the average decode latency
+#[cfg(feature = "media")]
+const DECODE_LATENCY_SMOOTHING: f64 = 0.1;
+
+/// Orders prefetched by the scheduler, waiting for a processing slot
+static PENDING_ORDERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Jobs in progress on all the workers of the process
+static RUNNING_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+/// Responses being published to the broker
+static PUBLISH_BACKLOG: AtomicUsize = AtomicUsize::new(0);
+
+/// State of the internal queues of the SDK during the processing of a job
+///
+/// Adaptive workers shed load under pressure, like lowering the analysis detail:
+///
+/// ```ignore
+/// let metrics = context.get_metrics();
+/// if metrics.pending_orders > 0 || metrics.output_backlog > 100 {
+///   self.analysis_detail = AnalysisDetail::Low;
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessingMetrics {
+  /// Orders prefetched by the worker, waiting for a processing slot (with `RERUN_LOOKAHEAD`)
+  pub pending_orders: usize,
+  /// Jobs in progress on the worker
+  pub running_jobs: usize,
+  /// Responses (progressions, results, job events...) being published to the broker
+  pub publish_backlog: usize,
+  /// Results of the job pushed to its output and not yet handled (`media` feature)
+  pub output_backlog: usize,
+  /// Moving average of the duration to read and decode a frame of the job (`media` feature)
+  pub decode_latency: Option<Duration>,
+}
+
+/// Gauges of a job, shared by the clones of its context
+#[derive(Debug, Default)]
+pub(crate) struct JobMetrics {
+  output_backlog: Mutex<Option<Arc<AtomicUsize>>>,
+  decode_latency: Mutex<Option<f64>>,
+  #[cfg(feature = "media")]
+  frames_processed: std::sync::atomic::AtomicU64,
+}
+
+impl JobMetrics {
+  /// Counter of the results pushed to the output of the job, decremented once handled
+  #[cfg(feature = "media")]
+  pub(crate) fn set_output_backlog(&self, output_backlog: Arc<AtomicUsize>) {
+    *self.output_backlog.lock().unwrap() = Some(output_backlog);
+  }
+
+  #[cfg(feature = "media")]
+  pub(crate) fn record_decode_latency(&self, latency: Duration) {
+    let latency = latency.as_secs_f64();
+    let mut decode_latency = self.decode_latency.lock().unwrap();
+    *decode_latency = Some(match *decode_latency {
+      Some(average) => average + (latency - average) * DECODE_LATENCY_SMOOTHING,
+      None => latency,
+    });
+  }
+
+  #[cfg(feature = "media")]
+  pub(crate) fn record_frame(&self) {
+    self.frames_processed.fetch_add(1, Ordering::SeqCst);
+  }
+
+  /// Frames of the first stream processed by the job
+  #[cfg(feature = "media")]
+  pub(crate) fn get_frames_processed(&self) -> u64 {
+    self.frames_processed.load(Ordering::SeqCst)
+  }
+
+  pub(crate) fn get_metrics(&self) -> ProcessingMetrics {
+    ProcessingMetrics {
+      pending_orders: PENDING_ORDERS.load(Ordering::SeqCst),
+      running_jobs: RUNNING_JOBS.load(Ordering::SeqCst),
+      publish_backlog: PUBLISH_BACKLOG.load(Ordering::SeqCst),
+      output_backlog: self
+        .output_backlog
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|output_backlog| output_backlog.load(Ordering::SeqCst))
+        .unwrap_or_default(),
+      decode_latency: self
+        .decode_latency
+        .lock()
+        .unwrap()
+        .map(Duration::from_secs_f64),
+    }
+  }
+}
+
+pub(crate) fn add_pending_orders(count: isize) {
+  update_gauge(&PENDING_ORDERS, count);
+}
+
+pub(crate) fn add_running_jobs(count: isize) {
+  update_gauge(&RUNNING_JOBS, count);
+}
+
+/// Update a gauge, never below zero
+pub(crate) fn update_gauge(gauge: &AtomicUsize, count: isize) {
+  let _ = gauge.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| {
+    Some(value.saturating_add_signed(count))
+  });
+}
+
+/// Response counted in the publish backlog until dropped
+pub(crate) struct PendingPublish;
+
+impl PendingPublish {
+  pub(crate) fn new() -> Self {
+    update_gauge(&PUBLISH_BACKLOG, 1);
+    PendingPublish
+  }
+}
+
+impl Drop for PendingPublish {
+  fn drop(&mut self) {
+    update_gauge(&PUBLISH_BACKLOG, -1);
+  }
+}
+
+#[test]
+pub fn test_processing_metrics() {
+  let job_metrics = JobMetrics::default();
+  let metrics = job_metrics.get_metrics();
+  assert_eq!(None, metrics.decode_latency);
+  assert_eq!(0, metrics.output_backlog);
+
+  let gauge = AtomicUsize::new(1);
+  update_gauge(&gauge, 2);
+  assert_eq!(3, gauge.load(Ordering::SeqCst));
+  update_gauge(&gauge, -4);
+  assert_eq!(0, gauge.load(Ordering::SeqCst));
+
+  let pending_publish = PendingPublish::new();
+  assert!(job_metrics.get_metrics().publish_backlog >= 1);
+  drop(pending_publish);
+}
+
+#[test]
+#[cfg(feature = "media")]
+pub fn test_job_metrics() {
+  let job_metrics = JobMetrics::default();
+
+  job_metrics.record_decode_latency(Duration::from_millis(10));
+  assert_eq!(
+    Some(Duration::from_millis(10)),
+    job_metrics.get_metrics().decode_latency
+  );
+  job_metrics.record_decode_latency(Duration::from_millis(20));
+  let decode_latency = job_metrics.get_metrics().decode_latency.unwrap();
+  assert!((decode_latency.as_secs_f64() - 0.011).abs() < 1e-9);
+
+  let output_backlog = Arc::new(AtomicUsize::new(3));
+  job_metrics.set_output_backlog(output_backlog.clone());
+  assert_eq!(3, job_metrics.get_metrics().output_backlog);
+  update_gauge(&output_backlog, -4);
+  assert_eq!(0, job_metrics.get_metrics().output_backlog);
+
+  job_metrics.record_frame();
+  job_metrics.record_frame();
+  assert_eq!(2, job_metrics.get_frames_processed());
+}
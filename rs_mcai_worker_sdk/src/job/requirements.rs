@@ -0,0 +1,128 @@
+//! Requirements of a job checked before its processing
+//!
+//! An order whose requirements are not met is handled with the `REQUIREMENTS_REQUEUE_POLICY`,
+//! like for a source still being uploaded or a service not yet available.
+
+use super::HttpClient;
+use crate::config::get_requirements_url_timeout;
+use crate::parameter::{FileRequirement, Parameter, ResultRequirement};
+use crate::{MessageError, Result};
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use std::fs::File;
+use std::time::Duration;
+
+pub(crate) fn check_file(requirement: &FileRequirement) -> Result<()> {
+  let metadata = std::fs::metadata(&requirement.path).map_err(|error| {
+    MessageError::RequirementsError(format!(
+      "Warning: Required file {:?} is not available: {}",
+      requirement.path, error
+    ))
+  })?;
+
+  if let Some(min_size) = requirement.min_size {
+    if metadata.len() < min_size {
+      return Err(MessageError::RequirementsError(format!(
+        "Warning: Required file {:?} has {} bytes, less than {}",
+        requirement.path,
+        metadata.len(),
+        min_size
+      )));
+    }
+  }
+
+  if requirement.readable {
+    File::open(&requirement.path).map_err(|error| {
+      MessageError::RequirementsError(format!(
+        "Warning: Required file {:?} is not readable: {}",
+        requirement.path, error
+      ))
+    })?;
+  }
+  Ok(())
+}
+
+/// The version of the worker must match the semantic versioning requirement, like `>=1.2, <2`
+pub(crate) fn check_worker_version(requirement: &str, version: &Version) -> Result<()> {
+  let version_requirement = VersionReq::parse(requirement).map_err(|error| {
+    MessageError::ParameterValueError(format!(
+      "Invalid worker version requirement {:?}: {}",
+      requirement, error
+    ))
+  })?;
+
+  if version_requirement.matches(version) {
+    Ok(())
+  } else {
+    Err(MessageError::RequirementsError(format!(
+      "Warning: Worker version {} does not match the required version {}",
+      version, requirement
+    )))
+  }
+}
+
+/// The URL must answer a `HEAD` request with a success status, within the `REQUIREMENTS_URL_TIMEOUT`
+pub(crate) fn check_url(job_id: u64, url: &str) -> Result<()> {
+  if !url.starts_with("http://") && !url.starts_with("https://") {
+    return Err(MessageError::ParameterValueError(format!(
+      "Unsupported scheme of the required URL {:?}, expected HTTP(S)",
+      url
+    )));
+  }
+
+  let timeout = Duration::from_secs(get_requirements_url_timeout());
+  let response = HttpClient::new(job_id)?
+    .get_client()
+    .head(url)
+    .timeout(timeout)
+    .send()
+    .map_err(|error| {
+      MessageError::RequirementsError(format!(
+        "Warning: Required URL {} is not reachable: {}",
+        url, error
+      ))
+    })?;
+
+  if !response.status().is_success() {
+    return Err(MessageError::RequirementsError(format!(
+      "Warning: Required URL {} answered with status {}",
+      url,
+      response.status()
+    )));
+  }
+  Ok(())
+}
+
+/// The value of a previous job must be set in the parameters of the order, and equal the expected value if any
+pub(crate) fn check_result(
+  parameters: &[Parameter],
+  requirement: &ResultRequirement,
+) -> Result<()> {
+  let mut path = requirement.key.split('.');
+  let parameter_id = path.next().unwrap_or_default();
+  let value = parameters
+    .iter()
+    .find(|parameter| parameter.id == parameter_id)
+    .and_then(|parameter| parameter.value.as_ref().or(parameter.default.as_ref()))
+    .and_then(|value| {
+      path.try_fold(value, |value, key| match value {
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        value => value.get(key),
+      })
+    })
+    .filter(|value| !value.is_null())
+    .ok_or_else(|| {
+      MessageError::RequirementsError(format!(
+        "Warning: Required result {} is not available",
+        requirement.key
+      ))
+    })?;
+
+  match &requirement.value {
+    Some(expected) if expected != value => Err(MessageError::RequirementsError(format!(
+      "Warning: Required result {} is {}, expected {}",
+      requirement.key, value, expected
+    ))),
+    _ => Ok(()),
+  }
+}
@@ -0,0 +1,75 @@
+use crate::job::{JobContext, JobPartialResult, JobResult, JobStatus};
+use crate::{McaiChannel, MessageError, Result};
+use serde::Serialize;
+use std::sync::{
+  atomic::{AtomicU64, Ordering},
+  Arc,
+};
+
+/// Publisher of the intermediate results of a job, like the outcome of each file of a batch
+///
+/// ```ignore
+/// let result_sender = ResultSender::new(channel.clone(), &context);
+/// for path in &parameters.paths {
+///   result_sender.send(&json!({"path": path, "checksum": compute_checksum(path)?}))?;
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ResultSender {
+  channel: Option<McaiChannel>,
+  context: JobContext,
+  index: Arc<AtomicU64>,
+}
+
+impl ResultSender {
+  pub fn new(channel: Option<McaiChannel>, context: &JobContext) -> Self {
+    ResultSender {
+      channel,
+      context: context.clone(),
+      index: Arc::new(AtomicU64::new(0)),
+    }
+  }
+
+  /// Publish the result on the `job_partial_result` queue
+  pub fn send<T: Serialize>(&self, result: &T) -> Result<()> {
+    let job_id = self.context.get_job_id();
+    let result = serde_json::to_value(result).map_err(|error| {
+      MessageError::ProcessingError(
+        JobResult::new(job_id)
+          .with_status(JobStatus::Error)
+          .with_message(&format!(
+            "Unable to serialize the partial result: {}",
+            error
+          )),
+      )
+    })?;
+
+    self.context.heartbeat();
+    let index = self.index.fetch_add(1, Ordering::SeqCst);
+    crate::message::publish_job_partial_result(
+      self.channel.clone(),
+      &JobPartialResult::new(job_id, index, result),
+    )
+  }
+
+  /// Number of published results
+  pub fn count(&self) -> u64 {
+    self.index.load(Ordering::SeqCst)
+  }
+}
+
+#[test]
+pub fn test_result_sender() {
+  let result_sender = ResultSender::new(None, &JobContext::new(123));
+  result_sender
+    .send(&json!({"path": "/data/a.mxf", "valid": true}))
+    .unwrap();
+
+  // the clones share the index of the results
+  let cloned_sender = result_sender.clone();
+  cloned_sender
+    .send(&json!({"path": "/data/b.mxf", "valid": false}))
+    .unwrap();
+
+  assert_eq!(2, result_sender.count());
+}
@@ -0,0 +1,108 @@
+//! Versions of the format of the orders
+//!
+//! An order gives the version of its format in `schema_version`, the orders without it being of version 1.
+//! The orders of an older version are upgraded to the current format once parsed:
+//! - version 1: a parameter of type `credential` is a string parameter read from its store,
+//!   the `BACKEND` store if none is given.
+//!
+//! An order of a version newer than the SDK supports is rejected.
+
+use crate::job::Job;
+use crate::worker::built_info;
+use crate::{MessageError, Result};
+
+/// Version of the format of the orders supported by the SDK
+pub const ORDER_SCHEMA_VERSION: u32 = 2;
+
+/// Version of the orders without `schema_version`
+const UNVERSIONED_ORDER_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+struct OrderVersion {
+  schema_version: Option<u32>,
+}
+
+/// Version of the order, read before the order itself to report a newer format
+pub(crate) fn check_message_schema_version(message: &str) -> Result<()> {
+  match serde_json::from_str::<OrderVersion>(message) {
+    Ok(order) => check_schema_version(order.schema_version).map(|_| ()),
+    // the error is reported by the parsing of the order
+    Err(_) => Ok(()),
+  }
+}
+
+fn check_schema_version(schema_version: Option<u32>) -> Result<u32> {
+  match schema_version.unwrap_or(UNVERSIONED_ORDER_SCHEMA_VERSION) {
+    0 => Err(MessageError::RuntimeError(
+      "Invalid order schema version 0".to_string(),
+    )),
+    version if version > ORDER_SCHEMA_VERSION => Err(MessageError::RuntimeError(format!(
+      "Order schema version {} is newer than the version {} supported by the SDK {}, upgrade the worker",
+      version,
+      ORDER_SCHEMA_VERSION,
+      built_info::PKG_VERSION
+    ))),
+    version => Ok(version),
+  }
+}
+
+/// Job of the order in the current format
+pub(crate) fn upgrade(mut job: Job) -> Result<Job> {
+  let version = check_schema_version(job.schema_version)?;
+
+  if version < 2 {
+    for parameter in &mut job.parameters {
+      if parameter.kind == "credential" {
+        parameter.kind = "string".to_string();
+        parameter.store.get_or_insert_with(|| "BACKEND".to_string());
+      }
+    }
+  }
+
+  job.schema_version = Some(ORDER_SCHEMA_VERSION);
+  Ok(job)
+}
+
+#[test]
+pub fn test_check_schema_version() {
+  assert_eq!(Ok(1), check_schema_version(None));
+  assert_eq!(
+    Ok(ORDER_SCHEMA_VERSION),
+    check_schema_version(Some(ORDER_SCHEMA_VERSION))
+  );
+  assert!(check_schema_version(Some(0)).is_err());
+
+  let message = format!(
+    r#"{{"schema_version": {}, "job_id": 123, "parameters": {{"new": "format"}}}}"#,
+    ORDER_SCHEMA_VERSION + 1
+  );
+  assert_eq!(
+    Err(MessageError::RuntimeError(format!(
+      "Order schema version 3 is newer than the version 2 supported by the SDK {}, upgrade the worker",
+      built_info::PKG_VERSION
+    ))),
+    Job::new(&message).map(|job| job.job_id)
+  );
+}
+
+#[test]
+pub fn test_upgrade_version_1_order() {
+  let job = Job::new(
+    r#"{
+      "job_id": 123,
+      "parameters": [
+        {"id": "api_key", "type": "credential", "value": "API_KEY"},
+        {"id": "token", "type": "credential", "store": "VAULT", "value": "TOKEN"},
+        {"id": "source_path", "type": "string", "value": "/data/source.mp4"}
+      ]
+    }"#,
+  )
+  .unwrap();
+
+  assert_eq!(Some(ORDER_SCHEMA_VERSION), job.schema_version);
+  assert_eq!("string", job.parameters[0].kind);
+  assert_eq!(Some("BACKEND".to_string()), job.parameters[0].store);
+  assert_eq!("string", job.parameters[1].kind);
+  assert_eq!(Some("VAULT".to_string()), job.parameters[1].store);
+  assert_eq!(None, job.parameters[2].store);
+}
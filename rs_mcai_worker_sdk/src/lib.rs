@@ -42,11 +42,21 @@
 //!
 //! ## Runtime configuration
 //!
+//! The configuration is read from the environment variables below.
+//! They can be overridden programmatically with [`SdkConfig`](struct.SdkConfig.html), applied with `SdkConfig::apply`
+//! before starting the worker, or given to `WorkerBuilder::with_config`.
+//!
+//! The configuration is validated when the worker starts: every invalid value (malformed number, boolean,
+//! port or URL, unknown policy...) is logged, then the process exits with the status `1`.
+//! Besides the SDK settings, the `<STORE_CODE>_HOSTNAME` of the backend stores configured with their credentials
+//! (`<STORE_CODE>_USERNAME` or `<STORE_CODE>_PASSWORD`) must be HTTP(S) URLs, the other `*_HOSTNAME` variables are not checked.
+//! The IPv6 addresses of `AMQP_HOSTNAME` are bracketed to give a port, like `[fd00::1]:5672`.
+//!
 //! ### AMQP connection
 //!
 //! |    Variable     | Description |
 //! |-----------------|-------------|
-//! | `AMQP_HOSTNAME` | IP or host of AMQP server (default: `localhost`) |
+//! | `AMQP_HOSTNAME` | IP or host of AMQP server, or comma separated `host[:port]` brokers to fail over between (default: `localhost`) |
 //! | `AMQP_PORT`     | AMQP server port (default: `5672`) |
 //! | `AMQP_TLS`      | enable secure connection using AMQPS (default: `false`, enable with `true` or `1` or `TRUE` or `True`) |
 //! | `AMQP_USERNAME` | Username used to connect to AMQP server (default: `guest`) |
@@ -54,6 +64,51 @@
 //! | `AMQP_VHOST`    | AMQP virtual host (default: `/`) |
 //! | `AMQP_QUEUE`    | AMQP queue name used to receive job orders (default: `job_undefined`) |
 //!
+//! ### Processing
+//!
+//! |    Variable             | Description |
+//! |-------------------------|-------------|
+//! | `MAX_CONCURRENT_JOBS`   | Number of jobs processed concurrently, by as many job threads (default: `1`, always `1` with the `media` feature) |
+//! | `JOB_TIMEOUT`           | Maximum duration of a job in seconds, overridden by the `sdk_timeout` job parameter (default: none) |
+//! | `RERUN_LOOKAHEAD`       | Number of orders prefetched to process the re-runs, flagged with the `sdk_rerun` boolean job parameter, ahead of the queue order and in a dedicated slot (default: `0`, disabled) |
+//! | `PROCESSING_WINDOWS`    | Cron expressions (`minute hour day-of-month month day-of-week`, local time) of the windows in which jobs are consumed, separated by `;`. Out of the windows the worker stays connected, completes its jobs in progress and reports `waiting` (default: none, always consuming) |
+//! | `JOB_EVENTS_EXCHANGE`   | Topic exchange, declared by the worker, on which the state transitions of the jobs (`received`, `validated`, `initializing`, `processing`, `publishing`, `completed` or `error`) are published as `JobEvent`, with the state as routing key (default: none) |
+//! | `HEARTBEAT_EXCHANGE`    | Topic exchange, declared by the worker, on which a heartbeat is published periodically with the queue of the worker as routing key: identity, `status` (`initializing`, `idle`, `processing`, `waiting` or `draining`), `cpu_usage` in percent, `total_memory` and `used_memory` in bytes, `disk_free` bytes of the workspace disk, `running_jobs` and `processed_jobs` (default: none) |
+//! | `HEARTBEAT_INTERVAL`    | Interval between two heartbeats in seconds (default: `30`) |
+//! | `JOB_TIMEOUT_POLICY`    | Handling of a timed out order once the error is published: `ack`, `requeue` or `dead_letter` (default: `ack`) |
+//! | `JOB_TIMEOUT_MAX_REQUEUES` | Number of requeues of an order timing out with the `requeue` policy, counted in the `x-timeout-count` header, before it is dead-lettered (default: `3`) |
+//! | `MAX_VIDEO_RESOLUTION`  | Resolution above which decoded images are downscaled, as `<width>x<height>` (default: none, `media` feature only) |
+//! | `MAX_SOURCE_RESOLUTION` | Resolution above which video sources are rejected, as `<width>x<height>` (default: `16384x16384`, `media` feature only) |
+//! | `SOURCE_MAX_BANDWIDTH`  | Read bandwidth in bytes per second shared by the sources of all the jobs, the SRT streams are not throttled (default: none, `media` feature only) |
+//! | `REQUIREMENTS_REQUEUE_POLICY` | Handling of an order whose requirements are not met: `reject`, or `back_of_queue` to publish it again behind the other orders after the `REQUIREMENTS_REQUEUE_DELAY` (default: `reject`) |
+//! | `REQUIREMENTS_REQUEUE_DELAY` | Delay in milliseconds before an order whose requirements are not met is published again with the `back_of_queue` policy (default: `10000`) |
+//! | `REQUIREMENTS_URL_TIMEOUT` | Timeout in seconds of the `HEAD` request checking a required URL (default: `5`) |
+//! | `TRANSIENT_MAX_RETRIES` | Number of retries of an order failing with a `Transient` error, counted in the `x-retry-count` header (default: `3`) |
+//! | `TRANSIENT_RETRY_DELAY` | Delay in milliseconds before the first retry, doubled on each retry. The order waits in a `<queue>.delay.<milliseconds>` queue, dead-lettered to its queue once expired (default: `1000`) |
+//! | `INIT_MAX_RETRIES`      | Number of retries of the worker initialization before exiting, jobs are consumed once initialized (default: `0`) |
+//! | `INIT_RETRY_DELAY`      | Delay in milliseconds before the first initialization retry, doubled on each retry (default: `5000`) |
+//! | `WATCHDOG_TIMEOUT`      | Duration in seconds without heartbeat (progression, checkpoint, processed frame or `JobContext::heartbeat`) after which a job is declared stuck and an error is published (default: none) |
+//! | `WATCHDOG_RESTART`      | Restart the worker process when a job is stuck, its order is delivered again (default: `false`) |
+//! | `HANDLE_TERMINATION_SIGNALS` | Run the shutdown hooks on `SIGTERM` and `SIGINT`, see [Shutdown](#shutdown) (default: `false`) |
+//! | `WORKSPACE_ROOT`        | Directory of the job workspaces, the scratch directories removed once each job is processed (default: system temporary directory) |
+//! | `WORKSPACE_QUOTA`       | Maximum size in bytes of a job workspace, checked by `JobWorkspace::check_quota` (default: none) |
+//! | `ADMISSION_MAX_CPU_LOAD`    | One minute load average per processor above which new orders are requeued, e.g. `0.9` (default: none) |
+//! | `ADMISSION_MIN_FREE_MEMORY` | Available memory in bytes below which new orders are requeued (default: none) |
+//! | `ADMISSION_MIN_FREE_DISK`   | Available space in bytes of the `WORKSPACE_ROOT` disk below which new orders are requeued (default: none) |
+//! | `ADMISSION_REQUEUE_DELAY`   | Delay in milliseconds during which an order is held in a delay queue before it is requeued while the host is saturated, the next orders being received meanwhile (default: `5000`) |
+//! | `CLAIM_REQUEUE_DELAY`   | Delay in milliseconds before requeueing an order whose resources are locked by another worker, see `MessageEvent::claim_job` (default: `5000`) |
+//! | `DELIVERY_LEASE_RENEWAL_INTERVAL` | Interval in seconds between two renewals of the delivery of an order in progress, lower than the delivery timeout of the broker (e.g. the RabbitMQ `consumer_timeout`), see [Delivery leases](#delivery-leases) (default: none) |
+//! | `WORKER_LABELS`         | Free-form labels of the worker for the filtering of the fleet, as comma separated `key=value` pairs like `team=ingest,gpu=true`, added by `WorkerBuilder::with_label`. They are in the worker description and the status responses (default: none) |
+//! | `WORKER_REGISTRATION_URL` | Endpoint of the backend on which the worker configuration (its description) is posted on startup, to keep the StepFlow catalog in sync. The worker is deregistered on shutdown with a `DELETE` request on `<WORKER_REGISTRATION_URL>/<instance_id>?queue_name=<queue_name>`. The requests are authenticated with a session of the `BACKEND` store once `BACKEND_USERNAME` is set (default: none) |
+//! | `DESCRIBE`              | Print the worker description and exit (default: none) |
+//! | `DESCRIBE_FORMAT`       | Format of the `DESCRIBE` output: `json` (pretty), `json-compact`, `yaml`, or `schema-only` for the JSON Schema of the parameters only (default: `json`) |
+//! | `LOG_FORMAT`            | Format of the log lines: `text`, or `json` for one JSON object per line with the `timestamp`, `level`, `job_id` (null out of the jobs), `worker` (the instance name), `queue`, `message`, and the `target`, `module`, `file` and `line` of the record as `fields` (default: `text`) |
+//! | `JOB_LOG_CAPTURE`       | Capture of the log records of each job, see [Job logs](#job-logs): `none`, `queue` or `result` (default: `none`) |
+//! | `JOB_LOG_CAPTURE_SIZE`  | Maximum number of log records captured per job, the oldest ones are dropped (default: `200`) |
+//! | `OTEL_EXPORTER_OTLP_ENDPOINT` | Base URL of the OTLP/HTTP collector to which the spans of the orders are exported, see [OpenTelemetry](#opentelemetry) (default: none, `otel` feature only) |
+//! | `OTEL_SERVICE_NAME`     | Service name of the exported spans (default: the worker name, `otel` feature only) |
+//! | `SELF_TEST`             | Initialize the worker, run its self-test, print the diagnostic and exit with `1` if a check failed (default: none) |
+//!
 //! ### Vault connection
 //!
 //! |    Variable        | Description |
@@ -62,6 +117,526 @@
 //! | `BACKEND_USERNAME` | Username used to connect to backend server |
 //! | `BACKEND_PASSWORD` | Password used to connect to backend server |
 //!
+//! The parameters with a `store` are resolved from the credential store. In an object value,
+//! only the fields declared as [`Credential<T>`](parameter/credential/struct.Credential.html) are resolved,
+//! their value being the key of the credential in the store of the parameter.
+//! The session opened on the backend is reused for the following credentials,
+//! and opened again once its token expires or is rejected.
+//!
+//! With the `ENVIRONMENT` store (or `env`), the credentials are read from the environment variables of the worker,
+//! for the local runs and the CI without backend. The variable is the key of the credential,
+//! prefixed with `ENVIRONMENT_STORE_PREFIX` if set (e.g. `SECRET_`) to restrict the variables the orders can read.
+//!
+//! With the `FILE` store, the credentials are read from the secrets mounted as files, like the Docker and Kubernetes secrets:
+//! the value is the content of the `<FILE_STORE_PATH>/<key>` file (default directory: `/run/secrets`),
+//! cached until the file is modified.
+//!
+//! With the `HASHICORP_VAULT` store, the credentials are read from the KV v2 secrets engine of a HashiCorp Vault server.
+//! The key of a credential is the path of the secret, with the field to extract after a `#` (e.g. `s3/ingest#secret_key`):
+//!
+//! |    Variable                  | Description |
+//! |------------------------------|-------------|
+//! | `HASHICORP_VAULT_HOSTNAME`   | Address of the Vault server (default: `http://127.0.0.1:8200`) |
+//! | `HASHICORP_VAULT_MOUNT`      | Mount path of the KV v2 secrets engine (default: `secret`) |
+//! | `HASHICORP_VAULT_TOKEN`      | Token of the worker, used as is |
+//! | `HASHICORP_VAULT_ROLE_ID`    | Role of the AppRole login, without token. The token obtained is renewed before the end of its lease |
+//! | `HASHICORP_VAULT_SECRET_ID`  | Secret of the AppRole login |
+//!
+//! With the `AWS_SECRETS_MANAGER` store, the credentials are read from AWS Secrets Manager.
+//! The key of a credential is the name or ARN of the secret, with the field of a JSON secret to extract after a `#`
+//! (e.g. `prod/s3-ingest#secret_key`). The region is set with `AWS_SECRETS_MANAGER_REGION`, or `AWS_REGION`.
+//! The requests are signed with the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN` credentials,
+//! or with the role assumed from `AWS_ROLE_ARN` and `AWS_WEB_IDENTITY_TOKEN_FILE`, as set up by EKS for the service accounts.
+//!
+//! With the `GCP_SECRET_MANAGER` store, the credentials are read from Google Secret Manager, authenticated with the
+//! workload identity of the worker on GKE. The key of a credential is the name of the secret, with its version after a `@`
+//! and the field of a JSON secret to extract after a `#` (e.g. `s3-ingest@3#secret_key`), or a full resource name.
+//! The secrets are read from `GCP_SECRET_MANAGER_PROJECT`, or the project of the instance.
+//!
+//! Other secret backends, like a corporate KMS or a database, implement the
+//! [`ParameterStore`](parameter/store/trait.ParameterStore.html) trait and are registered with their store code
+//! by `parameter::store::register_store`, taking precedence over the built-in stores.
+//!
+//! Any other store code is an MCAI backend, configured with the `<STORE_CODE>_HOSTNAME`, `_USERNAME` and `_PASSWORD` variables.
+//! A parameter referencing a store neither registered nor configured is rejected with an explicit error.
+//!
+//! ### External HTTP client
+//!
+//! Client returned by `JobContext::get_http_client`, for the external APIs called by the jobs.
+//!
+//! |    Variable                              | Description |
+//! |------------------------------------------|-------------|
+//! | `HTTP_CLIENT_TIMEOUT`                    | Timeout of the requests in seconds (default: none) |
+//! | `HTTP_CLIENT_PROXY`                      | URL of the proxy of the requests (default: system proxy) |
+//! | `HTTP_CLIENT_CA_CERTIFICATE`             | Path of an additional PEM root certificate (default: none) |
+//! | `HTTP_CLIENT_ACCEPT_INVALID_CERTIFICATES` | Accept invalid TLS certificates (default: `false`) |
+//! | `HTTP_CLIENT_MAX_RETRIES`                | Number of retries on connection errors, `429` and `5xx` responses, before failing the job with a `Transient` error (default: `3`) |
+//! | `HTTP_CLIENT_RETRY_DELAY`                | Delay in milliseconds before the first retry, doubled on each retry, unless given by `Retry-After` (default: `500`) |
+//! | `HTTP_CLIENT_RATE_LIMIT`                 | Maximum number of requests per second to a host, shared by the jobs of the worker (default: none) |
+//!
+//! ### Security
+//!
+//! |    Variable               | Description |
+//! |---------------------------|-------------|
+//! | `ORDER_SIGNATURE_KEY`     | if set, orders must carry a `x-signature` header with the hex HMAC-SHA256 of the message body |
+//! | `RESPONSE_ENCRYPTION_KEY` | if set, response messages are encrypted with AES-256-GCM using this hex encoded 32 bytes key |
+//!
+//! ### Chaos mode
+//!
+//! For staging environments only, to rehearse the retries of the workflows. Probabilities are between `0` and `1` (default: `0`).
+//!
+//! |    Variable                       | Description |
+//! |-----------------------------------|-------------|
+//! | `CHAOS_MODE`                      | Inject the faults below (default: `false`) |
+//! | `CHAOS_LATENCY_PROBABILITY`       | Probability to wait before processing a job |
+//! | `CHAOS_LATENCY_MAX`               | Maximum latency in milliseconds, the latency is random (default: `5000`) |
+//! | `CHAOS_FAILURE_PROBABILITY`       | Probability to fail a job with a processing error instead of processing it |
+//! | `CHAOS_TRANSIENT_PROBABILITY`     | Probability to fail a job with a `Transient` error instead of processing it |
+//! | `CHAOS_PUBLISH_DELAY_PROBABILITY` | Probability to delay the publish of a response (progression, completion or error) |
+//! | `CHAOS_PUBLISH_DELAY_MAX`         | Maximum publish delay in milliseconds, the delay is random (default: `5000`) |
+//!
+//! ## Direct messaging
+//!
+//! Each worker instance consumes its own `direct_messaging_<instance_id>` queue:
+//!
+//! |    Message                                   | Description |
+//! |----------------------------------------------|-------------|
+//! | `{"type": "status"}`                         | publish the identity of the worker (`docker_container_id`, `instance_name`, `worker_name`, `worker_version`, `queue_name`, `labels`), its `uptime` in seconds, the system information, the state (`ready`, `init_attempts`, `init_error`, `draining`, `waiting`, `running_jobs`, `drained`), the `jobs` in progress with their last `progression`, and the `last_error` of a job, on the `worker_status_response` queue (default for any other message) |
+//! | `{"type": "stop_job", "job_id": <job_id>}`   | cancel the job in progress, which aborts once the worker polls its `JobContext` |
+//! | `{"type": "self_test"}`                      | run the worker self-test, publish its diagnostic on the `worker_status_response` queue |
+//! | `{"type": "describe"}`                       | publish the worker description, like with `DESCRIBE=1` at startup, on the `reply_to` queue of the message (with its `correlation_id`) or on the `DESCRIBE_RESPONSE_QUEUE` queue (default: `worker_discovery`). The description is cached for `DESCRIBE_CACHE_TTL` seconds (default: `60`) |
+//! | `{"type": "drain", "exit": <bool>}`          | stop consuming jobs, publish the status with `drained` once the jobs in progress are completed, then exit if requested |
+//! | `{"type": "reload_configuration", "values": {"<KEY>": "<value>"}}` | apply new values of the non-secret reloadable configuration: `HTTP_CLIENT_TIMEOUT`, `HTTP_CLIENT_MAX_RETRIES`, `HTTP_CLIENT_RETRY_DELAY`, `HTTP_CLIENT_RATE_LIMIT` and `MAX_CONCURRENT_JOBS` (not with `RERUN_LOOKAHEAD` nor media workers, the job consumer being started again with the new prefetch), then call `MessageEvent::on_config_reload` and drop the sessions of the parameter stores. The other keys, like the hostnames and credentials of the stores, are ignored: the worker re-reads them in `on_config_reload`, e.g. with `SdkConfig::apply` |
+//!
+//! ## Instance identification
+//!
+//! The identifier of the instance, returned by `get_instance_id`, names its direct messaging queue and is set in its responses.
+//! It is the first available of the `INSTANCE_ID` variable, the name (`POD_NAME`) or UID (`POD_UID`) of the Kubernetes pod
+//! given by the downward API, the hostname in a Kubernetes pod, the Docker container ID (from the cgroups v1 or v2),
+//! or an UUID generated for the process.
+//!
+//! ```yaml
+//! env:
+//!   - name: POD_NAME
+//!     valueFrom:
+//!       fieldRef:
+//!         fieldPath: metadata.name
+//! ```
+//!
+//! The `INSTANCE_NAME` variable gives the instance a human-readable name (e.g. `transcoder-gpu-01`), its identifier by default.
+//! It is the tag of the consumer of the job orders (`status_<name>` for the direct messages), listed by the management UI of the broker,
+//! prefixes the log lines, and is the `instance_name` of the status responses, heartbeats and worker description.
+//!
+//! ## Hardware capabilities
+//!
+//! The worker description advertises the `hardware` of the instance, for the orchestrators to route the heavy jobs to the capable instances:
+//! the number of processors (`cpu_cores`), the `total_memory` in bytes, and the `gpus`,
+//! the NVIDIA devices listed by `nvidia-smi` (with their memory) and the VAAPI render nodes of `/dev/dri`.
+//!
+//! ## Content types
+//!
+//! The worker declares the MIME types of the sources it accepts with `MessageEvent::get_input_content_types`
+//! (e.g. `video/mp4` or `video/*`) and of the files it produces with `MessageEvent::get_output_content_types`.
+//! They are the `input_content_types` and `output_content_types` of the worker description, empty if not declared,
+//! for the workflow builders to check the compatibility of the steps.
+//!
+//! ## Instance parameters
+//!
+//! Besides the job parameters, the worker may declare the parameters of its instance (model path, device index, license key...)
+//! with `MessageEvent::get_instance_parameters_schema`. Each one is read at startup from the configuration variable
+//! of its uppercased name prefixed with `INSTANCE_PARAMETER_` (`model_path` from `INSTANCE_PARAMETER_MODEL_PATH`),
+//! the non-string values being JSON (`2`, `true`, `["en", "fr"]`).
+//! They are given to `MessageEvent::init_with_parameters`, where `InstanceParameters::deserialize` returns them typed,
+//! and are described in the `instance_parameters` of the worker description.
+//! A missing required parameter or an invalid value fails the initialization.
+//!
+//! ## Media job parameters
+//!
+//! With the `media` feature, these job parameters are handled by the SDK:
+//!
+//! |    Parameter                    | Description |
+//! |---------------------------------|-------------|
+//! | `sdk_start_index`               | Start of the processed segment in milliseconds (integer) |
+//! | `sdk_stop_index`                | End of the processed segment in milliseconds (integer) |
+//! | `sdk_check_audio_continuity`    | Check the timestamps of the audio frames, logging gaps and overlaps, and add an `audio_continuity` report to the job result (boolean, default: `false`) |
+//! | `sdk_source_bandwidth`          | Read bandwidth of the source in bytes per second, within the `SOURCE_MAX_BANDWIDTH` shared by the jobs. The SRT streams are not throttled (integer, default: none) |
+//!
+//! ## Parameters validation
+//!
+//! Before calling `process`, the parameters missing from the order take the default value declared in the schema of the parameters,
+//! like with `#[schemars(default = "default_threshold")]`, including the fields of nested objects.
+//! The parameters are then validated against this schema (types, required fields, ranges, lengths, patterns, enumerations...).
+//! An invalid order is published on the `job_error` queue, with every violation listed in the `validation_errors` parameter of the job result:
+//!
+//! ```json
+//! [{"path": "/threshold", "message": "1.5 is greater than the maximum 1"}]
+//! ```
+//!
+//! `Job::get_validated_parameters` applies the defaults and the validation to the parameters of a job,
+//! `Job::get_parameters_with_defaults` only the defaults, and `Job::get_parameters` none of them.
+//!
+//! The parameters of an order not declared in the schema, like a misspelled `destiantion_path`, are ignored with a warning,
+//! and listed in the `unknown_parameters` parameter of the job result. The SDK parameters (`requirements`, `log_level`, `sdk_*`) are not reported.
+//!
+//! ## Parameter templates
+//!
+//! The parameters declared as [`Template<T>`](parameter/template/struct.Template.html) can hold placeholders in their strings,
+//! expanded before the deserialization of the parameters by `Job::get_validated_parameters` (and `get_parameters_with_defaults`),
+//! so that generic orders give concrete paths to the worker, like `{env:OUTPUT_ROOT}/{date}/{job_id}.mp4`:
+//! `{job_id}` is the identifier of the job, `{date}` the current UTC date (`YYYY-MM-DD`)
+//! and `{env:NAME}` the `NAME` environment variable of the worker.
+//! The orders can only read the variables listed, comma separated, in `PARAMETER_TEMPLATE_VARIABLES`, none by default.
+//! Any other text between braces is kept as is. The other parameters and the parameters with a `store` are not expanded.
+//!
+//! ## Parameter references
+//!
+//! To keep the AMQP messages small, the value of a parameter can reference a JSON document by URL,
+//! like `{"@ref": "https://config.example.com/big_config.json"}`.
+//! The document is downloaded with the external HTTP client when the parameter is read,
+//! and replaces the reference before the deserialization of the parameters.
+//! Its size is limited to `PARAMETER_REFERENCE_MAX_SIZE` bytes (default: 64 MiB).
+//! Only the URLs under the comma separated prefixes of `PARAMETER_REFERENCE_ALLOWED_URLS`
+//! (like `https://config.example.com/workers/`) are downloaded, none by default,
+//! and the redirects are followed within these prefixes only.
+//! Each document is downloaded once per job, while its order is processed.
+//! The referenced value of a [`Template`](parameter/template/struct.Template.html) parameter is expanded as its literal value.
+//!
+//! ## Deprecated parameters
+//!
+//! A parameter is deprecated by the `#[deprecated]` attribute of its field, or with its replacement
+//! in `MessageEvent::get_parameter_deprecations` (e.g. `ParameterDeprecation::new("source_url").replaced_by("source_path")`).
+//! It is marked as `deprecated` in the worker description, with its replacement in `replaced_by`,
+//! and a warning is logged for each job using it.
+//!
+//! ## Parameter display hints
+//!
+//! The forms of the StepFlow frontend are built from the worker description.
+//! `MessageEvent::get_parameter_hints` gives a human readable label to the parameters, groups them in sections
+//! and selects their widget (slider, file picker...), e.g.
+//! `ParameterHint::new("threshold").with_label("Detection threshold").in_section("Detection").with_widget(Widget::Slider)`.
+//! The label is the `title` of the parameter in the worker description, the other hints are in its `ui` object,
+//! with the labels of the values of an enumeration in `enum_labels`.
+//!
+//! ## Secret parameters
+//!
+//! The fields declared as [`Secret<T>`](parameter/secret/struct.Secret.html) are marked as `secret` (and `writeOnly`) in the worker description.
+//! While a job is in progress, the values of its secret parameters are replaced by `***` in the logs
+//! (including the dump of the received job and the error messages) and in all the messages published by the worker.
+//! A `Secret` is serialized as `***`, its default value and examples are masked in the worker description and its default value is not applied.
+//!
+//! ## Durations and byte sizes
+//!
+//! The fields declared as [`Duration`](parameter/duration/struct.Duration.html) accept a number of milliseconds,
+//! or a string like `"00:01:30"`, `"90s"`, `"1h30m"` or `"500ms"`. The fields declared as [`ByteSize`](parameter/byte_size/struct.ByteSize.html)
+//! accept a number of bytes, or a string like `"500MB"` or `"1.5GiB"`. Their strings are checked by the validation of the parameters,
+//! and they are described with the `duration` and `byte_size` formats in the worker description.
+//! With `get_parameter`, they are the `duration` and `byte_size` parameter types.
+//!
+//! ## Source and destination paths
+//!
+//! The fields declared as [`SourcePath`](parameter/media_path/struct.SourcePath.html) or [`DestinationPath`](parameter/media_path/struct.DestinationPath.html)
+//! are local paths, or URLs with a `file`, `http`, `https`, `s3` or `ftp` scheme. Any other scheme is rejected when the parameters are deserialized.
+//! Their components are parsed once: `local_path`, `host`, `port`, `path`, `file_name`, and `bucket` and `key` of an S3 location.
+//! They are string parameters, also read with `get_parameter::<SourcePath>("source_path")`.
+//!
+//! ## Segments
+//!
+//! The workers processing parts of a media declare them as [`MediaSegments`](parameter/media_segment/type.MediaSegments.html),
+//! an array of `{start, end}` boundaries in milliseconds or as timecodes (`array_of_media_segments` parameter type).
+//! A segment ending before its start is rejected, `media_segment::check_non_overlapping` also rejects the overlapping segments.
+//! Each segment gives its positions as timestamps in the time base of a stream (`MediaSegment::to_pts`),
+//! or as the `-ss` and `-to` options of the ffmpeg command line (`MediaSegment::to_ffmpeg_arguments`).
+//!
+//! ## Arrays of objects
+//!
+//! A job parameter of type `array_of_objects` is a list of nested objects, like `segments: [{start, end, label}]`.
+//! It is deserialized in the parameters struct as a `Vec` of the item type, and can also be set as a JSON string.
+//! To get it with `get_parameter`, the item type implements [`ObjectParameter`](parameter/trait.ObjectParameter.html).
+//! The nested objects are described in place in the schemas of the `DESCRIBE` output.
+//!
+//! ## Job requirements
+//!
+//! The `requirements` job parameter is checked before the processing of the job,
+//! an order whose requirements are not met is handled with the `REQUIREMENTS_REQUEUE_POLICY`:
+//!
+//! ```json
+//! {
+//!   "paths": ["/data/source.mxf"],
+//!   "files": [{"path": "/data/source.mxf", "min_size": 1048576, "readable": true}],
+//!   "urls": ["https://storage.example.com/source.mxf"]
+//! }
+//! ```
+//!
+//! The `paths` must exist, the `files` must have at least `min_size` bytes and be readable if requested,
+//! and the `urls` must answer a `HEAD` request with a success status.
+//!
+//! With `"results": [{"key": "probe.duration"}, {"key": "probe.streams.0.codec", "value": "h264"}]`,
+//! the values produced by the previous jobs of the workflow must be set in the order, `key` being a parameter
+//! followed by the path in its JSON value, and equal the expected `value` if any.
+//! The order is requeued until then, with the missing result as reason.
+//!
+//! With `"free_disk_space": [{"path": "/scratch", "bytes": 10737418240}]`, the disk of the path must have the space available.
+//! It is checked before accepting the order, which is held in a delay queue for the `ADMISSION_REQUEUE_DELAY` otherwise,
+//! like when the host is saturated, instead of failing once the disk is full. The consumer receives the next orders meanwhile.
+//!
+//! With `"worker_version": ">=1.2, <2"`, the version of the worker (`MessageEvent::get_version`) must match
+//! the semantic versioning requirement. Otherwise the order is held in a delay queue for the `ADMISSION_REQUEUE_DELAY`,
+//! the consumer receiving the next orders meanwhile, to be processed by a compatible worker of a mixed-version fleet,
+//! with the versions of the worker and of the requirement as reason.
+//!
+//! ## Order schema version
+//!
+//! An order gives the version of its format in `schema_version` (`1` if not set, the current version being
+//! [`ORDER_SCHEMA_VERSION`](job/constant.ORDER_SCHEMA_VERSION.html)). The orders of an older version are upgraded
+//! to the current format once parsed, like the version 1 parameters of type `credential`, read from their store or the `BACKEND` store by default.
+//! An order of a newer version is rejected with both versions in the error, the worker having to be upgraded.
+//!
+//! ## Job priority
+//!
+//! An order can have a `priority` field, available to the worker in `Job::priority`.
+//! The orders are delivered by priority by the broker, the job queues being declared with a maximum priority of `100`:
+//! the order messages are published with this priority as AMQP `priority` property.
+//! The orders published again by the worker (requeued, delayed or retried) keep their AMQP priority,
+//! or take the `priority` of the order, bounded between `0` and `100`.
+//!
+//! ## Distributed tracing
+//!
+//! An order can carry the W3C `traceparent` of its distributed trace in a `trace_context` field.
+//! The responses published for the job (progressions, partial results, job events and its result) carry it
+//! in their `traceparent` header, and the worker gets it from `JobContext::get_trace_context`
+//! to continue the trace in its own outgoing calls. An invalid trace context is ignored.
+//!
+//! ## OpenTelemetry
+//!
+//! With the `otel` feature and `OTEL_EXPORTER_OTLP_ENDPOINT` set, each order is exported as an `order` span,
+//! child of its `trace_context` when present, with the `process`, `resolve_credential` and `publish` spans
+//! of its processing, the resolution of its credentials and the publication of its responses.
+//! A failed step sets the error status of its span. The pending spans are exported on shutdown.
+//!
+//! ## Job claims
+//!
+//! Workers sharing resources, like a destination file, acquire an external lock or lease in `MessageEvent::claim_job`,
+//! called once the order is delivered and before processing it. `JobClaim::Contended` requeues the order
+//! after `CLAIM_REQUEUE_DELAY` milliseconds through a delay queue, the next orders being received meanwhile, while the [`JobLease`](job/struct.JobLease.html) of an acquired claim
+//! is released once the result of the job is published. The batches are not claimed.
+//!
+//! ```ignore
+//! fn claim_job(&self, job: &Job) -> Result<JobClaim> {
+//!   let destination: String = job.get_parameter("destination_path")?;
+//!   match self.locks.try_lock(&destination)? {
+//!     Some(lock) => Ok(JobClaim::Acquired(JobLease::new().with_release(move || lock.release()))),
+//!     None => Ok(JobClaim::Contended(format!("{} is locked", destination))),
+//!   }
+//! }
+//! ```
+//!
+//! ## Delivery leases
+//!
+//! The broker delivers an order again if its delivery is not acknowledged in time, like after the `consumer_timeout`
+//! of RabbitMQ (30 minutes by default), even if its job is still in progress. With `DELIVERY_LEASE_RENEWAL_INTERVAL`,
+//! the delivery of an order in progress is renewed: the order is published on a `<queue>.lease.<uuid>` queue,
+//! got back as a new delivery and the previous one is acknowledged. If the worker is lost,
+//! the pending copy expires after 10 seconds and is dead-lettered to the job queue, as the original order would be.
+//! The lease queues are deleted by the broker once unused.
+//!
+//! ## Skipped jobs and warnings
+//!
+//! A job with nothing to do, e.g. its input has already been processed, returns `JobResult::skipped(reason)`,
+//! with the `skipped` status and the reason as `message`.
+//! The non-fatal issues of a job are added with `JobResult::with_warning(warning)`: the job completed with warnings
+//! has the `completed_with_warnings` status, and the issues in the `warnings` parameter of its result.
+//! Both are published on the `job_completed` queue, like the completed jobs.
+//!
+//! ## Response origin
+//!
+//! The completed, error and progression messages of a job carry the `sdk_version`, with the `worker_name` and `worker_version`
+//! of the worker processing it, for the orchestrator to audit which binary produced which result.
+//!
+//! ## Partial results
+//!
+//! A job can publish intermediate results, like the outcome of each file of a batch, before its final result:
+//! a [`ResultSender`](job/struct.ResultSender.html) created in `process` from the channel and the job context publishes
+//! each result on the `job_partial_result` queue, with the job identifier and the index of the result.
+//!
+//! ## Artifacts
+//!
+//! The files produced by a job are declared in its result with `JobResult::with_artifact(name, path_or_url, content_type, checksum)`.
+//! They are listed in the `artifacts` of the completed message, for the workflow engine to pick them up:
+//!
+//! ```json
+//! "artifacts": [{"name": "proxy", "location": "s3://bucket/proxy.mp4", "content_type": "video/mp4", "checksum": "sha256:9f86d081..."}]
+//! ```
+//!
+//! ## Structured results
+//!
+//! `JobResult::with_json(id, value)` stores the JSON value of the payload in the result message,
+//! objects and arrays are not embedded as strings. An untyped payload is a `serde_json::Value`, set as a `json` parameter:
+//!
+//! ```ignore
+//! let job_result = job_result.with_json("detections", &json!({"faces": [{"x": 10, "y": 20}]}))?;
+//! ```
+//!
+//! ## Frame results
+//!
+//! Analysis workers accumulate their per-frame results (stream, PTS and payload) in a
+//! [`FrameResultCollector`](job/struct.FrameResultCollector.html): the numeric fields of the payloads
+//! can be aggregated (count, min, max, mean and histogram), the results can be streamed as they are pushed,
+//! and `attach` adds the `frame_results` and `frame_aggregates` parameters to the job result.
+//!
+//! ## Progression details
+//!
+//! Besides the percentage of `publish_job_progression`, `publish_job_progression_details` publishes the current step of the job,
+//! e.g. `ProgressionDetails::new().with_step("encoding").with_items(3, 12)`. The progression message then carries
+//! the `step`, `items_done` and `items_total`, with the estimated remaining time in seconds as `eta`,
+//! computed from the time elapsed since the start of the job and the items processed (or the percentage without items).
+//!
+//! ## Checkpoints
+//!
+//! Long jobs can save their progress with [`publish_job_checkpoint`](fn.publish_job_checkpoint.html)
+//! or `JobContext::set_checkpoint`, and resume from `JobContext::get_checkpoint` when the order is delivered again.
+//! The checkpoint is carried by the `sdk_checkpoint` job parameter, as a JSON string:
+//! it is set by the worker when an order is retried after a `Transient` error or requeued after a timeout,
+//! and can be set by the backend from the `checkpoint` of the job progressions.
+//!
+//! ## Processing metrics
+//!
+//! `JobContext::get_metrics` returns the state of the internal queues of the SDK during the processing:
+//! the orders prefetched waiting for a slot, the jobs in progress, the responses being published to the broker,
+//! and with the `media` feature the results not yet handled by the output and the average decode latency of the frames.
+//! Adaptive workers rely on them to shed load under pressure, like lowering the detail of an analysis.
+//!
+//! ## Execution metrics
+//!
+//! The result of a completed job carries the resources it used in its `execution_metrics` parameter,
+//! for the performance dashboards of the orchestrator: the wall-clock duration and the CPU time of the processing
+//! in seconds, the peak resident memory of the worker process in bytes, and with the `media` feature
+//! the number of frames processed.
+//!
+//! ```json
+//! {"id": "execution_metrics", "type": "execution_metrics", "value": {"wall_clock_time": 12.4, "cpu_time": 38.1, "peak_rss": 512000000, "frames_processed": 310}}
+//! ```
+//!
+//! The CPU time is measured for the whole process, it includes the jobs processed concurrently.
+//!
+//! ## Embedding in an application
+//!
+//! `start_worker` owns the process. To run the worker alongside other services,
+//! [`Worker::builder`](struct.Worker.html) builds a worker spawned on its own thread,
+//! controlled with the returned [`WorkerHandle`](struct.WorkerHandle.html) (`stop`, `join`, readiness and running jobs).
+//! The logger is not initialized in this mode, and the `DESCRIBE`, `SELF_TEST` and `SOURCE_ORDERS` modes are not available.
+//!
+//! The SDK does not require any asynchronous runtime: jobs run on threads of the worker and `process_async`
+//! is driven by a runtime created for each job. Inside an application already running Tokio,
+//! enable the `tokio_runtime` feature and provide its runtime:
+//!
+//! ```ignore
+//! let handle = Worker::builder()
+//!   .with_message_event(WorkerEvent {})
+//!   .with_executor(mcai_worker_sdk::runtime::TokioExecutor::current())
+//!   .build()?
+//!   .spawn()?;
+//! ```
+//!
+//! It then drives the AMQP connection and `process_async`, and only the orders with a timeout
+//! (and the re-runs scheduler) run on its blocking threads. The other jobs still run on the job pool of the worker,
+//! or on the thread consuming the orders with a single concurrent job.
+//!
+//! Other runtimes are supported by implementing the [`Executor`](runtime/trait.Executor.html) trait.
+//!
+//! ## Shutdown
+//!
+//! `MessageEvent::on_shutdown` is called once when the worker terminates: on `SIGTERM` or `SIGINT`
+//! with `HANDLE_TERMINATION_SIGNALS`, on a fatal error like a failed `init`, after a drain or a watchdog restart, and when an embedded worker is stopped.
+//! Implementations flush their caches, close their connections or persist their models there.
+//! The hook is skipped if a job still holds the worker after 10 seconds.
+//! The termination signals are only handled once enabled, not to replace the handlers of the application
+//! embedding the worker: the previous handlers are called after the hooks and the process exits with `128 + signal`.
+//!
+//! ## Job actions
+//!
+//! A worker can process several kinds of orders: the `action` field of an order selects its handler
+//! among the ones returned by `MessageEvent::get_actions`, each parsing its own parameters.
+//! Orders without `action` are processed by `process`. The parameter schemas of the actions
+//! are published in the `actions` of the worker description (`DESCRIBE`).
+//! Actions are not supported with the `media` feature.
+//!
+//! ```json
+//! {"job_id": 123, "action": "thumbnail", "parameters": [{"id": "width", "type": "integer", "value": 320}]}
+//! ```
+//!
+//! ## Job log level
+//!
+//! The logs of a job are the records targeting its identifier (`info!(target: &job_id.to_string(), ...)`).
+//! The `log_level` job parameter (`error`, `warn`, `info`, `debug` or `trace`) raises their verbosity for that job only,
+//! the logs of the other jobs remain filtered by `RUST_LOG`.
+//!
+//! ```json
+//! {"job_id": 123, "parameters": [{"id": "log_level", "type": "string", "value": "debug"}]}
+//! ```
+//!
+//! ## Job logs
+//!
+//! With `JOB_LOG_CAPTURE`, the records of a job emitted through the logger of the worker are also captured while it is processed,
+//! with their `datetime`, `level` and masked `message`, up to the last `JOB_LOG_CAPTURE_SIZE` ones.
+//! With `queue`, they are published on the `job_logs` queue once the job is processed, with the `job_id` and the number of `dropped_records`.
+//! With `result`, they are added to the `logs` parameter of the result of the job, completed or in error.
+//!
+//! ## External programs
+//!
+//! [`job::command::Command`](job/command/struct.Command.html) runs a program (ffmpeg, imagemagick...)
+//! in the job workspace: its output lines are written in the job log and can be parsed into progressions.
+//! The program is killed when the job is cancelled or its timeout is reached,
+//! and a non-zero exit code fails the job with its last error lines.
+//!
+//! ```ignore
+//! let output = Command::new("ffmpeg")
+//!   .with_args(&["-i", &source_path, &destination_path])
+//!   .with_timeout(Duration::from_secs(3600))
+//!   .run(channel, &context)?;
+//! ```
+//!
+//! ## Middlewares
+//!
+//! Cross-cutting concerns (metrics, authorization checks, payload rewriting...) are plugged around the processing
+//! of the orders with a [`Middleware`](message/middleware/trait.Middleware.html), registered for the whole process
+//! with `message::middleware::register_middleware` (or `WorkerBuilder::with_middleware`):
+//!
+//! |    Hook              | Description |
+//! |----------------------|-------------|
+//! | `on_order_received`  | Called with the payload of each order before it is parsed, returns the payload to process |
+//! | `before_process`     | Called before processing each job, an error fails the job without processing it |
+//! | `after_process`      | Called with the result of each processed job, returns the result to publish |
+//! | `on_response_sent`   | Called once a response (progression, completion or error) is published |
+//!
+//! ## Hosting several workers
+//!
+//! Tiny workers can share a process: [`WorkerRegistry`](struct.WorkerRegistry.html) hosts several
+//! `MessageEvent` implementations, each consuming its own queue on a shared AMQP connection.
+//! The logger is shared, and each worker answers the direct messages on its own queue (the instance identifier suffixed with the queue name).
+//! The `DESCRIBE`, `SELF_TEST` and `SOURCE_ORDERS` modes are not available.
+//!
+//! ```ignore
+//! WorkerRegistry::new()
+//!   .register(ThumbnailEvent {}, "job_thumbnail")?
+//!   .register(ChecksumEvent {}, "job_checksum")?
+//!   .start();
+//! ```
+//!
+//! ## Command line arguments
+//!
+//! With the `cli` feature, `start_worker` parses the arguments of the binary, overriding the environment variables:
+//!
+//! |    Argument           | Description |
+//! |-----------------------|-------------|
+//! | `--queue <QUEUE>`     | Queue of the job orders, like `AMQP_QUEUE` |
+//! | `--amqp-url <URL>`    | Broker URL, as `amqp[s]://<username>:<password>@<hostname>:<port>/<vhost>`, like the `AMQP_*` variables |
+//! | `--instance-name <NAME>` | Name of the instance, like `INSTANCE_NAME` |
+//! | `--describe`          | Print the worker description and exit, like `DESCRIBE` |
+//! | `--order <FILE>`      | Process the JSON order of the file without broker, may be repeated, like `SOURCE_ORDERS` |
+//! | `--log-level <LEVEL>` | Log level or filter, like `RUST_LOG` |
+//!
+//! `--help` prints the usage with the name, version and description of the worker.
+//!
 //! ## Start worker locally
 //!
 //! MCAI Worker SDK can be launched locally - without RabbitMQ.
@@ -76,6 +651,9 @@
 //! RUST_LOG=info SOURCE_ORDERS=./examples/success_order.json:./examples/error_order.json cargo run --example worker
 //! ```
 
+// `MessageError` carries the `JobResult` of the failed jobs
+#![allow(clippy::result_large_err)]
+
 #[macro_use]
 extern crate log;
 #[macro_use]
@@ -87,11 +665,16 @@ extern crate serde_json;
 extern crate yaserde_derive;
 
 mod channels;
+#[cfg(feature = "cli")]
+mod cli;
 mod config;
 mod error;
 pub mod job;
+mod logger;
 pub mod message;
 pub mod parameter;
+pub mod runtime;
+mod telemetry;
 pub mod worker;
 
 /// Re-export from lapin Channel
@@ -101,38 +684,77 @@ pub use schemars::JsonSchema;
 /// Re-export from semver:
 pub use semver::Version;
 
+pub use config::SdkConfig;
 pub use error::{MessageError, Result};
 #[cfg(feature = "media")]
 pub use message::media::{
-  audio::AudioFormat,
+  audio::{
+    continuity::{
+      AudioContinuityReport, AudioContinuitySummary, AudioDiscontinuity, DiscontinuityKind,
+    },
+    AudioFormat,
+  },
+  bitmap_subtitle::{BitmapSubtitle, SubtitleRegion},
+  chapters::get_chapters,
+  data_codec::{DataCodec, DataCodecRegistry},
   ebu_ttml_live::{
     Body, Div, EbuTtmlLive, Frames, Head, Paragraph, Span, Styling, TimeExpression, TimeUnit, Title,
   },
   filters::{AudioFilter, GenericFilter, VideoFilter},
-  video::{RegionOfInterest, Scaling, VideoFormat},
-  StreamDescriptor,
+  id3::{Id3Codec, Id3Frame, Id3Tag},
+  scte35::{Scte35Codec, SpliceInfoSection},
+  teletext::{TeletextCodec, TeletextDecoder, TeletextPage},
+  video::{AspectRatioSignaling, RegionOfInterest, Scaling, VideoFormat},
+  FlushMode, StreamDescriptor,
+};
+pub use message::{
+  publish_job_checkpoint, publish_job_progression, publish_job_progression_details,
 };
-pub use message::publish_job_progression;
 pub use parameter::container::ParametersContainer;
-pub use parameter::{Parameter, ParameterValue, Requirement};
+pub use parameter::{
+  DiskSpaceRequirement, FileRequirement, ObjectParameter, Parameter, ParameterValue, Requirement,
+  ResultRequirement,
+};
 #[cfg(feature = "media")]
 pub use stainless_ffmpeg::{format_context::FormatContext, frame::Frame};
+#[cfg(feature = "media")]
+pub use stainless_ffmpeg_sys::AVCodecID;
+pub use worker::embedded::{Worker, WorkerBuilder, WorkerHandle};
+pub use worker::instance::get_instance_id;
+pub use worker::instance_parameters::InstanceParameters;
+pub use worker::registry::WorkerRegistry;
 
-use crate::worker::docker;
-use chrono::prelude::*;
+use crate::worker::{
+  description,
+  embedded::StopSignal,
+  instance,
+  processing_window::{self, ProcessingWindows},
+  readiness::WorkerReadiness,
+  registry::{JobConsumer, WorkerConsumer},
+  self_test::{SelfTestCheck, SelfTestDiagnostic},
+};
 use config::*;
-use env_logger::Builder;
-use futures_executor::LocalPool;
-use futures_util::{future::FutureExt, stream::StreamExt, task::LocalSpawnExt};
-use job::JobResult;
+use futures::channel::oneshot;
+use futures_util::{
+  future::{self, FutureExt, LocalBoxFuture},
+  stream::StreamExt,
+};
+use job::{Job, JobActions, JobClaim, JobContext, JobLease, JobResult, RunningJobs};
 use lapin::{options::*, types::FieldTable, Connection, ConnectionProperties};
+use message::{read_message_event, write_message_event};
+use parameter::deprecation::ParameterDeprecation;
+use parameter::ui_hint::ParameterHint;
+use schemars::schema::RootSchema;
 use serde::de::DeserializeOwned;
 #[cfg(feature = "media")]
 use serde::Serialize;
-use std::str::FromStr;
 #[cfg(feature = "media")]
 use std::sync::{mpsc::Sender, Mutex};
-use std::{cell::RefCell, fs, io::Write, rc::Rc, sync::Arc, thread, time};
+use std::{
+  fs,
+  sync::{Arc, RwLock},
+  thread, time,
+};
 #[cfg(feature = "media")]
 use yaserde::YaSerialize;
 
@@ -191,6 +813,14 @@ pub enum ProcessFrame {
   AudioVideo(Frame),
   EbuTtmlLive(Box<EbuTtmlLive>),
   Data(Vec<u8>),
+  /// Decoded bitmap subtitle, with its display timing and regions
+  BitmapSubtitle(Box<BitmapSubtitle>),
+  /// Payload decoded by the data codec registered for the stream codec
+  TypedData {
+    codec_id: AVCodecID,
+    pts: i64,
+    payload: Box<dyn std::any::Any + Send>,
+  },
 }
 
 #[cfg(feature = "media")]
@@ -198,12 +828,24 @@ impl ProcessFrame {
   pub fn get_pts(&self) -> i64 {
     match self {
       ProcessFrame::AudioVideo(frame) => frame.get_pts(),
+      ProcessFrame::TypedData { pts, .. } => *pts,
+      ProcessFrame::BitmapSubtitle(subtitle) => subtitle.pts,
       ProcessFrame::EbuTtmlLive(_) | ProcessFrame::Data(_) => {
         // improvement: support pts to terminate
         0
       }
     }
   }
+
+  /// Aspect ratio signaling of the video frames
+  pub fn get_aspect_ratio_signaling(&self) -> Option<AspectRatioSignaling> {
+    match self {
+      ProcessFrame::AudioVideo(frame) if unsafe { (*frame.frame).width } > 0 => {
+        Some(AspectRatioSignaling::from_frame(frame))
+      }
+      _ => None,
+    }
+  }
 }
 
 /// # Trait to describe a worker
@@ -218,6 +860,73 @@ pub trait MessageEvent<P: DeserializeOwned + JsonSchema> {
     Ok(())
   }
 
+  /// JSON Schema of the parameters of the worker instance (model path, device index, license key...),
+  /// read from the configuration at startup and described apart from the job parameters
+  fn get_instance_parameters_schema(&self) -> Option<RootSchema> {
+    None
+  }
+
+  /// Initialization with the instance parameters, calls `init` by default
+  fn init_with_parameters(&mut self, _parameters: &InstanceParameters) -> Result<()> {
+    self.init()
+  }
+
+  /// Checks of the worker self-test (e.g. model files, GPU, decoding of a bundled sample),
+  /// run with `SELF_TEST` or the `self_test` direct message
+  fn self_test(&self) -> Vec<SelfTestCheck> {
+    vec![]
+  }
+
+  /// JSON Schema of the typed output added to the job results, if any (e.g. `schema_for!(Output)`),
+  /// published with the message schemas in the worker description
+  fn get_output_schema(&self) -> Option<RootSchema> {
+    None
+  }
+
+  /// MIME types of the sources accepted by the worker (e.g. `video/mp4` or `video/*`), any if empty,
+  /// published in the worker description
+  fn get_input_content_types(&self) -> Vec<String> {
+    vec![]
+  }
+
+  /// MIME types of the files produced by the worker, published in the worker description
+  fn get_output_content_types(&self) -> Vec<String> {
+    vec![]
+  }
+
+  /// Deprecated parameters, with their replacement, marked in the worker description
+  fn get_parameter_deprecations(&self) -> Vec<ParameterDeprecation> {
+    vec![]
+  }
+
+  /// Display hints of the parameters (label, section, widget...), for the forms of the StepFlow frontend
+  fn get_parameter_hints(&self) -> Vec<ParameterHint> {
+    vec![]
+  }
+
+  /// Handlers of the orders with an `action`, each with its own parameters
+  fn get_actions(&self) -> JobActions<Self>
+  where
+    Self: std::marker::Sized,
+  {
+    JobActions::default()
+  }
+
+  /// Called once when the worker terminates (termination signal, fatal error, drain with exit,
+  /// or stop of an embedded worker), to flush caches, close connections or persist models
+  fn on_shutdown(&mut self) {}
+
+  /// Called once the configuration is reloaded with the `reload_configuration` direct message,
+  /// to re-read the settings kept by the worker, e.g. its own endpoints,
+  /// and the hostnames and credentials of the parameter stores, applied with `SdkConfig::apply`
+  fn on_config_reload(&mut self) {}
+
+  /// Acquire an external lock or lease on the resources of the job (e.g. its destination file)
+  /// once the order is delivered, before processing it
+  fn claim_job(&self, _job: &Job) -> Result<JobClaim> {
+    Ok(JobClaim::Acquired(JobLease::new()))
+  }
+
   #[cfg(feature = "media")]
   fn init_process(
     &mut self,
@@ -228,12 +937,27 @@ pub trait MessageEvent<P: DeserializeOwned + JsonSchema> {
     Ok(vec![])
   }
 
+  /// Register decoders for data stream codecs, selected by the codec of each data stream
+  ///
+  /// ID3 and SCTE-35 codecs are registered by default, registering the same codec replaces them
+  #[cfg(feature = "media")]
+  fn register_data_codecs(&self, _registry: &mut DataCodecRegistry) {}
+
+  /// Handling of the frames remaining in the decoders at the end of the stream
+  ///
+  /// `ending_process` is called once after the last delivered frame, even if the processing failed
+  #[cfg(feature = "media")]
+  fn get_flush_mode(&self) -> FlushMode {
+    FlushMode::default()
+  }
+
   #[cfg(feature = "media")]
   fn process_frame(
     &mut self,
     _job_result: JobResult,
     _stream_index: usize,
     _frame: ProcessFrame,
+    _context: JobContext,
   ) -> Result<ProcessResult> {
     Err(MessageError::NotImplemented())
   }
@@ -244,43 +968,61 @@ pub trait MessageEvent<P: DeserializeOwned + JsonSchema> {
   }
 
   /// Not called when the "media" feature is enabled
+  ///
+  /// Long-running computations should poll the context to abort when the job is cancelled
   fn process(
     &self,
     _channel: Option<McaiChannel>,
     _parameters: P,
     _job_result: JobResult,
+    _context: JobContext,
   ) -> Result<JobResult>
   where
     Self: std::marker::Sized,
   {
     Err(MessageError::NotImplemented())
   }
+
+  /// Asynchronous variant of `process`, driven by the SDK runtime
+  ///
+  /// By default it calls `process`. Not called when the "media" feature is enabled
+  fn process_async<'a>(
+    &'a self,
+    channel: Option<McaiChannel>,
+    parameters: P,
+    job_result: JobResult,
+    context: JobContext,
+  ) -> LocalBoxFuture<'a, Result<JobResult>>
+  where
+    Self: std::marker::Sized,
+  {
+    future::ready(self.process(channel, parameters, job_result, context)).boxed_local()
+  }
 }
 
 /// Function to start a worker
-pub fn start_worker<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(mut message_event: ME)
-where
-  ME: std::marker::Sync,
+pub fn start_worker<P: 'static + DeserializeOwned + JsonSchema, ME: 'static + MessageEvent<P>>(
+  message_event: ME,
+) where
+  ME: std::marker::Send + std::marker::Sync,
 {
-  let mut builder = Builder::from_default_env();
+  #[cfg(feature = "cli")]
+  {
+    let arguments = cli::parse(
+      &message_event.get_name(),
+      &message_event.get_version().to_string(),
+      &message_event.get_description(),
+    );
+    arguments.config.apply();
+    if let Some(log_level) = arguments.log_level {
+      std::env::set_var("RUST_LOG", log_level);
+    }
+  }
+
   let amqp_queue = get_amqp_queue();
-  let instance_id = docker::get_instance_id("/proc/self/cgroup");
-
-  let container_id = instance_id.clone();
-  builder
-    .format(move |stream, record| {
-      writeln!(
-        stream,
-        "{} - {} - {} - {} - {} - {}",
-        Utc::now(),
-        &container_id,
-        get_amqp_queue(),
-        record.target().parse::<i64>().unwrap_or(-1),
-        record.level(),
-        record.args(),
-      )
-    })
-    .init();
+  let instance_id = instance::get_instance_id();
+  logger::init(&instance::get_instance_name(&instance_id), &amqp_queue);
+  exit_on_invalid_configuration();
 
   let worker_configuration =
     worker::WorkerConfiguration::new(&amqp_queue, &message_event, &instance_id);
@@ -298,28 +1040,61 @@ where
     worker_configuration.get_sdk_version(),
   );
 
-  if let Ok(enabled) = std::env::var("DESCRIBE") {
-    if enabled == "1" || bool::from_str(&enabled.to_lowercase()).unwrap_or(false) {
-      match serde_json::to_string_pretty(&worker_configuration) {
-        Ok(serialized_configuration) => {
-          println!("{}", serialized_configuration);
-          return;
-        }
-        Err(error) => error!("Could not serialize worker configuration: {:?}", error),
+  if is_describe_enabled() {
+    match description::format_description(&worker_configuration, &get_describe_format()) {
+      Ok(serialized_configuration) => {
+        println!("{}", serialized_configuration);
+        return;
       }
+      Err(error) => error!("Could not serialize worker configuration: {}", error),
     }
   }
 
-  if let Err(message) = message_event.init() {
-    error!("{:?}", message);
+  telemetry::init(
+    &worker_configuration.get_worker_name(),
+    Some(&worker_configuration.get_worker_version()),
+    &instance_id,
+  );
+
+  let message_event_ref = Arc::new(RwLock::new(message_event));
+  let running_jobs = RunningJobs::default();
+  let readiness = WorkerReadiness::default();
+
+  if get_self_test() {
+    let init_check = SelfTestCheck::new(
+      "init",
+      init_worker(&message_event_ref, &readiness).map_err(|error| format!("{:?}", error)),
+    );
+
+    let diagnostic = if init_check.passed {
+      SelfTestDiagnostic::run(
+        &*read_message_event(&message_event_ref),
+        &worker_configuration,
+      )
+      .with_first_check(init_check)
+    } else {
+      SelfTestDiagnostic::new(&worker_configuration, vec![init_check], 0.0)
+    };
+
+    match serde_json::to_string_pretty(&diagnostic) {
+      Ok(serialized_diagnostic) => println!("{}", serialized_diagnostic),
+      Err(error) => error!("Could not serialize self-test diagnostic: {:?}", error),
+    }
+
+    if !diagnostic.passed {
+      std::process::exit(1);
+    }
     return;
   }
 
-  let message_event_ref = Rc::new(RefCell::new(message_event));
-
-  info!("Worker initialized, ready to receive jobs");
+  let max_concurrent_jobs = get_effective_max_concurrent_jobs();
 
   if let Some(source_orders) = get_source_orders() {
+    if let Err(message) = init_worker(&message_event_ref, &readiness) {
+      error!("{:?}", message);
+      return;
+    }
+
     warn!("Worker will process source orders");
     for source_order in &source_orders {
       info!("Start to process order: {:?}", source_order);
@@ -333,6 +1108,7 @@ where
         &message_data,
         count,
         channel,
+        &running_jobs,
         message::publish_job_progression,
       );
 
@@ -347,86 +1123,382 @@ where
       }
     }
 
+    worker::shutdown::shutdown_message_event(&message_event_ref);
     return;
   }
 
+  worker::shutdown::handle_signals();
+  let shutdown_message_event = message_event_ref.clone();
+  worker::shutdown::register(move || {
+    worker::shutdown::shutdown_message_event(&shutdown_message_event)
+  });
+  worker::registration::register_worker(&worker_configuration);
+
+  // status requests are answered during the initialization, jobs are consumed once initialized
+  let init_message_event = message_event_ref.clone();
+  let init_readiness = readiness.clone();
+  thread::spawn(move || {
+    if let Err(message) = init_worker(&init_message_event, &init_readiness) {
+      error!("{:?}", message);
+      worker::shutdown::exit(1);
+    }
+  });
+
+  let worker: Arc<dyn JobConsumer> = Arc::new(WorkerConsumer::new(
+    message_event_ref,
+    worker_configuration,
+    running_jobs,
+    readiness,
+    max_concurrent_jobs,
+  ));
+  run_amqp_worker(&[worker], &StopSignal::default());
+}
+
+/// Consume the job orders of the workers until stopped, reconnecting when the connection is lost
+pub(crate) fn run_amqp_worker(workers: &[Arc<dyn JobConsumer>], stop: &StopSignal) {
+  if get_chaos_mode() {
+    warn!("Chaos mode enabled, latency, failures and delayed publishes are injected");
+  }
+
+  let mut brokers = channels::failover::BrokerFailover::new(get_amqp_uris());
+
   loop {
-    let amqp_uri = get_amqp_uri();
-    let mut executor = LocalPool::new();
-    let spawner = executor.spawner();
-
-    executor.run_until(async {
-      let conn = Connection::connect_uri(
-        amqp_uri,
-        ConnectionProperties::default().with_default_executor(8),
-      )
-      .wait()
-      .unwrap();
+    if stop.is_stopped() {
+      return;
+    }
 
-      info!("Connected");
-      let channel = Arc::new(channels::declare_consumer_channel(
-        &conn,
-        &worker_configuration,
-      ));
+    let amqp_uri = brokers.select();
 
-      let consumer = channel
-        .clone()
-        .basic_consume(
-          &amqp_queue,
-          "amqp_worker",
-          BasicConsumeOptions::default(),
-          FieldTable::default(),
+    let connection_properties = match runtime::get_amqp_executor() {
+      Some(executor) => ConnectionProperties::default().with_executor(executor),
+      None => ConnectionProperties::default().with_default_executor(8),
+    };
+
+    let connected = futures_executor::block_on(async {
+      let conn = match Connection::connect_uri(amqp_uri, connection_properties).wait() {
+        Ok(conn) => conn,
+        Err(error) => {
+          error!(
+            "Unable to connect to {}: {:?}",
+            brokers.get_active_endpoint(),
+            error
+          );
+          return false;
+        }
+      };
+
+      info!("Connected to {}", brokers.get_active_endpoint());
+
+      if let Some(timeout) = get_watchdog_timeout() {
+        worker::watchdog::start_watchdog(
+          Arc::new(conn.create_channel().wait().unwrap()),
+          workers
+            .iter()
+            .map(|worker| worker.get_running_jobs().clone())
+            .collect(),
+          time::Duration::from_secs(timeout),
+          get_watchdog_restart(),
+        );
+      }
+
+      // the workers reconnect together once the connection is lost
+      let consume = future::select_all(workers.iter().map(|worker| worker.consume(&conn)));
+
+      future::select(consume, Box::pin(stop.wait())).await;
+      if stop.is_stopped() {
+        if let Err(error) = conn.close(200, "Worker stopped").await {
+          warn!("Unable to close the connection: {:?}", error);
+        }
+      }
+      true
+    });
+
+    if stop.is_stopped() {
+      info!("Worker stopped");
+      return;
+    }
+
+    if !connected {
+      brokers.fail_over();
+    }
+
+    let sleep_duration = time::Duration::new(1, 0);
+    thread::sleep(sleep_duration);
+    info!("Reconnection...");
+  }
+}
+
+/// Report every problem of the runtime configuration at once, then exit
+pub(crate) fn exit_on_invalid_configuration() {
+  if let Err(errors) = config::validate() {
+    for error in &errors {
+      error!("Invalid configuration {}", error);
+    }
+    error!(
+      "{} invalid configuration value(s), the worker is not started",
+      errors.len()
+    );
+    std::process::exit(1);
+  }
+}
+
+/// Consume the job orders of the worker queue on the connection, until it is lost
+pub(crate) async fn consume_jobs<
+  P: 'static + DeserializeOwned + JsonSchema,
+  ME: 'static + MessageEvent<P> + Send + Sync,
+>(
+  conn: &Connection,
+  message_event_ref: &Arc<RwLock<ME>>,
+  worker_configuration: &worker::WorkerConfiguration,
+  running_jobs: &RunningJobs,
+  readiness: &WorkerReadiness,
+  max_concurrent_jobs: u16,
+) {
+  let amqp_queue = worker_configuration.get_queue_name();
+
+  let (ready_sender, ready_receiver) = oneshot::channel();
+  let waiting_readiness = readiness.clone();
+  thread::spawn(move || {
+    waiting_readiness.wait();
+    let _ = ready_sender.send(());
+  });
+
+  // Media processing relies on a per-job state in the worker, no slot is dedicated to the re-runs
+  let rerun_lookahead = get_rerun_lookahead();
+  let rerun_slot = rerun_lookahead > 0 && !cfg!(feature = "media");
+  let prefetch_count = if rerun_lookahead > 0 {
+    max_concurrent_jobs
+      .saturating_add(rerun_lookahead)
+      .saturating_add(rerun_slot as u16)
+  } else {
+    max_concurrent_jobs
+  };
+
+  let channel = Arc::new(channels::declare_consumer_channel(
+    conn,
+    worker_configuration,
+    prefetch_count,
+  ));
+
+  let status_consumer = channel
+    .clone()
+    .basic_consume(
+      &worker_configuration.get_direct_messaging_queue_name(),
+      &worker_configuration.get_direct_messaging_consumer_tag(),
+      BasicConsumeOptions::default(),
+      FieldTable::default(),
+    )
+    .await
+    .unwrap();
+
+  let status_response_channel = channel.clone();
+  let status_worker_configuration = worker_configuration.clone();
+  let status_running_jobs = running_jobs.clone();
+  let status_readiness = readiness.clone();
+  let status_message_event = message_event_ref.clone();
+
+  // the status requests are answered as long as the job orders are consumed
+  let status = async move {
+    status_consumer
+      .for_each(move |delivery| {
+        let (_channel, delivery) = delivery.expect("error caught in in consumer");
+
+        worker::direct_message::process_direct_message(
+          delivery,
+          &status_response_channel,
+          &status_worker_configuration,
+          &status_running_jobs,
+          &status_readiness,
+          &status_message_event,
         )
-        .await
-        .unwrap();
+        .map(|_| ())
+      })
+      .await;
+    future::pending::<()>().await
+  };
+
+  // the heartbeats are published during the initialization too
+  worker::heartbeat::start(
+    channel.clone(),
+    worker_configuration.clone(),
+    readiness.clone(),
+    running_jobs.clone(),
+  );
+
+  // an invalid configuration matches no window, the worker never consumes out of the expected ones
+  let processing_windows = get_processing_windows().map(|expressions| {
+    ProcessingWindows::new(&expressions).unwrap_or_else(|error| {
+      error!("{:?}", error);
+      ProcessingWindows::default()
+    })
+  });
+
+  let jobs = async {
+    if !readiness.is_ready() {
+      info!("Waiting for the worker initialization to consume jobs");
+    }
+    let _ = ready_receiver.await;
+    if readiness.is_draining() {
+      future::pending::<()>().await;
+    }
+
+    let scheduler = if rerun_lookahead > 0 {
+      Some(message::scheduler::SchedulerGuard(
+        message::scheduler::OrderScheduler::start(
+          message_event_ref.clone(),
+          channel.clone(),
+          running_jobs.clone(),
+          readiness.clone(),
+          max_concurrent_jobs,
+          rerun_slot,
+        ),
+      ))
+    } else {
+      None
+    };
+    let order_scheduler = scheduler.as_ref().map(|scheduler| scheduler.0.clone());
+    let job_pool = Arc::new(worker::job_pool::JobPool::new());
 
-      let status_consumer = channel
+    if let Some(processing_windows) = processing_windows {
+      processing_window::start_watcher(
+        processing_windows,
+        channel.clone(),
+        readiness.clone(),
+        worker_configuration.get_consumer_tag(),
+      );
+    }
+
+    loop {
+      if readiness.is_waiting() {
+        info!("Out of the processing windows, waiting to consume jobs");
+        let _ = processing_window::wait_opening(channel.clone(), readiness.clone()).await;
+        if !channel.status().connected() {
+          break;
+        }
+      }
+      if readiness.is_draining() {
+        future::pending::<()>().await;
+      }
+
+      let consumer = channel
         .clone()
         .basic_consume(
-          &worker_configuration.get_direct_messaging_queue_name(),
-          "status_amqp_worker",
+          &amqp_queue,
+          &worker_configuration.get_consumer_tag(),
           BasicConsumeOptions::default(),
           FieldTable::default(),
         )
         .await
         .unwrap();
 
-      let status_response_channel = channel.clone();
-      let status_worker_configuration = worker_configuration.clone();
-
-      let _consumer = spawner.spawn_local(async move {
-        status_consumer
-          .for_each(move |delivery| {
-            let (_channel, delivery) = delivery.expect("error caught in in consumer");
-
-            worker::system_information::send_real_time_information(
-              delivery,
-              &status_response_channel,
-              &status_worker_configuration,
-            )
-            .map(|_| ())
-          })
-          .await
-      });
+      // the window may have been closed while the consumer was started
+      if readiness.is_waiting() {
+        processing_window::cancel_consumer(&channel, &worker_configuration.get_consumer_tag());
+      }
 
-      info!("Start to consume on queue {:?}", amqp_queue);
+      info!(
+        "Start to consume on queue {:?} (max concurrent jobs: {})",
+        amqp_queue,
+        worker::reload::get_reloaded_max_concurrent_jobs(max_concurrent_jobs)
+      );
 
+      let order_scheduler = order_scheduler.clone();
+      let job_pool = job_pool.clone();
       let clone_channel = channel.clone();
       let message_event = message_event_ref.clone();
+      let running_jobs = running_jobs.clone();
 
       consumer
         .for_each(move |delivery| {
           let (_channel, delivery) = delivery.expect("error caught in in consumer");
 
-          message::process_message(message_event.clone(), delivery, clone_channel.clone())
-            .map(|_| ())
+          if let Some(order_scheduler) = &order_scheduler {
+            order_scheduler.push(delivery);
+            return future::ready(());
+          }
+
+          let message_event = message_event.clone();
+          let channel = clone_channel.clone();
+          let running_jobs = running_jobs.clone();
+          let process = move || {
+            if let Err(error) =
+              message::process_message(message_event, delivery, channel, running_jobs).wait()
+            {
+              error!("Unable to respond to the order: {:?}", error);
+            }
+          };
+
+          let max_concurrent_jobs =
+            worker::reload::get_reloaded_max_concurrent_jobs(max_concurrent_jobs);
+          if max_concurrent_jobs > 1 {
+            job_pool.resize(max_concurrent_jobs);
+            job_pool.execute(process);
+          } else {
+            process();
+          }
+
+          future::ready(())
         })
-        .await
-    });
+        .await;
 
-    let sleep_duration = time::Duration::new(1, 0);
-    thread::sleep(sleep_duration);
-    info!("Reconnection...");
+      // the job consumer is cancelled to drain the worker, status requests are still answered
+      if readiness.is_draining() {
+        future::pending::<()>().await;
+      }
+
+      // the job consumer is cancelled to apply the reloaded maximum number of concurrent jobs
+      if worker::reload::take_consumer_restart() {
+        continue;
+      }
+
+      // the job consumer is cancelled out of the processing windows, otherwise the connection is lost
+      if !readiness.is_waiting() {
+        break;
+      }
+    }
+  };
+
+  future::select(Box::pin(jobs), Box::pin(status)).await;
+}
+
+/// Initialize the worker, retrying with an exponential backoff on failure
+fn init_worker<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
+  message_event: &Arc<RwLock<ME>>,
+  readiness: &WorkerReadiness,
+) -> Result<()> {
+  let schema = read_message_event(message_event).get_instance_parameters_schema();
+  let parameters = InstanceParameters::resolve(schema.as_ref()).map_err(|error| {
+    readiness.set_init_error(&format!("{:?}", error));
+    error
+  })?;
+
+  let max_retries = get_init_max_retries();
+  let mut delay = get_init_retry_delay();
+  let mut retry = 0;
+
+  loop {
+    let result = write_message_event(message_event).init_with_parameters(&parameters);
+    match result {
+      Ok(()) => {
+        readiness.set_ready();
+        info!("Worker initialized, ready to receive jobs");
+        return Ok(());
+      }
+      Err(error) => {
+        readiness.set_init_error(&format!("{:?}", error));
+        if retry >= max_retries {
+          return Err(error);
+        }
+
+        retry += 1;
+        warn!(
+          "Worker initialization failed, retry {}/{} in {} ms: {:?}",
+          retry, max_retries, delay, error
+        );
+        thread::sleep(time::Duration::from_millis(delay));
+        delay = delay.saturating_mul(2);
+      }
+    }
   }
 }
 
@@ -457,12 +1529,16 @@ fn empty_message_event_impl() {
   let parameters = CustomParameters {};
 
   let job = job::Job {
+    schema_version: None,
     job_id: 1234,
+    action: None,
     parameters: vec![],
+    priority: None,
+    trace_context: None,
   };
 
   let job_result = job::JobResult::new(job.job_id);
 
-  let result = custom_event.process(None, parameters, job_result);
+  let result = custom_event.process(None, parameters, job_result, JobContext::new(1234));
   assert!(result == Err(MessageError::NotImplemented()));
 }
@@ -0,0 +1,363 @@
+//! Logger of the worker, with a log level overridable per job
+//!
+//! The records of a job are the ones targeting its identifier (`info!(target: &job_id.to_string(), ...)`).
+//! The `log_level` job parameter raises the verbosity of these records only,
+//! the other records remain filtered by `RUST_LOG`.
+//! The values of the secret parameters of the jobs in progress are masked.
+//! With `LOG_FORMAT=json`, each record is a JSON object on its own line, for the log aggregators.
+//! With `JOB_LOG_CAPTURE`, the records of each job are also kept until it is processed, to be sent with its result.
+
+use crate::config::{get_job_log_capture, get_job_log_capture_size, get_log_format};
+use crate::job::Job;
+use crate::parameter::container::ParametersContainer;
+use crate::parameter::secret::mask_secrets;
+use crate::{MessageError, Result};
+use chrono::{DateTime, Utc};
+use env_logger::{Builder, Logger};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+
+pub(crate) const LOG_LEVEL_PARAMETER: &str = "log_level";
+/// Result parameter of the captured log records, with `JOB_LOG_CAPTURE=result`
+pub(crate) const LOGS_PARAMETER: &str = "logs";
+
+/// Log levels of the jobs in progress overriding the default one, by job identifier
+static JOB_LOG_LEVELS: RwLock<BTreeMap<String, LevelFilter>> = RwLock::new(BTreeMap::new());
+
+/// Records of the jobs in progress whose logs are captured, by job identifier
+static JOB_LOGS: Mutex<BTreeMap<String, JobLogs>> = Mutex::new(BTreeMap::new());
+
+/// Level of the records enabled by `RUST_LOG`, none if the logger of the worker is not used
+static DEFAULT_LOG_LEVEL: RwLock<Option<LevelFilter>> = RwLock::new(None);
+
+struct JobLogger {
+  logger: Logger,
+  /// Logger of the jobs with an overridden log level, enabling any record
+  job_logger: Logger,
+}
+
+impl JobLogger {
+  fn get_job_log_level(&self, target: &str) -> Option<LevelFilter> {
+    JOB_LOG_LEVELS.read().unwrap().get(target).cloned()
+  }
+}
+
+impl Log for JobLogger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    self.logger.enabled(metadata)
+      || self
+        .get_job_log_level(metadata.target())
+        .map(|level| metadata.level() <= level)
+        .unwrap_or(false)
+  }
+
+  fn log(&self, record: &Record) {
+    if self.logger.matches(record) {
+      self.logger.log(record);
+      capture(record);
+    } else if self.enabled(record.metadata()) {
+      self.job_logger.log(record);
+      capture(record);
+    }
+  }
+
+  fn flush(&self) {
+    self.logger.flush();
+  }
+}
+
+/// Initialize the logger, prefixing the records with the name of the instance and the queue(s) of the worker
+pub(crate) fn init(instance_name: &str, queue_name: &str) {
+  let logger = build_logger(Builder::from_default_env(), instance_name, queue_name);
+  let mut job_builder = Builder::new();
+  job_builder.filter_level(LevelFilter::Trace);
+  let job_logger = build_logger(job_builder, instance_name, queue_name);
+
+  *DEFAULT_LOG_LEVEL.write().unwrap() = Some(logger.filter());
+  log::set_max_level(logger.filter());
+  log::set_boxed_logger(Box::new(JobLogger { logger, job_logger }))
+    .expect("The logger should not be initialized twice");
+}
+
+fn build_logger(mut builder: Builder, instance_name: &str, queue_name: &str) -> Logger {
+  let instance_name = instance_name.to_string();
+  let queue_name = queue_name.to_string();
+  let json_format = get_log_format() == "json";
+
+  builder
+    .format(move |stream, record| {
+      if json_format {
+        writeln!(
+          stream,
+          "{}",
+          format_json_record(record, &instance_name, &queue_name)
+        )
+      } else {
+        writeln!(
+          stream,
+          "{} - {} - {} - {} - {} - {}",
+          Utc::now(),
+          &instance_name,
+          &queue_name,
+          record.target().parse::<i64>().unwrap_or(-1),
+          record.level(),
+          mask_secrets(&record.args().to_string()),
+        )
+      }
+    })
+    .build()
+}
+
+/// The job identifier is null for the records out of the jobs
+fn format_json_record(record: &Record, instance_name: &str, queue_name: &str) -> String {
+  json!({
+    "timestamp": Utc::now().to_rfc3339(),
+    "level": record.level().to_string(),
+    "job_id": record.target().parse::<u64>().ok(),
+    "worker": instance_name,
+    "queue": queue_name,
+    "message": mask_secrets(&record.args().to_string()),
+    "fields": {
+      "target": record.target(),
+      "module": record.module_path(),
+      "file": record.file(),
+      "line": record.line(),
+    },
+  })
+  .to_string()
+}
+
+/// Log level of a job, restored to the default one once dropped
+pub(crate) struct JobLogLevel {
+  job_id: Option<String>,
+}
+
+impl JobLogLevel {
+  /// Apply the `log_level` parameter of the job, if any
+  pub(crate) fn new(job: &Job) -> Result<Self> {
+    let level = match job.get_parameter::<String>(LOG_LEVEL_PARAMETER) {
+      Ok(level) => LevelFilter::from_str(&level).map_err(|_| {
+        MessageError::ParameterValueError(format!("Invalid log level: {:?}", level))
+      })?,
+      Err(_) => return Ok(JobLogLevel { job_id: None }),
+    };
+
+    let job_id = job.job_id.to_string();
+    JOB_LOG_LEVELS
+      .write()
+      .unwrap()
+      .insert(job_id.clone(), level);
+    update_max_level();
+
+    Ok(JobLogLevel {
+      job_id: Some(job_id),
+    })
+  }
+}
+
+impl Drop for JobLogLevel {
+  fn drop(&mut self) {
+    if let Some(job_id) = &self.job_id {
+      JOB_LOG_LEVELS.write().unwrap().remove(job_id);
+      update_max_level();
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct JobLogRecord {
+  pub datetime: DateTime<Utc>,
+  pub level: String,
+  pub message: String,
+}
+
+/// Log records of a job, published on the `job_logs` queue
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct JobLogs {
+  pub job_id: u64,
+  pub records: VecDeque<JobLogRecord>,
+  /// Oldest records dropped beyond `JOB_LOG_CAPTURE_SIZE`
+  pub dropped_records: u64,
+}
+
+fn capture(record: &Record) {
+  let mut job_logs = JOB_LOGS.lock().unwrap();
+  if let Some(logs) = job_logs.get_mut(record.target()) {
+    if logs.records.len() >= get_job_log_capture_size() {
+      logs.records.pop_front();
+      logs.dropped_records += 1;
+    }
+    logs.records.push_back(JobLogRecord {
+      datetime: Utc::now(),
+      level: record.level().to_string(),
+      message: mask_secrets(&record.args().to_string()),
+    });
+  }
+}
+
+/// Capture of the log records of a job, stopped once dropped
+pub(crate) struct JobLogCapture {
+  job_id: Option<String>,
+}
+
+impl JobLogCapture {
+  pub(crate) fn new(job_id: u64) -> Self {
+    if get_job_log_capture() == "none" {
+      return JobLogCapture { job_id: None };
+    }
+
+    let logs = JobLogs {
+      job_id,
+      ..Default::default()
+    };
+    JOB_LOGS.lock().unwrap().insert(job_id.to_string(), logs);
+    JobLogCapture {
+      job_id: Some(job_id.to_string()),
+    }
+  }
+
+  /// Records captured since the start of the job, none if not captured
+  pub(crate) fn finish(mut self) -> Option<JobLogs> {
+    let job_id = self.job_id.take()?;
+    JOB_LOGS.lock().unwrap().remove(&job_id)
+  }
+}
+
+impl Drop for JobLogCapture {
+  fn drop(&mut self) {
+    if let Some(job_id) = &self.job_id {
+      JOB_LOGS.lock().unwrap().remove(job_id);
+    }
+  }
+}
+
+/// The records are filtered out by the log macros above the maximum level
+fn update_max_level() {
+  let default_level = match *DEFAULT_LOG_LEVEL.read().unwrap() {
+    Some(default_level) => default_level,
+    None => return,
+  };
+  let max_level = JOB_LOG_LEVELS
+    .read()
+    .unwrap()
+    .values()
+    .cloned()
+    .fold(default_level, std::cmp::max);
+
+  log::set_max_level(max_level);
+}
+
+#[test]
+pub fn test_job_log_level() {
+  let get_job_log_level = |job_id: &str| JOB_LOG_LEVELS.read().unwrap().get(job_id).cloned();
+
+  let job = Job::new(
+    r#"{"job_id": 3001, "parameters": [{"id": "log_level", "type": "string", "value": "trace"}]}"#,
+  )
+  .unwrap();
+  let job_log_level = JobLogLevel::new(&job).unwrap();
+  assert_eq!(Some(LevelFilter::Trace), get_job_log_level("3001"));
+  drop(job_log_level);
+  assert_eq!(None, get_job_log_level("3001"));
+
+  let job = Job::new(r#"{"job_id": 3002, "parameters": []}"#).unwrap();
+  let _job_log_level = JobLogLevel::new(&job).unwrap();
+  assert_eq!(None, get_job_log_level("3002"));
+
+  let job = Job::new(
+    r#"{"job_id": 3003, "parameters": [{"id": "log_level", "type": "string", "value": "verbose"}]}"#,
+  )
+  .unwrap();
+  assert!(matches!(
+    JobLogLevel::new(&job),
+    Err(MessageError::ParameterValueError(_))
+  ));
+  assert_eq!(None, get_job_log_level("3003"));
+}
+
+#[test]
+pub fn test_format_json_record() {
+  use log::Level;
+
+  let line = format_json_record(
+    &Record::builder()
+      .args(format_args!("Start processing"))
+      .level(Level::Info)
+      .target("3004")
+      .module_path(Some("worker::process"))
+      .line(Some(42))
+      .build(),
+    "transcoder-01",
+    "job_transcoder",
+  );
+  let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+  assert!(!line.contains('\n'));
+  assert!(record["timestamp"].is_string());
+  assert_eq!("INFO", record["level"]);
+  assert_eq!(3004, record["job_id"]);
+  assert_eq!("transcoder-01", record["worker"]);
+  assert_eq!("job_transcoder", record["queue"]);
+  assert_eq!("Start processing", record["message"]);
+  assert_eq!("worker::process", record["fields"]["module"]);
+  assert_eq!(42, record["fields"]["line"]);
+
+  let line = format_json_record(
+    &Record::builder()
+      .args(format_args!("Connected"))
+      .level(Level::Warn)
+      .target("mcai_worker_sdk")
+      .build(),
+    "transcoder-01",
+    "job_transcoder",
+  );
+  let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+  assert!(record["job_id"].is_null());
+  assert_eq!("mcai_worker_sdk", record["fields"]["target"]);
+}
+
+#[test]
+pub fn test_job_log_capture() {
+  use log::Level;
+
+  let log = |job_id: &str, message: &str| {
+    capture(
+      &Record::builder()
+        .args(format_args!("{}", message))
+        .level(Level::Info)
+        .target(job_id)
+        .build(),
+    )
+  };
+
+  crate::config::SdkConfig::new()
+    .with_value("JOB_LOG_CAPTURE", "queue")
+    .with_value("JOB_LOG_CAPTURE_SIZE", "2")
+    .apply();
+  let job_log_capture = JobLogCapture::new(3005);
+  log("3005", "first");
+  log("3006", "other job");
+  log("3005", "second");
+  log("3005", "third");
+
+  let logs = job_log_capture.finish().unwrap();
+  crate::config::SdkConfig::new()
+    .with_value("JOB_LOG_CAPTURE", "none")
+    .with_value("JOB_LOG_CAPTURE_SIZE", "200")
+    .apply();
+
+  assert_eq!(3005, logs.job_id);
+  assert_eq!(1, logs.dropped_records);
+  let messages: Vec<&str> = logs
+    .records
+    .iter()
+    .map(|record| record.message.as_str())
+    .collect();
+  assert_eq!(vec!["second", "third"], messages);
+  assert_eq!("INFO", logs.records[0].level);
+  assert!(JOB_LOGS.lock().unwrap().is_empty());
+
+  assert_eq!(None, JobLogCapture::new(3007).finish());
+}
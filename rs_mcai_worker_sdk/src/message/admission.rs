@@ -0,0 +1,233 @@
+//! Admission of the job orders depending on the resources of the host
+//!
+//! Before processing an order, the CPU load, the available memory and the available space of the
+//! workspace disk are compared to the thresholds of the worker. While the host is saturated,
+//! the orders are requeued after a delay, to be processed by a less loaded worker or later.
+
+use crate::config::*;
+use crate::job::Job;
+use crate::parameter::{DiskSpaceRequirement, Requirement};
+use crate::ParametersContainer;
+use std::path::Path;
+use sysinfo::{Disk, DiskExt, RefreshKind, SystemExt};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AdmissionThresholds {
+  /// One minute load average per processor
+  pub max_cpu_load: Option<f64>,
+  /// Available memory in bytes
+  pub min_free_memory: Option<u64>,
+  /// Available space in bytes of the workspace disk
+  pub min_free_disk: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostResources {
+  pub cpu_load: f64,
+  pub free_memory: u64,
+  pub free_disk: Option<u64>,
+}
+
+impl AdmissionThresholds {
+  pub fn from_config() -> Self {
+    AdmissionThresholds {
+      max_cpu_load: get_admission_max_cpu_load(),
+      min_free_memory: get_admission_min_free_memory(),
+      min_free_disk: get_admission_min_free_disk(),
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.max_cpu_load.is_some() || self.min_free_memory.is_some() || self.min_free_disk.is_some()
+  }
+
+  /// Reason of the saturation of the host, if any
+  pub fn check(&self, resources: &HostResources) -> Result<(), String> {
+    if let Some(max_cpu_load) = self.max_cpu_load {
+      if resources.cpu_load > max_cpu_load {
+        return Err(format!(
+          "CPU load {:.2} above {:.2}",
+          resources.cpu_load, max_cpu_load
+        ));
+      }
+    }
+
+    if let Some(min_free_memory) = self.min_free_memory {
+      if resources.free_memory < min_free_memory {
+        return Err(format!(
+          "available memory {} bytes below {} bytes",
+          resources.free_memory, min_free_memory
+        ));
+      }
+    }
+
+    if let (Some(min_free_disk), Some(free_disk)) = (self.min_free_disk, resources.free_disk) {
+      if free_disk < min_free_disk {
+        return Err(format!(
+          "available disk space {} bytes below {} bytes",
+          free_disk, min_free_disk
+        ));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl HostResources {
+  pub fn new(workspace_root: &Path) -> Self {
+    let system = sysinfo::System::new_with_specifics(
+      RefreshKind::new()
+        .with_cpu()
+        .with_memory()
+        .with_disks_list(),
+    );
+
+    let processors = system.get_processors().len().max(1);
+    let cpu_load = system.get_load_average().one / processors as f64;
+    let free_memory = system.get_available_memory() * 1024;
+
+    let free_disk = find_available_space(system.get_disks(), workspace_root);
+
+    HostResources {
+      cpu_load,
+      free_memory,
+      free_disk,
+    }
+  }
+}
+
+/// Available space in bytes of the disk of the path, if known
+pub(crate) fn get_available_disk_space(path: &Path) -> Option<u64> {
+  let system = sysinfo::System::new_with_specifics(RefreshKind::new().with_disks_list());
+  find_available_space(system.get_disks(), path)
+}
+
+/// The disk of a path is the one with the longest mount point containing it
+fn find_available_space(disks: &[Disk], path: &Path) -> Option<u64> {
+  let path = std::env::current_dir()
+    .map(|current_dir| current_dir.join(path))
+    .unwrap_or_else(|_| path.to_path_buf());
+
+  disks
+    .iter()
+    .filter(|disk| path.starts_with(disk.get_mount_point()))
+    .max_by_key(|disk| disk.get_mount_point().as_os_str().len())
+    .map(|disk| disk.get_available_space())
+}
+
+/// Reason of the lack of space for the disk space requirement, the requirement is met if the disk is unknown
+pub(crate) fn check_disk_space(requirement: &DiskSpaceRequirement) -> Result<(), String> {
+  match get_available_disk_space(Path::new(&requirement.path)) {
+    Some(available) if available < requirement.bytes => Err(format!(
+      "available disk space of {:?} {} bytes below {} bytes",
+      requirement.path, available, requirement.bytes
+    )),
+    Some(_) => Ok(()),
+    None => {
+      warn!(
+        "Unable to find the disk of {:?}, its free space is not checked",
+        requirement.path
+      );
+      Ok(())
+    }
+  }
+}
+
+/// Check the `free_disk_space` requirements of the order before accepting it
+pub fn check_order_disk_space(message_data: &str) -> Result<(), String> {
+  let requirements = match Job::new(message_data)
+    .ok()
+    .and_then(|job| job.get_parameter::<Requirement>("requirements").ok())
+  {
+    Some(requirements) => requirements,
+    None => return Ok(()),
+  };
+
+  for requirement in requirements.free_disk_space.iter().flatten() {
+    check_disk_space(requirement)?;
+  }
+  Ok(())
+}
+
+/// Check the resources of the host against the configured thresholds
+pub fn check_admission() -> Result<(), String> {
+  let thresholds = AdmissionThresholds::from_config();
+  if !thresholds.is_enabled() {
+    return Ok(());
+  }
+
+  thresholds.check(&HostResources::new(&get_workspace_root()))
+}
+
+#[test]
+pub fn test_admission_thresholds() {
+  let resources = HostResources {
+    cpu_load: 0.75,
+    free_memory: 2_000_000_000,
+    free_disk: Some(10_000_000_000),
+  };
+
+  let thresholds = AdmissionThresholds::default();
+  assert!(!thresholds.is_enabled());
+  assert!(thresholds.check(&resources).is_ok());
+
+  let thresholds = AdmissionThresholds {
+    max_cpu_load: Some(0.9),
+    min_free_memory: Some(1_000_000_000),
+    min_free_disk: Some(5_000_000_000),
+  };
+  assert!(thresholds.is_enabled());
+  assert!(thresholds.check(&resources).is_ok());
+
+  let saturated = HostResources {
+    cpu_load: 1.5,
+    ..resources.clone()
+  };
+  assert_eq!(
+    Err("CPU load 1.50 above 0.90".to_string()),
+    thresholds.check(&saturated)
+  );
+
+  let saturated = HostResources {
+    free_memory: 500_000_000,
+    ..resources.clone()
+  };
+  assert!(thresholds.check(&saturated).is_err());
+
+  let saturated = HostResources {
+    free_disk: Some(1_000_000_000),
+    ..resources.clone()
+  };
+  assert!(thresholds.check(&saturated).is_err());
+
+  // the disk is not checked if the workspace disk is unknown
+  let unknown_disk = HostResources {
+    free_disk: None,
+    ..resources
+  };
+  assert!(thresholds.check(&unknown_disk).is_ok());
+
+  let resources = HostResources::new(&std::env::temp_dir());
+  assert!(resources.cpu_load >= 0.0);
+  assert!(resources.free_memory > 0);
+}
+
+#[test]
+pub fn test_check_disk_space() {
+  let requirement = DiskSpaceRequirement {
+    path: std::env::temp_dir().to_string_lossy().to_string(),
+    bytes: 1,
+  };
+  assert!(check_disk_space(&requirement).is_ok());
+
+  let requirement = DiskSpaceRequirement {
+    bytes: u64::MAX,
+    ..requirement
+  };
+  if get_available_disk_space(&std::env::temp_dir()).is_some() {
+    assert!(check_disk_space(&requirement).is_err());
+  }
+
+  assert!(check_order_disk_space("{}").is_ok());
+}
@@ -0,0 +1,84 @@
+//! Chaos mode, injecting faults to rehearse the failure handling of the workflows
+//!
+//! Each fault is injected with its configured probability: a latency before processing the job,
+//! a processing or transient error instead of processing it, and a delay before publishing a response.
+
+use crate::config::*;
+use crate::job::{JobResult, JobStatus};
+use crate::{MessageError, Result};
+use rand::Rng;
+use std::thread;
+use std::time::Duration;
+
+/// Sleep a random duration before processing the job
+pub fn inject_latency(job_id: u64) {
+  if !get_chaos_mode() || !happens(get_chaos_latency_probability()) {
+    return;
+  }
+
+  let latency = random_duration(get_chaos_latency_max());
+  warn!(target: &job_id.to_string(), "Chaos mode: {} ms of latency injected", latency.as_millis());
+  thread::sleep(latency);
+}
+
+/// Fail the job instead of processing it
+pub fn inject_failure(job_id: u64) -> Result<()> {
+  if !get_chaos_mode() {
+    return Ok(());
+  }
+
+  if happens(get_chaos_failure_probability()) {
+    warn!(target: &job_id.to_string(), "Chaos mode: processing error injected");
+    return Err(MessageError::ProcessingError(get_job_result(
+      job_id,
+      "processing error",
+    )));
+  }
+
+  if happens(get_chaos_transient_probability()) {
+    warn!(target: &job_id.to_string(), "Chaos mode: transient error injected");
+    return Err(MessageError::Transient(get_job_result(
+      job_id,
+      "transient error",
+    )));
+  }
+
+  Ok(())
+}
+
+/// Sleep a random duration before publishing a response
+pub fn delay_publish(queue_name: &str) {
+  if !get_chaos_mode() || !happens(get_chaos_publish_delay_probability()) {
+    return;
+  }
+
+  let delay = random_duration(get_chaos_publish_delay_max());
+  warn!(
+    "Chaos mode: publish on {} delayed by {} ms",
+    queue_name,
+    delay.as_millis()
+  );
+  thread::sleep(delay);
+}
+
+fn get_job_result(job_id: u64, fault: &str) -> JobResult {
+  JobResult::new(job_id)
+    .with_status(JobStatus::Error)
+    .with_message(&format!("Chaos mode: {} injected", fault))
+}
+
+fn happens(probability: f64) -> bool {
+  probability > 0.0 && rand::thread_rng().gen_bool(probability)
+}
+
+fn random_duration(max: u64) -> Duration {
+  Duration::from_millis(rand::thread_rng().gen_range(0, max + 1))
+}
+
+#[test]
+pub fn test_chaos_probabilities() {
+  assert!(!happens(0.0));
+  assert!(happens(1.0));
+  assert!(random_duration(10) <= Duration::from_millis(10));
+  assert_eq!(Duration::from_millis(0), random_duration(0));
+}
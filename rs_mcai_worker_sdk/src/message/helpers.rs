@@ -1,19 +1,61 @@
+use crate::channels::delayed::is_holding_queue;
 use amq_protocol_types::{AMQPValue, FieldTable};
 use lapin::message::Delivery;
 
+/// Header counting the retries of an order after transient errors
+pub const RETRY_COUNT_HEADER: &str = "x-retry-count";
+/// Header counting the requeues of an order after timeouts
+pub const TIMEOUT_COUNT_HEADER: &str = "x-timeout-count";
+
 pub fn get_message_death_count(message: &Delivery) -> Option<i64> {
   get_count_from_header(message.properties.headers())
 }
 
+pub fn get_message_retry_count(message: &Delivery) -> i64 {
+  get_retry_count_from_header(message.properties.headers())
+}
+
+pub fn get_message_timeout_count(message: &Delivery) -> i64 {
+  get_counter_from_header(message.properties.headers(), TIMEOUT_COUNT_HEADER)
+}
+
+fn get_retry_count_from_header(header: &Option<FieldTable>) -> i64 {
+  get_counter_from_header(header, RETRY_COUNT_HEADER)
+}
+
+fn get_counter_from_header(header: &Option<FieldTable>, name: &str) -> i64 {
+  match header.as_ref().and_then(|header| header.inner().get(name)) {
+    Some(AMQPValue::LongLongInt(value)) => *value,
+    _ => 0,
+  }
+}
+
+/// Copy of the headers with the retry count set
+pub fn set_retry_count_in_header(header: &Option<FieldTable>, retry_count: i64) -> FieldTable {
+  set_counter_in_header(header, RETRY_COUNT_HEADER, retry_count)
+}
+
+/// Copy of the headers with the timeout count set
+pub fn set_timeout_count_in_header(header: &Option<FieldTable>, timeout_count: i64) -> FieldTable {
+  set_counter_in_header(header, TIMEOUT_COUNT_HEADER, timeout_count)
+}
+
+fn set_counter_in_header(header: &Option<FieldTable>, name: &str, value: i64) -> FieldTable {
+  let mut header = header.clone().unwrap_or_default();
+  header.insert(name.into(), AMQPValue::LongLongInt(value));
+  header
+}
+
 fn get_count_from_header(header: &Option<FieldTable>) -> Option<i64> {
   if let Some(header) = header {
     if let Some(death) = header.inner().get("x-death") {
       if let AMQPValue::FieldArray(array) = death {
-        let raw_array = array.as_slice();
-        if raw_array.is_empty() {
-          return None;
-        }
-        if let AMQPValue::FieldTable(params) = &raw_array[0] {
+        // the most recent death first, the expirations in the delay and lease queues are not counted
+        let death = array.as_slice().iter().find_map(|death| match death {
+          AMQPValue::FieldTable(params) if !is_holding_queue_death(params) => Some(params),
+          _ => None,
+        });
+        if let Some(params) = death {
           if let Some(AMQPValue::LongLongInt(value)) = params.inner().get("count") {
             return Some(*value);
           }
@@ -24,6 +66,13 @@ fn get_count_from_header(header: &Option<FieldTable>) -> Option<i64> {
   None
 }
 
+fn is_holding_queue_death(params: &FieldTable) -> bool {
+  match params.inner().get("queue") {
+    Some(AMQPValue::LongString(queue)) => is_holding_queue(&queue.to_string()),
+    _ => false,
+  }
+}
+
 #[test]
 fn header_information() {
   use std::collections::BTreeMap;
@@ -52,4 +101,44 @@ fn header_information() {
   let header = Some(map);
   let count = get_count_from_header(&header);
   assert!(count == Some(666));
+
+  let mut job_properties = FieldTable::from(BTreeMap::new());
+  job_properties.insert("count".into(), AMQPValue::LongLongInt(666));
+  let mut delay_properties = FieldTable::from(BTreeMap::new());
+  delay_properties.insert("count".into(), AMQPValue::LongLongInt(2));
+  delay_properties.insert(
+    "queue".into(),
+    AMQPValue::LongString("job_test.delay.2000".into()),
+  );
+  let mut map = FieldTable::from(BTreeMap::new());
+  map.insert(
+    "x-death".into(),
+    AMQPValue::FieldArray(
+      vec![
+        AMQPValue::FieldTable(delay_properties),
+        AMQPValue::FieldTable(job_properties),
+      ]
+      .into(),
+    ),
+  );
+  let count = get_count_from_header(&Some(map));
+  assert!(count == Some(666));
+}
+
+#[test]
+fn retry_count_header() {
+  assert_eq!(0, get_retry_count_from_header(&None));
+
+  let header = set_retry_count_in_header(&None, 1);
+  assert_eq!(1, get_retry_count_from_header(&Some(header.clone())));
+
+  let header = set_retry_count_in_header(&Some(header), 2);
+  assert_eq!(2, get_retry_count_from_header(&Some(header.clone())));
+
+  let header = set_timeout_count_in_header(&Some(header), 1);
+  assert_eq!(
+    1,
+    get_counter_from_header(&Some(header.clone()), TIMEOUT_COUNT_HEADER)
+  );
+  assert_eq!(2, get_retry_count_from_header(&Some(header)));
 }
@@ -0,0 +1,88 @@
+//! Events of the state transitions of the order processing
+//!
+//! When `JOB_EVENTS_EXCHANGE` is set, each transition is published on this topic exchange,
+//! with the state as routing key, to build the timeline of the jobs.
+//! The events are best effort: a failed publish never fails the job.
+
+use crate::config::get_job_events_exchange;
+use crate::job::{JobEvent, JobState, PendingPublish};
+use crate::message::trace_context;
+use crate::parameter::container::ParametersContainer;
+use crate::{McaiChannel, MessageError};
+use lapin::options::BasicPublishOptions;
+
+pub fn publish_job_event(channel: Option<&McaiChannel>, job_event: JobEvent) {
+  let job_id = job_event.get_job_id();
+  let state = job_event.get_state();
+  trace!(target: &job_id.to_string(), "Job state: {}", state.as_str());
+
+  let (channel, exchange) = match (channel, get_job_events_exchange()) {
+    (Some(channel), Some(exchange)) => (channel, exchange),
+    _ => return,
+  };
+
+  let _pending_publish = PendingPublish::new();
+  let payload = json!(job_event).to_string();
+  if let Err(error) = channel
+    .basic_publish(
+      &exchange,
+      state.as_str(),
+      BasicPublishOptions::default(),
+      payload.into_bytes(),
+      trace_context::get_response_properties(Some(job_id)),
+    )
+    .wait()
+  {
+    warn!(target: &job_id.to_string(), "Unable to publish the {} job event: {:?}", state.as_str(), error);
+  }
+}
+
+pub fn publish_job_state(channel: Option<&McaiChannel>, job_id: u64, state: JobState) {
+  publish_job_event(channel, JobEvent::new(job_id, state));
+}
+
+/// Event of the end of the order processing
+pub fn get_result_event<T>(job_id: u64, result: &Result<T, MessageError>) -> JobEvent {
+  let message = match result {
+    Ok(_) => return JobEvent::new(job_id, JobState::Completed),
+    Err(MessageError::RuntimeError(message))
+    | Err(MessageError::ParameterValueError(message))
+    | Err(MessageError::RequirementsError(message)) => message.clone(),
+    Err(MessageError::ProcessingError(job_result)) | Err(MessageError::Transient(job_result)) => {
+      job_result
+        .get_parameter::<String>("message")
+        .unwrap_or_default()
+    }
+    Err(MessageError::NotImplemented()) => "Not implemented feature".to_string(),
+  };
+
+  JobEvent::new(job_id, JobState::Error).with_message(&message)
+}
+
+#[test]
+pub fn test_get_result_event() {
+  use crate::job::JobResult;
+
+  let job_event = get_result_event(123, &Ok(()));
+  assert_eq!(JobState::Completed, job_event.get_state());
+  assert!(json!(job_event).get("message").is_none());
+
+  let job_event = get_result_event::<()>(
+    123,
+    &Err(MessageError::ParameterValueError(
+      "invalid width".to_string(),
+    )),
+  );
+  assert_eq!(JobState::Error, job_event.get_state());
+  assert_eq!(
+    Some(&json!("invalid width")),
+    json!(job_event).get("message")
+  );
+
+  let job_result = JobResult::new(123).with_message("decoding failed");
+  let job_event = get_result_event::<()>(123, &Err(MessageError::ProcessingError(job_result)));
+  assert_eq!(
+    Some(&json!("decoding failed")),
+    json!(job_event).get("message")
+  );
+}
@@ -0,0 +1,181 @@
+//! Audio timestamp continuity checking
+//!
+//! Each decoded audio frame is expected to start where the previous one ended,
+//! i.e. at its PTS plus the duration of its samples. Gaps reveal dropped samples,
+//! overlaps reveal duplicated samples, both leading to audio/video drift.
+
+use crate::parameter::ParameterValue;
+
+/// Discontinuities shorter than this duration in milliseconds are timestamp rounding
+const TOLERANCE_MS: f64 = 1.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscontinuityKind {
+  Gap,
+  Overlap,
+}
+
+/// Audio frame not starting where the previous one ended
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioDiscontinuity {
+  pub stream_index: usize,
+  pub kind: DiscontinuityKind,
+  /// PTS of the frame, in the stream time base
+  pub pts: i64,
+  /// PTS expected from the previous frame, in the stream time base
+  pub expected_pts: i64,
+  /// Duration of the gap or overlap in milliseconds
+  pub duration: f64,
+  /// Number of samples missing or duplicated
+  pub samples: i64,
+}
+
+/// Continuity summary of an audio stream
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioContinuitySummary {
+  pub stream_index: usize,
+  pub frames: u64,
+  pub samples: u64,
+  pub gaps: u64,
+  pub overlaps: u64,
+  /// Total duration of the gaps in milliseconds
+  pub gap_duration: f64,
+  /// Total duration of the overlaps in milliseconds
+  pub overlap_duration: f64,
+}
+
+/// Summaries and discontinuities of the checked audio streams, added to the job result
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioContinuityReport {
+  pub summaries: Vec<AudioContinuitySummary>,
+  pub discontinuities: Vec<AudioDiscontinuity>,
+}
+
+impl ParameterValue for AudioContinuityReport {
+  fn get_type_as_string() -> String {
+    "audio_continuity_report".to_string()
+  }
+}
+
+#[derive(Debug)]
+pub struct AudioContinuityChecker {
+  /// Stream time base, as numerator and denominator
+  time_base: (i32, i32),
+  /// End of the previous frame, in the stream time base
+  next_pts: Option<f64>,
+  summary: AudioContinuitySummary,
+  discontinuities: Vec<AudioDiscontinuity>,
+}
+
+impl AudioContinuityChecker {
+  pub fn new(stream_index: usize, time_base: (i32, i32)) -> Self {
+    AudioContinuityChecker {
+      time_base,
+      next_pts: None,
+      summary: AudioContinuitySummary {
+        stream_index,
+        ..Default::default()
+      },
+      discontinuities: vec![],
+    }
+  }
+
+  /// Check a decoded frame, returning the discontinuity with the previous frame if any
+  pub fn check(
+    &mut self,
+    pts: i64,
+    nb_samples: i32,
+    sample_rate: i32,
+  ) -> Option<AudioDiscontinuity> {
+    let (num, den) = self.time_base;
+    if pts == i64::MIN || nb_samples <= 0 || sample_rate <= 0 || num <= 0 || den <= 0 {
+      return None;
+    }
+
+    let ticks_per_ms = den as f64 / num as f64 / 1000.0;
+    let ticks_per_sample = den as f64 / (num as f64 * sample_rate as f64);
+
+    let discontinuity = self.next_pts.and_then(|next_pts| {
+      let offset = pts as f64 - next_pts;
+      let duration = offset.abs() / ticks_per_ms;
+      if duration < TOLERANCE_MS {
+        return None;
+      }
+
+      let kind = if offset > 0.0 {
+        self.summary.gaps += 1;
+        self.summary.gap_duration += duration;
+        DiscontinuityKind::Gap
+      } else {
+        self.summary.overlaps += 1;
+        self.summary.overlap_duration += duration;
+        DiscontinuityKind::Overlap
+      };
+
+      Some(AudioDiscontinuity {
+        stream_index: self.summary.stream_index,
+        kind,
+        pts,
+        expected_pts: next_pts.round() as i64,
+        duration,
+        samples: (offset.abs() / ticks_per_sample).round() as i64,
+      })
+    });
+
+    self.summary.frames += 1;
+    self.summary.samples += nb_samples as u64;
+    // keep the exact end of the frame to not accumulate rounding errors
+    let next_pts = match (&discontinuity, self.next_pts) {
+      (None, Some(next_pts)) => next_pts,
+      _ => pts as f64,
+    };
+    self.next_pts = Some(next_pts + nb_samples as f64 * ticks_per_sample);
+
+    if let Some(discontinuity) = &discontinuity {
+      self.discontinuities.push(discontinuity.clone());
+    }
+    discontinuity
+  }
+
+  pub fn get_summary(&self) -> &AudioContinuitySummary {
+    &self.summary
+  }
+
+  pub fn get_discontinuities(&self) -> &Vec<AudioDiscontinuity> {
+    &self.discontinuities
+  }
+}
+
+#[test]
+pub fn test_audio_continuity() {
+  // MPEG-TS time base with 1024 samples frames at 48 kHz, lasting 1920 ticks
+  let mut checker = AudioContinuityChecker::new(1, (1, 90000));
+
+  assert_eq!(None, checker.check(0, 1024, 48000));
+  assert_eq!(None, checker.check(1920, 1024, 48000));
+  assert_eq!(None, checker.check(3841, 1024, 48000));
+
+  let gap = checker.check(3840 + 1920 * 2, 1024, 48000).unwrap();
+  assert_eq!(DiscontinuityKind::Gap, gap.kind);
+  assert_eq!(5760, gap.expected_pts);
+  assert_eq!(1024, gap.samples);
+  assert!((gap.duration - 21.333).abs() < 0.001);
+
+  let overlap = checker.check(9600 - 960, 1024, 48000).unwrap();
+  assert_eq!(DiscontinuityKind::Overlap, overlap.kind);
+  assert_eq!(9600, overlap.expected_pts);
+  assert_eq!(512, overlap.samples);
+
+  assert_eq!(None, checker.check(10560, 1024, 48000));
+  assert_eq!(None, checker.check(i64::MIN, 1024, 48000));
+
+  let summary = checker.get_summary();
+  assert_eq!(1, summary.stream_index);
+  assert_eq!(6, summary.frames);
+  assert_eq!(6 * 1024, summary.samples);
+  assert_eq!(1, summary.gaps);
+  assert_eq!(1, summary.overlaps);
+  assert!((summary.overlap_duration - 10.666).abs() < 0.001);
+  assert_eq!(2, checker.get_discontinuities().len());
+}
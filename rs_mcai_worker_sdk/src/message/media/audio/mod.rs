@@ -6,6 +6,8 @@ use std::collections::HashMap;
 
 use crate::message::media::filters::FilterParameters;
 
+pub mod continuity;
+
 #[cfg(feature = "media")]
 #[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[cfg_attr(feature = "python", derive(FromPyObject, IntoPyObject))]
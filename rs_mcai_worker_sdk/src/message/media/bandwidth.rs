@@ -0,0 +1,139 @@
+//! Limitation of the read bandwidth of the sources
+//!
+//! The packets read from a source are accounted against the bandwidth of the job
+//! (`sdk_source_bandwidth` job parameter) and the one shared by all the jobs of the process
+//! (`SOURCE_MAX_BANDWIDTH`), the reading is paused once a budget is exceeded.
+
+use crate::config::get_source_max_bandwidth;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const SOURCE_BANDWIDTH_PARAMETER: &str = "sdk_source_bandwidth";
+
+/// Bandwidth shared by the sources of all the jobs, created on its first use
+static SHARED_LIMITER: Mutex<Option<RateLimiter>> = Mutex::new(None);
+
+/// Token bucket, allowing bursts of one second of bandwidth
+#[derive(Debug)]
+struct RateLimiter {
+  /// Bytes per second
+  rate: f64,
+  allowance: f64,
+  last: Instant,
+}
+
+impl RateLimiter {
+  fn new(rate: u64) -> Self {
+    RateLimiter {
+      rate: rate as f64,
+      allowance: rate as f64,
+      last: Instant::now(),
+    }
+  }
+
+  /// Account the bytes, returns the delay before reading them
+  fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+    let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+    self.last = now;
+    self.allowance = (self.allowance + elapsed * self.rate).min(self.rate);
+    self.allowance -= bytes as f64;
+
+    if self.allowance >= 0.0 {
+      Duration::ZERO
+    } else {
+      Duration::from_secs_f64(-self.allowance / self.rate)
+    }
+  }
+}
+
+/// Bandwidth limits of the source of a job
+#[derive(Debug)]
+pub struct SourceThrottle {
+  job_limiter: Option<RateLimiter>,
+  shared: bool,
+}
+
+impl SourceThrottle {
+  /// Throttle the source with the bandwidth of the job in bytes per second,
+  /// none if neither the job nor the process bandwidth is limited
+  pub fn new(job_bandwidth: Option<u64>) -> Option<Self> {
+    let job_limiter = job_bandwidth
+      .filter(|bandwidth| *bandwidth > 0)
+      .map(RateLimiter::new);
+
+    let shared = match get_source_max_bandwidth().filter(|bandwidth| *bandwidth > 0) {
+      Some(bandwidth) => {
+        let mut shared_limiter = SHARED_LIMITER.lock().unwrap();
+        if shared_limiter.is_none() {
+          *shared_limiter = Some(RateLimiter::new(bandwidth));
+        }
+        true
+      }
+      None => false,
+    };
+
+    if job_limiter.is_none() && !shared {
+      return None;
+    }
+    Some(SourceThrottle {
+      job_limiter,
+      shared,
+    })
+  }
+
+  /// Account the bytes read, waiting until they fit in the bandwidth
+  pub fn consume(&mut self, bytes: usize) {
+    let now = Instant::now();
+
+    let job_delay = self
+      .job_limiter
+      .as_mut()
+      .map(|limiter| limiter.reserve(bytes, now))
+      .unwrap_or_default();
+
+    let shared_delay = if self.shared {
+      SHARED_LIMITER
+        .lock()
+        .unwrap()
+        .as_mut()
+        .map(|limiter| limiter.reserve(bytes, now))
+        .unwrap_or_default()
+    } else {
+      Duration::ZERO
+    };
+
+    let delay = job_delay.max(shared_delay);
+    if delay > Duration::ZERO {
+      trace!("Source throttled for {:?}", delay);
+      thread::sleep(delay);
+    }
+  }
+}
+
+#[test]
+pub fn test_rate_limiter() {
+  let start = Instant::now();
+  let mut limiter = RateLimiter::new(1000);
+  limiter.last = start;
+
+  // one second of burst
+  assert_eq!(Duration::ZERO, limiter.reserve(600, start));
+  assert_eq!(Duration::ZERO, limiter.reserve(400, start));
+  assert_eq!(Duration::from_millis(500), limiter.reserve(500, start));
+
+  // refilled over time, up to one second of bandwidth
+  let later = start + Duration::from_secs(10);
+  assert_eq!(Duration::ZERO, limiter.reserve(1000, later));
+  assert_eq!(Duration::from_millis(100), limiter.reserve(100, later));
+}
+
+#[test]
+pub fn test_source_throttle() {
+  assert!(SourceThrottle::new(None).is_none());
+  assert!(SourceThrottle::new(Some(0)).is_none());
+
+  let throttle = SourceThrottle::new(Some(1_000_000)).unwrap();
+  assert!(throttle.job_limiter.is_some());
+  assert!(!throttle.shared);
+}
@@ -0,0 +1,185 @@
+//! Bitmap subtitles (DVB, ARIB, PGS, DVD) delivered to the worker, i.e. to be converted by OCR
+
+use stainless_ffmpeg_sys::{AVCodecID, AVSubtitle, AVSubtitleType};
+
+/// Subtitle codecs decoded as bitmap subtitles
+pub const BITMAP_SUBTITLE_CODECS: [AVCodecID; 4] = [
+  AVCodecID::AV_CODEC_ID_DVB_SUBTITLE,
+  AVCodecID::AV_CODEC_ID_ARIB_CAPTION,
+  AVCodecID::AV_CODEC_ID_HDMV_PGS_SUBTITLE,
+  AVCodecID::AV_CODEC_ID_DVD_SUBTITLE,
+];
+
+/// Decoded subtitle, displayed between `start_display_time` and `end_display_time` after its `pts`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BitmapSubtitle {
+  pub pts: i64,
+  /// Display start, relative to the PTS, in milliseconds
+  pub start_display_time: u32,
+  /// Display end, relative to the PTS, in milliseconds
+  pub end_display_time: u32,
+  pub regions: Vec<SubtitleRegion>,
+}
+
+/// Positioned bitmap of a subtitle, as RGBA pixels
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubtitleRegion {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<u8>,
+}
+
+impl BitmapSubtitle {
+  pub(crate) unsafe fn from_av_subtitle(av_subtitle: &AVSubtitle, pts: i64) -> Self {
+    let mut regions = vec![];
+    for index in 0..av_subtitle.num_rects as isize {
+      let rect = &**av_subtitle.rects.offset(index);
+      if rect.type_ != AVSubtitleType::SUBTITLE_BITMAP || rect.w <= 0 || rect.h <= 0 {
+        continue;
+      }
+
+      let palette =
+        std::slice::from_raw_parts(rect.data[1] as *const u32, rect.nb_colors.max(0) as usize);
+
+      let mut pixels = Vec::with_capacity((rect.w * rect.h * 4) as usize);
+      for line in 0..rect.h {
+        let indexes = std::slice::from_raw_parts(
+          rect.data[0].offset((line * rect.linesize[0]) as isize),
+          rect.w as usize,
+        );
+
+        for color_index in indexes {
+          // palette colors are stored as native endian ARGB
+          let argb = palette.get(*color_index as usize).cloned().unwrap_or(0);
+          pixels.extend_from_slice(&[
+            (argb >> 16) as u8,
+            (argb >> 8) as u8,
+            argb as u8,
+            (argb >> 24) as u8,
+          ]);
+        }
+      }
+
+      regions.push(SubtitleRegion {
+        x: rect.x.max(0) as u32,
+        y: rect.y.max(0) as u32,
+        width: rect.w as u32,
+        height: rect.h as u32,
+        pixels,
+      });
+    }
+
+    BitmapSubtitle {
+      pts,
+      start_display_time: av_subtitle.start_display_time,
+      end_display_time: av_subtitle.end_display_time,
+      regions,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.regions.is_empty()
+  }
+
+  /// Composite the regions onto a transparent RGBA canvas of the given size,
+  /// an error if a region has not the pixels of its size
+  pub fn composite(&self, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let canvas_size = get_rgba_size(width, height)
+      .ok_or_else(|| format!("Invalid subtitle canvas size: {}x{}", width, height))?;
+    let mut canvas = vec![0; canvas_size];
+
+    for region in &self.regions {
+      if get_rgba_size(region.width, region.height) != Some(region.pixels.len()) {
+        return Err(format!(
+          "Invalid subtitle region: {} bytes of pixels for {}x{}",
+          region.pixels.len(),
+          region.width,
+          region.height
+        ));
+      }
+
+      for line in 0..region.height {
+        let y = match region.y.checked_add(line) {
+          Some(y) if y < height => y as usize,
+          _ => break,
+        };
+
+        for column in 0..region.width {
+          let x = match region.x.checked_add(column) {
+            Some(x) if x < width => x as usize,
+            _ => break,
+          };
+
+          let source = (line as usize * region.width as usize + column as usize) * 4;
+          let pixel = &region.pixels[source..source + 4];
+          if pixel[3] == 0 {
+            continue;
+          }
+
+          let destination = (y * width as usize + x) * 4;
+          canvas[destination..destination + 4].copy_from_slice(pixel);
+        }
+      }
+    }
+
+    Ok(canvas)
+  }
+}
+
+/// Number of bytes of the RGBA pixels of the size, none on overflow
+fn get_rgba_size(width: u32, height: u32) -> Option<usize> {
+  (width as usize)
+    .checked_mul(height as usize)
+    .and_then(|pixels| pixels.checked_mul(4))
+}
+
+#[test]
+pub fn test_bitmap_subtitle_composite() {
+  let subtitle = BitmapSubtitle {
+    pts: 900,
+    start_display_time: 0,
+    end_display_time: 2000,
+    regions: vec![SubtitleRegion {
+      x: 1,
+      y: 1,
+      width: 2,
+      height: 1,
+      pixels: vec![255, 255, 255, 255, 0, 0, 0, 0],
+    }],
+  };
+
+  let canvas = subtitle.composite(3, 2).unwrap();
+  assert_eq!(24, canvas.len());
+  assert_eq!(vec![0; 16], canvas[0..16].to_vec());
+  assert_eq!(vec![255, 255, 255, 255], canvas[16..20].to_vec());
+  assert_eq!(vec![0, 0, 0, 0], canvas[20..24].to_vec());
+
+  let canvas = subtitle.composite(1, 1).unwrap();
+  assert_eq!(vec![0, 0, 0, 0], canvas);
+
+  let out_of_canvas = BitmapSubtitle {
+    regions: vec![SubtitleRegion {
+      x: u32::MAX,
+      y: 0,
+      width: 2,
+      height: 1,
+      pixels: vec![255; 8],
+    }],
+    ..subtitle.clone()
+  };
+  assert_eq!(vec![0; 24], out_of_canvas.composite(3, 2).unwrap());
+
+  let malformed = BitmapSubtitle {
+    regions: vec![SubtitleRegion {
+      x: 0,
+      y: 0,
+      width: 4,
+      height: 4,
+      pixels: vec![255; 8],
+    }],
+    ..subtitle
+  };
+  assert!(malformed.composite(3, 2).is_err());
+}
@@ -0,0 +1,50 @@
+use crate::parameter::{chapter::Chapter, Chapters};
+use stainless_ffmpeg::{format_context::FormatContext, tools};
+use stainless_ffmpeg_sys::{av_dict_get, AVRational};
+use std::ffi::CString;
+
+/// Chapters declared by the container (MP4 chapter lists, Matroska chapters, MXF markers)
+pub fn get_chapters(format_context: &FormatContext) -> Chapters {
+  let mut chapters = vec![];
+
+  unsafe {
+    let context = format_context.format_context;
+    let title_key = CString::new("title").unwrap();
+
+    for index in 0..(*context).nb_chapters as isize {
+      let chapter = *(*context).chapters.offset(index);
+
+      let title_tag = av_dict_get((*chapter).metadata, title_key.as_ptr(), std::ptr::null(), 0);
+      let title = if title_tag.is_null() {
+        None
+      } else {
+        Some(tools::to_string((*title_tag).value))
+      };
+
+      chapters.push(Chapter::new(
+        (*chapter).id as i64,
+        to_milliseconds((*chapter).start, (*chapter).time_base),
+        to_milliseconds((*chapter).end, (*chapter).time_base),
+        title,
+      ));
+    }
+  }
+
+  chapters
+}
+
+fn to_milliseconds(timestamp: i64, time_base: AVRational) -> u64 {
+  if time_base.den == 0 || timestamp < 0 {
+    return 0;
+  }
+  (timestamp as f64 * time_base.num as f64 / time_base.den as f64 * 1000.0) as u64
+}
+
+#[test]
+pub fn test_chapter_time_base_conversion() {
+  let time_base = AVRational { num: 1, den: 90000 };
+  assert_eq!(0, to_milliseconds(0, time_base));
+  assert_eq!(2000, to_milliseconds(180_000, time_base));
+  assert_eq!(0, to_milliseconds(-1, time_base));
+  assert_eq!(0, to_milliseconds(1000, AVRational { num: 1, den: 0 }));
+}
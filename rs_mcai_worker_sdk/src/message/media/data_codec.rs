@@ -0,0 +1,73 @@
+use crate::message::media::{id3::Id3Codec, scte35::Scte35Codec};
+use stainless_ffmpeg_sys::AVCodecID;
+use std::{any::Any, collections::HashMap, sync::Arc};
+
+/// Decoder of data stream packets into a typed payload
+///
+/// The payload is delivered to the worker as `ProcessFrame::TypedData`,
+/// it can be retrieved with a downcast to the type produced by the codec.
+pub trait DataCodec: Send + Sync {
+  fn decode(&self, data: &[u8]) -> Result<Box<dyn Any + Send>, String>;
+}
+
+/// Registry of data codecs, indexed by FFmpeg codec identifier
+#[derive(Clone, Default)]
+pub struct DataCodecRegistry {
+  codecs: HashMap<u32, Arc<dyn DataCodec>>,
+}
+
+impl DataCodecRegistry {
+  /// Registry with the codecs of the timed metadata: ID3 tags and SCTE-35 markers
+  pub fn with_default_codecs() -> Self {
+    let mut registry = DataCodecRegistry::default();
+    registry.register(AVCodecID::AV_CODEC_ID_TIMED_ID3, Id3Codec {});
+    registry.register(AVCodecID::AV_CODEC_ID_SCTE_35, Scte35Codec {});
+    registry
+  }
+
+  pub fn register<C: 'static + DataCodec>(&mut self, codec_id: AVCodecID, codec: C) {
+    self.codecs.insert(codec_id as u32, Arc::new(codec));
+  }
+
+  pub fn get(&self, codec_id: AVCodecID) -> Option<Arc<dyn DataCodec>> {
+    self.codecs.get(&(codec_id as u32)).cloned()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.codecs.is_empty()
+  }
+}
+
+impl std::fmt::Debug for DataCodecRegistry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DataCodecRegistry")
+      .field("codecs", &self.codecs.keys().collect::<Vec<&u32>>())
+      .finish()
+  }
+}
+
+#[test]
+pub fn test_data_codec_registry() {
+  struct LengthCodec {}
+
+  impl DataCodec for LengthCodec {
+    fn decode(&self, data: &[u8]) -> Result<Box<dyn Any + Send>, String> {
+      Ok(Box::new(data.len()))
+    }
+  }
+
+  let mut registry = DataCodecRegistry::default();
+  assert!(registry.is_empty());
+
+  registry.register(AVCodecID::AV_CODEC_ID_SMPTE_KLV, LengthCodec {});
+  assert!(!registry.is_empty());
+  assert!(registry.get(AVCodecID::AV_CODEC_ID_SCTE_35).is_none());
+
+  let codec = registry.get(AVCodecID::AV_CODEC_ID_SMPTE_KLV).unwrap();
+  let payload = codec.decode(&[1, 2, 3]).unwrap();
+  assert_eq!(Some(&3), payload.downcast_ref::<usize>());
+
+  let registry = DataCodecRegistry::with_default_codecs();
+  assert!(registry.get(AVCodecID::AV_CODEC_ID_TIMED_ID3).is_some());
+  assert!(registry.get(AVCodecID::AV_CODEC_ID_SCTE_35).is_some());
+}
@@ -0,0 +1,303 @@
+//! ID3v2 timed metadata parsing, as carried in HLS and MPEG-TS streams
+//!
+//! Tags are delivered as `ProcessFrame::TypedData` payloads by the
+//! [`Id3Codec`](struct.Id3Codec.html), registered by default for the `AV_CODEC_ID_TIMED_ID3` codec.
+
+use crate::message::media::data_codec::DataCodec;
+use std::any::Any;
+
+const HEADER_SIZE: usize = 10;
+const EXTENDED_HEADER_FLAG: u8 = 0x40;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Id3Tag {
+  pub major_version: u8,
+  pub revision: u8,
+  pub frames: Vec<Id3Frame>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum Id3Frame {
+  /// Text information frame (`TIT2`, `TALB`, ...)
+  Text {
+    id: String,
+    value: String,
+  },
+  /// User defined text information frame (`TXXX`)
+  UserText {
+    description: String,
+    value: String,
+  },
+  /// Private frame (`PRIV`), i.e. the HLS timestamp of `com.apple.streaming.transportStreamTimestamp`
+  Private {
+    owner: String,
+    data: Vec<u8>,
+  },
+  Other {
+    id: String,
+    data: Vec<u8>,
+  },
+}
+
+impl Id3Tag {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    if data.len() < HEADER_SIZE || &data[0..3] != b"ID3" {
+      return Err("Invalid ID3 tag header".to_string());
+    }
+
+    let major_version = data[3];
+    let revision = data[4];
+    let flags = data[5];
+    if !(3..=4).contains(&major_version) {
+      return Err(format!("Unsupported ID3 version: 2.{}", major_version));
+    }
+
+    let size = read_syncsafe(&data[6..10]) as usize;
+    let end = HEADER_SIZE + size;
+    if data.len() < end {
+      return Err(format!(
+        "Truncated ID3 tag: {} bytes, expected {}",
+        data.len(),
+        end
+      ));
+    }
+
+    let mut position = HEADER_SIZE;
+    if flags & EXTENDED_HEADER_FLAG != 0 {
+      if end < position + 4 {
+        return Err("Truncated ID3 extended header".to_string());
+      }
+      let extended_header_size = &data[position..position + 4];
+      position += if major_version == 4 {
+        read_syncsafe(extended_header_size) as usize
+      } else {
+        read_u32(extended_header_size) as usize + 4
+      };
+    }
+
+    let mut frames = vec![];
+    while position + HEADER_SIZE <= end && data[position] != 0 {
+      let id = String::from_utf8_lossy(&data[position..position + 4]).to_string();
+      let frame_size = if major_version == 4 {
+        read_syncsafe(&data[position + 4..position + 8])
+      } else {
+        read_u32(&data[position + 4..position + 8])
+      } as usize;
+      position += HEADER_SIZE;
+
+      if position + frame_size > end {
+        return Err(format!("Truncated ID3 frame {}", id));
+      }
+
+      frames.push(Id3Frame::parse(id, &data[position..position + frame_size]));
+      position += frame_size;
+    }
+
+    Ok(Id3Tag {
+      major_version,
+      revision,
+      frames,
+    })
+  }
+}
+
+impl Id3Frame {
+  fn parse(id: String, data: &[u8]) -> Self {
+    match id.as_str() {
+      "TXXX" if !data.is_empty() => {
+        let (description, value) = split_encoded(data[0], &data[1..]);
+        Id3Frame::UserText {
+          description: decode_text(data[0], description),
+          value: decode_text(data[0], value),
+        }
+      }
+      "PRIV" => {
+        let (owner, data) = split_encoded(0, data);
+        Id3Frame::Private {
+          owner: decode_text(0, owner),
+          data: data.to_vec(),
+        }
+      }
+      _ if id.starts_with('T') && !data.is_empty() => Id3Frame::Text {
+        value: decode_text(data[0], &data[1..]),
+        id,
+      },
+      _ => Id3Frame::Other {
+        id,
+        data: data.to_vec(),
+      },
+    }
+  }
+}
+
+/// Data codec producing [`Id3Tag`](struct.Id3Tag.html) payloads
+#[derive(Debug, Default)]
+pub struct Id3Codec {}
+
+impl DataCodec for Id3Codec {
+  fn decode(&self, data: &[u8]) -> Result<Box<dyn Any + Send>, String> {
+    Ok(Box::new(Id3Tag::parse(data)?))
+  }
+}
+
+fn read_syncsafe(data: &[u8]) -> u32 {
+  data
+    .iter()
+    .fold(0, |value, byte| (value << 7) | (*byte & 0x7F) as u32)
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+  data
+    .iter()
+    .fold(0, |value, byte| (value << 8) | *byte as u32)
+}
+
+/// Split on the first terminator of the text encoding
+fn split_encoded(encoding: u8, data: &[u8]) -> (&[u8], &[u8]) {
+  let terminator = if encoding == 1 || encoding == 2 {
+    data
+      .chunks(2)
+      .position(|chunk| chunk == [0, 0])
+      .map(|index| (index * 2, index * 2 + 2))
+  } else {
+    data
+      .iter()
+      .position(|byte| *byte == 0)
+      .map(|index| (index, index + 1))
+  };
+
+  match terminator {
+    Some((end, start)) => (&data[..end], &data[start.min(data.len())..]),
+    None => (data, &[]),
+  }
+}
+
+fn decode_text(encoding: u8, data: &[u8]) -> String {
+  let text = match encoding {
+    // UTF-16 with byte order mark, big endian without
+    1 | 2 => {
+      let little_endian = encoding == 1 && data.starts_with(&[0xFF, 0xFE]);
+      let data = if encoding == 1
+        && data.len() >= 2
+        && (data[0..2] == [0xFF, 0xFE] || data[0..2] == [0xFE, 0xFF])
+      {
+        &data[2..]
+      } else {
+        data
+      };
+      let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|chunk| {
+          if little_endian {
+            u16::from_le_bytes([chunk[0], chunk[1]])
+          } else {
+            u16::from_be_bytes([chunk[0], chunk[1]])
+          }
+        })
+        .collect();
+      String::from_utf16_lossy(&units)
+    }
+    3 => String::from_utf8_lossy(data).to_string(),
+    // ISO-8859-1
+    _ => data.iter().map(|byte| *byte as char).collect(),
+  };
+
+  text.trim_end_matches('\0').to_string()
+}
+
+#[cfg(test)]
+fn get_test_tag(major_version: u8, frames: &[(&str, Vec<u8>)], padding: usize) -> Vec<u8> {
+  let encode_size = |size: usize, syncsafe: bool| {
+    if syncsafe {
+      (0..4)
+        .rev()
+        .map(|index| ((size >> (index * 7)) & 0x7F) as u8)
+        .collect::<Vec<u8>>()
+    } else {
+      (size as u32).to_be_bytes().to_vec()
+    }
+  };
+
+  let mut content = vec![];
+  for (id, data) in frames {
+    content.extend_from_slice(id.as_bytes());
+    content.extend(encode_size(data.len(), major_version == 4));
+    content.extend(&[0, 0]);
+    content.extend(data);
+  }
+  content.resize(content.len() + padding, 0);
+
+  let mut tag = vec![b'I', b'D', b'3', major_version, 0, 0];
+  tag.extend(encode_size(content.len(), true));
+  tag.extend(content);
+  tag
+}
+
+#[test]
+pub fn test_parse_id3_hls_timestamp() {
+  let timestamp = [0, 0, 0, 0, 0, 0x01, 0x5F, 0x90];
+  let priv_data = [
+    b"com.apple.streaming.transportStreamTimestamp\0".to_vec(),
+    timestamp.to_vec(),
+  ]
+  .concat();
+
+  let data = get_test_tag(4, &[("PRIV", priv_data)], 0);
+  let codec = Id3Codec {};
+  let payload = codec.decode(&data).unwrap();
+  let tag = payload.downcast_ref::<Id3Tag>().unwrap();
+
+  assert_eq!(4, tag.major_version);
+  assert_eq!(
+    vec![Id3Frame::Private {
+      owner: "com.apple.streaming.transportStreamTimestamp".to_string(),
+      data: timestamp.to_vec(),
+    }],
+    tag.frames
+  );
+}
+
+#[test]
+pub fn test_parse_id3_text_frames() {
+  let data = get_test_tag(
+    3,
+    &[
+      ("TIT2", b"\x03Live title".to_vec()),
+      ("TXXX", b"\x00cue\x00ad-break".to_vec()),
+      ("TALB", vec![0x01, 0xFF, 0xFE, b'A', 0, b'b', 0, 0, 0]),
+      ("GEOB", vec![1, 2, 3]),
+    ],
+    4,
+  );
+
+  let tag = Id3Tag::parse(&data).unwrap();
+  assert_eq!(
+    vec![
+      Id3Frame::Text {
+        id: "TIT2".to_string(),
+        value: "Live title".to_string()
+      },
+      Id3Frame::UserText {
+        description: "cue".to_string(),
+        value: "ad-break".to_string()
+      },
+      Id3Frame::Text {
+        id: "TALB".to_string(),
+        value: "Ab".to_string()
+      },
+      Id3Frame::Other {
+        id: "GEOB".to_string(),
+        data: vec![1, 2, 3]
+      },
+    ],
+    tag.frames
+  );
+}
+
+#[test]
+pub fn test_parse_invalid_id3() {
+  assert!(Id3Tag::parse(b"ID").is_err());
+  assert!(Id3Tag::parse(b"TAG\x03\x00\x00\x00\x00\x00\x00").is_err());
+  assert!(Id3Tag::parse(b"ID3\x02\x00\x00\x00\x00\x00\x00").is_err());
+  assert!(Id3Tag::parse(b"ID3\x03\x00\x00\x00\x00\x00\x10").is_err());
+}
@@ -1,23 +1,32 @@
 use crate::{
-  job::{Job, JobResult, JobStatus},
-  message::publish_job_progression,
+  job::{Job, JobContext, JobResult, JobStatus},
+  message::{publish_job_progression, read_message_event, write_message_event},
   parameter::container::ParametersContainer,
-  AudioFilter, McaiChannel, MessageEvent, Result,
+  AudioFilter, McaiChannel,
+  MessageError::RuntimeError,
+  MessageEvent, Result,
 };
 use filters::VideoFilter;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use source::DecodeResult;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
 pub mod audio;
+mod bandwidth;
+pub mod bitmap_subtitle;
+pub mod chapters;
+pub mod data_codec;
 pub mod ebu_ttml_live;
 pub mod filters;
+pub mod id3;
 mod media_stream;
 mod output;
+pub mod scte35;
 pub mod source;
 mod srt;
+pub mod teletext;
 pub mod video;
 
 pub const SOURCE_PATH_PARAMETER: &str = "source_path";
@@ -25,6 +34,8 @@ pub const DESTINATION_PATH_PARAMETER: &str = "destination_path";
 
 pub const START_INDEX_PARAMETER: &str = "sdk_start_index";
 pub const STOP_INDEX_PARAMETER: &str = "sdk_stop_index";
+pub const AUDIO_CONTINUITY_PARAMETER: &str = "sdk_check_audio_continuity";
+pub use bandwidth::SOURCE_BANDWIDTH_PARAMETER;
 
 #[cfg(all(feature = "media"))]
 #[derive(Debug, PartialEq)]
@@ -72,12 +83,28 @@ pub struct ImageConfiguration {
   filters: Vec<VideoFilter>,
 }
 
+/// Handling of the frames buffered in the decoders at the end of the stream
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlushMode {
+  /// Deliver the remaining frames to `process_frame` before `ending_process`
+  Deliver,
+  /// Drop the remaining frames
+  Skip,
+}
+
+impl Default for FlushMode {
+  fn default() -> Self {
+    FlushMode::Deliver
+  }
+}
+
 pub fn process<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
-  message_event: Rc<RefCell<ME>>,
+  message_event: Arc<RwLock<ME>>,
   channel: Option<McaiChannel>,
   job: &Job,
   parameters: P,
   job_result: JobResult,
+  context: JobContext,
 ) -> Result<JobResult> {
   let str_job_id = job.job_id.to_string();
 
@@ -85,8 +112,18 @@ pub fn process<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
   let output_url: String = job.get_parameter(DESTINATION_PATH_PARAMETER)?;
   let start_index_ms: Option<i64> = job.get_parameter(START_INDEX_PARAMETER).ok();
   let stop_index_ms: Option<i64> = job.get_parameter(STOP_INDEX_PARAMETER).ok();
+  let check_audio_continuity: bool = job
+    .get_parameter(AUDIO_CONTINUITY_PARAMETER)
+    .unwrap_or(false);
+  let source_bandwidth: Option<u64> = job
+    .get_parameter::<i64>(SOURCE_BANDWIDTH_PARAMETER)
+    .ok()
+    .map(|bandwidth| bandwidth.max(0) as u64);
 
   let mut output = output::Output::new(&output_url)?;
+  context
+    .get_job_metrics()
+    .set_output_backlog(output.get_backlog());
 
   let mut source = source::Source::new(
     message_event.clone(),
@@ -98,6 +135,11 @@ pub fn process<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
     stop_index_ms,
   )?;
 
+  if check_audio_continuity {
+    source.enable_audio_continuity_check();
+  }
+  source.limit_bandwidth(source_bandwidth);
+
   debug!(
     target: &str_job_id,
     "Start to process media (start: {} ms, duration: {})",
@@ -109,44 +151,111 @@ pub fn process<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
   );
 
   let total_duration = source.get_duration();
+  let flush_mode = read_message_event(&message_event).get_flush_mode();
   let mut count = 0;
   let mut previous_progress = 0;
 
-  loop {
-    match source.next_frame()? {
-      DecodeResult::Frame {
-        stream_index,
-        frame,
-      } => {
-        if stream_index == source.get_first_stream_index() {
-          count += 1;
-
-          if let Some(duration) = total_duration {
-            let progress = std::cmp::min((count / duration * 100) as u8, 100);
-            if progress > previous_progress {
-              publish_job_progression(channel.clone(), job.job_id, progress)?;
-              previous_progress = progress;
+  let mut process_frames = || -> Result<()> {
+    loop {
+      context.check_cancelled()?;
+
+      let decode_start = Instant::now();
+      match source.next_frame()? {
+        DecodeResult::Frame {
+          stream_index,
+          frame,
+        } => {
+          context
+            .get_job_metrics()
+            .record_decode_latency(decode_start.elapsed());
+
+          if stream_index == source.get_first_stream_index() {
+            count += 1;
+            context.get_job_metrics().record_frame();
+
+            if let Some(duration) = total_duration {
+              let progress = std::cmp::min((count / duration * 100) as u8, 100);
+              if progress > previous_progress {
+                publish_job_progression(channel.clone(), job.job_id, progress)?;
+                previous_progress = progress;
+              }
+            }
+          }
+
+          trace!(target: &job_result.get_str_job_id(), "Process frame {}", count);
+          deliver_frame(
+            &message_event,
+            &mut output,
+            &job_result,
+            stream_index,
+            frame,
+            &context,
+          )?;
+        }
+        DecodeResult::WaitMore => {}
+        DecodeResult::Nothing => {}
+        DecodeResult::EndOfStream => {
+          if flush_mode == FlushMode::Deliver {
+            for (stream_index, frame) in source.flush()? {
+              trace!(target: &job_result.get_str_job_id(), "Process flushed frame of stream {}", stream_index);
+              deliver_frame(
+                &message_event,
+                &mut output,
+                &job_result,
+                stream_index,
+                frame,
+                &context,
+              )?;
             }
           }
+          return Ok(());
         }
+      }
+    }
+  };
 
-        trace!(target: &job_result.get_str_job_id(), "Process frame {}", count);
-        let result =
-          message_event
-            .borrow_mut()
-            .process_frame(job_result.clone(), stream_index, frame)?;
+  let result = process_frames();
 
-        output.push(result);
-      }
-      DecodeResult::WaitMore => {}
-      DecodeResult::Nothing => {}
-      DecodeResult::EndOfStream => {
-        message_event.borrow_mut().ending_process()?;
-
-        output.complete()?;
-        let job_result = job_result.with_status(JobStatus::Completed);
-        return Ok(job_result);
-      }
+  // called once after the last delivered frame, even when the processing failed
+  let ending_result = write_message_event(&message_event).ending_process();
+  if let Err(error) = result {
+    if let Err(ending_error) = ending_result {
+      error!(target: &str_job_id, "Ending process failed: {:?}", ending_error);
     }
+    return Err(error);
   }
+  ending_result?;
+
+  output.complete()?;
+  let mut job_result = job_result.with_status(JobStatus::Completed);
+
+  if let Some(report) = source.get_audio_continuity_report() {
+    for summary in &report.summaries {
+      info!(target: &str_job_id, "Audio continuity: {:?}", summary);
+    }
+    job_result = job_result
+      .with_json("audio_continuity", &report)
+      .map_err(RuntimeError)?;
+  }
+  Ok(job_result)
+}
+
+fn deliver_frame<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
+  message_event: &Arc<RwLock<ME>>,
+  output: &mut output::Output,
+  job_result: &JobResult,
+  stream_index: usize,
+  frame: ProcessFrame,
+  context: &JobContext,
+) -> Result<()> {
+  let result = write_message_event(message_event).process_frame(
+    job_result.clone(),
+    stream_index,
+    frame,
+    context.clone(),
+  )?;
+  context.heartbeat();
+
+  output.push(result);
+  Ok(())
 }
@@ -1,8 +1,10 @@
+use crate::job::update_gauge;
 use crate::message::media::srt::SrtStream;
 use crate::{MessageError, ProcessResult, Result};
 use bytes::Bytes;
 use std::{
   sync::{
+    atomic::AtomicUsize,
     mpsc::{channel, Sender},
     Arc, Mutex,
   },
@@ -14,6 +16,8 @@ pub struct Output {
   url: String,
   thread: Option<JoinHandle<()>>,
   sender: Arc<Mutex<Sender<ProcessResult>>>,
+  /// Results pushed and not yet handled by the output thread
+  backlog: Arc<AtomicUsize>,
 }
 
 impl Output {
@@ -24,6 +28,8 @@ impl Output {
 
     let results = Arc::new(Mutex::new(vec![]));
     let cloned_results = results.clone();
+    let backlog = Arc::new(AtomicUsize::new(0));
+    let cloned_backlog = backlog.clone();
 
     let thread = Some(std::thread::spawn(move || {
       let mut srt_stream = if SrtStream::is_srt_stream(&output) {
@@ -33,6 +39,7 @@ impl Output {
       };
 
       while let Ok(message) = receiver.recv() {
+        update_gauge(&cloned_backlog, -1);
         match message {
           ProcessResult {
             end_of_process: true,
@@ -95,13 +102,19 @@ impl Output {
       url,
       thread,
       sender,
+      backlog,
     })
   }
 
   pub fn push(&mut self, content: ProcessResult) {
+    update_gauge(&self.backlog, 1);
     self.sender.lock().unwrap().send(content).unwrap();
   }
 
+  pub fn get_backlog(&self) -> Arc<AtomicUsize> {
+    self.backlog.clone()
+  }
+
   pub fn get_sender(&self) -> Arc<Mutex<Sender<ProcessResult>>> {
     self.sender.clone()
   }
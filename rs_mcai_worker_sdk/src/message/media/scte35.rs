@@ -0,0 +1,500 @@
+//! SCTE-35 splice information section parsing
+//!
+//! Sections are delivered as `ProcessFrame::TypedData` payloads by the
+//! [`Scte35Codec`](struct.Scte35Codec.html), registered by default for the `AV_CODEC_ID_SCTE_35` codec.
+
+use crate::message::media::data_codec::DataCodec;
+use std::any::Any;
+
+const SPLICE_INFO_TABLE_ID: u8 = 0xFC;
+const SEGMENTATION_DESCRIPTOR_TAG: u8 = 0x02;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpliceInfoSection {
+  pub protocol_version: u8,
+  pub encrypted_packet: bool,
+  pub pts_adjustment: u64,
+  pub tier: u16,
+  pub splice_command: SpliceCommand,
+  pub descriptors: Vec<SpliceDescriptor>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum SpliceCommand {
+  SpliceNull,
+  SpliceInsert(SpliceInsert),
+  TimeSignal(SpliceTime),
+  BandwidthReservation,
+  Other { command_type: u8, data: Vec<u8> },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpliceInsert {
+  pub splice_event_id: u32,
+  pub splice_event_cancel_indicator: bool,
+  pub out_of_network_indicator: bool,
+  pub splice_immediate: bool,
+  pub splice_time: Option<SpliceTime>,
+  pub components: Vec<SpliceComponent>,
+  pub break_duration: Option<BreakDuration>,
+  pub unique_program_id: u16,
+  pub avail_num: u8,
+  pub avails_expected: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpliceTime {
+  /// PTS in 90 kHz ticks, `None` when the time is not specified
+  pub pts_time: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpliceComponent {
+  pub component_tag: u8,
+  pub splice_time: Option<SpliceTime>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BreakDuration {
+  pub auto_return: bool,
+  /// Duration in 90 kHz ticks
+  pub duration: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum SpliceDescriptor {
+  Segmentation(SegmentationDescriptor),
+  Other {
+    tag: u8,
+    identifier: u32,
+    data: Vec<u8>,
+  },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SegmentationDescriptor {
+  pub identifier: u32,
+  pub segmentation_event_id: u32,
+  pub segmentation_event_cancel_indicator: bool,
+  pub delivery_not_restricted: bool,
+  /// Duration in 90 kHz ticks
+  pub segmentation_duration: Option<u64>,
+  pub segmentation_upid_type: u8,
+  pub segmentation_upid: Vec<u8>,
+  pub segmentation_type_id: u8,
+  pub segment_num: u8,
+  pub segments_expected: u8,
+  pub sub_segment_num: Option<u8>,
+  pub sub_segments_expected: Option<u8>,
+}
+
+impl SpliceInfoSection {
+  pub fn parse(data: &[u8]) -> Result<Self, String> {
+    let mut reader = BitReader::new(data);
+
+    let table_id = reader.read(8)? as u8;
+    if table_id != SPLICE_INFO_TABLE_ID {
+      return Err(format!("Invalid SCTE-35 table identifier: {:#x}", table_id));
+    }
+
+    reader.skip(4)?;
+    let section_length = reader.read(12)? as usize;
+    if section_length < 4 {
+      return Err(format!(
+        "Invalid SCTE-35 section length: {} bytes, shorter than its CRC",
+        section_length
+      ));
+    }
+    if data.len() < section_length + 3 {
+      return Err(format!(
+        "Truncated SCTE-35 section: {} bytes expected, got {}",
+        section_length + 3,
+        data.len()
+      ));
+    }
+
+    let section = &data[..section_length + 3];
+    let (payload, crc) = section.split_at(section.len() - 4);
+    let expected_crc = u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]);
+    if crc32_mpeg2(payload) != expected_crc {
+      return Err("Invalid SCTE-35 section CRC".to_string());
+    }
+
+    let protocol_version = reader.read(8)? as u8;
+    let encrypted_packet = reader.read_flag()?;
+    reader.skip(6)?;
+    let pts_adjustment = reader.read(33)?;
+    reader.skip(8)?;
+    let tier = reader.read(12)? as u16;
+
+    if encrypted_packet {
+      return Ok(SpliceInfoSection {
+        protocol_version,
+        encrypted_packet,
+        pts_adjustment,
+        tier,
+        splice_command: SpliceCommand::Other {
+          command_type: 0,
+          data: vec![],
+        },
+        descriptors: vec![],
+      });
+    }
+
+    let splice_command_length = reader.read(12)? as usize;
+    let splice_command_type = reader.read(8)? as u8;
+
+    let splice_command = match splice_command_type {
+      0x00 => SpliceCommand::SpliceNull,
+      0x05 => SpliceCommand::SpliceInsert(SpliceInsert::parse(&mut reader)?),
+      0x06 => SpliceCommand::TimeSignal(SpliceTime::parse(&mut reader)?),
+      0x07 => SpliceCommand::BandwidthReservation,
+      command_type => {
+        if splice_command_length == 0xFFF {
+          return Err(format!(
+            "Unable to skip SCTE-35 command {:#x} of unknown length",
+            command_type
+          ));
+        }
+        SpliceCommand::Other {
+          command_type,
+          data: reader.read_bytes(splice_command_length)?,
+        }
+      }
+    };
+
+    let descriptor_loop_length = reader.read(16)? as usize;
+    let descriptors_end = reader.position() + descriptor_loop_length;
+    let mut descriptors = vec![];
+    while reader.position() < descriptors_end {
+      descriptors.push(SpliceDescriptor::parse(&mut reader)?);
+    }
+
+    Ok(SpliceInfoSection {
+      protocol_version,
+      encrypted_packet,
+      pts_adjustment,
+      tier,
+      splice_command,
+      descriptors,
+    })
+  }
+}
+
+impl SpliceInsert {
+  fn parse(reader: &mut BitReader) -> Result<Self, String> {
+    let splice_event_id = reader.read(32)? as u32;
+    let splice_event_cancel_indicator = reader.read_flag()?;
+    reader.skip(7)?;
+
+    let mut splice_insert = SpliceInsert {
+      splice_event_id,
+      splice_event_cancel_indicator,
+      out_of_network_indicator: false,
+      splice_immediate: false,
+      splice_time: None,
+      components: vec![],
+      break_duration: None,
+      unique_program_id: 0,
+      avail_num: 0,
+      avails_expected: 0,
+    };
+
+    if splice_event_cancel_indicator {
+      return Ok(splice_insert);
+    }
+
+    splice_insert.out_of_network_indicator = reader.read_flag()?;
+    let program_splice_flag = reader.read_flag()?;
+    let duration_flag = reader.read_flag()?;
+    splice_insert.splice_immediate = reader.read_flag()?;
+    reader.skip(4)?;
+
+    if program_splice_flag && !splice_insert.splice_immediate {
+      splice_insert.splice_time = Some(SpliceTime::parse(reader)?);
+    }
+
+    if !program_splice_flag {
+      let component_count = reader.read(8)?;
+      for _ in 0..component_count {
+        let component_tag = reader.read(8)? as u8;
+        let splice_time = if splice_insert.splice_immediate {
+          None
+        } else {
+          Some(SpliceTime::parse(reader)?)
+        };
+        splice_insert.components.push(SpliceComponent {
+          component_tag,
+          splice_time,
+        });
+      }
+    }
+
+    if duration_flag {
+      let auto_return = reader.read_flag()?;
+      reader.skip(6)?;
+      let duration = reader.read(33)?;
+      splice_insert.break_duration = Some(BreakDuration {
+        auto_return,
+        duration,
+      });
+    }
+
+    splice_insert.unique_program_id = reader.read(16)? as u16;
+    splice_insert.avail_num = reader.read(8)? as u8;
+    splice_insert.avails_expected = reader.read(8)? as u8;
+
+    Ok(splice_insert)
+  }
+}
+
+impl SpliceTime {
+  fn parse(reader: &mut BitReader) -> Result<Self, String> {
+    let pts_time = if reader.read_flag()? {
+      reader.skip(6)?;
+      Some(reader.read(33)?)
+    } else {
+      reader.skip(7)?;
+      None
+    };
+    Ok(SpliceTime { pts_time })
+  }
+}
+
+impl SpliceDescriptor {
+  fn parse(reader: &mut BitReader) -> Result<Self, String> {
+    let tag = reader.read(8)? as u8;
+    let length = reader.read(8)? as usize;
+    let data = reader.read_bytes(length)?;
+
+    let mut descriptor_reader = BitReader::new(&data);
+    let identifier = descriptor_reader.read(32)? as u32;
+
+    if tag == SEGMENTATION_DESCRIPTOR_TAG {
+      let descriptor = SegmentationDescriptor::parse(identifier, &mut descriptor_reader)?;
+      Ok(SpliceDescriptor::Segmentation(descriptor))
+    } else {
+      Ok(SpliceDescriptor::Other {
+        tag,
+        identifier,
+        data: data[4..].to_vec(),
+      })
+    }
+  }
+}
+
+impl SegmentationDescriptor {
+  fn parse(identifier: u32, reader: &mut BitReader) -> Result<Self, String> {
+    let segmentation_event_id = reader.read(32)? as u32;
+    let segmentation_event_cancel_indicator = reader.read_flag()?;
+    reader.skip(7)?;
+
+    let mut descriptor = SegmentationDescriptor {
+      identifier,
+      segmentation_event_id,
+      segmentation_event_cancel_indicator,
+      delivery_not_restricted: true,
+      segmentation_duration: None,
+      segmentation_upid_type: 0,
+      segmentation_upid: vec![],
+      segmentation_type_id: 0,
+      segment_num: 0,
+      segments_expected: 0,
+      sub_segment_num: None,
+      sub_segments_expected: None,
+    };
+
+    if segmentation_event_cancel_indicator {
+      return Ok(descriptor);
+    }
+
+    let program_segmentation_flag = reader.read_flag()?;
+    let segmentation_duration_flag = reader.read_flag()?;
+    descriptor.delivery_not_restricted = reader.read_flag()?;
+    reader.skip(5)?;
+
+    if !program_segmentation_flag {
+      let component_count = reader.read(8)?;
+      // component_tag (8), reserved (7), pts_offset (33)
+      reader.skip(48 * component_count as usize)?;
+    }
+
+    if segmentation_duration_flag {
+      descriptor.segmentation_duration = Some(reader.read(40)?);
+    }
+
+    descriptor.segmentation_upid_type = reader.read(8)? as u8;
+    let upid_length = reader.read(8)? as usize;
+    descriptor.segmentation_upid = reader.read_bytes(upid_length)?;
+    descriptor.segmentation_type_id = reader.read(8)? as u8;
+    descriptor.segment_num = reader.read(8)? as u8;
+    descriptor.segments_expected = reader.read(8)? as u8;
+
+    if reader.remaining() >= 16 {
+      descriptor.sub_segment_num = Some(reader.read(8)? as u8);
+      descriptor.sub_segments_expected = Some(reader.read(8)? as u8);
+    }
+
+    Ok(descriptor)
+  }
+}
+
+/// Data codec producing [`SpliceInfoSection`](struct.SpliceInfoSection.html) payloads
+#[derive(Debug, Default)]
+pub struct Scte35Codec {}
+
+impl DataCodec for Scte35Codec {
+  fn decode(&self, data: &[u8]) -> Result<Box<dyn Any + Send>, String> {
+    Ok(Box::new(SpliceInfoSection::parse(data)?))
+  }
+}
+
+struct BitReader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+
+impl<'a> BitReader<'a> {
+  fn new(data: &'a [u8]) -> Self {
+    BitReader { data, position: 0 }
+  }
+
+  /// Position in bytes
+  fn position(&self) -> usize {
+    self.position / 8
+  }
+
+  /// Remaining bits
+  fn remaining(&self) -> usize {
+    self.data.len() * 8 - self.position
+  }
+
+  fn read(&mut self, bits: usize) -> Result<u64, String> {
+    if bits > self.remaining() {
+      return Err("Unexpected end of SCTE-35 section".to_string());
+    }
+
+    let mut value = 0;
+    for _ in 0..bits {
+      let byte = self.data[self.position / 8];
+      let bit = (byte >> (7 - self.position % 8)) & 1;
+      value = (value << 1) | bit as u64;
+      self.position += 1;
+    }
+    Ok(value)
+  }
+
+  fn read_flag(&mut self) -> Result<bool, String> {
+    Ok(self.read(1)? == 1)
+  }
+
+  fn skip(&mut self, bits: usize) -> Result<(), String> {
+    if bits > self.remaining() {
+      return Err("Unexpected end of SCTE-35 section".to_string());
+    }
+    self.position += bits;
+    Ok(())
+  }
+
+  fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, String> {
+    (0..length).map(|_| self.read(8).map(|v| v as u8)).collect()
+  }
+}
+
+fn crc32_mpeg2(data: &[u8]) -> u32 {
+  let mut crc = 0xFFFF_FFFFu32;
+  for byte in data {
+    crc ^= (*byte as u32) << 24;
+    for _ in 0..8 {
+      crc = if crc & 0x8000_0000 != 0 {
+        (crc << 1) ^ 0x04C1_1DB7
+      } else {
+        crc << 1
+      };
+    }
+  }
+  crc
+}
+
+#[test]
+pub fn test_parse_splice_insert() {
+  let data =
+    base64::decode("/DAvAAAAAAAA///wFAVIAACPf+/+c2nALv4AUsz1AAAAAAAKAAhDVUVJAAABNWLbowo=").unwrap();
+
+  let section = SpliceInfoSection::parse(&data).unwrap();
+  assert_eq!(0, section.pts_adjustment);
+  assert_eq!(0xFFF, section.tier);
+
+  let expected = SpliceCommand::SpliceInsert(SpliceInsert {
+    splice_event_id: 0x4800_008F,
+    splice_event_cancel_indicator: false,
+    out_of_network_indicator: true,
+    splice_immediate: false,
+    splice_time: Some(SpliceTime {
+      pts_time: Some(0x0_7369_C02E),
+    }),
+    components: vec![],
+    break_duration: Some(BreakDuration {
+      auto_return: true,
+      duration: 0x0_0052_CCF5,
+    }),
+    unique_program_id: 0,
+    avail_num: 0,
+    avails_expected: 0,
+  });
+  assert_eq!(expected, section.splice_command);
+
+  assert_eq!(
+    vec![SpliceDescriptor::Other {
+      tag: 0,
+      identifier: 0x4355_4549,
+      data: vec![0x00, 0x00, 0x01, 0x35],
+    }],
+    section.descriptors
+  );
+}
+
+#[test]
+pub fn test_parse_time_signal() {
+  let data =
+    base64::decode("/DA0AAAAAAAA///wBQb+cr0AUAAeAhxDVUVJSAAAjn/PAAGlmbAICAAAAAAsoKGKNAIAmsnRfg==")
+      .unwrap();
+
+  let section = SpliceInfoSection::parse(&data).unwrap();
+  assert_eq!(
+    SpliceCommand::TimeSignal(SpliceTime {
+      pts_time: Some(0x0_72BD_0050)
+    }),
+    section.splice_command
+  );
+
+  let expected = SpliceDescriptor::Segmentation(SegmentationDescriptor {
+    identifier: 0x4355_4549,
+    segmentation_event_id: 0x4800_008E,
+    segmentation_event_cancel_indicator: false,
+    delivery_not_restricted: false,
+    segmentation_duration: Some(0x00_01A5_99B0),
+    segmentation_upid_type: 0x08,
+    segmentation_upid: vec![0x00, 0x00, 0x00, 0x00, 0x2C, 0xA0, 0xA1, 0x8A],
+    segmentation_type_id: 0x34,
+    segment_num: 2,
+    segments_expected: 0,
+    sub_segment_num: None,
+    sub_segments_expected: None,
+  });
+  assert_eq!(vec![expected], section.descriptors);
+}
+
+#[test]
+pub fn test_parse_invalid_section() {
+  let data =
+    base64::decode("/DAvAAAAAAAA///wFAVIAACPf+/+c2nALv4AUsz1AAAAAAAKAAhDVUVJAAABNWLbowo=").unwrap();
+
+  let mut corrupted = data.clone();
+  corrupted[10] = 0;
+  assert!(SpliceInfoSection::parse(&corrupted).is_err());
+  assert!(SpliceInfoSection::parse(&data[..10]).is_err());
+  assert!(SpliceInfoSection::parse(&[0x00]).is_err());
+  assert!(SpliceInfoSection::parse(&[0xFC, 0x30, 0x00]).is_err());
+  assert!(SpliceInfoSection::parse(&[0xFC, 0x30, 0x02, 0x00, 0x00]).is_err());
+}
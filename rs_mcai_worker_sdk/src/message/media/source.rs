@@ -1,9 +1,9 @@
 use std::sync::{
   mpsc,
   mpsc::{Receiver, Sender},
-  Arc, Mutex,
+  Arc, Mutex, RwLock,
 };
-use std::{cell::RefCell, collections::HashMap, io::Cursor, rc::Rc, thread};
+use std::{collections::HashMap, ffi::CString, io::Cursor, thread};
 
 use ringbuf::RingBuffer;
 use schemars::JsonSchema;
@@ -11,18 +11,34 @@ use serde::de::DeserializeOwned;
 use stainless_ffmpeg::tools::rational::Rational;
 use stainless_ffmpeg::{
   audio_decoder::AudioDecoder, check_result, filter_graph::FilterGraph,
-  format_context::FormatContext, frame::Frame, packet::Packet, tools, video_decoder::VideoDecoder,
+  format_context::FormatContext, frame::Frame, packet::Packet, subtitle_decoder::SubtitleDecoder,
+  tools, video_decoder::VideoDecoder,
 };
 use stainless_ffmpeg_sys::{
-  av_frame_alloc, av_frame_clone, av_seek_frame, av_strerror, avcodec_receive_frame,
-  avcodec_send_packet, AVSEEK_FLAG_BACKWARD, AV_ERROR_MAX_STRING_SIZE,
+  av_dict_set, av_frame_alloc, av_frame_clone, av_frame_free, av_seek_frame, av_strerror,
+  avcodec_decode_subtitle2, avcodec_receive_frame, avcodec_send_packet, avsubtitle_free,
+  AVCodecContext, AVCodecID, AVFrame, AVSubtitle, AVSEEK_FLAG_BACKWARD, AV_ERROR_MAX_STRING_SIZE,
 };
 
 use crate::{
+  config::{get_max_source_resolution, get_max_video_resolution},
   error::MessageError::RuntimeError,
   job::JobResult,
-  message::media::{ebu_ttml_live::EbuTtmlLiveDecoder, media_stream::MediaStream, srt::SrtStream},
-  AudioFilter, MessageError, MessageEvent, ProcessFrame, ProcessResult, Result, VideoFilter,
+  message::media::{
+    audio::continuity::{AudioContinuityChecker, AudioContinuityReport, AudioDiscontinuity},
+    bandwidth::SourceThrottle,
+    bitmap_subtitle::{BitmapSubtitle, BITMAP_SUBTITLE_CODECS},
+    ebu_ttml_live::EbuTtmlLiveDecoder,
+    media_stream::MediaStream,
+    srt::SrtStream,
+    video::{
+      aspect_ratio::{format_ratio, CONTAINER_SAMPLE_ASPECT_RATIO_KEY},
+      check_source_resolution,
+    },
+  },
+  message::{read_message_event, write_message_event},
+  AudioFilter, DataCodec, DataCodecRegistry, MessageError, MessageEvent, ProcessFrame,
+  ProcessResult, Result, Scaling, VideoFilter,
 };
 use bytes::Buf;
 
@@ -53,11 +69,12 @@ pub struct Source {
   start_offset: u64,
   /// Time offset into the program
   position: u64,
+  throttle: Option<SourceThrottle>,
 }
 
 impl Source {
   pub fn new<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
-    message_event: Rc<RefCell<ME>>,
+    message_event: Arc<RwLock<ME>>,
     job_result: &JobResult,
     parameters: P,
     source_url: &str,
@@ -147,6 +164,7 @@ impl Source {
         segment_duration: None,
         start_offset: 0,
         position: 0,
+        throttle: None,
       })
     } else {
       let mut format_context = FormatContext::new(source_url).map_err(RuntimeError)?;
@@ -179,6 +197,7 @@ impl Source {
         segment_duration,
         start_offset: start_offset as u64,
         position: 0,
+        throttle: None,
       })
     }
   }
@@ -241,6 +260,80 @@ impl Source {
     self.segment_duration
   }
 
+  /// Drain the frames buffered in the decoders at the end of the stream, within the segment
+  pub fn flush(&mut self) -> Result<Vec<(usize, ProcessFrame)>> {
+    let format_context = self.format_context.lock().unwrap();
+    let segment_end = self
+      .segment_duration
+      .map(|segment_duration| self.start_offset + segment_duration);
+
+    let mut stream_indexes: Vec<usize> = self.decoders.keys().cloned().collect();
+    stream_indexes.sort_unstable();
+
+    let mut frames = vec![];
+    for stream_index in stream_indexes {
+      let time_base = Self::get_stream_time_base(stream_index as isize, &format_context);
+      let decoder = self.decoders.get_mut(&stream_index).unwrap();
+
+      for frame in decoder.flush().map_err(RuntimeError)? {
+        let position = Self::get_milliseconds_from_pts(frame.get_pts(), &time_base);
+        let after_end = segment_end.map_or(false, |segment_end| position >= segment_end);
+        if position >= self.start_offset && !after_end {
+          frames.push((stream_index, frame));
+        }
+      }
+    }
+    Ok(frames)
+  }
+
+  /// Check the timestamp continuity of the decoded audio streams
+  /// Limit the read bandwidth in bytes per second, the SRT streams are not throttled
+  pub fn limit_bandwidth(&mut self, bandwidth: Option<u64>) {
+    if self.thread.is_none() {
+      self.throttle = SourceThrottle::new(bandwidth);
+    }
+  }
+
+  pub fn enable_audio_continuity_check(&mut self) {
+    let format_context = self.format_context.lock().unwrap();
+    for (stream_index, decoder) in self.decoders.iter_mut() {
+      if decoder.audio_decoder.is_some() {
+        let time_base = Self::get_stream_time_base(*stream_index as isize, &format_context);
+        decoder.continuity_checker = Some(AudioContinuityChecker::new(
+          *stream_index,
+          (time_base.num, time_base.den),
+        ));
+      }
+    }
+  }
+
+  /// Continuity report of the checked audio streams, if enabled
+  pub fn get_audio_continuity_report(&self) -> Option<AudioContinuityReport> {
+    let mut checkers: Vec<&AudioContinuityChecker> = self
+      .decoders
+      .values()
+      .filter_map(|decoder| decoder.continuity_checker.as_ref())
+      .collect();
+    if checkers.is_empty() {
+      return None;
+    }
+    checkers.sort_by_key(|checker| checker.get_summary().stream_index);
+
+    let mut discontinuities: Vec<AudioDiscontinuity> = checkers
+      .iter()
+      .flat_map(|checker| checker.get_discontinuities().clone())
+      .collect();
+    discontinuities.sort_by_key(|discontinuity| discontinuity.stream_index);
+
+    Some(AudioContinuityReport {
+      summaries: checkers
+        .iter()
+        .map(|checker| checker.get_summary().clone())
+        .collect(),
+      discontinuities,
+    })
+  }
+
   pub fn get_first_stream_index(&self) -> usize {
     self.decoders.keys().cloned().min().unwrap_or(0)
   }
@@ -265,6 +358,10 @@ impl Source {
         }
       }
       Ok(packet) => {
+        if let Some(throttle) = &mut self.throttle {
+          throttle.consume(unsafe { (*packet.packet).size } as usize);
+        }
+
         let stream_index = packet.get_stream_index() as usize;
 
         if let Some(decoder) = self.decoders.get_mut(&stream_index) {
@@ -315,23 +412,27 @@ impl Source {
   }
 
   fn get_decoders<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
-    message_event: Rc<RefCell<ME>>,
+    message_event: Arc<RwLock<ME>>,
     job_id: &str,
     parameters: P,
     format_context: Arc<Mutex<FormatContext>>,
     sender: Arc<Mutex<Sender<ProcessResult>>>,
     start_index_ms: Option<i64>,
   ) -> Result<HashMap<usize, Decoder>> {
-    let selected_streams =
-      message_event
-        .borrow_mut()
-        .init_process(parameters, format_context.clone(), sender)?;
+    let selected_streams = write_message_event(&message_event).init_process(
+      parameters,
+      format_context.clone(),
+      sender,
+    )?;
 
     info!(
       target: job_id,
       "Selected stream IDs: {:?}", selected_streams
     );
 
+    let mut data_codecs = DataCodecRegistry::with_default_codecs();
+    read_message_event(&message_event).register_data_codecs(&mut data_codecs);
+
     let mut decoders = HashMap::<usize, Decoder>::new();
     for selected_stream in &selected_streams {
       if let Some(audio_configuration) = &selected_stream.audio_configuration {
@@ -358,7 +459,11 @@ impl Source {
         let decoder = Decoder {
           audio_decoder: Some(audio_decoder),
           video_decoder: None,
+          subtitle_decoder: None,
           ebu_ttml_live_decoder: None,
+          data_codec: None,
+          container_sample_aspect_ratio: None,
+          continuity_checker: None,
           graph: audio_graph,
         };
 
@@ -371,13 +476,38 @@ impl Source {
         )
         .map_err(RuntimeError)?;
 
-        let video_graph =
-          Source::get_video_filter_graph(&image_configuration.filters, &video_decoder)?;
+        check_source_resolution(
+          video_decoder.get_width(),
+          video_decoder.get_height(),
+          get_max_source_resolution(),
+        )
+        .map_err(RuntimeError)?;
+
+        let mut video_filters = image_configuration.filters.clone();
+        if let Some((max_width, max_height)) = get_max_video_resolution() {
+          video_filters.push(VideoFilter::Generic(Scaling::get_max_resolution_filter(
+            max_width, max_height,
+          )));
+        }
+
+        let video_graph = Source::get_video_filter_graph(&video_filters, &video_decoder)?;
+
+        let container_sample_aspect_ratio = unsafe {
+          let stream = format_context
+            .lock()
+            .unwrap()
+            .get_stream(selected_stream.index as isize);
+          format_ratio((*stream).sample_aspect_ratio)
+        };
 
         let decoder = Decoder {
           audio_decoder: None,
           video_decoder: Some(video_decoder),
+          subtitle_decoder: None,
           ebu_ttml_live_decoder: None,
+          data_codec: None,
+          container_sample_aspect_ratio,
+          continuity_checker: None,
           graph: video_graph,
         };
 
@@ -392,12 +522,55 @@ impl Source {
 
         decoders.insert(selected_stream.index, decoder);
       } else {
-        let ebu_ttml_live_decoder = EbuTtmlLiveDecoder::new();
-        let decoder = Decoder {
-          audio_decoder: None,
-          video_decoder: None,
-          ebu_ttml_live_decoder: Some(ebu_ttml_live_decoder),
-          graph: None,
+        let codec_id = format_context
+          .lock()
+          .unwrap()
+          .get_codec_id(selected_stream.index as isize);
+
+        let decoder = if let Some(data_codec) = data_codecs.get(codec_id) {
+          debug!(
+            target: job_id,
+            "Use registered data codec for stream {} ({:?})", selected_stream.index, codec_id
+          );
+          Decoder {
+            audio_decoder: None,
+            video_decoder: None,
+            subtitle_decoder: None,
+            ebu_ttml_live_decoder: None,
+            data_codec: Some((codec_id, data_codec)),
+            container_sample_aspect_ratio: None,
+            continuity_checker: None,
+            graph: None,
+          }
+        } else if BITMAP_SUBTITLE_CODECS.contains(&codec_id) {
+          let subtitle_decoder = SubtitleDecoder::new(
+            format!("decoder_{}", selected_stream.index),
+            &format_context.clone().lock().unwrap(),
+            selected_stream.index as isize,
+          )
+          .map_err(RuntimeError)?;
+
+          Decoder {
+            audio_decoder: None,
+            video_decoder: None,
+            subtitle_decoder: Some(subtitle_decoder),
+            ebu_ttml_live_decoder: None,
+            data_codec: None,
+            container_sample_aspect_ratio: None,
+            continuity_checker: None,
+            graph: None,
+          }
+        } else {
+          Decoder {
+            audio_decoder: None,
+            video_decoder: None,
+            subtitle_decoder: None,
+            ebu_ttml_live_decoder: Some(EbuTtmlLiveDecoder::new()),
+            data_codec: None,
+            container_sample_aspect_ratio: None,
+            continuity_checker: None,
+            graph: None,
+          }
         };
 
         decoders.insert(selected_stream.index, decoder);
@@ -557,97 +730,219 @@ impl Source {
   }
 }
 
+/// Presentation timestamp of the packet, or its decoding timestamp when unset
+/// Payload of the packet, empty without data
+unsafe fn get_packet_data(packet: &Packet) -> &[u8] {
+  let data = (*packet.packet).data;
+  let size = (*packet.packet).size;
+  if data.is_null() || size <= 0 {
+    return &[];
+  }
+  std::slice::from_raw_parts(data, size as usize)
+}
+
+unsafe fn get_packet_pts(packet: &Packet) -> i64 {
+  // AV_NOPTS_VALUE
+  if (*packet.packet).pts == i64::MIN {
+    (*packet.packet).dts
+  } else {
+    (*packet.packet).pts
+  }
+}
+
 struct Decoder {
   audio_decoder: Option<AudioDecoder>,
   video_decoder: Option<VideoDecoder>,
+  subtitle_decoder: Option<SubtitleDecoder>,
   ebu_ttml_live_decoder: Option<EbuTtmlLiveDecoder>,
+  data_codec: Option<(AVCodecID, Arc<dyn DataCodec>)>,
+  container_sample_aspect_ratio: Option<String>,
+  continuity_checker: Option<AudioContinuityChecker>,
   graph: Option<FilterGraph>,
 }
 
 impl Decoder {
   fn decode(&mut self, packet: &Packet) -> std::result::Result<Option<ProcessFrame>, String> {
-    if let Some(audio_decoder) = &self.audio_decoder {
-      trace!("[FFmpeg] Send packet to audio decoder");
-
-      let av_frame = unsafe {
-        let ret_code = avcodec_send_packet(audio_decoder.codec_context, packet.packet);
-        check_result!(ret_code);
+    if let Some(codec_context) = self.get_codec_context() {
+      trace!("[FFmpeg] Send packet to decoder");
+      unsafe {
+        check_result!(avcodec_send_packet(codec_context, packet.packet));
+      }
 
-        let av_frame = av_frame_alloc();
-        let ret_code = avcodec_receive_frame(audio_decoder.codec_context, av_frame);
+      self.receive_frame(codec_context).map(Some)
+    } else if let Some((codec_id, data_codec)) = &self.data_codec {
+      let data = unsafe { get_packet_data(packet) };
+
+      let frame = match data_codec.decode(data) {
+        Ok(payload) => ProcessFrame::TypedData {
+          codec_id: *codec_id,
+          pts: unsafe { get_packet_pts(packet) },
+          payload,
+        },
+        Err(error) => {
+          warn!(
+            "Unable to decode data packet with {:?} codec, forward raw bytes: {}",
+            codec_id, error
+          );
+          ProcessFrame::Data(data.to_vec())
+        }
+      };
+      Ok(Some(frame))
+    } else if let Some(subtitle_decoder) = &self.subtitle_decoder {
+      trace!("[FFmpeg] Send packet to subtitle decoder");
+
+      let subtitle = unsafe {
+        let mut av_subtitle: AVSubtitle = std::mem::zeroed();
+        let mut got_subtitle = 0;
+        let ret_code = avcodec_decode_subtitle2(
+          subtitle_decoder.codec_context,
+          &mut av_subtitle,
+          &mut got_subtitle,
+          packet.packet,
+        );
         check_result!(ret_code);
 
-        let frame = Frame {
-          frame: av_frame,
-          name: Some("audio_source_1".to_string()),
-          index: 1,
-        };
-
-        if let Some(graph) = &self.graph {
-          if let Ok((audio_frames, _video_frames)) = graph.process(&[frame], &[]) {
-            trace!("[FFmpeg] Output graph count {} frames", audio_frames.len());
-            let frame = audio_frames.first().unwrap();
-            av_frame_clone((*frame).frame)
-          } else {
-            av_frame
-          }
-        } else {
-          av_frame
+        if got_subtitle == 0 {
+          return Ok(None);
         }
+
+        let subtitle = BitmapSubtitle::from_av_subtitle(&av_subtitle, (*packet.packet).pts);
+        avsubtitle_free(&mut av_subtitle);
+        subtitle
       };
 
-      let frame = Frame {
-        frame: av_frame,
-        name: Some("audio".to_string()),
-        index: 1,
+      Ok(Some(ProcessFrame::BitmapSubtitle(Box::new(subtitle))))
+    } else if let Some(ebu_ttml_live_decoder) = &mut self.ebu_ttml_live_decoder {
+      let result = match ebu_ttml_live_decoder.decode(packet)? {
+        Some(ttml_content) => Some(ProcessFrame::EbuTtmlLive(Box::new(ttml_content))),
+        None => None,
       };
+      Ok(result)
+    } else {
+      Err("No audio/video decoder found".to_string())
+    }
+  }
 
-      Ok(Some(ProcessFrame::AudioVideo(frame)))
-    } else if let Some(video_decoder) = &self.video_decoder {
-      trace!("[FFmpeg] Send packet to video decoder");
+  /// Drain the frames buffered in the audio or video decoder at the end of the stream
+  fn flush(&mut self) -> std::result::Result<Vec<ProcessFrame>, String> {
+    let codec_context = match self.get_codec_context() {
+      Some(codec_context) => codec_context,
+      None => return Ok(vec![]),
+    };
 
-      let av_frame = unsafe {
-        let ret_code = avcodec_send_packet(video_decoder.codec_context, packet.packet);
-        check_result!(ret_code);
+    trace!("[FFmpeg] Flush decoder");
+    unsafe {
+      check_result!(avcodec_send_packet(codec_context, std::ptr::null()));
+    }
 
-        let av_frame = av_frame_alloc();
-        let ret_code = avcodec_receive_frame(video_decoder.codec_context, av_frame);
-        check_result!(ret_code);
+    let mut frames = vec![];
+    // the decoder returns an end of file error once drained
+    while let Ok(frame) = self.receive_frame(codec_context) {
+      frames.push(frame);
+    }
+    Ok(frames)
+  }
 
-        let frame = Frame {
-          frame: av_frame,
-          name: Some("video_source_1".to_string()),
-          index: 1,
-        };
+  fn get_codec_context(&self) -> Option<*mut AVCodecContext> {
+    match (&self.audio_decoder, &self.video_decoder) {
+      (Some(audio_decoder), _) => Some(audio_decoder.codec_context),
+      (None, Some(video_decoder)) => Some(video_decoder.codec_context),
+      (None, None) => None,
+    }
+  }
 
-        if let Some(graph) = &self.graph {
-          if let Ok((_audio_frames, video_frames)) = graph.process(&[], &[frame]) {
-            trace!("[FFmpeg] Output graph count {} frames", video_frames.len());
-            let frame = video_frames.first().unwrap();
-            av_frame_clone((*frame).frame)
-          } else {
-            av_frame
-          }
+  fn receive_frame(
+    &mut self,
+    codec_context: *mut AVCodecContext,
+  ) -> std::result::Result<ProcessFrame, String> {
+    let mut av_frame = unsafe { av_frame_alloc() };
+    unsafe {
+      check_result!(avcodec_receive_frame(codec_context, av_frame), {
+        av_frame_free(&mut av_frame);
+      });
+    }
+
+    let frame = if self.audio_decoder.is_some() {
+      self.process_audio_frame(av_frame)
+    } else {
+      self.process_video_frame(av_frame)
+    };
+    Ok(ProcessFrame::AudioVideo(frame))
+  }
+
+  fn process_audio_frame(&mut self, av_frame: *mut AVFrame) -> Frame {
+    let av_frame = unsafe {
+      // timestamps are checked in the stream time base, before any resampling
+      if let Some(checker) = &mut self.continuity_checker {
+        if let Some(discontinuity) = checker.check(
+          (*av_frame).pts,
+          (*av_frame).nb_samples,
+          (*av_frame).sample_rate,
+        ) {
+          warn!("Audio discontinuity: {:?}", discontinuity);
+        }
+      }
+
+      let frame = Frame {
+        frame: av_frame,
+        name: Some("audio_source_1".to_string()),
+        index: 1,
+      };
+
+      if let Some(graph) = &self.graph {
+        if let Ok((audio_frames, _video_frames)) = graph.process(&[frame], &[]) {
+          trace!("[FFmpeg] Output graph count {} frames", audio_frames.len());
+          let frame = audio_frames.first().unwrap();
+          av_frame_clone((*frame).frame)
         } else {
           av_frame
         }
-      };
+      } else {
+        av_frame
+      }
+    };
 
+    Frame {
+      frame: av_frame,
+      name: Some("audio".to_string()),
+      index: 1,
+    }
+  }
+
+  fn process_video_frame(&mut self, av_frame: *mut AVFrame) -> Frame {
+    let av_frame = unsafe {
       let frame = Frame {
         frame: av_frame,
-        name: Some("video".to_string()),
+        name: Some("video_source_1".to_string()),
         index: 1,
       };
 
-      Ok(Some(ProcessFrame::AudioVideo(frame)))
-    } else if let Some(ebu_ttml_live_decoder) = &mut self.ebu_ttml_live_decoder {
-      let result = match ebu_ttml_live_decoder.decode(packet)? {
-        Some(ttml_content) => Some(ProcessFrame::EbuTtmlLive(Box::new(ttml_content))),
-        None => None,
-      };
-      Ok(result)
-    } else {
-      Err("No audio/video decoder found".to_string())
+      if let Some(graph) = &self.graph {
+        if let Ok((_audio_frames, video_frames)) = graph.process(&[], &[frame]) {
+          trace!("[FFmpeg] Output graph count {} frames", video_frames.len());
+          let frame = video_frames.first().unwrap();
+          av_frame_clone((*frame).frame)
+        } else {
+          av_frame
+        }
+      } else {
+        av_frame
+      }
+    };
+
+    // keep the container signaling along the frame, as the decoder may not report it
+    if let Some(container_sample_aspect_ratio) = &self.container_sample_aspect_ratio {
+      let key = CString::new(CONTAINER_SAMPLE_ASPECT_RATIO_KEY).unwrap();
+      let value = CString::new(container_sample_aspect_ratio.as_str()).unwrap();
+      unsafe {
+        av_dict_set(&mut (*av_frame).metadata, key.as_ptr(), value.as_ptr(), 0);
+      }
+    }
+
+    Frame {
+      frame: av_frame,
+      name: Some("video".to_string()),
+      index: 1,
     }
   }
 }
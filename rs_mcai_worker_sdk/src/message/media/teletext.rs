@@ -0,0 +1,337 @@
+//! Teletext subtitle pages extraction
+//!
+//! Supports DVB teletext streams (EN 300 472) with the [`TeletextCodec`](struct.TeletextCodec.html)
+//! data codec, and OP-47 subtitling data packets carried in VANC.
+
+use crate::message::media::data_codec::DataCodec;
+use crate::message::media::ebu_ttml_live::{
+  Body, Div, EbuTtmlLive, Paragraph, Span, TimeExpression,
+};
+use std::{any::Any, collections::HashMap, sync::Mutex};
+
+const PACKET_SIZE: usize = 42;
+const DVB_DATA_UNIT_LENGTH: usize = 44;
+const OP47_SDP_IDENTIFIER: [u8; 2] = [0x51, 0x15];
+const OP47_PACKET_SIZE: usize = 45;
+
+/// Teletext page, displayed when complete
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TeletextPage {
+  pub magazine: u8,
+  /// Page number, as displayed on the receiver (e.g. 888)
+  pub page_number: u16,
+  /// Non empty text rows, indexed by row number
+  pub rows: Vec<(u8, String)>,
+}
+
+impl TeletextPage {
+  pub fn is_empty(&self) -> bool {
+    self.rows.is_empty()
+  }
+
+  pub fn get_text(&self) -> String {
+    self
+      .rows
+      .iter()
+      .map(|(_row, text)| text.clone())
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+
+  /// Convert the page to an EBU TTML Live document, one paragraph per row
+  pub fn to_ebu_ttml_live(
+    &self,
+    begin: TimeExpression,
+    end: Option<TimeExpression>,
+  ) -> EbuTtmlLive {
+    let paragraphs = self
+      .rows
+      .iter()
+      .map(|(_row, text)| Paragraph {
+        spans: vec![Span {
+          content: text.clone(),
+        }],
+        duration: None,
+        begin: Some(begin.clone()),
+        end: end.clone(),
+      })
+      .collect();
+
+    EbuTtmlLive {
+      sequence_identifier: Some(format!("Teletext{}", self.page_number)),
+      body: Body {
+        duration: None,
+        begin: Some(begin),
+        end,
+        divs: vec![Div { paragraphs }],
+      },
+      ..Default::default()
+    }
+  }
+}
+
+/// Teletext decoder, aggregating packets into pages
+#[derive(Debug, Default)]
+pub struct TeletextDecoder {
+  page_filter: Option<u16>,
+  pages: HashMap<u8, TeletextPage>,
+}
+
+impl TeletextDecoder {
+  /// Decoder of all pages, or only of the selected page number
+  pub fn new(page_filter: Option<u16>) -> Self {
+    TeletextDecoder {
+      page_filter,
+      pages: HashMap::new(),
+    }
+  }
+
+  /// Decode a 42 bytes teletext packet, returning the previous page of the magazine once completed
+  pub fn decode_packet(&mut self, packet: &[u8]) -> Result<Option<TeletextPage>, String> {
+    if packet.len() < PACKET_SIZE {
+      return Err(format!(
+        "Invalid teletext packet size: {} bytes",
+        packet.len()
+      ));
+    }
+
+    let address_1 = unham84(packet[0]);
+    let address_2 = unham84(packet[1]);
+    let magazine = match address_1 & 0x07 {
+      0 => 8,
+      magazine => magazine,
+    };
+    let row = (address_1 >> 3) | (address_2 << 1);
+
+    match row {
+      0 => {
+        let units = unham84(packet[2]);
+        let tens = unham84(packet[3]);
+        let completed = self.pages.remove(&magazine);
+
+        if units <= 9 && tens <= 9 {
+          let page_number = magazine as u16 * 100 + tens as u16 * 10 + units as u16;
+          if self.page_filter.is_none() || self.page_filter == Some(page_number) {
+            self.pages.insert(
+              magazine,
+              TeletextPage {
+                magazine,
+                page_number,
+                rows: vec![],
+              },
+            );
+          }
+        }
+
+        Ok(completed.filter(|page| !page.is_empty()))
+      }
+      1..=24 => {
+        if let Some(page) = self.pages.get_mut(&magazine) {
+          let text = decode_text(&packet[2..PACKET_SIZE]);
+          if !text.is_empty() {
+            page.rows.push((row, text));
+          }
+        }
+        Ok(None)
+      }
+      _ => Ok(None),
+    }
+  }
+
+  /// Decode a DVB teletext PES payload
+  pub fn decode_dvb(&mut self, data: &[u8]) -> Result<Vec<TeletextPage>, String> {
+    if data.is_empty() {
+      return Ok(vec![]);
+    }
+
+    let mut pages = vec![];
+    // skip the data identifier
+    let mut position = 1;
+    while position + 2 <= data.len() {
+      let data_unit_id = data[position];
+      let data_unit_length = data[position + 1] as usize;
+      position += 2;
+
+      if position + data_unit_length > data.len() {
+        return Err("Truncated DVB teletext data unit".to_string());
+      }
+
+      // EBU teletext non-subtitle and subtitle data units
+      if (data_unit_id == 0x02 || data_unit_id == 0x03) && data_unit_length == DVB_DATA_UNIT_LENGTH
+      {
+        // skip the field parity/line offset and the framing code
+        let packet: Vec<u8> = data[position + 2..position + data_unit_length]
+          .iter()
+          .map(|byte| byte.reverse_bits())
+          .collect();
+
+        if let Some(page) = self.decode_packet(&packet)? {
+          pages.push(page);
+        }
+      }
+      position += data_unit_length;
+    }
+    Ok(pages)
+  }
+
+  /// Decode an OP-47 subtitling distribution packet (SMPTE RDD 8) from VANC
+  pub fn decode_op47(&mut self, data: &[u8]) -> Result<Vec<TeletextPage>, String> {
+    if data.len() < 9 || data[0..2] != OP47_SDP_IDENTIFIER {
+      return Err("Invalid OP-47 subtitling distribution packet".to_string());
+    }
+
+    let mut pages = vec![];
+    // identifier, length, format code and the 5 line descriptors
+    let mut position = 9;
+    for descriptor in &data[4..9] {
+      if *descriptor == 0 {
+        continue;
+      }
+      if position + OP47_PACKET_SIZE > data.len() {
+        return Err("Truncated OP-47 subtitling distribution packet".to_string());
+      }
+
+      // skip the clock run-in and the framing code
+      if let Some(page) = self.decode_packet(&data[position + 3..position + OP47_PACKET_SIZE])? {
+        pages.push(page);
+      }
+      position += OP47_PACKET_SIZE;
+    }
+    Ok(pages)
+  }
+}
+
+/// Data codec producing the completed `Vec<TeletextPage>` from DVB teletext packets
+#[derive(Debug, Default)]
+pub struct TeletextCodec {
+  decoder: Mutex<TeletextDecoder>,
+}
+
+impl TeletextCodec {
+  pub fn new(page_filter: Option<u16>) -> Self {
+    TeletextCodec {
+      decoder: Mutex::new(TeletextDecoder::new(page_filter)),
+    }
+  }
+}
+
+impl DataCodec for TeletextCodec {
+  fn decode(&self, data: &[u8]) -> Result<Box<dyn Any + Send>, String> {
+    let pages = self
+      .decoder
+      .lock()
+      .map_err(|error| error.to_string())?
+      .decode_dvb(data)?;
+    Ok(Box::new(pages))
+  }
+}
+
+fn unham84(byte: u8) -> u8 {
+  ((byte >> 1) & 0x01) | ((byte >> 2) & 0x02) | ((byte >> 3) & 0x04) | ((byte >> 4) & 0x08)
+}
+
+fn decode_text(data: &[u8]) -> String {
+  data
+    .iter()
+    .map(|byte| match byte & 0x7F {
+      character if character < 0x20 => ' ',
+      character => character as char,
+    })
+    .collect::<String>()
+    .trim()
+    .to_string()
+}
+
+#[cfg(test)]
+fn ham84(nibble: u8) -> u8 {
+  ((nibble & 0x01) << 1) | ((nibble & 0x02) << 2) | ((nibble & 0x04) << 3) | ((nibble & 0x08) << 4)
+}
+
+#[cfg(test)]
+fn get_test_packet(magazine: u8, row: u8, content: &[u8]) -> Vec<u8> {
+  let mut packet = vec![
+    ham84((magazine & 0x07) | ((row & 0x01) << 3)),
+    ham84(row >> 1),
+  ];
+  packet.extend_from_slice(content);
+  packet.resize(PACKET_SIZE, 0x20);
+  packet
+}
+
+#[test]
+pub fn test_decode_teletext_packets() {
+  let mut decoder = TeletextDecoder::new(Some(888));
+
+  let header = get_test_packet(8, 0, &[ham84(8), ham84(8)]);
+  assert_eq!(None, decoder.decode_packet(&header).unwrap());
+
+  let row = get_test_packet(8, 22, b"\x0d  Hello world  ");
+  assert_eq!(None, decoder.decode_packet(&row).unwrap());
+
+  let other_page_header = get_test_packet(1, 0, &[ham84(0), ham84(0)]);
+  assert_eq!(None, decoder.decode_packet(&other_page_header).unwrap());
+  let other_page_row = get_test_packet(1, 1, b"Index");
+  assert_eq!(None, decoder.decode_packet(&other_page_row).unwrap());
+
+  let page = decoder.decode_packet(&header).unwrap().unwrap();
+  assert_eq!(8, page.magazine);
+  assert_eq!(888, page.page_number);
+  assert_eq!(vec![(22, "Hello world".to_string())], page.rows);
+  assert_eq!("Hello world", page.get_text());
+
+  let ebu_ttml_live = page.to_ebu_ttml_live(TimeExpression::new_frames(25.0), None);
+  assert_eq!(1, ebu_ttml_live.body.divs[0].paragraphs.len());
+  assert_eq!(
+    "Hello world",
+    ebu_ttml_live.body.divs[0].paragraphs[0].spans[0].content
+  );
+}
+
+#[test]
+pub fn test_decode_dvb_teletext() {
+  let get_data_unit = |packet: Vec<u8>| {
+    let mut data_unit = vec![0x03, DVB_DATA_UNIT_LENGTH as u8, 0x00, 0xE4];
+    data_unit.extend(packet.iter().map(|byte| byte.reverse_bits()));
+    data_unit
+  };
+
+  let mut data = vec![0x10];
+  data.extend(get_data_unit(get_test_packet(8, 0, &[ham84(8), ham84(8)])));
+  data.extend(get_data_unit(get_test_packet(8, 23, b"Subtitle")));
+  data.extend(vec![0xFF, 0x02, 0xFF, 0xFF]);
+
+  let codec = TeletextCodec::new(None);
+  let payload = codec.decode(&data).unwrap();
+  assert!(payload
+    .downcast_ref::<Vec<TeletextPage>>()
+    .unwrap()
+    .is_empty());
+
+  let data = [
+    vec![0x10],
+    get_data_unit(get_test_packet(8, 0, &[ham84(8), ham84(8)])),
+  ]
+  .concat();
+  let payload = codec.decode(&data).unwrap();
+  let pages = payload.downcast_ref::<Vec<TeletextPage>>().unwrap();
+  assert_eq!(1, pages.len());
+  assert_eq!(vec![(23, "Subtitle".to_string())], pages[0].rows);
+}
+
+#[test]
+pub fn test_decode_op47() {
+  let get_packet = |packet: Vec<u8>| [vec![0x55, 0x55, 0x27], packet].concat();
+
+  let mut data = vec![0x51, 0x15, 0x00, 0x02, 0x15, 0x16, 0x00, 0x00, 0x00];
+  data.extend(get_packet(get_test_packet(8, 0, &[ham84(8), ham84(8)])));
+  data.extend(get_packet(get_test_packet(8, 20, b"OP-47")));
+
+  let mut decoder = TeletextDecoder::new(Some(888));
+  assert!(decoder.decode_op47(&data).unwrap().is_empty());
+  assert!(decoder.decode_op47(&[0x00]).is_err());
+
+  let pages = decoder
+    .decode_packet(&get_test_packet(8, 0, &[ham84(8), ham84(8)]))
+    .unwrap();
+  assert_eq!(vec![(20, "OP-47".to_string())], pages.unwrap().rows);
+}
@@ -0,0 +1,131 @@
+use stainless_ffmpeg::frame::Frame;
+use stainless_ffmpeg_sys::{av_frame_get_side_data, AVFrameSideDataType, AVRational};
+
+/// Frame metadata key of the sample aspect ratio declared by the container
+pub const CONTAINER_SAMPLE_ASPECT_RATIO_KEY: &str = "sdk.container_sample_aspect_ratio";
+
+/// Aspect ratio signaling of a video frame, from the container and the video stream
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AspectRatioSignaling {
+  pub width: u32,
+  pub height: u32,
+  /// Active Format Description code (SMPTE ST 2016-1), from the video user data or VANC
+  pub active_format_description: Option<u8>,
+  pub sample_aspect_ratio: Option<(u32, u32)>,
+  pub container_sample_aspect_ratio: Option<(u32, u32)>,
+}
+
+impl AspectRatioSignaling {
+  pub fn from_frame(frame: &Frame) -> Self {
+    unsafe {
+      let av_frame = frame.frame;
+
+      let side_data = av_frame_get_side_data(av_frame, AVFrameSideDataType::AV_FRAME_DATA_AFD);
+      let active_format_description = if side_data.is_null() || (*side_data).size < 1 {
+        None
+      } else {
+        Some(*(*side_data).data)
+      };
+
+      let container_sample_aspect_ratio = frame
+        .get_metadata(CONTAINER_SAMPLE_ASPECT_RATIO_KEY)
+        .and_then(|value| parse_ratio(&value));
+
+      AspectRatioSignaling {
+        width: (*av_frame).width.max(0) as u32,
+        height: (*av_frame).height.max(0) as u32,
+        active_format_description,
+        sample_aspect_ratio: to_ratio((*av_frame).sample_aspect_ratio),
+        container_sample_aspect_ratio,
+      }
+    }
+  }
+
+  /// Display aspect ratio, using the stream sample aspect ratio or else the container one
+  pub fn get_display_aspect_ratio(&self) -> Option<(u32, u32)> {
+    if self.width == 0 || self.height == 0 {
+      return None;
+    }
+
+    let (num, den) = self
+      .sample_aspect_ratio
+      .or(self.container_sample_aspect_ratio)
+      .unwrap_or((1, 1));
+
+    let width = self.width as u64 * num as u64;
+    let height = self.height as u64 * den as u64;
+    let divisor = gcd(width, height);
+    Some(((width / divisor) as u32, (height / divisor) as u32))
+  }
+}
+
+/// Sample aspect ratio as a `num:den` string, for the frame metadata
+pub(crate) fn format_ratio(ratio: AVRational) -> Option<String> {
+  to_ratio(ratio).map(|(num, den)| format!("{}:{}", num, den))
+}
+
+/// Unknown ratios are signaled as 0/1
+fn to_ratio(ratio: AVRational) -> Option<(u32, u32)> {
+  if ratio.num <= 0 || ratio.den <= 0 {
+    None
+  } else {
+    Some((ratio.num as u32, ratio.den as u32))
+  }
+}
+
+fn parse_ratio(value: &str) -> Option<(u32, u32)> {
+  let mut parts = value.split(':').map(|part| part.parse::<u32>().ok());
+  match (parts.next(), parts.next()) {
+    (Some(Some(num)), Some(Some(den))) if num > 0 && den > 0 => Some((num, den)),
+    _ => None,
+  }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
+
+#[test]
+pub fn test_display_aspect_ratio() {
+  let signaling = AspectRatioSignaling {
+    width: 720,
+    height: 576,
+    active_format_description: Some(0x0A),
+    sample_aspect_ratio: Some((64, 45)),
+    container_sample_aspect_ratio: Some((16, 15)),
+  };
+  assert_eq!(Some((16, 9)), signaling.get_display_aspect_ratio());
+
+  let signaling = AspectRatioSignaling {
+    sample_aspect_ratio: None,
+    ..signaling
+  };
+  assert_eq!(Some((4, 3)), signaling.get_display_aspect_ratio());
+
+  let signaling = AspectRatioSignaling {
+    width: 1920,
+    height: 1080,
+    ..Default::default()
+  };
+  assert_eq!(Some((16, 9)), signaling.get_display_aspect_ratio());
+  assert_eq!(
+    None,
+    AspectRatioSignaling::default().get_display_aspect_ratio()
+  );
+}
+
+#[test]
+pub fn test_sample_aspect_ratio_format() {
+  assert_eq!(
+    Some("16:15".to_string()),
+    format_ratio(AVRational { num: 16, den: 15 })
+  );
+  assert_eq!(None, format_ratio(AVRational { num: 0, den: 1 }));
+  assert_eq!(Some((16, 15)), parse_ratio("16:15"));
+  assert_eq!(None, parse_ratio("16"));
+  assert_eq!(None, parse_ratio("0:1"));
+}
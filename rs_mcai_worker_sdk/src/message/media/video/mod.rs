@@ -5,9 +5,11 @@ use dict_derive::{FromPyObject, IntoPyObject};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::message::media::filters::FilterParameters;
+use crate::message::media::filters::{FilterParameters, GenericFilter};
+pub use aspect_ratio::AspectRatioSignaling;
 pub use region_of_interest::RegionOfInterest;
 
+pub mod aspect_ratio;
 mod region_of_interest;
 
 #[cfg(feature = "media")]
@@ -18,11 +20,17 @@ pub struct VideoFormat {
 }
 
 #[cfg(feature = "media")]
-#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
 #[cfg_attr(feature = "python", derive(FromPyObject, IntoPyObject))]
 pub struct Scaling {
   pub width: Option<u32>,
   pub height: Option<u32>,
+  /// Software scaler algorithm: `fast_bilinear`, `bilinear`, `bicubic`, `neighbor`, `area`, `lanczos`, `spline`...
+  #[serde(default)]
+  pub algorithm: Option<String>,
+  /// Process the chroma planes at full resolution, for a better quality
+  #[serde(default)]
+  pub full_chroma: Option<bool>,
 }
 
 impl FilterParameters for Scaling {
@@ -30,13 +38,59 @@ impl FilterParameters for Scaling {
     let width = self.width.map_or((-1).to_string(), |w| w.to_string());
     let height = self.height.map_or((-1).to_string(), |h| h.to_string());
 
-    [("width", width), ("height", height)]
+    let mut parameters: HashMap<String, String> = [("width", width), ("height", height)]
       .iter()
       .map(|(key, value)| (key.to_string(), value.clone()))
-      .collect()
+      .collect();
+
+    let mut flags = self.algorithm.clone().unwrap_or_default();
+    if self.full_chroma == Some(true) {
+      flags.push_str("+full_chroma_int+full_chroma_inp");
+    }
+    if !flags.is_empty() {
+      parameters.insert("flags".to_string(), flags);
+    }
+
+    parameters
   }
 }
 
+impl Scaling {
+  /// Downscale filter of the images larger than the maximum resolution, keeping the aspect ratio
+  pub(crate) fn get_max_resolution_filter(max_width: u32, max_height: u32) -> GenericFilter {
+    let parameters = [
+      ("width", format!("min(iw,{})", max_width)),
+      ("height", format!("min(ih,{})", max_height)),
+      ("force_original_aspect_ratio", "decrease".to_string()),
+    ]
+    .iter()
+    .map(|(key, value)| (key.to_string(), value.clone()))
+    .collect();
+
+    GenericFilter {
+      name: "scale".to_string(),
+      label: Some("max_resolution_scale_filter".to_string()),
+      parameters,
+    }
+  }
+}
+
+/// Reject the absurd image dimensions, i.e. of malformed headers
+pub(crate) fn check_source_resolution(
+  width: i32,
+  height: i32,
+  max_resolution: (u32, u32),
+) -> Result<(), String> {
+  let (max_width, max_height) = max_resolution;
+  if width < 0 || height < 0 || width as u32 > max_width || height as u32 > max_height {
+    return Err(format!(
+      "Source resolution {}x{} exceeds the maximum resolution {}x{}",
+      width, height, max_width, max_height
+    ));
+  }
+  Ok(())
+}
+
 #[cfg(feature = "media")]
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[cfg_attr(feature = "python", derive(FromPyObject, IntoPyObject))]
@@ -67,6 +121,7 @@ pub fn test_get_scale_filter_parameters() {
   let scaling = Scaling {
     width: None,
     height: None,
+    ..Default::default()
   };
   let parameters = scaling.get_filter_parameters();
   assert_eq!(&(-1).to_string(), parameters.get("width").unwrap());
@@ -75,6 +130,7 @@ pub fn test_get_scale_filter_parameters() {
   let scaling = Scaling {
     width: Some(1234),
     height: None,
+    ..Default::default()
   };
   let parameters = scaling.get_filter_parameters();
   assert_eq!(&1234.to_string(), parameters.get("width").unwrap());
@@ -83,6 +139,7 @@ pub fn test_get_scale_filter_parameters() {
   let scaling = Scaling {
     width: None,
     height: Some(1234),
+    ..Default::default()
   };
   let parameters = scaling.get_filter_parameters();
   assert_eq!(&(-1).to_string(), parameters.get("width").unwrap());
@@ -91,10 +148,39 @@ pub fn test_get_scale_filter_parameters() {
   let scaling = Scaling {
     width: Some(1234),
     height: Some(5678),
+    ..Default::default()
   };
   let parameters = scaling.get_filter_parameters();
   assert_eq!(&1234.to_string(), parameters.get("width").unwrap());
   assert_eq!(&5678.to_string(), parameters.get("height").unwrap());
+  assert_eq!(None, parameters.get("flags"));
+}
+
+#[test]
+pub fn test_get_scale_filter_flags() {
+  let scaling = Scaling {
+    width: Some(320),
+    height: None,
+    algorithm: Some("bilinear".to_string()),
+    full_chroma: None,
+  };
+  let parameters = scaling.get_filter_parameters();
+  assert_eq!("bilinear", parameters.get("flags").unwrap());
+
+  let scaling = Scaling {
+    width: Some(1920),
+    height: None,
+    algorithm: Some("lanczos".to_string()),
+    full_chroma: Some(true),
+  };
+  let parameters = scaling.get_filter_parameters();
+  assert_eq!(
+    "lanczos+full_chroma_int+full_chroma_inp",
+    parameters.get("flags").unwrap()
+  );
+
+  let scaling: Scaling = serde_json::from_str(r#"{"width": 640, "height": 360}"#).unwrap();
+  assert_eq!(None, scaling.algorithm);
 }
 
 #[test]
@@ -111,3 +197,22 @@ pub fn test_get_crop_filter_parameters() {
   assert_eq!(&123.to_string(), parameters.get("out_w").unwrap());
   assert_eq!(&456.to_string(), parameters.get("out_h").unwrap());
 }
+
+#[test]
+pub fn test_max_resolution() {
+  let filter = Scaling::get_max_resolution_filter(1920, 1080);
+  assert_eq!("scale", filter.name);
+  assert_eq!("min(iw,1920)", filter.parameters.get("width").unwrap());
+  assert_eq!("min(ih,1080)", filter.parameters.get("height").unwrap());
+  assert_eq!(
+    "decrease",
+    filter
+      .parameters
+      .get("force_original_aspect_ratio")
+      .unwrap()
+  );
+
+  assert!(check_source_resolution(1920, 1080, (16384, 16384)).is_ok());
+  assert!(check_source_resolution(40000, 40000, (16384, 16384)).is_err());
+  assert!(check_source_resolution(-1, 1080, (16384, 16384)).is_err());
+}
@@ -0,0 +1,78 @@
+//! Hooks around the processing of the orders, for the cross-cutting concerns of the workers
+//!
+//! A middleware can measure, check or rewrite the orders and their responses without modifying the workers.
+//! The middlewares are registered for the whole process and called in their order of registration:
+//!
+//! ```ignore
+//! mcai_worker_sdk::message::middleware::register_middleware(MetricsMiddleware::default());
+//! ```
+
+use crate::job::{Job, JobResult};
+use crate::Result;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+
+static MIDDLEWARES: RwLock<Vec<Arc<dyn Middleware>>> = RwLock::new(vec![]);
+
+pub trait Middleware: Debug + Send + Sync {
+  /// Called with the payload of each order before it is parsed, returns the payload to process
+  fn on_order_received(&self, order: String) -> Result<String> {
+    Ok(order)
+  }
+
+  /// Called before processing each job, an error fails the job without processing it
+  fn before_process(&self, _job: &Job) -> Result<()> {
+    Ok(())
+  }
+
+  /// Called with the result of each processed job, returns the result to publish
+  fn after_process(&self, _job: &Job, result: Result<JobResult>) -> Result<JobResult> {
+    result
+  }
+
+  /// Called once a response (progression, completion or error) is published on the queue
+  fn on_response_sent(&self, _queue_name: &str, _response: &str) {}
+}
+
+pub fn register_middleware<M: Middleware + 'static>(middleware: M) {
+  register_shared_middleware(Arc::new(middleware));
+}
+
+pub(crate) fn register_shared_middleware(middleware: Arc<dyn Middleware>) {
+  MIDDLEWARES.write().unwrap().push(middleware);
+}
+
+/// Unregister all the middlewares
+pub fn clear_middlewares() {
+  MIDDLEWARES.write().unwrap().clear();
+}
+
+fn get_middlewares() -> Vec<Arc<dyn Middleware>> {
+  MIDDLEWARES.read().unwrap().clone()
+}
+
+pub(crate) fn on_order_received(order: &str) -> Result<String> {
+  get_middlewares()
+    .iter()
+    .try_fold(order.to_string(), |order, middleware| {
+      middleware.on_order_received(order)
+    })
+}
+
+pub(crate) fn before_process(job: &Job) -> Result<()> {
+  get_middlewares()
+    .iter()
+    .try_for_each(|middleware| middleware.before_process(job))
+}
+
+pub(crate) fn after_process(job: &Job, result: Result<JobResult>) -> Result<JobResult> {
+  get_middlewares().iter().fold(result, |result, middleware| {
+    middleware.after_process(job, result)
+  })
+}
+
+pub(crate) fn on_response_sent(queue_name: &str, response: &str) {
+  for middleware in get_middlewares() {
+    middleware.on_response_sent(queue_name, response);
+  }
+}
@@ -1,43 +1,237 @@
+pub(crate) mod admission;
+mod chaos;
 mod helpers;
+mod job_events;
 #[cfg(feature = "media")]
 pub mod media;
+pub mod middleware;
+mod panic_handler;
+pub mod scheduler;
+mod security;
+pub(crate) mod trace_context;
 
 #[cfg(feature = "media")]
 pub use media::{DESTINATION_PATH_PARAMETER, SOURCE_PATH_PARAMETER};
+pub(crate) use panic_handler::{read_message_event, write_message_event};
 
 use crate::{
-  job::{Job, JobProgression, JobResult, JobStatus},
+  channels::{delayed::publish_delayed, lease::DeliveryLease},
+  config::{
+    get_admission_requeue_delay, get_claim_requeue_delay, get_delivery_lease_renewal_interval,
+    get_job_log_capture, get_job_timeout, get_job_timeout_max_requeues, get_job_timeout_policy,
+    get_requirements_requeue_delay, get_requirements_requeue_policy, get_transient_max_retries,
+    get_transient_retry_delay,
+  },
+  job::{
+    ExecutionRecorder, Job, JobBatch, JobClaim, JobContext, JobEvent, JobLease, JobOrigin,
+    JobPartialResult, JobProgression, JobResult, JobState, JobStatus, JobWorkspace, PendingPublish,
+    ProgressionDetails, RunningJobs, EXECUTION_METRICS_PARAMETER,
+  },
+  logger::{JobLogCapture, JobLogLevel, JobLogs, LOGS_PARAMETER},
+  parameter::{
+    container::ParametersContainer,
+    deprecation::warn_deprecated_parameters,
+    reference::ReferencesCache,
+    secret::{mask_secrets, JobSecrets},
+    validation::UNKNOWN_PARAMETERS_PARAMETER,
+  },
+  telemetry::{JobSpan, OrderSpan},
+  worker::{
+    parameter_schema_for,
+    watchdog::{self, StuckJobDiagnostic},
+  },
   McaiChannel, MessageError, MessageEvent, Result,
 };
+use amq_protocol_types::FieldTable;
 use lapin::{message::Delivery, options::*, BasicProperties, Promise};
 
 use schemars::JsonSchema;
-use serde::de::DeserializeOwned;
-use std::cell::RefCell;
-use std::rc::Rc;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::sync::{mpsc, Arc, RwLock};
+use std::time::Duration;
 
 static RESPONSE_EXCHANGE: &str = "job_response";
 static QUEUE_JOB_COMPLETED: &str = "job_completed";
 static QUEUE_JOB_ERROR: &str = "job_error";
 static QUEUE_JOB_PROGRESSION: &str = "job_progression";
+static QUEUE_JOB_PARTIAL_RESULT: &str = "job_partial_result";
+static QUEUE_JOB_LOGS: &str = "job_logs";
 
-pub fn process_message<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
-  message_event: Rc<RefCell<ME>>,
+pub const TIMEOUT_PARAMETER: &str = "sdk_timeout";
+
+/// Maximum priority of the job queues
+const MAX_ORDER_PRIORITY: i64 = 100;
+
+pub fn process_message<
+  P: 'static + DeserializeOwned + JsonSchema,
+  ME: 'static + MessageEvent<P> + Send + Sync,
+>(
+  message_event: Arc<RwLock<ME>>,
   message: Delivery,
   channel: McaiChannel,
+  running_jobs: RunningJobs,
 ) -> Promise<()> {
+  if let Err(error) = security::verify_signature(message.properties.headers(), &message.data) {
+    return publish_invalid_signature(channel, message, &error);
+  }
+
+  if let Err(reason) = admission::check_admission() {
+    return requeue_saturated_order(channel, message, &reason);
+  }
+
   let count = helpers::get_message_death_count(&message);
-  let message_data = std::str::from_utf8(&message.data).unwrap();
+  let message_data =
+    match middleware::on_order_received(std::str::from_utf8(&message.data).unwrap()) {
+      Ok(message_data) => message_data,
+      Err(error) => return publish_result(channel, message, Err(error)),
+    };
+  let message_data = message_data.as_str();
+  let order_jobs = get_order_jobs(message_data);
+  // the values of the secret parameters are masked until the response is published
+  let _secrets: Vec<JobSecrets> = order_jobs.iter().map(JobSecrets::new).collect();
+  // the references are downloaded once per job
+  let _references: Vec<ReferencesCache> = order_jobs.iter().map(ReferencesCache::new).collect();
+  // the responses of the job carry its trace context until its result is published
+  let _trace_context = trace_context::JobTraceContext::new(Job::new(message_data).ok().as_ref());
+  // and the name and version of its worker
+  let _origin = {
+    let message_event = read_message_event(&message_event);
+    JobOrigin::new(
+      get_order_id(message_data),
+      message_event.get_name(),
+      message_event.get_version().to_string(),
+    )
+  };
+  // its steps are spans of the trace of the order
+  let _order_span = {
+    let order_id = get_order_id(message_data);
+    let traceparent = order_id.and_then(trace_context::get_trace_context);
+    OrderSpan::new(order_id, traceparent.as_deref())
+  };
 
-  match parse_and_process_message(
-    message_event,
-    message_data,
-    count,
-    Some(channel.clone()),
-    publish_job_progression,
-  ) {
+  // a job is not started on a nearly full scratch volume, it would fail mid-processing
+  if let Err(reason) = admission::check_order_disk_space(message_data) {
+    return requeue_saturated_order(channel, message, &reason);
+  }
+
+  // an order for another version of the worker is left to the compatible workers of the fleet
+  if let Some(Err(error)) = Job::new(message_data)
+    .ok()
+    .map(|job| job.check_worker_version(&read_message_event(&message_event).get_version()))
+  {
+    return match error {
+      MessageError::RequirementsError(reason) => {
+        requeue_incompatible_order(channel, message, &reason)
+      }
+      error => publish_result(channel, message, Err(error)),
+    };
+  }
+
+  // the lease is held until the result is published
+  let _lease = match claim_job(&message_event, message_data) {
+    Ok(JobClaim::Acquired(lease)) => lease,
+    Ok(JobClaim::Contended(reason)) => {
+      let delay = get_claim_requeue_delay();
+      warn!(
+        "Job resources locked, order requeued in {} ms: {}",
+        delay, reason
+      );
+      return requeue_order_later(channel, message, delay);
+    }
+    Err(error) => return publish_result(channel, message, Err(error)),
+  };
+
+  let order_timeout = match get_order_timeout(message_data) {
+    Ok(order_timeout) => order_timeout,
+    Err(error) => return publish_result(channel, message, Err(error)),
+  };
+
+  // the delivery is renewed until the result is published, the broker would deliver it again otherwise
+  let delivery_lease = DeliveryLease::start(
+    channel.clone(),
+    message,
+    get_delivery_lease_renewal_interval(),
+  );
+
+  let result = if let Some((order_id, timeout)) = order_timeout {
+    let (sender, receiver) = mpsc::channel();
+    let message_data = message_data.to_string();
+    let process_channel = channel.clone();
+    let process_running_jobs = running_jobs.clone();
+
+    crate::runtime::spawn_blocking(move || {
+      let result = parse_and_process_message(
+        message_event,
+        &message_data,
+        count,
+        Some(process_channel),
+        &process_running_jobs,
+        publish_job_progression,
+      );
+      // the receiver is dropped if the job has timed out
+      let _ = sender.send(result);
+    });
+
+    match receiver.recv_timeout(Duration::from_secs(timeout)) {
+      Ok(result) => result,
+      Err(_) => {
+        // let the abandoned job abort if it polls its context
+        running_jobs.cancel(order_id);
+        running_jobs.set_last_error(order_id, "Job timed out");
+        let checkpoint = running_jobs.get_checkpoint(order_id);
+        job_events::publish_job_event(
+          Some(&channel),
+          JobEvent::new(order_id, JobState::Error).with_message("Job timed out"),
+        );
+        let message = delivery_lease.release();
+        return publish_timeout_error(channel, message, order_id, timeout, checkpoint);
+      }
+    }
+  } else {
+    parse_and_process_message(
+      message_event,
+      message_data,
+      count,
+      Some(channel.clone()),
+      &running_jobs,
+      publish_job_progression,
+    )
+  };
+
+  let order_id = get_order_id(message_data);
+  if let Some(order_id) = order_id {
+    job_events::publish_job_state(Some(&channel), order_id, JobState::Publishing);
+  }
+  if let (Some(order_id), Err(error)) = (order_id, &result) {
+    running_jobs.set_last_error(order_id, &get_error_message(error));
+  }
+  let result_event = order_id.map(|order_id| job_events::get_result_event(order_id, &result));
+  let event_channel = channel.clone();
+
+  let message = delivery_lease.release();
+  let promise = publish_result(channel, message, result);
+
+  if let Some(result_event) = result_event {
+    job_events::publish_job_event(Some(&event_channel), result_event);
+  }
+  promise
+}
+
+fn publish_result(
+  channel: McaiChannel,
+  message: Delivery,
+  result: Result<JobResult>,
+) -> Promise<()> {
+  match result {
     Ok(job_result) => {
-      info!(target: &job_result.get_str_job_id(), "Completed");
+      match job_result.get_status() {
+        JobStatus::Skipped => info!(target: &job_result.get_str_job_id(), "Skipped"),
+        JobStatus::CompletedWithWarnings => {
+          warn!(target: &job_result.get_str_job_id(), "Completed with warnings: {}", job_result.get_warnings().join(", "))
+        }
+        _ => info!(target: &job_result.get_str_job_id(), "Completed"),
+      }
       publish_job_completed(channel, message, job_result)
     }
     Err(error) => match error {
@@ -51,6 +245,7 @@ pub fn process_message<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
       MessageError::ProcessingError(job_result) => {
         publish_processing_error(channel, message, job_result)
       }
+      MessageError::Transient(job_result) => publish_transient_error(channel, message, job_result),
       MessageError::RuntimeError(error_message) => {
         publish_runtime_error(channel, message, &error_message)
       }
@@ -63,32 +258,399 @@ pub fn parse_and_process_message<
   ME: MessageEvent<P>,
   F: Fn(Option<McaiChannel>, u64, u8) -> Result<()> + 'static,
 >(
-  message_event: Rc<RefCell<ME>>,
+  message_event: Arc<RwLock<ME>>,
   message_data: &str,
   count: Option<i64>,
   channel: Option<McaiChannel>,
+  running_jobs: &RunningJobs,
   publish_job_progression: F,
 ) -> Result<JobResult> {
-  let job = Job::new(message_data)?;
+  match Job::new(message_data) {
+    Ok(job) => {
+      let context = running_jobs.start(job.job_id);
+      let result = process_job(
+        message_event,
+        &job,
+        count,
+        channel,
+        context,
+        &publish_job_progression,
+      );
+      running_jobs.finish(job.job_id);
+      result
+    }
+    Err(job_error) => {
+      let batch = JobBatch::new(message_data).map_err(|_| job_error)?;
+      let context = running_jobs.start(batch.batch_id);
+      let result = process_batch(
+        message_event,
+        &batch,
+        count,
+        channel,
+        context,
+        &publish_job_progression,
+      );
+      running_jobs.finish(batch.batch_id);
+      result
+    }
+  }
+}
+
+fn process_job<
+  P: DeserializeOwned + JsonSchema,
+  ME: MessageEvent<P>,
+  F: Fn(Option<McaiChannel>, u64, u8) -> Result<()>,
+>(
+  message_event: Arc<RwLock<ME>>,
+  job: &Job,
+  count: Option<i64>,
+  channel: Option<McaiChannel>,
+  context: JobContext,
+  publish_job_progression: &F,
+) -> Result<JobResult> {
+  // the records of the job are captured until it is processed, rejected orders included
+  let log_capture = JobLogCapture::new(job.job_id);
+  let logs_channel = channel.clone();
+
+  let result = {
+    let span = JobSpan::new(Some(job.job_id), "process");
+    let result = run_job(
+      message_event,
+      job,
+      count,
+      channel,
+      context,
+      publish_job_progression,
+    );
+    if let Err(error) = &result {
+      span.set_error(&get_error_message(error));
+    }
+    result
+  };
+
+  send_job_logs(logs_channel.as_ref(), log_capture.finish(), result)
+}
+
+fn run_job<
+  P: DeserializeOwned + JsonSchema,
+  ME: MessageEvent<P>,
+  F: Fn(Option<McaiChannel>, u64, u8) -> Result<()>,
+>(
+  message_event: Arc<RwLock<ME>>,
+  job: &Job,
+  count: Option<i64>,
+  channel: Option<McaiChannel>,
+  context: JobContext,
+  publish_job_progression: &F,
+) -> Result<JobResult> {
+  // the log level of the job is restored once processed
+  let _log_level = JobLogLevel::new(job)?;
+  // the values of the secret parameters are masked while the job runs,
+  // the orders consumed by the worker until their response is published
+  let _secrets = JobSecrets::new(job);
+  let _references = ReferencesCache::new(job);
+  job_events::publish_job_state(channel.as_ref(), job.job_id, JobState::Received);
+
   debug!(target: &job.job_id.to_string(),
          "received message: {:?} (iteration: {})",
          job,
          count.unwrap_or(0));
 
+  middleware::before_process(job)?;
+
   job.check_requirements()?;
-  let parameters: P = job.get_parameters()?;
+  job.check_worker_version(&read_message_event(&message_event).get_version())?;
+
+  #[cfg(feature = "media")]
+  let parameters: P = match &job.action {
+    Some(action) => {
+      return Err(MessageError::ParameterValueError(format!(
+        "Job actions are not supported by media workers: {:?}",
+        action
+      )))
+    }
+    None => job.get_validated_parameters()?,
+  };
+
+  // the parameters of an action are parsed by its handler
+  #[cfg(not(feature = "media"))]
+  let parameters: Option<P> = match &job.action {
+    Some(action)
+      if !read_message_event(&message_event)
+        .get_actions()
+        .contains(action) =>
+    {
+      return Err(MessageError::ParameterValueError(format!(
+        "Unknown job action: {:?}",
+        action
+      )))
+    }
+    Some(_) => None,
+    None => Some(job.get_validated_parameters()?),
+  };
+
+  let unknown_parameters = match &job.action {
+    Some(action) => read_message_event(&message_event)
+      .get_actions()
+      .get_schemas()
+      .get(action)
+      .map(|schema| job.get_unknown_parameters(schema))
+      .unwrap_or_default(),
+    None => job.get_unknown_parameters(&parameter_schema_for::<P>()),
+  };
+  if !unknown_parameters.is_empty() {
+    warn!(target: &job.job_id.to_string(),
+          "Unknown parameters ignored: {}",
+          unknown_parameters.join(", "));
+  }
+  warn_deprecated_parameters(job);
 
+  job_events::publish_job_state(channel.as_ref(), job.job_id, JobState::Validated);
   publish_job_progression(channel.clone(), job.job_id, 0)?;
 
+  chaos::inject_latency(job.job_id);
+  chaos::inject_failure(job.job_id)?;
+
+  job_events::publish_job_state(channel.as_ref(), job.job_id, JobState::Initializing);
+  context.set_checkpoint_value(job.get_checkpoint());
+  let workspace = Arc::new(JobWorkspace::new(job.job_id)?);
+  let context = context.with_workspace(workspace.clone());
+  let process_context = context.clone();
+  watchdog::start(job.job_id);
+
   let job_result = JobResult::new(job.job_id);
+  let handler = message_event.clone();
+  job_events::publish_job_state(channel.as_ref(), job.job_id, JobState::Processing);
+  let execution_recorder = ExecutionRecorder::start();
 
-  #[cfg(feature = "media")]
-  return media::process(message_event, channel, &job, parameters, job_result);
+  let result = panic_handler::catch_panic(job.job_id, move || {
+    #[cfg(feature = "media")]
+    return media::process(
+      handler,
+      channel,
+      job,
+      parameters,
+      job_result,
+      process_context,
+    );
 
+    #[cfg(not(feature = "media"))]
+    {
+      let message_event = read_message_event(&handler);
+      match (parameters, &job.action) {
+        (Some(parameters), _) => {
+          let process =
+            message_event.process_async(channel, parameters, job_result, process_context);
+          crate::runtime::block_on(process)?
+        }
+        (None, Some(action)) => message_event.get_actions().process(
+          action,
+          &*message_event,
+          channel,
+          job,
+          job_result,
+          process_context,
+        ),
+        (None, None) => Err(MessageError::NotImplemented()),
+      }
+    }
+  });
+
+  watchdog::finish(job.job_id);
+  workspace.cleanup();
+
+  #[cfg(feature = "media")]
+  let frames_processed = Some(context.get_job_metrics().get_frames_processed());
   #[cfg(not(feature = "media"))]
-  message_event
-    .borrow_mut()
-    .process(channel, parameters, job_result)
+  let frames_processed = None;
+  let result = result.and_then(|job_result| {
+    let metrics = execution_recorder.finish(frames_processed);
+    let job_result = job_result
+      .with_json(EXECUTION_METRICS_PARAMETER, &metrics)
+      .map_err(MessageError::RuntimeError)?;
+
+    if unknown_parameters.is_empty() {
+      return Ok(job_result);
+    }
+    job_result
+      .with_json(UNKNOWN_PARAMETERS_PARAMETER, &unknown_parameters)
+      .map_err(MessageError::RuntimeError)
+  });
+
+  let result = match (result, context.get_checkpoint_value()) {
+    (Err(MessageError::Transient(job_result)), Some(checkpoint))
+      if job_result.get_checkpoint().is_none() =>
+    {
+      Err(MessageError::Transient(
+        job_result.with_checkpoint(&checkpoint),
+      ))
+    }
+    (result, _) => result,
+  };
+
+  middleware::after_process(job, result)
+}
+
+/// Publish the captured records of the job on the `job_logs` queue, or add them to its result
+fn send_job_logs(
+  channel: Option<&McaiChannel>,
+  logs: Option<JobLogs>,
+  result: Result<JobResult>,
+) -> Result<JobResult> {
+  let logs = match logs {
+    Some(logs) => logs,
+    None => return result,
+  };
+
+  if get_job_log_capture() == "queue" {
+    if let Some(channel) = channel {
+      let msg = json!(logs).to_string();
+      if let Err(error) = publish_response(channel, QUEUE_JOB_LOGS, &msg, Some(logs.job_id)) {
+        warn!(target: &logs.job_id.to_string(), "Unable to publish the job logs: {}", error);
+      }
+    }
+    return result;
+  }
+
+  let records = json!(logs.records);
+  let add_logs = |job_result: JobResult| {
+    job_result
+      .clone()
+      .with_json(LOGS_PARAMETER, &records)
+      .unwrap_or(job_result)
+  };
+  match result {
+    Ok(job_result) => Ok(add_logs(job_result)),
+    Err(MessageError::ProcessingError(job_result)) => {
+      Err(MessageError::ProcessingError(add_logs(job_result)))
+    }
+    Err(MessageError::Transient(job_result)) => Err(MessageError::Transient(add_logs(job_result))),
+    Err(error) => Err(error),
+  }
+}
+
+fn process_batch<
+  P: DeserializeOwned + JsonSchema,
+  ME: MessageEvent<P>,
+  F: Fn(Option<McaiChannel>, u64, u8) -> Result<()>,
+>(
+  message_event: Arc<RwLock<ME>>,
+  batch: &JobBatch,
+  count: Option<i64>,
+  channel: Option<McaiChannel>,
+  context: JobContext,
+  publish_job_progression: &F,
+) -> Result<JobResult> {
+  debug!(target: &batch.batch_id.to_string(),
+         "received batch of {} jobs (iteration: {})",
+         batch.jobs.len(),
+         count.unwrap_or(0));
+
+  batch.check_requirements()?;
+
+  let job_results = batch
+    .jobs
+    .iter()
+    .map(|job| {
+      // the remaining jobs are not started once the batch is cancelled
+      let job_context = context.for_job(job.job_id);
+      job_context
+        .check_cancelled()
+        .and_then(|_| {
+          process_job(
+            message_event.clone(),
+            job,
+            count,
+            channel.clone(),
+            job_context,
+            publish_job_progression,
+          )
+        })
+        .unwrap_or_else(|error| get_job_result_from_error(job.job_id, error))
+    })
+    .collect();
+
+  batch.aggregate(job_results)
+}
+
+/// Claim the resources of a job, the batches are not claimed
+fn claim_job<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
+  message_event: &Arc<RwLock<ME>>,
+  message_data: &str,
+) -> Result<JobClaim> {
+  match Job::new(message_data) {
+    Ok(job) => read_message_event(message_event).claim_job(&job),
+    Err(_) => Ok(JobClaim::Acquired(JobLease::new())),
+  }
+}
+
+/// Job of the order, or each job of a batch
+fn get_order_jobs(message_data: &str) -> Vec<Job> {
+  match Job::new(message_data) {
+    Ok(job) => vec![job],
+    Err(_) => JobBatch::new(message_data)
+      .map(|batch| batch.jobs)
+      .unwrap_or_default(),
+  }
+}
+
+fn get_order_id(message_data: &str) -> Option<u64> {
+  Job::new(message_data)
+    .map(|job| job.job_id)
+    .or_else(|_| JobBatch::new(message_data).map(|batch| batch.batch_id))
+    .ok()
+}
+
+/// Identifier and timeout in seconds of the order,
+/// from the `sdk_timeout` job parameter or the `JOB_TIMEOUT` configuration
+fn get_order_timeout(message_data: &str) -> Result<Option<(u64, u64)>> {
+  if let Ok(job) = Job::new(message_data) {
+    let timeout = match job.get_parameter::<i64>(TIMEOUT_PARAMETER).ok() {
+      Some(timeout) if timeout <= 0 => {
+        return Err(MessageError::ParameterValueError(format!(
+          "Invalid {} parameter: expected a positive number of seconds, got {}",
+          TIMEOUT_PARAMETER, timeout
+        )))
+      }
+      Some(timeout) => Some(timeout as u64),
+      None => get_job_timeout(),
+    };
+    return Ok(timeout.map(|timeout| (job.job_id, timeout)));
+  }
+
+  Ok(
+    JobBatch::new(message_data)
+      .ok()
+      .and_then(|batch| get_job_timeout().map(|timeout| (batch.batch_id, timeout))),
+  )
+}
+
+fn get_job_result_from_error(job_id: u64, error: MessageError) -> JobResult {
+  match error {
+    MessageError::ProcessingError(job_result) | MessageError::Transient(job_result) => job_result,
+    MessageError::RuntimeError(message)
+    | MessageError::ParameterValueError(message)
+    | MessageError::RequirementsError(message) => JobResult::new(job_id)
+      .with_status(JobStatus::Error)
+      .with_message(&message),
+    MessageError::NotImplemented() => JobResult::new(job_id)
+      .with_status(JobStatus::Error)
+      .with_message("Not implemented feature"),
+  }
+}
+
+/// Message of the error, reported on the status requests and the spans, the secret values masked
+fn get_error_message(error: &MessageError) -> String {
+  let message = match error {
+    MessageError::ProcessingError(job_result) | MessageError::Transient(job_result) => job_result
+      .get_parameter::<String>("message")
+      .unwrap_or_else(|_| "Job returned in error".to_string()),
+    MessageError::RuntimeError(message)
+    | MessageError::ParameterValueError(message)
+    | MessageError::RequirementsError(message) => message.clone(),
+    MessageError::NotImplemented() => "Not implemented feature".to_string(),
+  };
+  mask_secrets(&message)
 }
 
 fn publish_job_completed(
@@ -98,18 +660,8 @@ fn publish_job_completed(
 ) -> Promise<()> {
   let msg = json!(job_result).to_string();
 
-  let result = channel
-    .basic_publish(
-      RESPONSE_EXCHANGE,
-      QUEUE_JOB_COMPLETED,
-      BasicPublishOptions::default(),
-      msg.as_bytes().to_vec(),
-      BasicProperties::default(),
-    )
-    .wait()
-    .is_ok();
-
-  if result {
+  let job_id = Some(job_result.get_job_id());
+  if publish_response(&channel, QUEUE_JOB_COMPLETED, &msg, job_id).is_ok() {
     channel.basic_ack(
       message.delivery_tag,
       BasicAckOptions::default(), /*not requeue*/
@@ -122,6 +674,37 @@ fn publish_job_completed(
   }
 }
 
+fn publish_response(
+  channel: &McaiChannel,
+  queue_name: &str,
+  content: &str,
+  job_id: Option<u64>,
+) -> std::result::Result<(), String> {
+  let _pending_publish = PendingPublish::new();
+  let span =
+    JobSpan::new(job_id, "publish").with_attribute("messaging.destination.name", queue_name);
+  chaos::delay_publish(queue_name);
+  let content = &mask_secrets(content);
+  let payload = security::encode_response(content)?;
+
+  let result = channel
+    .basic_publish(
+      RESPONSE_EXCHANGE,
+      queue_name,
+      BasicPublishOptions::default(),
+      payload,
+      trace_context::get_response_properties(job_id),
+    )
+    .wait()
+    .map(|_| middleware::on_response_sent(queue_name, content))
+    .map_err(|error| error.to_string());
+
+  if let Err(error) = &result {
+    span.set_error(error);
+  }
+  result
+}
+
 /// Function to publish a progression event
 ///
 /// It will be an integer between 0 and 100.
@@ -130,27 +713,87 @@ pub fn publish_job_progression(
   job_id: u64,
   progression: u8,
 ) -> Result<()> {
+  publish_progression(channel, JobProgression::new(job_id, progression))
+}
+
+/// Function to publish a progression event with the current step of the job
+///
+/// The progression carries the label of the step, the items processed and the estimated remaining time (`eta`),
+/// computed from the time elapsed since the start of the job.
+pub fn publish_job_progression_details(
+  channel: Option<McaiChannel>,
+  context: &JobContext,
+  progression: u8,
+  details: ProgressionDetails,
+) -> Result<()> {
+  let remaining = details.estimate_remaining(progression, context.get_elapsed());
+  let mut job_progression =
+    JobProgression::new(context.get_job_id(), progression).with_details(details);
+  if let Some(remaining) = remaining {
+    job_progression = job_progression.with_eta(remaining);
+  }
+  publish_progression(channel, job_progression)
+}
+
+/// Function to publish an intermediate result of the job, before its final result
+pub fn publish_job_partial_result(
+  channel: Option<McaiChannel>,
+  partial_result: &JobPartialResult,
+) -> Result<()> {
+  let job_id = partial_result.get_job_id();
+  let msg = json!(partial_result).to_string();
+
   if let Some(channel) = channel {
-    let msg = json!(JobProgression::new(job_id, progression)).to_string();
-
-    channel
-      .basic_publish(
-        RESPONSE_EXCHANGE,
-        QUEUE_JOB_PROGRESSION,
-        BasicPublishOptions::default(),
-        msg.as_bytes().to_vec(),
-        BasicProperties::default(),
-      )
-      .wait()
-      .map_err(|e| {
-        let result = JobResult::new(job_id)
-          .with_status(JobStatus::Error)
-          .with_message(&e.to_string());
-        MessageError::ProcessingError(result)
-      })
-      .map(|_| ())
+    publish_response(&channel, QUEUE_JOB_PARTIAL_RESULT, &msg, Some(job_id)).map_err(|e| {
+      let result = JobResult::new(job_id)
+        .with_status(JobStatus::Error)
+        .with_message(&e);
+      MessageError::ProcessingError(result)
+    })
+  } else {
+    info!(target: &job_id.to_string(), "partial result: {}", msg);
+    Ok(())
+  }
+}
+
+/// Function to publish a progression event with a checkpoint of the job
+///
+/// The checkpoint is saved in the job context, to be included in the order if it is requeued.
+/// It is also sent with the progression, to be stored by the backend for the next deliveries of the order.
+pub fn publish_job_checkpoint<T: Serialize>(
+  channel: Option<McaiChannel>,
+  context: &JobContext,
+  progression: u8,
+  checkpoint: &T,
+) -> Result<()> {
+  context.set_checkpoint(checkpoint)?;
+  let checkpoint = context.get_checkpoint_value().unwrap_or_default();
+  publish_progression(
+    channel,
+    JobProgression::new(context.get_job_id(), progression).with_checkpoint(checkpoint),
+  )
+}
+
+/// Every progression beats the watchdog of the job, then is published or logged without channel
+fn publish_progression(
+  channel: Option<McaiChannel>,
+  job_progression: JobProgression,
+) -> Result<()> {
+  let job_id = job_progression.get_job_id();
+  let progression = job_progression.get_progression();
+  watchdog::beat_progression(job_id, progression);
+  let msg = json!(job_progression).to_string();
+
+  if let Some(channel) = channel {
+    publish_response(&channel, QUEUE_JOB_PROGRESSION, &msg, Some(job_id)).map_err(|e| {
+      let result = JobResult::new(job_id)
+        .with_status(JobStatus::Error)
+        .with_message(&e);
+      MessageError::ProcessingError(result)
+    })
   } else {
     info!(target: &job_id.to_string(), "progression: {}%", progression);
+    debug!(target: &job_id.to_string(), "progression: {}", msg);
     Ok(())
   }
 }
@@ -161,9 +804,163 @@ fn publish_missing_requirements(
   details: &str,
 ) -> Promise<()> {
   debug!("{}", details);
+
+  // publishing the order again moves it behind the other orders of the queue,
+  // it is delayed not to be delivered again at once if its requirements are never met
+  if get_requirements_requeue_policy() == "back_of_queue" {
+    let headers = message.properties.headers().clone().unwrap_or_default();
+    let delay = get_requirements_requeue_delay();
+    match republish_order(&channel, &message, headers, None, delay) {
+      Ok(()) => {
+        return channel.basic_ack(
+          message.delivery_tag,
+          BasicAckOptions::default(), /*not requeue*/
+        );
+      }
+      Err(error) => warn!(
+        "Unable to publish the order to the back of the queue: {}",
+        error
+      ),
+    }
+  }
+
   channel.basic_reject(message.delivery_tag, BasicRejectOptions::default())
 }
 
+/// Requeue the order after a delay, to be processed once the host is no longer saturated
+/// or once its disk has the free space required by the order
+fn requeue_saturated_order(channel: McaiChannel, message: Delivery, reason: &str) -> Promise<()> {
+  let delay = get_admission_requeue_delay();
+  warn!("Host saturated, order requeued in {} ms: {}", delay, reason);
+  requeue_order_later(channel, message, delay)
+}
+
+/// Requeue the order after a delay, to be processed by a worker of a compatible version,
+/// without blocking the consumer meanwhile
+fn requeue_incompatible_order(
+  channel: McaiChannel,
+  message: Delivery,
+  reason: &str,
+) -> Promise<()> {
+  let delay = get_admission_requeue_delay();
+  warn!(
+    "Incompatible worker, order requeued in {} ms: {}",
+    delay, reason
+  );
+  requeue_order_later(channel, message, delay)
+}
+
+/// Publish the order again through a delay queue, the consumer receives the next orders meanwhile
+fn requeue_order_later(channel: McaiChannel, message: Delivery, delay: u64) -> Promise<()> {
+  let headers = message.properties.headers().clone().unwrap_or_default();
+  match republish_order(&channel, &message, headers, None, delay) {
+    Ok(()) => channel.basic_ack(
+      message.delivery_tag,
+      BasicAckOptions::default(), /*not requeue*/
+    ),
+    Err(error) => {
+      warn!("Unable to delay the order, requeued at once: {}", error);
+      channel.basic_reject(
+        message.delivery_tag,
+        BasicRejectOptions { requeue: true }, /*requeue*/
+      )
+    }
+  }
+}
+
+fn publish_invalid_signature(
+  channel: McaiChannel,
+  message: Delivery,
+  details: &str,
+) -> Promise<()> {
+  error!("Order signature verification failed: {}", details);
+  channel.basic_reject(message.delivery_tag, BasicRejectOptions::default())
+}
+
+fn publish_timeout_error(
+  channel: McaiChannel,
+  message: Delivery,
+  job_id: u64,
+  timeout: u64,
+  checkpoint: Option<Value>,
+) -> Promise<()> {
+  error!(target: &job_id.to_string(), "Job timed out after {} seconds, it is abandoned", timeout);
+
+  let job_result = JobResult::new(job_id)
+    .with_status(JobStatus::Error)
+    .with_message(&format!("Job timed out after {} seconds", timeout));
+  let job_result = job_result
+    .clone()
+    .with_json("error_code", &"timeout".to_string())
+    .unwrap_or(job_result);
+
+  let content = json!(job_result).to_string();
+
+  if publish_response(&channel, QUEUE_JOB_ERROR, &content, Some(job_id)).is_err() {
+    return channel.basic_reject(
+      message.delivery_tag,
+      BasicRejectOptions { requeue: true }, /*requeue*/
+    );
+  }
+
+  match get_job_timeout_policy().as_str() {
+    "requeue" => {
+      // an order always timing out is not delivered forever
+      let timeout_count = helpers::get_message_timeout_count(&message);
+      if timeout_count >= get_job_timeout_max_requeues() as i64 {
+        warn!(target: &job_id.to_string(), "Job timed out {} times, the order is dead-lettered", timeout_count + 1);
+        return channel.basic_reject(
+          message.delivery_tag,
+          BasicRejectOptions { requeue: false }, /*dead letter*/
+        );
+      }
+
+      let headers =
+        helpers::set_timeout_count_in_header(message.properties.headers(), timeout_count + 1);
+      if republish_order(&channel, &message, headers, checkpoint.as_ref(), 0).is_ok() {
+        channel.basic_ack(
+          message.delivery_tag,
+          BasicAckOptions::default(), /*not requeue*/
+        )
+      } else {
+        channel.basic_reject(
+          message.delivery_tag,
+          BasicRejectOptions { requeue: true }, /*requeue*/
+        )
+      }
+    }
+    "dead_letter" => channel.basic_reject(
+      message.delivery_tag,
+      BasicRejectOptions { requeue: false }, /*dead letter*/
+    ),
+    _ => channel.basic_ack(
+      message.delivery_tag,
+      BasicAckOptions::default(), /*not requeue*/
+    ),
+  }
+}
+
+/// The order is left unacknowledged, the job may still complete
+pub(crate) fn publish_stuck_job_error(channel: &McaiChannel, diagnostic: &StuckJobDiagnostic) {
+  let job_result = JobResult::new(diagnostic.job_id)
+    .with_status(JobStatus::Error)
+    .with_message(&format!(
+      "Job stuck, no heartbeat for {:.0} seconds",
+      diagnostic.idle_duration
+    ));
+  let job_result = job_result
+    .clone()
+    .with_json("error_code", &"stuck".to_string())
+    .and_then(|job_result| job_result.with_json("diagnostic", diagnostic))
+    .unwrap_or(job_result);
+
+  let content = json!(job_result).to_string();
+  if let Err(error) = publish_response(channel, QUEUE_JOB_ERROR, &content, Some(diagnostic.job_id))
+  {
+    error!(target: &diagnostic.job_id.to_string(), "Unable to publish the stuck job error: {}", error);
+  }
+}
+
 fn publish_not_implemented(channel: McaiChannel, message: Delivery) -> Promise<()> {
   error!("Not implemented feature");
   channel.basic_reject(
@@ -177,6 +974,13 @@ fn publish_parameter_error(channel: McaiChannel, message: Delivery, details: &st
   channel.basic_reject(message.delivery_tag, BasicRejectOptions::default())
 }
 
+fn get_processing_error_content(job_result: &JobResult) -> String {
+  json!(JobResult::new(job_result.get_job_id())
+    .with_status(JobStatus::Error)
+    .with_parameters(&mut job_result.get_parameters().clone()))
+  .to_string()
+}
+
 fn publish_processing_error(
   channel: McaiChannel,
   message: Delivery,
@@ -184,22 +988,57 @@ fn publish_processing_error(
 ) -> Promise<()> {
   error!(target: &job_result.get_str_job_id(), "Job returned in error: {:?}", job_result.get_parameters());
 
-  let content = json!(JobResult::new(job_result.get_job_id())
-    .with_status(JobStatus::Error)
-    .with_parameters(&mut job_result.get_parameters().clone()))
-  .to_string();
+  let content = get_processing_error_content(&job_result);
 
-  if channel
-    .basic_publish(
-      RESPONSE_EXCHANGE,
-      QUEUE_JOB_ERROR,
-      BasicPublishOptions::default(),
-      content.as_bytes().to_vec(),
-      BasicProperties::default(),
+  let job_id = Some(job_result.get_job_id());
+  if publish_response(&channel, QUEUE_JOB_ERROR, &content, job_id).is_ok() {
+    channel.basic_ack(
+      message.delivery_tag,
+      BasicAckOptions::default(), /*not requeue*/
     )
-    .wait()
-    .is_ok()
-  {
+  } else {
+    channel.basic_reject(
+      message.delivery_tag,
+      BasicRejectOptions { requeue: true }, /*requeue*/
+    )
+  }
+}
+
+fn publish_transient_error(
+  channel: McaiChannel,
+  message: Delivery,
+  job_result: JobResult,
+) -> Promise<()> {
+  let retry_count = helpers::get_message_retry_count(&message);
+  let max_retries = get_transient_max_retries() as i64;
+
+  if retry_count >= max_retries {
+    let job_result = job_result
+      .clone()
+      .with_json("retry_count", &retry_count)
+      .unwrap_or(job_result);
+    return publish_processing_error(channel, message, job_result);
+  }
+
+  let delay = get_retry_delay(retry_count);
+  warn!(target: &job_result.get_str_job_id(),
+        "Transient error, retry {}/{} in {} ms: {:?}",
+        retry_count + 1,
+        max_retries,
+        delay,
+        job_result.get_parameters());
+
+  // the order waits for its retry in a delay queue, the other orders are still consumed
+  let headers = helpers::set_retry_count_in_header(message.properties.headers(), retry_count + 1);
+  let republished = republish_order(
+    &channel,
+    &message,
+    headers,
+    job_result.get_checkpoint(),
+    delay,
+  );
+
+  if republished.is_ok() {
     channel.basic_ack(
       message.delivery_tag,
       BasicAckOptions::default(), /*not requeue*/
@@ -212,6 +1051,72 @@ fn publish_processing_error(
   }
 }
 
+/// Publish the order again with the given headers, including the checkpoint to resume from,
+/// once the delay in milliseconds has elapsed
+fn republish_order(
+  channel: &McaiChannel,
+  message: &Delivery,
+  headers: FieldTable,
+  checkpoint: Option<&Value>,
+  delay: u64,
+) -> std::result::Result<(), String> {
+  let data = match checkpoint {
+    Some(checkpoint) => get_order_with_checkpoint(&message.data, checkpoint),
+    None => message.data.clone(),
+  };
+  let headers = security::sign_order(headers, &data)?;
+
+  let properties = get_order_properties(message.properties.clone(), &data).with_headers(headers);
+
+  publish_delayed(
+    channel,
+    message.exchange.as_str(),
+    message.routing_key.as_str(),
+    data,
+    properties,
+    delay,
+  )
+}
+
+/// The order keeps its AMQP priority, or takes the priority of the job
+fn get_order_properties(properties: BasicProperties, data: &[u8]) -> BasicProperties {
+  if properties.priority().is_some() {
+    return properties;
+  }
+
+  match get_order_priority(data) {
+    Some(priority) => properties.with_priority(priority),
+    None => properties,
+  }
+}
+
+/// Priority of the job, within the maximum priority of the job queues
+fn get_order_priority(data: &[u8]) -> Option<u8> {
+  std::str::from_utf8(data)
+    .ok()
+    .and_then(|message_data| Job::new(message_data).ok())
+    .and_then(|job| job.priority)
+    .map(|priority| priority.clamp(0, MAX_ORDER_PRIORITY) as u8)
+}
+
+/// Batches are published again unchanged
+fn get_order_with_checkpoint(data: &[u8], checkpoint: &Value) -> Vec<u8> {
+  std::str::from_utf8(data)
+    .ok()
+    .and_then(|message_data| Job::new(message_data).ok())
+    .map(|job| {
+      json!(job.with_checkpoint(checkpoint))
+        .to_string()
+        .into_bytes()
+    })
+    .unwrap_or_else(|| data.to_vec())
+}
+
+/// Exponential backoff in milliseconds before the next retry
+fn get_retry_delay(retry_count: i64) -> u64 {
+  get_transient_retry_delay().saturating_mul(1 << retry_count.clamp(0, 16))
+}
+
 fn publish_runtime_error(channel: McaiChannel, message: Delivery, details: &str) -> Promise<()> {
   error!("An error occurred: {:?}", details);
   let content = json!({
@@ -220,17 +1125,7 @@ fn publish_runtime_error(channel: McaiChannel, message: Delivery, details: &str)
   })
   .to_string();
 
-  if channel
-    .basic_publish(
-      RESPONSE_EXCHANGE,
-      QUEUE_JOB_ERROR,
-      BasicPublishOptions::default(),
-      content.as_bytes().to_vec(),
-      BasicProperties::default(),
-    )
-    .wait()
-    .is_ok()
-  {
+  if publish_response(&channel, QUEUE_JOB_ERROR, &content, None).is_ok() {
     channel.basic_ack(
       message.delivery_tag,
       BasicAckOptions::default(), /*not requeue*/
@@ -242,3 +1137,263 @@ fn publish_runtime_error(channel: McaiChannel, message: Delivery, details: &str)
     )
   }
 }
+
+#[test]
+fn order_timeout() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"sdk_timeout",
+        "type":"integer",
+        "value": 60 }
+    ]
+  }"#;
+  assert_eq!(Some((123, 60)), get_order_timeout(message).unwrap());
+
+  assert_eq!(None, get_order_timeout("{}").unwrap());
+
+  for timeout in &[0, -60] {
+    let message = json!({
+      "job_id": 123,
+      "parameters": [{ "id": "sdk_timeout", "type": "integer", "value": timeout }]
+    })
+    .to_string();
+    assert!(matches!(
+      get_order_timeout(&message),
+      Err(MessageError::ParameterValueError(_))
+    ));
+  }
+}
+
+#[test]
+fn transient_retry_delay() {
+  assert_eq!(1000, get_retry_delay(0));
+  assert_eq!(2000, get_retry_delay(1));
+  assert_eq!(8000, get_retry_delay(3));
+  assert_eq!(1000, get_retry_delay(-1));
+  assert_eq!(65_536_000, get_retry_delay(40));
+}
+
+#[test]
+fn order_with_checkpoint() {
+  let message = br#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"sdk_checkpoint",
+        "type":"string",
+        "value": "{\"segment\":1}" }
+    ]
+  }"#;
+  let job = Job::new(std::str::from_utf8(message).unwrap()).unwrap();
+  assert_eq!(Some(json!({"segment": 1})), job.get_checkpoint());
+
+  let order = get_order_with_checkpoint(message, &json!({"segment": 2}));
+  let job = Job::new(std::str::from_utf8(&order).unwrap()).unwrap();
+  assert_eq!(1, job.parameters.len());
+  assert_eq!(Some(json!({"segment": 2})), job.get_checkpoint());
+
+  let batch = br#"{"batch_id": 1, "jobs": []}"#;
+  assert_eq!(
+    batch.to_vec(),
+    get_order_with_checkpoint(batch, &json!({"segment": 2}))
+  );
+}
+
+#[test]
+fn error_message() {
+  let job_result = JobResult::new(123)
+    .with_status(JobStatus::Error)
+    .with_message("Source not found");
+  assert_eq!(
+    "Source not found",
+    get_error_message(&MessageError::ProcessingError(job_result))
+  );
+  assert_eq!(
+    "Job returned in error",
+    get_error_message(&MessageError::Transient(JobResult::new(123)))
+  );
+  assert_eq!(
+    "Missing parameter",
+    get_error_message(&MessageError::ParameterValueError(
+      "Missing parameter".to_string()
+    ))
+  );
+}
+
+#[test]
+fn job_logs_in_result() {
+  use crate::logger::JobLogRecord;
+  use chrono::Utc;
+
+  let logs = || JobLogs {
+    job_id: 123,
+    records: vec![JobLogRecord {
+      datetime: Utc::now(),
+      level: "INFO".to_string(),
+      message: "Start processing".to_string(),
+    }]
+    .into(),
+    dropped_records: 0,
+  };
+
+  let job_result = send_job_logs(None, Some(logs()), Ok(JobResult::new(123))).unwrap();
+  let records = job_result.get_parameter::<Value>(LOGS_PARAMETER).unwrap();
+  assert_eq!(json!("Start processing"), records[0]["message"]);
+
+  let error = send_job_logs(
+    None,
+    Some(logs()),
+    Err(MessageError::ProcessingError(JobResult::new(123))),
+  );
+  match error {
+    Err(MessageError::ProcessingError(job_result)) => {
+      assert!(job_result.get_parameter::<Value>(LOGS_PARAMETER).is_ok())
+    }
+    _ => panic!("the job should be in error"),
+  }
+
+  let job_result = send_job_logs(None, None, Ok(JobResult::new(123))).unwrap();
+  assert!(job_result.get_parameter::<Value>(LOGS_PARAMETER).is_err());
+}
+
+#[test]
+fn contended_claim() {
+  #[derive(JsonSchema, Deserialize)]
+  struct CustomParameters {}
+
+  struct LockedEvent {}
+
+  impl MessageEvent<CustomParameters> for LockedEvent {
+    fn get_name(&self) -> String {
+      "locked".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+    fn claim_job(&self, job: &Job) -> Result<JobClaim> {
+      Ok(JobClaim::Contended(format!("job {} is locked", job.job_id)))
+    }
+  }
+
+  let message_event = Arc::new(RwLock::new(LockedEvent {}));
+
+  let job = r#"{"job_id": 123, "parameters": []}"#;
+  match claim_job(&message_event, job) {
+    Ok(JobClaim::Contended(reason)) => assert_eq!("job 123 is locked", reason),
+    _ => panic!("the claim of the job must be contended"),
+  }
+
+  // the batches are not claimed
+  let batch = r#"{"batch_id": 1, "jobs": []}"#;
+  assert!(matches!(
+    claim_job(&message_event, batch),
+    Ok(JobClaim::Acquired(_))
+  ));
+}
+
+#[test]
+fn secrets_masked_until_published() {
+  use crate::parameter::secret::{register_secret_parameters, Secret};
+
+  #[derive(JsonSchema, Deserialize)]
+  struct SecretParameters {
+    #[allow(dead_code)]
+    api_token: Secret<String>,
+  }
+
+  struct FailingEvent {}
+
+  impl MessageEvent<SecretParameters> for FailingEvent {
+    fn get_name(&self) -> String {
+      "failing".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+    fn process(
+      &self,
+      _channel: Option<McaiChannel>,
+      parameters: SecretParameters,
+      job_result: JobResult,
+      _context: JobContext,
+    ) -> Result<JobResult> {
+      Err(MessageError::ProcessingError(
+        job_result
+          .with_status(JobStatus::Error)
+          .with_message(&format!("Unauthorized token {}", *parameters.api_token)),
+      ))
+    }
+  }
+
+  register_secret_parameters(&parameter_schema_for::<SecretParameters>());
+  let message_data = r#"{"job_id": 4101, "parameters": [
+    {"id": "api_token", "type": "string", "value": "abc"}
+  ]}"#;
+
+  let secrets: Vec<JobSecrets> = get_order_jobs(message_data)
+    .iter()
+    .map(JobSecrets::new)
+    .collect();
+  let result = parse_and_process_message(
+    Arc::new(RwLock::new(FailingEvent {})),
+    message_data,
+    None,
+    None,
+    &RunningJobs::default(),
+    publish_job_progression,
+  );
+  let job_result = match result {
+    Err(MessageError::ProcessingError(job_result)) => job_result,
+    _ => panic!("the job must fail"),
+  };
+
+  // the error is published while the secrets of the order are still masked
+  let content = mask_secrets(&get_processing_error_content(&job_result));
+  assert!(content.contains("Unauthorized token ***"));
+  assert!(!content.contains("abc"));
+  let error = MessageError::ProcessingError(job_result.clone());
+  assert!(get_error_message(&error).contains("Unauthorized token ***"));
+
+  drop(secrets);
+  assert!(get_processing_error_content(&job_result).contains("Unauthorized token abc"));
+}
+
+#[test]
+fn order_priority() {
+  let order = br#"{"job_id": 123, "priority": 5, "parameters": []}"#;
+  assert_eq!(Some(5), get_order_priority(order));
+  assert_eq!(
+    &Some(5),
+    get_order_properties(BasicProperties::default(), order).priority()
+  );
+  // the AMQP priority of the order is kept
+  assert_eq!(
+    &Some(8),
+    get_order_properties(BasicProperties::default().with_priority(8), order).priority()
+  );
+
+  let order = br#"{"job_id": 123, "priority": 500, "parameters": []}"#;
+  assert_eq!(Some(100), get_order_priority(order));
+  let order = br#"{"job_id": 123, "priority": -2, "parameters": []}"#;
+  assert_eq!(Some(0), get_order_priority(order));
+
+  let order = br#"{"job_id": 123, "parameters": []}"#;
+  assert_eq!(None, get_order_priority(order));
+  assert_eq!(
+    &None,
+    get_order_properties(BasicProperties::default(), order).priority()
+  );
+  assert_eq!(None, get_order_priority(br#"{"batch_id": 1, "jobs": []}"#));
+}
@@ -0,0 +1,69 @@
+use crate::{
+  job::{JobResult, JobStatus},
+  MessageError, Result,
+};
+use std::{
+  any::Any,
+  backtrace::Backtrace,
+  cell::RefCell,
+  panic::{self, AssertUnwindSafe},
+  sync::{Once, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+  static PANIC_BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run the worker code, converting a panic into a processing error of the job
+pub fn catch_panic<T, F: FnOnce() -> Result<T>>(job_id: u64, function: F) -> Result<T> {
+  PANIC_HOOK.call_once(|| {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+      let backtrace = Backtrace::force_capture().to_string();
+      PANIC_BACKTRACE.with(|panic_backtrace| *panic_backtrace.borrow_mut() = Some(backtrace));
+      default_hook(panic_info);
+    }));
+  });
+
+  panic::catch_unwind(AssertUnwindSafe(function)).unwrap_or_else(|payload| {
+    let message = get_panic_message(payload.as_ref());
+    let backtrace = PANIC_BACKTRACE
+      .with(|panic_backtrace| panic_backtrace.borrow_mut().take())
+      .unwrap_or_default();
+
+    error!(target: &job_id.to_string(), "Worker panicked: {}", message);
+
+    let job_result = JobResult::new(job_id)
+      .with_status(JobStatus::Error)
+      .with_message(&format!("Worker panicked: {}", message));
+    let job_result = job_result
+      .clone()
+      .with_json("backtrace", &backtrace)
+      .unwrap_or(job_result);
+
+    Err(MessageError::ProcessingError(job_result))
+  })
+}
+
+/// The worker remains usable by the next jobs after a panic in one of its handlers
+pub(crate) fn read_message_event<T>(message_event: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+  message_event.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) fn write_message_event<T>(message_event: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+  message_event
+    .write()
+    .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn get_panic_message(payload: &(dyn Any + Send)) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "unknown panic payload".to_string()
+  }
+}
@@ -0,0 +1,174 @@
+//! Scheduling of the orders with a dedicated slot for the re-runs
+//!
+//! The orders are prefetched ahead of the processing slots, the re-runs found among them
+//! are processed before the other orders, in a dedicated slot when all the slots are busy.
+
+use crate::job::{add_pending_orders, Job, RunningJobs};
+use crate::parameter::container::ParametersContainer;
+use crate::worker::readiness::WorkerReadiness;
+use crate::{McaiChannel, MessageEvent};
+use lapin::{message::Delivery, options::BasicRejectOptions};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+
+/// Job parameter marking an order as a manual re-run of a job (boolean)
+pub const RERUN_PARAMETER: &str = "sdk_rerun";
+
+#[derive(Debug, Default)]
+struct PendingOrders {
+  reruns: VecDeque<Delivery>,
+  orders: VecDeque<Delivery>,
+  stopped: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OrderScheduler {
+  pending: Arc<(Mutex<PendingOrders>, Condvar)>,
+}
+
+impl OrderScheduler {
+  /// Start the processing slots, plus the dedicated slot of the re-runs if enabled
+  pub fn start<
+    P: 'static + DeserializeOwned + JsonSchema,
+    ME: 'static + MessageEvent<P> + Send + Sync,
+  >(
+    message_event: Arc<RwLock<ME>>,
+    channel: McaiChannel,
+    running_jobs: RunningJobs,
+    readiness: WorkerReadiness,
+    slots: u16,
+    rerun_slot: bool,
+  ) -> Self {
+    let scheduler = OrderScheduler::default();
+    let slots = if rerun_slot { slots + 1 } else { slots };
+
+    for slot in 0..slots {
+      // the last slot only processes the re-runs
+      let reruns_only = rerun_slot && slot == slots - 1;
+      let scheduler = scheduler.clone();
+      let message_event = message_event.clone();
+      let channel = channel.clone();
+      let running_jobs = running_jobs.clone();
+      let readiness = readiness.clone();
+
+      crate::runtime::spawn_blocking(move || {
+        while let Some(delivery) = scheduler.next(reruns_only) {
+          if readiness.is_draining() || readiness.is_waiting() {
+            // let another worker process the prefetched order
+            if let Err(error) = channel
+              .basic_reject(delivery.delivery_tag, BasicRejectOptions { requeue: true })
+              .wait()
+            {
+              error!("Unable to requeue the order: {:?}", error);
+            }
+            continue;
+          }
+
+          if let Err(error) = crate::message::process_message(
+            message_event.clone(),
+            delivery,
+            channel.clone(),
+            running_jobs.clone(),
+          )
+          .wait()
+          {
+            error!("Unable to respond to the order: {:?}", error);
+          }
+        }
+      });
+    }
+
+    scheduler
+  }
+
+  pub fn push(&self, delivery: Delivery) {
+    let (pending, condition) = &*self.pending;
+    let mut pending = pending.lock().unwrap();
+
+    if is_rerun(&delivery.data) {
+      info!("Re-run order received, it is processed ahead of the other orders");
+      pending.reruns.push_back(delivery);
+    } else {
+      pending.orders.push_back(delivery);
+    }
+    add_pending_orders(1);
+    condition.notify_all();
+  }
+
+  /// Stop the slots once their job is processed, the pending orders are delivered again by the broker
+  pub fn stop(&self) {
+    let (pending, condition) = &*self.pending;
+    let mut pending = pending.lock().unwrap();
+    pending.stopped = true;
+    add_pending_orders(-((pending.reruns.len() + pending.orders.len()) as isize));
+    pending.reruns.clear();
+    pending.orders.clear();
+    condition.notify_all();
+  }
+
+  /// Block until an order is available for the slot, the re-runs first
+  fn next(&self, reruns_only: bool) -> Option<Delivery> {
+    let (pending, condition) = &*self.pending;
+    let mut pending = condition
+      .wait_while(pending.lock().unwrap(), |pending| {
+        !pending.stopped && pending.reruns.is_empty() && (reruns_only || pending.orders.is_empty())
+      })
+      .unwrap();
+
+    if pending.stopped {
+      return None;
+    }
+
+    let delivery = match pending.reruns.pop_front() {
+      Some(delivery) => Some(delivery),
+      None if reruns_only => None,
+      None => pending.orders.pop_front(),
+    };
+    if delivery.is_some() {
+      add_pending_orders(-1);
+    }
+    delivery
+  }
+}
+
+/// Stop the scheduler when dropped, with the consumer of its orders
+pub struct SchedulerGuard(pub OrderScheduler);
+
+impl Drop for SchedulerGuard {
+  fn drop(&mut self) {
+    self.0.stop();
+  }
+}
+
+fn is_rerun(data: &[u8]) -> bool {
+  std::str::from_utf8(data)
+    .ok()
+    .and_then(|message_data| Job::new(message_data).ok())
+    .and_then(|job| job.get_parameter::<bool>(RERUN_PARAMETER).ok())
+    .unwrap_or(false)
+}
+
+#[test]
+pub fn test_is_rerun() {
+  let order = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id": "sdk_rerun", "type": "boolean", "value": true }
+    ]
+  }"#;
+  assert!(is_rerun(order.as_bytes()));
+
+  let order = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id": "sdk_rerun", "type": "boolean", "value": false }
+    ]
+  }"#;
+  assert!(!is_rerun(order.as_bytes()));
+
+  let order = r#"{"job_id": 123, "parameters": []}"#;
+  assert!(!is_rerun(order.as_bytes()));
+  assert!(!is_rerun(b"not an order"));
+}
@@ -0,0 +1,128 @@
+use crate::config::{get_order_signature_key, get_response_encryption_key};
+use aes_gcm::{
+  aead::{Aead, KeyInit, OsRng},
+  AeadCore, Aes256Gcm, Key,
+};
+use amq_protocol_types::{AMQPValue, FieldTable};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub static SIGNATURE_HEADER: &str = "x-signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Verify the HMAC-SHA256 signature of an order, if a signature key is configured.
+pub fn verify_signature(headers: &Option<FieldTable>, data: &[u8]) -> Result<(), String> {
+  if let Some(key) = get_order_signature_key() {
+    check_signature(&key, headers, data)
+  } else {
+    Ok(())
+  }
+}
+
+/// Sign an order published again by the worker, if a signature key is configured
+pub fn sign_order(mut headers: FieldTable, data: &[u8]) -> Result<FieldTable, String> {
+  if let Some(key) = get_order_signature_key() {
+    headers.insert(
+      SIGNATURE_HEADER.into(),
+      AMQPValue::LongString(sign(&key, data)?.into()),
+    );
+  }
+  Ok(headers)
+}
+
+/// Encrypt a response payload with AES-256-GCM, if an encryption key is configured.
+///
+/// The encrypted payload is the base64 encoding of the nonce followed by the cipher text.
+pub fn encode_response(content: &str) -> Result<Vec<u8>, String> {
+  if let Some(key) = get_response_encryption_key() {
+    encrypt(&key, content)
+  } else {
+    Ok(content.as_bytes().to_vec())
+  }
+}
+
+fn check_signature(key: &str, headers: &Option<FieldTable>, data: &[u8]) -> Result<(), String> {
+  let signature = headers
+    .as_ref()
+    .and_then(|headers| headers.inner().get(SIGNATURE_HEADER))
+    .and_then(|value| match value {
+      AMQPValue::LongString(signature) => Some(signature.to_string()),
+      _ => None,
+    })
+    .ok_or_else(|| format!("Missing '{}' header", SIGNATURE_HEADER))?;
+
+  let signature = hex::decode(signature).map_err(|error| format!("{:?}", error))?;
+
+  let mut mac =
+    <HmacSha256 as Mac>::new_from_slice(key.as_bytes()).map_err(|error| error.to_string())?;
+  mac.update(data);
+  mac
+    .verify_slice(&signature)
+    .map_err(|_| "Invalid order signature".to_string())
+}
+
+fn sign(key: &str, data: &[u8]) -> Result<String, String> {
+  let mut mac =
+    <HmacSha256 as Mac>::new_from_slice(key.as_bytes()).map_err(|error| error.to_string())?;
+  mac.update(data);
+  Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+fn encrypt(key: &str, content: &str) -> Result<Vec<u8>, String> {
+  let key = hex::decode(key).map_err(|error| format!("Invalid encryption key: {:?}", error))?;
+  if key.len() != 32 {
+    return Err(format!(
+      "Invalid encryption key: expected 32 bytes, got {}",
+      key.len()
+    ));
+  }
+
+  let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+  let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+  let mut encrypted = cipher
+    .encrypt(&nonce, content.as_bytes())
+    .map_err(|error| format!("Unable to encrypt response: {:?}", error))?;
+
+  let mut payload = nonce.to_vec();
+  payload.append(&mut encrypted);
+  Ok(base64::encode(payload).into_bytes())
+}
+
+#[test]
+fn order_signature() {
+  use std::collections::BTreeMap;
+
+  let key = "secret";
+  let data = br#"{"job_id": 123, "parameters": []}"#;
+
+  let signature = sign(key, data).unwrap();
+
+  let mut headers = FieldTable::from(BTreeMap::new());
+  headers.insert(
+    SIGNATURE_HEADER.into(),
+    AMQPValue::LongString(signature.into()),
+  );
+  let headers = Some(headers);
+
+  assert!(check_signature(key, &headers, data).is_ok());
+  assert!(check_signature("other", &headers, data).is_err());
+  assert!(check_signature(key, &headers, b"{}").is_err());
+  assert!(check_signature(key, &None, data).is_err());
+}
+
+#[test]
+fn response_encryption() {
+  let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+  let content = r#"{"job_id": 123, "status": "completed"}"#;
+
+  let encrypted = encrypt(key, content).unwrap();
+  let payload = base64::decode(encrypted).unwrap();
+
+  let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&hex::decode(key).unwrap()));
+  let (nonce, cipher_text) = payload.split_at(12);
+  let decrypted = cipher.decrypt(nonce.into(), cipher_text).unwrap();
+  assert_eq!(content.as_bytes(), decrypted.as_slice());
+
+  assert!(encrypt("00", content).is_err());
+}
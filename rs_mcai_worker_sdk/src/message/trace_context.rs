@@ -0,0 +1,140 @@
+//! Propagation of the distributed tracing context of the orders
+//!
+//! An order can carry a W3C `traceparent` in its `trace_context` field. While the order is processed,
+//! the responses published for its job carry it in their `traceparent` header,
+//! and the worker gets it from `JobContext::get_trace_context` to continue the trace in its own calls.
+
+use crate::job::Job;
+use amq_protocol_types::{AMQPValue, FieldTable};
+use lapin::BasicProperties;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Header of the published responses carrying the trace context of the job
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Trace contexts of the jobs in progress
+static TRACE_CONTEXTS: RwLock<BTreeMap<u64, String>> = RwLock::new(BTreeMap::new());
+
+/// Trace context of a job, registered until the order is processed
+pub(crate) struct JobTraceContext {
+  job_id: Option<u64>,
+}
+
+impl JobTraceContext {
+  pub(crate) fn new(job: Option<&Job>) -> Self {
+    let job = match job {
+      Some(job) => job,
+      None => return JobTraceContext { job_id: None },
+    };
+
+    match &job.trace_context {
+      Some(trace_context) if is_valid_traceparent(trace_context) => {
+        TRACE_CONTEXTS
+          .write()
+          .unwrap()
+          .insert(job.job_id, trace_context.clone());
+        JobTraceContext {
+          job_id: Some(job.job_id),
+        }
+      }
+      Some(trace_context) => {
+        warn!(target: &job.job_id.to_string(), "Invalid trace context ignored: {:?}", trace_context);
+        JobTraceContext { job_id: None }
+      }
+      None => JobTraceContext { job_id: None },
+    }
+  }
+}
+
+impl Drop for JobTraceContext {
+  fn drop(&mut self) {
+    if let Some(job_id) = self.job_id {
+      TRACE_CONTEXTS.write().unwrap().remove(&job_id);
+    }
+  }
+}
+
+pub(crate) fn get_trace_context(job_id: u64) -> Option<String> {
+  TRACE_CONTEXTS.read().unwrap().get(&job_id).cloned()
+}
+
+/// Properties of a response of the job, with its trace context if any
+pub(crate) fn get_response_properties(job_id: Option<u64>) -> BasicProperties {
+  match job_id.and_then(get_trace_context) {
+    Some(trace_context) => {
+      let mut headers = FieldTable::default();
+      headers.insert(
+        TRACEPARENT_HEADER.into(),
+        AMQPValue::LongString(trace_context.into()),
+      );
+      BasicProperties::default().with_headers(headers)
+    }
+    None => BasicProperties::default(),
+  }
+}
+
+/// `version-trace_id-parent_id-flags`, in lowercase hexadecimal
+fn is_valid_traceparent(traceparent: &str) -> bool {
+  let fields: Vec<&str> = traceparent.split('-').collect();
+  let is_hex = |field: &str, length: usize| {
+    field.len() == length
+      && field
+        .chars()
+        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+  };
+
+  fields.len() == 4
+    && is_hex(fields[0], 2)
+    && fields[0] != "ff"
+    && is_hex(fields[1], 32)
+    && fields[1].chars().any(|c| c != '0')
+    && is_hex(fields[2], 16)
+    && fields[2].chars().any(|c| c != '0')
+    && is_hex(fields[3], 2)
+}
+
+#[test]
+pub fn test_job_trace_context() {
+  let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+  let message = format!(
+    r#"{{"job_id": 9123, "trace_context": "{}", "parameters": []}}"#,
+    traceparent
+  );
+  let job = Job::new(&message).unwrap();
+
+  let job_trace_context = JobTraceContext::new(Some(&job));
+  assert_eq!(Some(traceparent.to_string()), get_trace_context(9123));
+
+  let properties = get_response_properties(Some(9123));
+  let header = properties
+    .headers()
+    .as_ref()
+    .and_then(|headers| headers.inner().get(TRACEPARENT_HEADER).cloned());
+  assert_eq!(
+    Some(AMQPValue::LongString(traceparent.to_string().into())),
+    header
+  );
+  assert!(get_response_properties(None).headers().is_none());
+
+  drop(job_trace_context);
+  assert_eq!(None, get_trace_context(9123));
+}
+
+#[test]
+pub fn test_is_valid_traceparent() {
+  assert!(is_valid_traceparent(
+    "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+  ));
+  assert!(!is_valid_traceparent(
+    "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"
+  ));
+  assert!(!is_valid_traceparent(
+    "00-00000000000000000000000000000000-00f067aa0ba902b7-01"
+  ));
+  assert!(!is_valid_traceparent(
+    "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+  ));
+  assert!(!is_valid_traceparent("00-4bf92f35-00f067aa0ba902b7-01"));
+  assert!(!is_valid_traceparent("not a traceparent"));
+}
@@ -0,0 +1,166 @@
+//! Byte size parameters
+//!
+//! A field declared as `ByteSize` accepts a number of bytes, or a string with a decimal (`kB`, `MB`, `GB`, `TB`)
+//! or binary (`KiB`, `MiB`, `GiB`, `TiB`) unit, like `"500MB"` or `"1.5 GiB"`. The units are case insensitive.
+//!
+//! It is serialized as a number of bytes.
+
+use schemars::{
+  gen::SchemaGenerator,
+  schema::{InstanceType, Schema, SchemaObject, StringValidation},
+  JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Pattern of the string values, checked by the validation of the parameters
+const BYTE_SIZE_PATTERN: &str = r"^\d+(\.\d+)?\s*([kKmMgGtTpP][iI]?)?[bB]?$";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+  pub fn new(bytes: u64) -> Self {
+    ByteSize(bytes)
+  }
+
+  pub fn as_u64(&self) -> u64 {
+    self.0
+  }
+}
+
+impl Deref for ByteSize {
+  type Target = u64;
+
+  fn deref(&self) -> &u64 {
+    &self.0
+  }
+}
+
+impl From<ByteSize> for u64 {
+  fn from(byte_size: ByteSize) -> Self {
+    byte_size.0
+  }
+}
+
+impl FromStr for ByteSize {
+  type Err = String;
+
+  fn from_str(string: &str) -> Result<Self, String> {
+    let invalid = || format!("Invalid byte size: {:?}", string);
+    let string = string.trim();
+
+    let number_end = string
+      .find(|c: char| !c.is_ascii_digit() && c != '.')
+      .unwrap_or(string.len());
+    let (number, unit) = string.split_at(number_end);
+
+    let factor: u64 = match unit.trim_start().to_lowercase().as_str() {
+      "" | "b" => 1,
+      "k" | "kb" => 1_000,
+      "m" | "mb" => 1_000_000,
+      "g" | "gb" => 1_000_000_000,
+      "t" | "tb" => 1_000_000_000_000,
+      "p" | "pb" => 1_000_000_000_000_000,
+      "ki" | "kib" => 1 << 10,
+      "mi" | "mib" => 1 << 20,
+      "gi" | "gib" => 1 << 30,
+      "ti" | "tib" => 1 << 40,
+      "pi" | "pib" => 1 << 50,
+      _ => return Err(invalid()),
+    };
+
+    let bytes = if number.contains('.') {
+      number
+        .parse::<f64>()
+        .ok()
+        .map(|number| number * factor as f64)
+        .filter(|bytes| *bytes <= u64::MAX as f64)
+        .map(|bytes| bytes.round() as u64)
+    } else {
+      number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(factor))
+    };
+
+    bytes.map(ByteSize).ok_or_else(invalid)
+  }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    match Value::deserialize(deserializer)? {
+      Value::Number(bytes) => bytes
+        .as_u64()
+        .map(ByteSize)
+        .ok_or_else(|| de::Error::custom(format!("Invalid byte size: {}", bytes))),
+      Value::String(string) => string.parse().map_err(de::Error::custom),
+      value => Err(de::Error::custom(format!("Invalid byte size: {}", value))),
+    }
+  }
+}
+
+/// Serialized as a number of bytes
+impl Serialize for ByteSize {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(self.0)
+  }
+}
+
+impl JsonSchema for ByteSize {
+  fn schema_name() -> String {
+    "ByteSize".to_string()
+  }
+
+  /// A number of bytes, or a string with a unit
+  fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+      instance_type: Some(vec![InstanceType::Integer, InstanceType::String].into()),
+      format: Some("byte_size".to_string()),
+      string: Some(Box::new(StringValidation {
+        pattern: Some(BYTE_SIZE_PATTERN.to_string()),
+        ..Default::default()
+      })),
+      ..Default::default()
+    }
+    .into()
+  }
+}
+
+#[test]
+pub fn test_parse_byte_size() {
+  assert_eq!(Ok(ByteSize(500_000_000)), "500MB".parse());
+  assert_eq!(Ok(ByteSize(500_000_000)), "500 mb".parse());
+  assert_eq!(Ok(ByteSize(1_610_612_736)), "1.5 GiB".parse());
+  assert_eq!(Ok(ByteSize(2048)), "2KiB".parse());
+  assert_eq!(Ok(ByteSize(1_000)), "1k".parse());
+  assert_eq!(Ok(ByteSize(42)), "42".parse());
+  assert_eq!(Ok(ByteSize(42)), "42B".parse());
+
+  assert!("".parse::<ByteSize>().is_err());
+  assert!("MB".parse::<ByteSize>().is_err());
+  assert!("500XB".parse::<ByteSize>().is_err());
+  assert!("-5MB".parse::<ByteSize>().is_err());
+  assert!("100000000PB".parse::<ByteSize>().is_err());
+}
+
+#[test]
+pub fn test_deserialize_byte_size() {
+  assert_eq!(ByteSize(1024), serde_json::from_value(json!(1024)).unwrap());
+  assert_eq!(
+    ByteSize(1024),
+    serde_json::from_value(json!("1KiB")).unwrap()
+  );
+  assert!(serde_json::from_value::<ByteSize>(json!(-1)).is_err());
+  assert!(serde_json::from_value::<ByteSize>(json!(1.5)).is_err());
+  assert_eq!(json!(1024), serde_json::to_value(ByteSize(1024)).unwrap());
+
+  let pattern = regex::Regex::new(BYTE_SIZE_PATTERN).unwrap();
+  for byte_size in &["500MB", "1.5 GiB", "2KiB", "42"] {
+    assert!(pattern.is_match(byte_size), "{}", byte_size);
+  }
+  assert!(!pattern.is_match("500XB"));
+}
@@ -0,0 +1,22 @@
+pub type Chapters = Vec<Chapter>;
+
+/// Chapter or marker of a media, with its boundaries in milliseconds
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Chapter {
+  pub id: i64,
+  pub start: u64,
+  pub end: u64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+}
+
+impl Chapter {
+  pub fn new(id: i64, start: u64, end: u64, title: Option<String>) -> Chapter {
+    Chapter {
+      id,
+      start,
+      end,
+      title,
+    }
+  }
+}
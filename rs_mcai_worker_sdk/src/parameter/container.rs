@@ -3,11 +3,17 @@ use crate::{
   MessageError, Result,
 };
 use serde::de::DeserializeOwned;
+use serde_json::Value;
 use std::collections::HashMap;
 
 pub trait ParametersContainer {
   fn get_parameters(&self) -> &Vec<Parameter>;
 
+  /// Value of a parameter before its parsing, as set in the container by default
+  fn resolve_value(&self, value: Value) -> Result<Value> {
+    Ok(value)
+  }
+
   fn get_parameter<T: DeserializeOwned>(&self, key: &str) -> Result<T>
   where
     T: ParameterValue,
@@ -15,9 +21,9 @@ pub trait ParametersContainer {
     for parameter in self.get_parameters() {
       if parameter.id == key && T::get_type_as_string() == parameter.kind {
         if let Some(value) = parameter.value.clone() {
-          return T::parse_value(value, &parameter.store);
+          return T::parse_value(self.resolve_value(value)?, &parameter.store);
         } else if let Some(default) = parameter.default.clone() {
-          return T::parse_value(default, &parameter.store);
+          return T::parse_value(self.resolve_value(default)?, &parameter.store);
         }
       }
     }
@@ -0,0 +1,258 @@
+//! Credential-backed fields of the parameter values
+//!
+//! A field declared as `Credential<T>` in a type deserialized from the parameters
+//! is resolved through the credential store before `process` is called:
+//!
+//! ```ignore
+//! #[derive(Debug, Deserialize, JsonSchema)]
+//! pub struct S3Configuration {
+//!   bucket: String,
+//!   access_key: Credential<String>,
+//!   secret_key: Credential<String>,
+//! }
+//! ```
+//!
+//! Within a parameter with a `store`, the value of the field is the key of the credential in this store.
+//! It can also reference a store explicitly, as `{"store": "BACKEND", "key": "S3_SECRET_KEY"}`.
+//! Without store, the value of the field is used as is.
+
+use super::store::request_value;
+use schemars::{
+  gen::SchemaGenerator,
+  schema::{InstanceType, Schema, SchemaObject},
+  JsonSchema,
+};
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+
+thread_local! {
+  /// Store of the parameter being deserialized
+  static STORE_SCOPE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run the deserialization with the store of the parameter, to resolve its credential fields
+pub(crate) fn with_store<T, F: FnOnce() -> T>(store: Option<&str>, function: F) -> T {
+  let previous = STORE_SCOPE.with(|scope| scope.replace(store.map(str::to_string)));
+  let result = function();
+  STORE_SCOPE.with(|scope| *scope.borrow_mut() = previous);
+  result
+}
+
+/// Value resolved from the credential store, hidden from the debug output
+#[derive(Clone, PartialEq)]
+pub struct Credential<T>(T);
+
+impl<T> Credential<T> {
+  pub fn new(value: T) -> Self {
+    Credential(value)
+  }
+
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> Deref for Credential<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T> fmt::Debug for Credential<T> {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("Credential(***)")
+  }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Credential<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    let scope = STORE_SCOPE.with(|scope| scope.borrow().clone());
+
+    let value = match (get_credential_reference(&value), value, scope) {
+      (Some((key, store_code)), _, _) => resolve(&key, &store_code)?,
+      (None, Value::String(key), Some(store_code)) => resolve(&key, &store_code)?,
+      (None, value, _) => value,
+    };
+
+    serde_json::from_value(value)
+      .map(Credential)
+      .map_err(de::Error::custom)
+  }
+}
+
+impl<T> JsonSchema for Credential<T> {
+  fn schema_name() -> String {
+    "Credential".to_string()
+  }
+
+  /// The key of the credential in the store of the parameter
+  fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+      instance_type: Some(InstanceType::String.into()),
+      format: Some("credential".to_string()),
+      ..Default::default()
+    }
+    .into()
+  }
+}
+
+/// Explicit reference to a credential, as `{"store": "<store>", "key": "<key>"}`
+fn get_credential_reference(value: &Value) -> Option<(String, String)> {
+  let object = value.as_object().filter(|object| object.len() == 2)?;
+  let key = object.get("key")?.as_str()?;
+  let store_code = object.get("store")?.as_str()?;
+  Some((key.to_string(), store_code.to_string()))
+}
+
+fn resolve<E: de::Error>(key: &str, store_code: &str) -> Result<Value, E> {
+  debug!(
+    "Retrieve credential value {} from store {}",
+    key, store_code
+  );
+  request_value(key, store_code)
+    .map_err(|error| E::custom(format!("Unable to retrieve credential {}: {}", key, error)))
+}
+
+/// Value of a parameter, deserialized with the store of the parameter in scope
+pub(crate) struct ScopedValue {
+  pub(crate) value: Value,
+  pub(crate) store: Option<String>,
+}
+
+impl ScopedValue {
+  fn scoped<T, F: FnOnce(Value) -> T>(self, function: F) -> T {
+    let value = self.value;
+    with_store(self.store.as_deref(), move || function(value))
+  }
+}
+
+macro_rules! forward_scoped {
+  ($($method: ident),*) => {
+    $(
+      fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.scoped(move |value| value.$method(visitor))
+      }
+    )*
+  };
+}
+
+impl<'de> Deserializer<'de> for ScopedValue {
+  type Error = serde_json::Error;
+
+  forward_scoped!(
+    deserialize_any,
+    deserialize_bool,
+    deserialize_i8,
+    deserialize_i16,
+    deserialize_i32,
+    deserialize_i64,
+    deserialize_u8,
+    deserialize_u16,
+    deserialize_u32,
+    deserialize_u64,
+    deserialize_f32,
+    deserialize_f64,
+    deserialize_char,
+    deserialize_str,
+    deserialize_string,
+    deserialize_bytes,
+    deserialize_byte_buf,
+    deserialize_option,
+    deserialize_unit,
+    deserialize_seq,
+    deserialize_map,
+    deserialize_identifier,
+    deserialize_ignored_any
+  );
+
+  fn deserialize_unit_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.scoped(move |value| value.deserialize_unit_struct(name, visitor))
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.scoped(move |value| value.deserialize_newtype_struct(name, visitor))
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(
+    self,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.scoped(move |value| value.deserialize_tuple(len, visitor))
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.scoped(move |value| value.deserialize_tuple_struct(name, len, visitor))
+  }
+
+  fn deserialize_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.scoped(move |value| value.deserialize_struct(name, fields, visitor))
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.scoped(move |value| value.deserialize_enum(name, variants, visitor))
+  }
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for ScopedValue {
+  type Deserializer = Self;
+
+  fn into_deserializer(self) -> Self {
+    self
+  }
+}
+
+#[test]
+pub fn test_credential() {
+  std::env::set_var("TEST_CREDENTIAL_FIELD", "secret");
+
+  let credential: Credential<String> = with_store(Some("env"), || {
+    serde_json::from_value(json!("TEST_CREDENTIAL_FIELD"))
+  })
+  .unwrap();
+  assert_eq!("secret", credential.as_str());
+  assert_eq!("Credential(***)", format!("{:?}", credential));
+
+  let credential: Credential<String> =
+    serde_json::from_value(json!({"store": "env", "key": "TEST_CREDENTIAL_FIELD"})).unwrap();
+  assert_eq!("secret", credential.into_inner());
+
+  let credential: Credential<String> =
+    serde_json::from_value(json!("TEST_CREDENTIAL_FIELD")).unwrap();
+  assert_eq!("TEST_CREDENTIAL_FIELD", *credential);
+
+  let missing: Result<Credential<String>, _> = with_store(Some("env"), || {
+    serde_json::from_value(json!("TEST_CREDENTIAL_FIELD_MISSING"))
+  });
+  assert!(missing.is_err());
+}
@@ -0,0 +1,123 @@
+//! Default values of the parameters declared in their schema
+//!
+//! A parameter missing from the order takes the `default` of its schema before deserialization,
+//! like with `#[schemars(default = "default_threshold")]`. The defaults of the fields
+//! of nested objects are applied to the objects set in the order.
+//! The defaults of the secret parameters are masked in the schema, they are not applied.
+
+use super::secret::is_secret_schema;
+use schemars::schema::{RootSchema, Schema, SchemaObject};
+use serde_json::{Map, Value};
+
+/// Fill the parameters missing from the order with the defaults of the schema
+pub(crate) fn apply_schema_defaults(schema: &RootSchema, parameters: &mut Map<String, Value>) {
+  apply_defaults(&schema.schema, parameters);
+}
+
+fn apply_defaults(schema: &SchemaObject, object: &mut Map<String, Value>) {
+  if let Some(validation) = &schema.object {
+    for (name, property) in &validation.properties {
+      if is_secret_schema(property) {
+        continue;
+      }
+      let property = match property {
+        Schema::Object(property) => property,
+        Schema::Bool(_) => continue,
+      };
+
+      match object.get_mut(name) {
+        Some(Value::Object(nested_object)) => {
+          for nested_schema in get_object_schemas(property) {
+            apply_defaults(nested_schema, nested_object);
+          }
+        }
+        Some(_) => {}
+        None => {
+          if let Some(default) = get_default(property) {
+            debug!("Set parameter {} to its default value {}", name, default);
+            object.insert(name.clone(), default.clone());
+          }
+        }
+      }
+    }
+  }
+
+  // fields of flattened structs and of the alternatives of optional objects
+  if let Some(subschemas) = &schema.subschemas {
+    for subschema in subschemas.all_of.iter().flatten() {
+      if let Schema::Object(subschema) = subschema {
+        apply_defaults(subschema, object);
+      }
+    }
+  }
+}
+
+/// Schemas describing the fields of an object, optional objects being described as alternatives with null
+fn get_object_schemas(schema: &SchemaObject) -> Vec<&SchemaObject> {
+  let alternatives = schema
+    .subschemas
+    .iter()
+    .flat_map(|subschemas| subschemas.any_of.iter().chain(subschemas.one_of.iter()))
+    .flatten()
+    .filter_map(|subschema| match subschema {
+      Schema::Object(subschema) => Some(subschema),
+      Schema::Bool(_) => None,
+    });
+
+  std::iter::once(schema).chain(alternatives).collect()
+}
+
+fn get_default(schema: &SchemaObject) -> Option<&Value> {
+  schema
+    .metadata
+    .as_ref()
+    .and_then(|metadata| metadata.default.as_ref())
+}
+
+#[test]
+pub fn test_apply_schema_defaults() {
+  use schemars::JsonSchema;
+
+  fn default_threshold() -> f64 {
+    0.5
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Detection {
+    label: String,
+    #[schemars(default = "default_threshold")]
+    threshold: f64,
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Parameters {
+    source_path: String,
+    #[schemars(default = "default_threshold")]
+    threshold: f64,
+    detection: Option<Detection>,
+  }
+
+  let schema = crate::worker::parameter_schema_for::<Parameters>();
+
+  let mut parameters = json!({"source_path": "/path/to/file"})
+    .as_object()
+    .cloned()
+    .unwrap();
+  apply_schema_defaults(&schema, &mut parameters);
+  assert_eq!(
+    json!({"source_path": "/path/to/file", "threshold": 0.5}),
+    Value::Object(parameters)
+  );
+
+  let mut parameters = json!({"threshold": 0.8, "detection": {"label": "car"}})
+    .as_object()
+    .cloned()
+    .unwrap();
+  apply_schema_defaults(&schema, &mut parameters);
+  assert_eq!(
+    json!({"threshold": 0.8, "detection": {"label": "car", "threshold": 0.5}}),
+    Value::Object(parameters)
+  );
+}
@@ -0,0 +1,164 @@
+//! Deprecated parameters of the workers
+//!
+//! A parameter is deprecated by the `#[deprecated]` attribute of its field, or with its replacement
+//! in `MessageEvent::get_parameter_deprecations`:
+//!
+//! ```ignore
+//! fn get_parameter_deprecations(&self) -> Vec<ParameterDeprecation> {
+//!   vec![ParameterDeprecation::new("source_url").replaced_by("source_path")]
+//! }
+//! ```
+//!
+//! It is marked as `deprecated` in the worker description, with its replacement in `replaced_by`,
+//! and a warning is logged for each job using it.
+
+use crate::job::Job;
+use schemars::schema::{RootSchema, Schema};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Extension of the schema of a deprecated parameter, with its replacement
+const REPLACED_BY_EXTENSION: &str = "replaced_by";
+
+/// Deprecated parameters of the workers, with their replacement if any
+static DEPRECATED_PARAMETERS: RwLock<BTreeMap<String, Option<String>>> =
+  RwLock::new(BTreeMap::new());
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterDeprecation {
+  parameter: String,
+  replacement: Option<String>,
+}
+
+impl ParameterDeprecation {
+  pub fn new(parameter: &str) -> Self {
+    ParameterDeprecation {
+      parameter: parameter.to_string(),
+      replacement: None,
+    }
+  }
+
+  /// Parameter to use instead
+  pub fn replaced_by(mut self, replacement: &str) -> Self {
+    self.replacement = Some(replacement.to_string());
+    self
+  }
+}
+
+/// Mark the deprecated parameters in the schema of the parameters of a worker
+pub(crate) fn apply_deprecations(schema: &mut RootSchema, deprecations: &[ParameterDeprecation]) {
+  let properties = &mut schema.schema.object().properties;
+  for deprecation in deprecations {
+    match properties.get_mut(&deprecation.parameter) {
+      Some(Schema::Object(property)) => {
+        property.metadata().deprecated = true;
+        if let Some(replacement) = &deprecation.replacement {
+          property.extensions.insert(
+            REPLACED_BY_EXTENSION.to_string(),
+            Value::String(replacement.clone()),
+          );
+        }
+      }
+      _ => warn!(
+        "Deprecated parameter {:?} missing from the parameters of the worker",
+        deprecation.parameter
+      ),
+    }
+  }
+}
+
+/// Register the deprecated parameters declared in the schema of the parameters of a worker
+pub(crate) fn register_deprecated_parameters(schema: &RootSchema) {
+  let mut deprecated_parameters = DEPRECATED_PARAMETERS.write().unwrap();
+  for (name, replacement) in get_deprecated_properties(schema) {
+    deprecated_parameters.insert(name, replacement);
+  }
+}
+
+fn get_deprecated_properties(schema: &RootSchema) -> Vec<(String, Option<String>)> {
+  schema
+    .schema
+    .object
+    .iter()
+    .flat_map(|validation| validation.properties.iter())
+    .filter_map(|(name, property)| match property {
+      Schema::Object(property)
+        if property
+          .metadata
+          .as_ref()
+          .map(|metadata| metadata.deprecated)
+          == Some(true) =>
+      {
+        let replacement = property
+          .extensions
+          .get(REPLACED_BY_EXTENSION)
+          .and_then(Value::as_str)
+          .map(str::to_string);
+        Some((name.clone(), replacement))
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+/// Log the deprecated parameters set in the order
+pub(crate) fn warn_deprecated_parameters(job: &Job) {
+  let deprecated_parameters = DEPRECATED_PARAMETERS.read().unwrap();
+  for parameter in &job.parameters {
+    match deprecated_parameters.get(&parameter.id) {
+      Some(Some(replacement)) => warn!(target: &job.job_id.to_string(),
+        "Parameter {} is deprecated, use {} instead", parameter.id, replacement),
+      Some(None) => warn!(target: &job.job_id.to_string(),
+        "Parameter {} is deprecated", parameter.id),
+      None => {}
+    }
+  }
+}
+
+#[test]
+pub fn test_deprecated_parameters() {
+  use schemars::JsonSchema;
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code, deprecated)]
+  struct Parameters {
+    source_path: String,
+    source_url: Option<String>,
+    #[deprecated]
+    legacy_mode: Option<bool>,
+  }
+
+  let mut schema = crate::worker::parameter_schema_for::<Parameters>();
+  apply_deprecations(
+    &mut schema,
+    &[
+      ParameterDeprecation::new("source_url").replaced_by("source_path"),
+      ParameterDeprecation::new("missing"),
+    ],
+  );
+
+  assert_eq!(
+    vec![
+      ("legacy_mode".to_string(), None),
+      ("source_url".to_string(), Some("source_path".to_string())),
+    ],
+    get_deprecated_properties(&schema)
+  );
+
+  let description = serde_json::to_value(&schema).unwrap();
+  assert_eq!(
+    json!(true),
+    description["properties"]["source_url"]["deprecated"]
+  );
+  assert_eq!(
+    json!("source_path"),
+    description["properties"]["source_url"]["replaced_by"]
+  );
+
+  register_deprecated_parameters(&schema);
+  assert_eq!(
+    Some(&Some("source_path".to_string())),
+    DEPRECATED_PARAMETERS.read().unwrap().get("source_url")
+  );
+}
@@ -0,0 +1,261 @@
+//! Duration parameters
+//!
+//! A field declared as `Duration` accepts a number of milliseconds, or a string like `"00:01:30"`,
+//! `"01:30.5"`, `"90s"`, `"1h30m"` or `"500ms"`:
+//!
+//! ```ignore
+//! #[derive(Debug, Deserialize, JsonSchema)]
+//! pub struct WorkerParameters {
+//!   source_path: String,
+//!   segment_duration: Duration,
+//! }
+//! ```
+//!
+//! It is serialized as a number of milliseconds.
+
+use schemars::{
+  gen::SchemaGenerator,
+  schema::{InstanceType, Schema, SchemaObject, StringValidation},
+  JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Pattern of the string values, checked by the validation of the parameters
+const DURATION_PATTERN: &str =
+  r"^(\d+(\.\d+)?|(\d+(\.\d+)?(ms|s|min|m|h))+|(\d+:)?\d{1,2}:\d{1,2}(\.\d+)?)$";
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration(std::time::Duration);
+
+impl Duration {
+  pub fn new(duration: std::time::Duration) -> Self {
+    Duration(duration)
+  }
+
+  pub fn from_millis(milliseconds: u64) -> Self {
+    Duration(std::time::Duration::from_millis(milliseconds))
+  }
+
+  pub fn into_inner(self) -> std::time::Duration {
+    self.0
+  }
+}
+
+impl Deref for Duration {
+  type Target = std::time::Duration;
+
+  fn deref(&self) -> &std::time::Duration {
+    &self.0
+  }
+}
+
+impl From<Duration> for std::time::Duration {
+  fn from(duration: Duration) -> Self {
+    duration.0
+  }
+}
+
+impl fmt::Debug for Duration {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "Duration({:?})", self.0)
+  }
+}
+
+impl FromStr for Duration {
+  type Err = String;
+
+  fn from_str(string: &str) -> Result<Self, String> {
+    let string = string.trim();
+    let seconds = if string.contains(':') {
+      parse_timecode(string)
+    } else if string.ends_with(|c: char| c.is_ascii_digit()) {
+      // a number without unit is in milliseconds
+      string.parse::<f64>().ok().map(|value| value / 1000.0)
+    } else {
+      parse_with_units(string)
+    };
+
+    seconds
+      .and_then(from_seconds)
+      .ok_or_else(|| format!("Invalid duration: {:?}", string))
+  }
+}
+
+/// None if the number of seconds is negative, not a number or out of range
+fn from_seconds(seconds: f64) -> Option<Duration> {
+  std::time::Duration::try_from_secs_f64(seconds)
+    .ok()
+    .map(Duration)
+}
+
+/// `[HH:]MM:SS[.fraction]`, in seconds
+fn parse_timecode(timecode: &str) -> Option<f64> {
+  let fields: Vec<&str> = timecode.split(':').collect();
+  if fields.len() > 3 {
+    return None;
+  }
+
+  let (seconds, fields) = fields.split_last()?;
+  if !seconds.starts_with(|c: char| c.is_ascii_digit()) {
+    return None;
+  }
+  let seconds = seconds.parse::<f64>().ok()?;
+
+  fields
+    .iter()
+    .rev()
+    .zip([60.0, 3600.0].iter())
+    .try_fold(seconds, |total, (field, factor)| {
+      if field.is_empty() || !field.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+      }
+      Some(total + field.parse::<f64>().ok()? * factor)
+    })
+}
+
+/// Sequence of numbers with their unit, like `1h30m` or `1.5s`, in seconds
+fn parse_with_units(string: &str) -> Option<f64> {
+  if string.is_empty() {
+    return None;
+  }
+
+  let mut total = 0.0;
+  let mut rest = string;
+
+  while !rest.is_empty() {
+    let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let unit_end = rest[number_end..]
+      .find(|c: char| !c.is_ascii_alphabetic())
+      .map(|end| number_end + end)
+      .unwrap_or(rest.len());
+
+    let value = rest[..number_end].parse::<f64>().ok()?;
+    let factor = match &rest[number_end..unit_end] {
+      "ms" => 0.001,
+      "s" => 1.0,
+      "m" | "min" => 60.0,
+      "h" => 3600.0,
+      _ => return None,
+    };
+    total += value * factor;
+    rest = &rest[unit_end..];
+  }
+  Some(total)
+}
+
+impl<'de> Deserialize<'de> for Duration {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    match Value::deserialize(deserializer)? {
+      Value::Number(milliseconds) => milliseconds
+        .as_f64()
+        .and_then(|milliseconds| from_seconds(milliseconds / 1000.0))
+        .ok_or_else(|| de::Error::custom(format!("Invalid duration: {}", milliseconds))),
+      Value::String(string) => string.parse().map_err(de::Error::custom),
+      value => Err(de::Error::custom(format!("Invalid duration: {}", value))),
+    }
+  }
+}
+
+/// Serialized as a number of milliseconds
+impl Serialize for Duration {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(self.0.as_millis() as u64)
+  }
+}
+
+impl JsonSchema for Duration {
+  fn schema_name() -> String {
+    "Duration".to_string()
+  }
+
+  /// A number of milliseconds, or a string with units or as a timecode
+  fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+      instance_type: Some(vec![InstanceType::Integer, InstanceType::String].into()),
+      format: Some("duration".to_string()),
+      string: Some(Box::new(StringValidation {
+        pattern: Some(DURATION_PATTERN.to_string()),
+        ..Default::default()
+      })),
+      ..Default::default()
+    }
+    .into()
+  }
+}
+
+#[test]
+pub fn test_parse_duration() {
+  let seconds = |seconds: f64| from_seconds(seconds).ok_or_else(String::new);
+
+  assert_eq!(seconds(90.0), "00:01:30".parse());
+  assert_eq!(seconds(90.5), "01:30.5".parse());
+  assert_eq!(seconds(5400.0), "1:30:00".parse());
+  assert_eq!(seconds(90.0), "90s".parse());
+  assert_eq!(seconds(5400.0), "1h30m".parse());
+  assert_eq!(seconds(150.0), "2min30s".parse());
+  assert_eq!(seconds(0.5), "500ms".parse());
+  assert_eq!(seconds(1.5), "1.5s".parse());
+  assert_eq!(seconds(1.5), "1500".parse());
+
+  assert!("".parse::<Duration>().is_err());
+  assert!("90x".parse::<Duration>().is_err());
+  assert!("s".parse::<Duration>().is_err());
+  assert!("1:2:3:4".parse::<Duration>().is_err());
+  assert!("-5s".parse::<Duration>().is_err());
+  assert!("1::30".parse::<Duration>().is_err());
+  assert!("1e30".parse::<Duration>().is_err());
+  assert!("99999999999999999999h".parse::<Duration>().is_err());
+  assert!("NaN".parse::<Duration>().is_err());
+}
+
+#[test]
+pub fn test_deserialize_duration() {
+  assert_eq!(
+    Duration::from_millis(90000),
+    serde_json::from_value(json!(90000)).unwrap()
+  );
+  assert_eq!(
+    Duration::from_millis(90000),
+    serde_json::from_value(json!("90s")).unwrap()
+  );
+  assert!(serde_json::from_value::<Duration>(json!(-1)).is_err());
+  assert!(serde_json::from_value::<Duration>(json!(1e30)).is_err());
+  assert!(serde_json::from_value::<Duration>(json!(true)).is_err());
+  assert_eq!(
+    json!(1500),
+    serde_json::to_value(Duration::from_millis(1500)).unwrap()
+  );
+
+  let pattern = regex::Regex::new(DURATION_PATTERN).unwrap();
+  for duration in &["00:01:30", "01:30.5", "90s", "1h30m", "500ms", "1500"] {
+    assert!(pattern.is_match(duration), "{}", duration);
+    assert!(duration.parse::<Duration>().is_ok(), "{}", duration);
+  }
+  assert!(!pattern.is_match("90x"));
+}
+
+#[test]
+pub fn test_get_out_of_range_duration() {
+  use crate::job::Job;
+  use crate::parameter::container::ParametersContainer;
+  use crate::MessageError;
+
+  let job = Job::new(
+    r#"{"job_id": 123, "parameters": [
+      {"id": "number", "type": "duration", "value": 1e30},
+      {"id": "string", "type": "duration", "value": "99999999999999999999h"}
+    ]}"#,
+  )
+  .unwrap();
+
+  for id in &["number", "string"] {
+    assert!(matches!(
+      job.get_parameter::<Duration>(id),
+      Err(MessageError::ParameterValueError(_))
+    ));
+  }
+}
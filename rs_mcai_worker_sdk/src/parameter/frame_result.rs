@@ -0,0 +1,44 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub type FrameResults = Vec<FrameResult>;
+
+/// Analysis result of a frame, identified by its stream and its presentation timestamp
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FrameResult {
+  pub stream_index: usize,
+  pub pts: i64,
+  pub payload: Value,
+}
+
+impl FrameResult {
+  pub fn new<T: Serialize>(stream_index: usize, pts: i64, payload: &T) -> FrameResult {
+    FrameResult {
+      stream_index,
+      pts,
+      payload: serde_json::to_value(payload).unwrap_or(Value::Null),
+    }
+  }
+}
+
+/// Aggregates of the frame results, by aggregation name
+pub type FrameAggregates = BTreeMap<String, FrameAggregate>;
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FrameAggregate {
+  pub count: u64,
+  pub min: f64,
+  pub max: f64,
+  pub mean: f64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub histogram: Option<Histogram>,
+}
+
+/// Counts of the values in bins of the same width between `min` and `max`
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Histogram {
+  pub min: f64,
+  pub max: f64,
+  pub bins: Vec<u64>,
+}
@@ -1,13 +1,223 @@
+//! Segments of a media, shared by the workers processing a part of their source
+//!
+//! The `start` and `end` boundaries of a segment are a number of milliseconds
+//! or a timecode like the [`Duration`](../duration/struct.Duration.html) parameters:
+//!
+//! ```json
+//! [{"start": "00:00:10", "end": "00:01:30.5"}, {"start": 120000, "end": 150000}]
+//! ```
+//!
+//! A segment ending before its start is rejected when the parameters are deserialized.
+//! The segments can overlap, `check_non_overlapping` rejects them otherwise.
+
+use super::Duration;
+use crate::{MessageError, Result};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::convert::TryFrom;
+
 pub type MediaSegments = Vec<MediaSegment>;
 
-#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+/// Boundaries in milliseconds, serialized as numbers
+#[derive(Clone, Default, Debug, Serialize, PartialEq)]
 pub struct MediaSegment {
   pub start: u64,
   pub end: u64,
 }
 
+/// Boundaries as they are set in the orders
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "MediaSegment")]
+struct Boundaries {
+  start: Duration,
+  end: Duration,
+}
+
 impl MediaSegment {
   pub fn new(start: u64, end: u64) -> MediaSegment {
     MediaSegment { start, end }
   }
+
+  pub fn duration(&self) -> std::time::Duration {
+    std::time::Duration::from_millis(self.end.saturating_sub(self.start))
+  }
+
+  /// Boundaries as presentation timestamps in the `numerator/denominator` time base of a stream,
+  /// to seek in it with `av_seek_frame`
+  pub fn to_pts(&self, time_base_numerator: i32, time_base_denominator: i32) -> Result<(i64, i64)> {
+    if time_base_numerator <= 0 || time_base_denominator <= 0 {
+      return Err(MessageError::ParameterValueError(format!(
+        "Invalid time base {}/{}",
+        time_base_numerator, time_base_denominator
+      )));
+    }
+
+    let to_pts = |position: u64| {
+      let pts =
+        position as i128 * time_base_denominator as i128 / (1000 * time_base_numerator as i128);
+      i64::try_from(pts).map_err(|_| {
+        MessageError::ParameterValueError(format!(
+          "Position {} ms is out of range in time base {}/{}",
+          position, time_base_numerator, time_base_denominator
+        ))
+      })
+    };
+    Ok((to_pts(self.start)?, to_pts(self.end)?))
+  }
+
+  /// `-ss` and `-to` options of the ffmpeg command line, placed before the input to seek in it
+  pub fn to_ffmpeg_arguments(&self) -> Vec<String> {
+    vec![
+      "-ss".to_string(),
+      format_timestamp(self.start),
+      "-to".to_string(),
+      format_timestamp(self.end),
+    ]
+  }
+
+  fn overlaps(&self, other: &MediaSegment) -> bool {
+    self.start < other.end && other.start < self.end
+  }
+}
+
+impl<'de> Deserialize<'de> for MediaSegment {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+    let boundaries = Boundaries::deserialize(deserializer)?;
+    let to_millis = |position: Duration| {
+      u64::try_from(position.as_millis())
+        .map_err(|_| de::Error::custom(format!("Segment boundary {:?} is out of range", position)))
+    };
+    let segment = MediaSegment::new(to_millis(boundaries.start)?, to_millis(boundaries.end)?);
+    if segment.start >= segment.end {
+      return Err(de::Error::custom(format!(
+        "Segment start {} ms is not before its end {} ms",
+        segment.start, segment.end
+      )));
+    }
+    Ok(segment)
+  }
+}
+
+impl JsonSchema for MediaSegment {
+  fn schema_name() -> String {
+    Boundaries::schema_name()
+  }
+
+  fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+    Boundaries::json_schema(gen)
+  }
+}
+
+/// Reject the segments overlapping each other
+pub fn check_non_overlapping(segments: &[MediaSegment]) -> Result<()> {
+  for (index, segment) in segments.iter().enumerate() {
+    if let Some(other) = segments[index + 1..]
+      .iter()
+      .find(|other| segment.overlaps(other))
+    {
+      return Err(MessageError::ParameterValueError(format!(
+        "Segment [{}, {}] ms overlaps segment [{}, {}] ms",
+        segment.start, segment.end, other.start, other.end
+      )));
+    }
+  }
+  Ok(())
+}
+
+/// Total duration of the segments, the overlapping parts counted once
+pub fn get_total_duration(segments: &[MediaSegment]) -> std::time::Duration {
+  let mut segments = segments.to_vec();
+  segments.sort_by_key(|segment| (segment.start, segment.end));
+
+  let mut total = 0;
+  let mut covered_until = 0;
+  for segment in segments {
+    let start = segment.start.max(covered_until);
+    if segment.end > start {
+      total += segment.end - start;
+      covered_until = segment.end;
+    }
+  }
+  std::time::Duration::from_millis(total)
+}
+
+/// `HH:MM:SS.mmm`, as ffmpeg reads the positions
+fn format_timestamp(milliseconds: u64) -> String {
+  format!(
+    "{:02}:{:02}:{:02}.{:03}",
+    milliseconds / 3_600_000,
+    milliseconds / 60_000 % 60,
+    milliseconds / 1000 % 60,
+    milliseconds % 1000
+  )
+}
+
+#[test]
+pub fn test_deserialize_media_segments() {
+  let segments: MediaSegments = serde_json::from_value(json!([
+    {"start": "00:00:10", "end": "00:01:30.5"},
+    {"start": 120000, "end": 150000}
+  ]))
+  .unwrap();
+
+  assert_eq!(
+    vec![
+      MediaSegment::new(10_000, 90_500),
+      MediaSegment::new(120_000, 150_000),
+    ],
+    segments
+  );
+  assert_eq!(
+    json!([{"start": 10000, "end": 90500}, {"start": 120000, "end": 150000}]),
+    serde_json::to_value(&segments).unwrap()
+  );
+
+  let error = serde_json::from_value::<MediaSegments>(json!([{"start": 2000, "end": 1000}]))
+    .unwrap_err()
+    .to_string();
+  assert_eq!("Segment start 2000 ms is not before its end 1000 ms", error);
+}
+
+#[test]
+pub fn test_check_non_overlapping_media_segments() {
+  let segments = vec![MediaSegment::new(0, 1000), MediaSegment::new(1000, 2000)];
+  assert!(check_non_overlapping(&segments).is_ok());
+  assert_eq!(
+    std::time::Duration::from_secs(2),
+    get_total_duration(&segments)
+  );
+
+  let segments = vec![
+    MediaSegment::new(5000, 8000),
+    MediaSegment::new(0, 1000),
+    MediaSegment::new(500, 6000),
+  ];
+  assert_eq!(
+    Err(MessageError::ParameterValueError(
+      "Segment [5000, 8000] ms overlaps segment [500, 6000] ms".to_string()
+    )),
+    check_non_overlapping(&segments)
+  );
+  assert_eq!(
+    std::time::Duration::from_secs(8),
+    get_total_duration(&segments)
+  );
+}
+
+#[test]
+pub fn test_media_segment_seek_positions() {
+  let segment = MediaSegment::new(90_500, 3_723_004);
+
+  assert_eq!(
+    vec!["-ss", "00:01:30.500", "-to", "01:02:03.004"],
+    segment.to_ffmpeg_arguments()
+  );
+  assert_eq!(Ok((8_145_000, 335_070_360)), segment.to_pts(1, 90_000));
+  assert_eq!(Ok((2262, 93075)), segment.to_pts(1, 25));
+  assert_eq!(
+    Err(MessageError::ParameterValueError(
+      "Invalid time base 0/25".to_string()
+    )),
+    segment.to_pts(0, 25)
+  );
 }
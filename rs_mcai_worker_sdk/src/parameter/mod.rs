@@ -1,11 +1,57 @@
+pub mod byte_size;
+pub mod chapter;
 pub mod container;
+pub mod credential;
+pub(crate) mod defaults;
+pub mod deprecation;
+pub mod duration;
+pub mod frame_result;
+pub mod media_path;
 pub mod media_segment;
+pub(crate) mod reference;
+pub mod secret;
 pub mod store;
+pub mod template;
+pub mod ui_hint;
+pub mod validation;
 
 use crate::{MessageError, Result};
+pub use byte_size::ByteSize;
+pub use chapter::Chapters;
+pub use credential::Credential;
+pub use duration::Duration;
+pub use frame_result::{FrameAggregates, FrameResults};
+pub use media_path::{DestinationPath, MediaPath, PathScheme, SourcePath};
 pub use media_segment::MediaSegments;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+pub use secret::Secret;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
+pub use template::Template;
+
+/// The extension is set to `true` in the schema, optional values being described as an alternative with null
+pub(crate) fn has_schema_extension(schema: &Schema, extension: &str) -> bool {
+  let schema = match schema {
+    Schema::Object(schema) => schema,
+    Schema::Bool(_) => return false,
+  };
+
+  schema.extensions.get(extension) == Some(&Value::Bool(true))
+    || schema
+      .subschemas
+      .iter()
+      .flat_map(|subschemas| {
+        subschemas
+          .any_of
+          .iter()
+          .chain(subschemas.one_of.iter())
+          .chain(subschemas.all_of.iter())
+      })
+      .flatten()
+      .any(|schema| has_schema_extension(schema, extension))
+}
 
 pub trait ParameterValue {
   fn parse_value(content: Value, store: &Option<String>) -> Result<Self>
@@ -19,13 +65,16 @@ pub trait ParameterValue {
         store_code
       );
 
-      if let Value::String(credential_key) = content {
-        Self::from_store(&credential_key, &store_code)
-      } else {
-        Err(MessageError::ParameterValueError(format!(
+      match content {
+        Value::String(credential_key) => Self::from_store(&credential_key, &store_code),
+        // the credential fields of objects are resolved with the store of the parameter
+        Value::Object(_) | Value::Array(_) => {
+          return credential::with_store(Some(store_code), || Self::from_value(content))
+        }
+        _ => Err(MessageError::ParameterValueError(format!(
           "Cannot handle credential type for {:?}",
           content
-        )))
+        ))),
       }?
     } else {
       content
@@ -140,6 +189,78 @@ impl ParameterValue for Vec<String> {
   }
 }
 
+/// Structured payload, like an object or an array, kept as a JSON value
+impl ParameterValue for Value {
+  fn get_type_as_string() -> String {
+    "json".to_string()
+  }
+}
+
+/// Item of an `array_of_objects` parameter
+///
+/// ```ignore
+/// #[derive(Debug, Deserialize, JsonSchema)]
+/// pub struct Segment {
+///   start: u64,
+///   end: u64,
+///   label: String,
+/// }
+///
+/// impl ObjectParameter for Segment {}
+///
+/// let segments: Vec<Segment> = job.get_parameter("segments")?;
+/// ```
+pub trait ObjectParameter: DeserializeOwned {}
+
+impl<T: ObjectParameter> ParameterValue for Vec<T> {
+  fn from_value(content: Value) -> Result<Self> {
+    let content = parse_array_of_objects(ARRAY_OF_OBJECTS, content)?;
+    serde_json::value::from_value(content)
+      .map_err(|e| MessageError::ParameterValueError(format!("{:?}", e)))
+  }
+
+  fn get_type_as_string() -> String {
+    ARRAY_OF_OBJECTS.to_string()
+  }
+}
+
+pub(crate) const ARRAY_OF_OBJECTS: &str = "array_of_objects";
+
+/// Arrays of objects can be set as a JSON string, like from a form
+pub(crate) fn parse_array_of_objects(kind: &str, content: Value) -> Result<Value> {
+  match content {
+    Value::String(json) if kind == ARRAY_OF_OBJECTS => {
+      serde_json::from_str(&json).map_err(|e| MessageError::ParameterValueError(format!("{:?}", e)))
+    }
+    content => Ok(content),
+  }
+}
+
+impl ParameterValue for Duration {
+  fn get_type_as_string() -> String {
+    "duration".to_string()
+  }
+}
+
+impl ParameterValue for ByteSize {
+  fn get_type_as_string() -> String {
+    "byte_size".to_string()
+  }
+}
+
+/// Paths are string parameters, to be read from the existing orders
+impl ParameterValue for SourcePath {
+  fn get_type_as_string() -> String {
+    "string".to_string()
+  }
+}
+
+impl ParameterValue for DestinationPath {
+  fn get_type_as_string() -> String {
+    "string".to_string()
+  }
+}
+
 impl ParameterValue for Requirement {
   fn get_type_as_string() -> String {
     "requirements".to_string()
@@ -152,12 +273,71 @@ impl ParameterValue for MediaSegments {
   }
 }
 
+impl ParameterValue for Chapters {
+  fn get_type_as_string() -> String {
+    "array_of_chapters".to_string()
+  }
+}
+
+impl ParameterValue for FrameResults {
+  fn get_type_as_string() -> String {
+    "array_of_frame_results".to_string()
+  }
+}
+
+impl ParameterValue for FrameAggregates {
+  fn get_type_as_string() -> String {
+    "frame_aggregates".to_string()
+  }
+}
+
 #[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Requirement {
   pub paths: Option<Vec<String>>,
+  /// HTTP(S) URLs answering a `HEAD` request with a success status
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub urls: Option<Vec<String>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub files: Option<Vec<FileRequirement>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub free_disk_space: Option<Vec<DiskSpaceRequirement>>,
+  /// Values produced by the previous jobs of the workflow, in the parameters of the order
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub results: Option<Vec<ResultRequirement>>,
+  /// Semantic versioning requirement on the version of the worker, like `>=1.2, <2`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub worker_version: Option<String>,
+}
+
+/// Value of a previous job, `key` being the parameter of the order followed by the path in its JSON value,
+/// like `probe.duration` or `probe.streams.0.codec`
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResultRequirement {
+  pub key: String,
+  /// Expected value, any non-null value if not set
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub value: Option<Value>,
+}
+
+/// Space in bytes available on the disk of the path, like a scratch volume
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DiskSpaceRequirement {
+  pub path: String,
+  pub bytes: u64,
+}
+
+/// File required with a minimum size, or readable by the worker
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FileRequirement {
+  pub path: String,
+  /// Minimum size in bytes, like for a file still being uploaded
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min_size: Option<u64>,
+  #[serde(default)]
+  pub readable: bool,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Parameter {
   pub id: String,
   #[serde(rename = "type")]
@@ -167,6 +347,31 @@ pub struct Parameter {
   pub default: Option<Value>,
 }
 
+/// The values of the secret parameters are masked
+impl fmt::Debug for Parameter {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    let mask = |value: &Option<Value>| {
+      value
+        .as_ref()
+        .map(|_| Value::String(secret::SECRET_MASK.to_string()))
+    };
+    let (value, default) = if secret::is_secret_parameter(&self.id) {
+      (mask(&self.value), mask(&self.default))
+    } else {
+      (self.value.clone(), self.default.clone())
+    };
+
+    formatter
+      .debug_struct("Parameter")
+      .field("id", &self.id)
+      .field("kind", &self.kind)
+      .field("store", &self.store)
+      .field("value", &value)
+      .field("default", &default)
+      .finish()
+  }
+}
+
 impl Parameter {
   pub fn get_id(&self) -> String {
     self.id.clone()
@@ -0,0 +1,275 @@
+//! Parameters referencing their value by URL
+//!
+//! A parameter value `{"@ref": "https://host/big_config.json"}` is replaced by the JSON content of the URL,
+//! downloaded when the parameter is read, to keep the orders small for the workers with huge configurations.
+//! The content is limited to `PARAMETER_REFERENCE_MAX_SIZE` bytes.
+//!
+//! Only the URLs starting with one of the `PARAMETER_REFERENCE_ALLOWED_URLS` prefixes are downloaded,
+//! none by default, the redirects leaving them being rejected too.
+//! The content is downloaded once per job while its order is processed, the parameters being read several times.
+
+use crate::config::{get_parameter_reference_allowed_urls, get_parameter_reference_max_size};
+use crate::job::{HttpClient, Job};
+use crate::{MessageError, Result};
+use reqwest::{redirect, Url};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::sync::Mutex;
+
+pub(crate) const REFERENCE_KEY: &str = "@ref";
+
+/// Redirects followed within the allowed URLs, as the default policy of the HTTP client
+const MAX_REDIRECTS: usize = 10;
+
+/// Content of the references downloaded by the jobs in progress
+static REFERENCES: Mutex<BTreeMap<u64, JobReferences>> = Mutex::new(BTreeMap::new());
+
+#[derive(Default)]
+struct JobReferences {
+  /// Number of `ReferencesCache` of the job
+  guards: usize,
+  contents: HashMap<String, Value>,
+}
+
+/// Keep the downloaded references of the job until dropped
+pub(crate) struct ReferencesCache {
+  job_id: u64,
+}
+
+impl ReferencesCache {
+  pub(crate) fn new(job: &Job) -> Self {
+    REFERENCES
+      .lock()
+      .unwrap()
+      .entry(job.job_id)
+      .or_default()
+      .guards += 1;
+    ReferencesCache { job_id: job.job_id }
+  }
+}
+
+impl Drop for ReferencesCache {
+  fn drop(&mut self) {
+    let mut references = REFERENCES.lock().unwrap();
+    if let Some(job_references) = references.get_mut(&self.job_id) {
+      job_references.guards -= 1;
+      if job_references.guards == 0 {
+        references.remove(&self.job_id);
+      }
+    }
+  }
+}
+
+/// URL of a reference value, an object with the single `@ref` key
+pub(crate) fn get_reference(value: &Value) -> Option<&str> {
+  match value {
+    Value::Object(object) if object.len() == 1 => object.get(REFERENCE_KEY).and_then(Value::as_str),
+    _ => None,
+  }
+}
+
+/// Value of the parameter, downloaded if it is a reference
+pub(crate) fn resolve(job_id: u64, value: Value) -> Result<Value> {
+  let url = match get_reference(&value) {
+    Some(url) => url,
+    None => return Ok(value),
+  };
+
+  let cached = REFERENCES
+    .lock()
+    .unwrap()
+    .get(&job_id)
+    .and_then(|job_references| job_references.contents.get(url).cloned());
+  if let Some(content) = cached {
+    return Ok(content);
+  }
+
+  let content = download(job_id, url)?;
+  if let Some(job_references) = REFERENCES.lock().unwrap().get_mut(&job_id) {
+    job_references
+      .contents
+      .insert(url.to_string(), content.clone());
+  }
+  Ok(content)
+}
+
+fn download(job_id: u64, url: &str) -> Result<Value> {
+  let allowed_urls = get_allowed_urls();
+  match Url::parse(url) {
+    Ok(parsed_url) if is_allowed(&allowed_urls, &parsed_url) => {}
+    _ => {
+      return Err(MessageError::ParameterValueError(format!(
+        "Parameter reference {:?} is not allowed by PARAMETER_REFERENCE_ALLOWED_URLS",
+        url
+      )))
+    }
+  }
+
+  debug!(target: &job_id.to_string(), "Download parameter reference {}", url);
+  let policy = redirect::Policy::custom(move |attempt| {
+    if attempt.previous().len() < MAX_REDIRECTS && is_allowed(&allowed_urls, attempt.url()) {
+      attempt.follow()
+    } else {
+      attempt.stop()
+    }
+  });
+  let response = HttpClient::with_redirect_policy(job_id, policy)?.get(url)?;
+  if !response.status().is_success() {
+    return Err(MessageError::ParameterValueError(format!(
+      "Cannot download parameter reference {}: status {}",
+      url,
+      response.status()
+    )));
+  }
+
+  let max_size = get_parameter_reference_max_size();
+  let mut content = vec![];
+  response
+    .take(max_size + 1)
+    .read_to_end(&mut content)
+    .map_err(|error| {
+      MessageError::ParameterValueError(format!(
+        "Cannot download parameter reference {}: {}",
+        url, error
+      ))
+    })?;
+
+  if content.len() as u64 > max_size {
+    return Err(MessageError::ParameterValueError(format!(
+      "Parameter reference {} is larger than {} bytes",
+      url, max_size
+    )));
+  }
+
+  serde_json::from_slice(&content).map_err(|error| {
+    MessageError::ParameterValueError(format!(
+      "Invalid JSON content of the parameter reference {}: {}",
+      url, error
+    ))
+  })
+}
+
+fn get_allowed_urls() -> Vec<Url> {
+  get_parameter_reference_allowed_urls()
+    .iter()
+    .filter_map(|prefix| match Url::parse(prefix) {
+      Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Some(url),
+      _ => {
+        warn!(
+          "Ignore the invalid parameter reference URL prefix {:?}",
+          prefix
+        );
+        None
+      }
+    })
+    .collect()
+}
+
+/// Same scheme, host and port as an allowed URL, and a path under its path
+fn is_allowed(allowed_urls: &[Url], url: &Url) -> bool {
+  allowed_urls.iter().any(|allowed_url| {
+    allowed_url.scheme() == url.scheme()
+      && allowed_url.host_str().is_some()
+      && allowed_url.host_str() == url.host_str()
+      && allowed_url.port_or_known_default() == url.port_or_known_default()
+      && is_under_path(allowed_url.path(), url.path())
+  })
+}
+
+fn is_under_path(prefix: &str, path: &str) -> bool {
+  if prefix.ends_with('/') {
+    path.starts_with(prefix)
+  } else {
+    path == prefix || path.starts_with(&format!("{}/", prefix))
+  }
+}
+
+#[test]
+pub fn test_get_reference() {
+  assert_eq!(
+    Some("https://host/config.json"),
+    get_reference(&json!({"@ref": "https://host/config.json"}))
+  );
+  assert_eq!(
+    None,
+    get_reference(&json!({"@ref": "https://host/config.json", "key": "value"}))
+  );
+  assert_eq!(None, get_reference(&json!({"@ref": 12})));
+  assert_eq!(None, get_reference(&json!("https://host/config.json")));
+  assert_eq!(
+    Ok(json!("https://host/config.json")),
+    resolve(123, json!("https://host/config.json"))
+  );
+}
+
+#[test]
+pub fn test_is_allowed_reference() {
+  let allowed_urls = vec![
+    Url::parse("https://config.example.com/workers/").unwrap(),
+    Url::parse("http://127.0.0.1:1234").unwrap(),
+  ];
+  let is_allowed_url = |url: &str| is_allowed(&allowed_urls, &Url::parse(url).unwrap());
+
+  assert!(is_allowed_url(
+    "https://config.example.com/workers/big_config.json"
+  ));
+  assert!(is_allowed_url(
+    "https://config.example.com:443/workers/big_config.json"
+  ));
+  assert!(is_allowed_url(
+    "http://127.0.0.1:1234/configs/big_config.json"
+  ));
+  assert!(!is_allowed_url(
+    "http://config.example.com/workers/big_config.json"
+  ));
+  assert!(!is_allowed_url(
+    "https://config.example.com/other/big_config.json"
+  ));
+  assert!(!is_allowed_url(
+    "https://config.example.com/workers/../secrets.json"
+  ));
+  assert!(!is_allowed_url(
+    "https://config.example.com.evil.com/workers/big_config.json"
+  ));
+  assert!(!is_allowed_url(
+    "https://user@evil.com/workers/big_config.json"
+  ));
+  assert!(!is_allowed_url(
+    "http://127.0.0.1:8080/configs/big_config.json"
+  ));
+  assert!(!is_allowed_url("http://169.254.169.254/latest/meta-data/"));
+  assert!(!is_allowed_url("file:///etc/passwd"));
+
+  assert!(resolve(
+    123,
+    json!({"@ref": "http://169.254.169.254/latest/meta-data/"})
+  )
+  .is_err());
+}
+
+#[test]
+pub fn test_resolve_cached_reference() {
+  use mockito::mock;
+
+  std::env::set_var("PARAMETER_REFERENCE_ALLOWED_URLS", mockito::server_url());
+  let download = mock("GET", "/configs/cached_config.json")
+    .with_body(r#"{"model": "large"}"#)
+    .expect(1)
+    .create();
+  let reference = json!({"@ref": format!("{}/configs/cached_config.json", mockito::server_url())});
+
+  let job = Job::new(r#"{"job_id": 5001, "parameters": []}"#).unwrap();
+  let cache = ReferencesCache::new(&job);
+  assert_eq!(
+    Ok(json!({"model": "large"})),
+    resolve(5001, reference.clone())
+  );
+  assert_eq!(Ok(json!({"model": "large"})), resolve(5001, reference));
+  download.assert();
+  // the other tests download from no allowed URL
+  std::env::remove_var("PARAMETER_REFERENCE_ALLOWED_URLS");
+
+  drop(cache);
+  assert!(!REFERENCES.lock().unwrap().contains_key(&5001));
+}
@@ -0,0 +1,322 @@
+//! Secret parameters, never logged nor published
+//!
+//! A field declared as `Secret<T>` in the worker parameters is marked as `secret` in the schema of the worker:
+//!
+//! ```ignore
+//! #[derive(Debug, Deserialize, JsonSchema)]
+//! pub struct WorkerParameters {
+//!   source_path: String,
+//!   api_token: Secret<String>,
+//! }
+//! ```
+//!
+//! While a job is in progress, the values of its secret parameters are replaced by `***`
+//! in the logs and in the messages published by the worker, until its response is published.
+//! The values resolved from a credential store are masked the same way, whatever the parameter.
+//! A `Secret` is serialized as `***`, like its default value and examples in the worker description.
+
+use super::has_schema_extension;
+use crate::job::Job;
+use schemars::{
+  gen::SchemaGenerator,
+  schema::{RootSchema, Schema, SchemaObject},
+  JsonSchema,
+};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::RwLock;
+
+pub const SECRET_MASK: &str = "***";
+
+/// Extension of the schema of a secret parameter
+const SECRET_EXTENSION: &str = "secret";
+
+/// Identifiers of the secret parameters of the workers
+static SECRET_PARAMETERS: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+
+/// Secret values of the jobs in progress, by job identifier
+static SECRET_VALUES: RwLock<BTreeMap<u64, JobSecretValues>> = RwLock::new(BTreeMap::new());
+
+thread_local! {
+  /// Job whose parameters are being resolved
+  static SECRETS_SCOPE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+#[derive(Default)]
+struct JobSecretValues {
+  /// Number of `JobSecrets` of the job
+  guards: usize,
+  values: BTreeSet<String>,
+}
+
+/// Value of a secret parameter, hidden from the debug output
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+  pub fn new(value: T) -> Self {
+    Secret(value)
+  }
+
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> Deref for Secret<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "Secret({})", SECRET_MASK)
+  }
+}
+
+/// Serialized as `***`, like the default values and the examples of the worker description
+impl<T> Serialize for Secret<T> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(SECRET_MASK)
+  }
+}
+
+impl<T: JsonSchema> JsonSchema for Secret<T> {
+  fn is_referenceable() -> bool {
+    false
+  }
+
+  fn schema_name() -> String {
+    format!("Secret_{}", T::schema_name())
+  }
+
+  fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<T>().into_object();
+    schema.metadata().write_only = true;
+    schema
+      .extensions
+      .insert(SECRET_EXTENSION.to_string(), Value::Bool(true));
+    schema.into()
+  }
+}
+
+/// Register the secret parameters declared in the schema of the parameters of a worker
+pub(crate) fn register_secret_parameters(schema: &RootSchema) {
+  let mut secret_parameters = SECRET_PARAMETERS.write().unwrap();
+  for name in get_secret_properties(&schema.schema) {
+    secret_parameters.insert(name);
+  }
+}
+
+pub fn is_secret_parameter(id: &str) -> bool {
+  SECRET_PARAMETERS.read().unwrap().contains(id)
+}
+
+fn get_secret_properties(schema: &SchemaObject) -> Vec<String> {
+  schema
+    .object
+    .iter()
+    .flat_map(|validation| validation.properties.iter())
+    .filter(|(_, property)| is_secret_schema(property))
+    .map(|(name, _)| name.clone())
+    .collect()
+}
+
+pub(crate) fn is_secret_schema(schema: &Schema) -> bool {
+  has_schema_extension(schema, SECRET_EXTENSION)
+}
+
+/// Values of the secret parameters of a job in progress, masked until every guard of the job is dropped
+pub(crate) struct JobSecrets {
+  job_id: u64,
+}
+
+impl JobSecrets {
+  /// Mask the literal values of the secret parameters,
+  /// the values resolved from a store are registered once resolved
+  pub(crate) fn new(job: &Job) -> Self {
+    let mut secret_values = SECRET_VALUES.write().unwrap();
+    let job_secret_values = secret_values.entry(job.job_id).or_default();
+    job_secret_values.guards += 1;
+
+    for parameter in &job.parameters {
+      if is_secret_parameter(&parameter.id) && parameter.store.is_none() {
+        for value in parameter.value.iter().chain(parameter.default.iter()) {
+          collect_strings(value, &mut job_secret_values.values);
+        }
+      }
+    }
+
+    JobSecrets { job_id: job.job_id }
+  }
+}
+
+impl Drop for JobSecrets {
+  fn drop(&mut self) {
+    let mut secret_values = SECRET_VALUES.write().unwrap();
+    if let Some(job_secret_values) = secret_values.get_mut(&self.job_id) {
+      job_secret_values.guards -= 1;
+      if job_secret_values.guards == 0 {
+        secret_values.remove(&self.job_id);
+      }
+    }
+  }
+}
+
+/// Resolve the parameters of the job, the secret values registered meanwhile being masked for this job
+pub(crate) fn with_job_secrets<T, F: FnOnce() -> T>(job_id: u64, function: F) -> T {
+  let previous = SECRETS_SCOPE.with(|scope| scope.replace(Some(job_id)));
+  let result = function();
+  SECRETS_SCOPE.with(|scope| scope.set(previous));
+  result
+}
+
+/// Mask a resolved value, like a credential got from a store, while the job of the scope is in progress.
+/// Out of a scope, the value is masked for every job in progress.
+pub(crate) fn register_secret_value(value: &Value) {
+  let scope = SECRETS_SCOPE.with(Cell::get);
+
+  // the values are not kept once the jobs are released
+  for (job_id, job_secret_values) in SECRET_VALUES.write().unwrap().iter_mut() {
+    if scope.is_none() || scope == Some(*job_id) {
+      collect_strings(value, &mut job_secret_values.values);
+    }
+  }
+}
+
+fn collect_strings(value: &Value, strings: &mut BTreeSet<String>) {
+  match value {
+    Value::String(string) if !string.is_empty() => {
+      strings.insert(string.clone());
+    }
+    Value::Array(items) => items.iter().for_each(|item| collect_strings(item, strings)),
+    Value::Object(object) => object
+      .values()
+      .for_each(|item| collect_strings(item, strings)),
+    _ => {}
+  }
+}
+
+/// Replace the values of the secret parameters of the jobs in progress, as is or JSON escaped
+pub fn mask_secrets(text: &str) -> String {
+  let secret_values = SECRET_VALUES.read().unwrap();
+  if secret_values.is_empty() {
+    return text.to_string();
+  }
+
+  let mut text = text.to_string();
+  // the longest values first, in case a value contains another one
+  let mut values: Vec<&String> = secret_values
+    .values()
+    .flat_map(|job_secret_values| job_secret_values.values.iter())
+    .collect::<BTreeSet<&String>>()
+    .into_iter()
+    .collect();
+  values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+  for value in values {
+    text = text.replace(value.as_str(), SECRET_MASK);
+    let escaped = Value::String(value.clone()).to_string();
+    let escaped = &escaped[1..escaped.len() - 1];
+    if escaped != value {
+      text = text.replace(escaped, SECRET_MASK);
+    }
+  }
+  text
+}
+
+#[test]
+pub fn test_secret_parameters() {
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct Parameters {
+    source_path: String,
+    #[schemars(default = "default_token")]
+    api_token: Secret<String>,
+    password: Option<Secret<String>>,
+  }
+
+  fn default_token() -> Secret<String> {
+    Secret::new("default-token".to_string())
+  }
+
+  let schema = crate::worker::parameter_schema_for::<Parameters>();
+  assert_eq!(
+    vec!["api_token".to_string(), "password".to_string()],
+    get_secret_properties(&schema.schema)
+  );
+
+  register_secret_parameters(&schema);
+  assert!(is_secret_parameter("api_token"));
+  assert!(is_secret_parameter("password"));
+  assert!(!is_secret_parameter("source_path"));
+
+  let description = serde_json::to_value(&schema).unwrap();
+  assert_eq!(
+    json!("***"),
+    description["properties"]["api_token"]["default"]
+  );
+  assert_eq!(
+    json!(true),
+    description["properties"]["api_token"]["writeOnly"]
+  );
+
+  let parameters: Parameters = serde_json::from_value(json!({
+    "source_path": "/path/to/file",
+    "api_token": "token"
+  }))
+  .unwrap();
+  assert_eq!("token", parameters.api_token.as_str());
+  assert_eq!("Secret(***)", format!("{:?}", parameters.api_token));
+
+  let job = Job::new(
+    r#"{"job_id": 4001, "parameters": [
+      {"id": "source_path", "type": "string", "value": "/path/to/file"},
+      {"id": "password", "type": "string", "value": "pass\"word"}
+    ]}"#,
+  )
+  .unwrap();
+  let job_secrets = JobSecrets::new(&job);
+  assert_eq!(
+    "path /path/to/file, password ***, escaped {\"password\":\"***\"}",
+    mask_secrets(r#"path /path/to/file, password pass"word, escaped {"password":"pass\"word"}"#)
+  );
+  drop(job_secrets);
+  assert_eq!("password pass\"word", mask_secrets("password pass\"word"));
+}
+
+#[test]
+pub fn test_resolved_secret_values() {
+  let job = Job::new(
+    r#"{"job_id": 4002, "parameters": [
+      {"id": "api_key", "type": "string", "store": "BACKEND", "value": "API_KEY"}
+    ]}"#,
+  )
+  .unwrap();
+
+  // not registered out of a job in progress
+  with_job_secrets(4002, || register_secret_value(&json!("s3cr")));
+  assert_eq!("key s3cr", mask_secrets("key s3cr"));
+
+  let job_secrets = JobSecrets::new(&job);
+  let batch_secrets = JobSecrets::new(&job);
+  with_job_secrets(4002, || register_secret_value(&json!({"key": "s3cr"})));
+  with_job_secrets(4003, || register_secret_value(&json!("other job")));
+  assert_eq!(
+    "store key API_KEY, key ***, other job",
+    mask_secrets("store key API_KEY, key s3cr, other job")
+  );
+
+  // masked until every guard of the job is dropped
+  drop(batch_secrets);
+  assert_eq!("key ***", mask_secrets("key s3cr"));
+  drop(job_secrets);
+  assert_eq!("key s3cr", mask_secrets("key s3cr"));
+}
@@ -0,0 +1,376 @@
+//! Credential store of AWS Secrets Manager
+//!
+//! The key of a credential is the name or ARN of the secret, with the field of a JSON secret to extract
+//! after a `#`, like `prod/s3-ingest#secret_key`.
+//!
+//! The requests are signed with the credentials of the environment (`AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`
+//! and `AWS_SESSION_TOKEN`), or with the credentials of the role of the web identity of the worker
+//! (`AWS_ROLE_ARN` and `AWS_WEB_IDENTITY_TOKEN_FILE`), as set up by EKS for the service accounts.
+
+use super::ParameterStore;
+use crate::config::{
+  get_aws_access_key_id, get_aws_role_arn, get_aws_secret_access_key,
+  get_aws_secrets_manager_endpoint, get_aws_secrets_manager_region, get_aws_session_token,
+  get_aws_sts_endpoint, get_aws_web_identity_token_file,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::time::Duration;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SERVICE: &str = "secretsmanager";
+
+/// Credentials of the web identity role, obtained from STS
+static ROLE_CREDENTIALS: Mutex<Option<AwsCredentials>> = Mutex::new(None);
+
+#[derive(Clone, Debug, PartialEq)]
+struct AwsCredentials {
+  access_key_id: String,
+  secret_access_key: String,
+  session_token: Option<String>,
+  expiration: Option<DateTime<Utc>>,
+}
+
+impl AwsCredentials {
+  /// The credentials are renewed 5 minutes before their expiration
+  fn is_expired(&self, now: DateTime<Utc>) -> bool {
+    self
+      .expiration
+      .map(|expiration| now + chrono::Duration::minutes(5) >= expiration)
+      .unwrap_or(false)
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct AwsSecretsManagerStore;
+
+impl ParameterStore for AwsSecretsManagerStore {
+  fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+    request_value(credential_key)
+  }
+}
+
+fn request_value(credential_key: &str) -> Result<Value, String> {
+  let (secret_id, field) = parse_key(credential_key)?;
+  let region = get_aws_secrets_manager_region()
+    .ok_or_else(|| "No region configured for AWS Secrets Manager".to_string())?;
+  let endpoint = get_aws_secrets_manager_endpoint()
+    .unwrap_or_else(|| format!("https://{}.{}.amazonaws.com", SERVICE, region));
+
+  let client = Client::builder()
+    .timeout(Duration::from_secs(30))
+    .build()
+    .map_err(|e| e.to_string())?;
+  let credentials = get_credentials(&client)?;
+
+  let body = json!({ "SecretId": secret_id }).to_string();
+  let host = endpoint
+    .split("://")
+    .last()
+    .unwrap_or(&endpoint)
+    .trim_end_matches('/')
+    .to_string();
+  let mut headers = vec![
+    (
+      "content-type".to_string(),
+      "application/x-amz-json-1.1".to_string(),
+    ),
+    ("host".to_string(), host),
+    (
+      "x-amz-target".to_string(),
+      "secretsmanager.GetSecretValue".to_string(),
+    ),
+  ];
+  sign_request(&mut headers, &body, &credentials, &region, Utc::now())?;
+
+  let mut request = client.post(&endpoint).body(body);
+  for (name, value) in headers.iter().filter(|(name, _)| name != "host") {
+    request = request.header(name.as_str(), value.as_str());
+  }
+  let response = request.send().map_err(|e| e.to_string())?;
+
+  if !response.status().is_success() {
+    let status = response.status();
+    let error = response.text().unwrap_or_default();
+    return Err(format!(
+      "Unable to read the AWS secret {}: {} {}",
+      secret_id, status, error
+    ));
+  }
+
+  let response: Value = response.json().map_err(|e| e.to_string())?;
+  get_secret_value(&response, field)
+}
+
+/// Name of the secret and field to extract, from a `secret_id#field` key
+fn parse_key(credential_key: &str) -> Result<(&str, Option<&str>), String> {
+  let (secret_id, field) = match credential_key.split_once('#') {
+    Some((secret_id, field)) => (secret_id, Some(field)),
+    None => (credential_key, None),
+  };
+
+  if secret_id.is_empty() || field == Some("") {
+    return Err(format!(
+      "Invalid AWS Secrets Manager key: {:?}",
+      credential_key
+    ));
+  }
+  Ok((secret_id, field))
+}
+
+/// Value of the `SecretString`, parsed as JSON if possible, or one of its fields
+fn get_secret_value(response: &Value, field: Option<&str>) -> Result<Value, String> {
+  let secret = response
+    .get("SecretString")
+    .and_then(Value::as_str)
+    .ok_or_else(|| "Missing secret string in the AWS Secrets Manager response".to_string())?;
+  let value = serde_json::from_str(secret).unwrap_or_else(|_| Value::String(secret.to_string()));
+
+  match field {
+    Some(field) => match value.get(field).cloned() {
+      Some(Value::String(string)) => {
+        Ok(serde_json::from_str(&string).unwrap_or(Value::String(string)))
+      }
+      Some(value) => Ok(value),
+      None => Err(format!("Missing field {:?} in the AWS secret", field)),
+    },
+    None => Ok(value),
+  }
+}
+
+fn get_credentials(client: &Client) -> Result<AwsCredentials, String> {
+  if let (Some(access_key_id), Some(secret_access_key)) =
+    (get_aws_access_key_id(), get_aws_secret_access_key())
+  {
+    return Ok(AwsCredentials {
+      access_key_id,
+      secret_access_key,
+      session_token: get_aws_session_token(),
+      expiration: None,
+    });
+  }
+
+  let (role_arn, token_file) = match (get_aws_role_arn(), get_aws_web_identity_token_file()) {
+    (Some(role_arn), Some(token_file)) => (role_arn, token_file),
+    _ => return Err("No AWS credentials nor web identity configured".to_string()),
+  };
+
+  let mut role_credentials = ROLE_CREDENTIALS.lock().unwrap();
+  if let Some(credentials) = role_credentials.as_ref() {
+    if !credentials.is_expired(Utc::now()) {
+      return Ok(credentials.clone());
+    }
+  }
+
+  let credentials = assume_role_with_web_identity(client, &role_arn, &token_file)?;
+  *role_credentials = Some(credentials.clone());
+  Ok(credentials)
+}
+
+fn assume_role_with_web_identity(
+  client: &Client,
+  role_arn: &str,
+  token_file: &str,
+) -> Result<AwsCredentials, String> {
+  let token = std::fs::read_to_string(token_file)
+    .map_err(|error| format!("Unable to read the web identity token: {}", error))?;
+
+  let response: Value = client
+    .post(&get_aws_sts_endpoint())
+    .header("Accept", "application/json")
+    .form(&[
+      ("Action", "AssumeRoleWithWebIdentity"),
+      ("Version", "2011-06-15"),
+      ("RoleArn", role_arn),
+      ("RoleSessionName", "mcai-worker"),
+      ("WebIdentityToken", token.trim()),
+    ])
+    .send()
+    .and_then(|response| response.error_for_status())
+    .map_err(|e| format!("Unable to assume the AWS role {}: {}", role_arn, e))?
+    .json()
+    .map_err(|e| e.to_string())?;
+
+  let credentials = response
+    .pointer("/AssumeRoleWithWebIdentityResponse/AssumeRoleWithWebIdentityResult/Credentials")
+    .ok_or_else(|| "Missing credentials in the AWS STS response".to_string())?;
+  let get_field = |name: &str| {
+    credentials
+      .get(name)
+      .and_then(Value::as_str)
+      .map(str::to_string)
+      .ok_or_else(|| format!("Missing {} in the AWS STS response", name))
+  };
+
+  Ok(AwsCredentials {
+    access_key_id: get_field("AccessKeyId")?,
+    secret_access_key: get_field("SecretAccessKey")?,
+    session_token: Some(get_field("SessionToken")?),
+    expiration: credentials
+      .get("Expiration")
+      .and_then(Value::as_f64)
+      .and_then(|expiration| DateTime::<Utc>::from_timestamp(expiration as i64, 0)),
+  })
+}
+
+/// Add the `x-amz-date`, `x-amz-security-token` and `authorization` headers of the Signature Version 4
+///
+/// The headers are lowercase, the request is a `POST` on the root path without query.
+fn sign_request(
+  headers: &mut Vec<(String, String)>,
+  body: &str,
+  credentials: &AwsCredentials,
+  region: &str,
+  now: DateTime<Utc>,
+) -> Result<(), String> {
+  let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+  let date = now.format("%Y%m%d").to_string();
+
+  headers.push(("x-amz-date".to_string(), amz_date.clone()));
+  if let Some(session_token) = &credentials.session_token {
+    headers.push(("x-amz-security-token".to_string(), session_token.clone()));
+  }
+  headers.sort();
+
+  let canonical_headers: String = headers
+    .iter()
+    .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+    .collect();
+  let signed_headers = headers
+    .iter()
+    .map(|(name, _)| name.as_str())
+    .collect::<Vec<_>>()
+    .join(";");
+  let canonical_request = format!(
+    "POST\n/\n\n{}\n{}\n{}",
+    canonical_headers,
+    signed_headers,
+    hex::encode(Sha256::digest(body.as_bytes()))
+  );
+
+  let scope = format!("{}/{}/{}/aws4_request", date, region, SERVICE);
+  let string_to_sign = format!(
+    "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+    amz_date,
+    scope,
+    hex::encode(Sha256::digest(canonical_request.as_bytes()))
+  );
+
+  let signing_key = get_signing_key(&credentials.secret_access_key, &date, region, SERVICE)?;
+  let signature = hex::encode(hmac_sha256(&signing_key, &string_to_sign)?);
+
+  headers.push((
+    "authorization".to_string(),
+    format!(
+      "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+      credentials.access_key_id, scope, signed_headers, signature
+    ),
+  ));
+  Ok(())
+}
+
+fn get_signing_key(
+  secret_access_key: &str,
+  date: &str,
+  region: &str,
+  service: &str,
+) -> Result<Vec<u8>, String> {
+  let key = format!("AWS4{}", secret_access_key);
+  let key = hmac_sha256(key.as_bytes(), date)?;
+  let key = hmac_sha256(&key, region)?;
+  let key = hmac_sha256(&key, service)?;
+  hmac_sha256(&key, "aws4_request")
+}
+
+fn hmac_sha256(key: &[u8], content: &str) -> Result<Vec<u8>, String> {
+  let mut mac = <HmacSha256 as Mac>::new_from_slice(key).map_err(|error| error.to_string())?;
+  mac.update(content.as_bytes());
+  Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Drop the credentials of the role, the next ones being requested with the current configuration
+pub(crate) fn clear_credentials() {
+  ROLE_CREDENTIALS.lock().unwrap().take();
+}
+
+#[test]
+pub fn test_parse_key() {
+  assert_eq!(
+    Ok(("prod/s3-ingest", Some("secret_key"))),
+    parse_key("prod/s3-ingest#secret_key")
+  );
+  assert_eq!(Ok(("prod/s3-ingest", None)), parse_key("prod/s3-ingest"));
+  assert!(parse_key("").is_err());
+  assert!(parse_key("prod/s3-ingest#").is_err());
+}
+
+#[test]
+pub fn test_get_secret_value() {
+  let response = json!({
+    "Name": "prod/s3-ingest",
+    "SecretString": "{\"access_key\": \"AKIA\", \"port\": 443}"
+  });
+
+  assert_eq!(
+    Ok(json!("AKIA")),
+    get_secret_value(&response, Some("access_key"))
+  );
+  assert_eq!(Ok(json!(443)), get_secret_value(&response, Some("port")));
+  assert_eq!(
+    Ok(json!({"access_key": "AKIA", "port": 443})),
+    get_secret_value(&response, None)
+  );
+  assert!(get_secret_value(&response, Some("missing")).is_err());
+
+  let response = json!({"SecretString": "plain secret"});
+  assert_eq!(Ok(json!("plain secret")), get_secret_value(&response, None));
+  assert!(get_secret_value(&json!({"SecretBinary": "AAAA"}), None).is_err());
+}
+
+#[test]
+pub fn test_signing_key() {
+  // example of the AWS documentation of the Signature Version 4
+  let signing_key = get_signing_key(
+    "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+    "20120215",
+    "us-east-1",
+    "iam",
+  )
+  .unwrap();
+  assert_eq!(
+    "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d",
+    hex::encode(signing_key)
+  );
+}
+
+#[test]
+pub fn test_sign_request() {
+  let credentials = AwsCredentials {
+    access_key_id: "AKIDEXAMPLE".to_string(),
+    secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+    session_token: Some("session_token".to_string()),
+    expiration: None,
+  };
+  let now = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+    .unwrap()
+    .with_timezone(&Utc);
+  let mut headers = vec![(
+    "host".to_string(),
+    "secretsmanager.eu-west-1.amazonaws.com".to_string(),
+  )];
+
+  sign_request(&mut headers, "{}", &credentials, "eu-west-1", now).unwrap();
+
+  let authorization = &headers.last().unwrap().1;
+  assert!(authorization.starts_with(
+    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/eu-west-1/secretsmanager/aws4_request, \
+     SignedHeaders=host;x-amz-date;x-amz-security-token, Signature="
+  ));
+  assert!(headers.contains(&("x-amz-date".to_string(), "20150830T123600Z".to_string())));
+  assert!(!credentials.is_expired(now));
+}
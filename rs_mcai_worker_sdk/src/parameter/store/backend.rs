@@ -0,0 +1,221 @@
+//! Credential store of the MCAI backend
+//!
+//! The session token of the backend is reused between the credential requests of the jobs.
+//! A session is opened again when the token expires, according to the `exp` claim of a JWT token,
+//! or when a request with the reused token is rejected.
+
+use super::ParameterStore;
+use crate::{
+  config::*,
+  job::{Session, SessionBody, SessionResponseBody, ValueResponseBody},
+};
+use reqwest::{
+  blocking::{Client, Response},
+  header::AUTHORIZATION,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Session tokens, by backend endpoint and username
+static SESSION_TOKENS: Mutex<BTreeMap<(String, String), SessionToken>> =
+  Mutex::new(BTreeMap::new());
+
+#[derive(Clone, Debug, PartialEq)]
+struct SessionToken {
+  access_token: String,
+  /// Expiration of a JWT token, none if unknown
+  expiration: Option<SystemTime>,
+}
+
+impl SessionToken {
+  fn new(access_token: String) -> Self {
+    let expiration = get_jwt_expiration(&access_token);
+    SessionToken {
+      access_token,
+      expiration,
+    }
+  }
+
+  /// The session is opened again 1 minute before the expiration of the token
+  fn is_expired(&self, now: SystemTime) -> bool {
+    self
+      .expiration
+      .map(|expiration| now + Duration::from_secs(60) >= expiration)
+      .unwrap_or(false)
+  }
+}
+
+/// Store of the MCAI backend, configured with the `<STORE_CODE>_HOSTNAME`, `_USERNAME` and `_PASSWORD` variables
+#[derive(Debug)]
+pub struct BackendStore {
+  store_code: String,
+}
+
+impl BackendStore {
+  pub fn new(store_code: &str) -> Self {
+    BackendStore {
+      store_code: store_code.to_string(),
+    }
+  }
+}
+
+impl ParameterStore for BackendStore {
+  fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+    request_value(credential_key, &self.store_code)
+  }
+}
+
+fn request_value(credential_key: &str, store_code: &str) -> Result<Value, String> {
+  let backend_endpoint = get_store_hostname(store_code);
+  let backend_username = get_store_username(store_code);
+  let backend_password = get_store_password(store_code);
+
+  let credential_url = format!("{}/credentials/{}", backend_endpoint, credential_key);
+  let session_key = (backend_endpoint.clone(), backend_username.clone());
+
+  let client = Client::builder().build().map_err(|e| format!("{:?}", e))?;
+
+  let cached_token = SESSION_TOKENS
+    .lock()
+    .unwrap()
+    .get(&session_key)
+    .filter(|token| !token.is_expired(SystemTime::now()))
+    .cloned();
+
+  let mut response = None;
+  if let Some(token) = cached_token {
+    let cached_response = get_credential(&client, &credential_url, &token)?;
+    if cached_response.status().is_success() {
+      response = Some(cached_response);
+    } else {
+      debug!(
+        "Credential request rejected with the {} session token: {}",
+        store_code,
+        cached_response.status()
+      );
+      SESSION_TOKENS.lock().unwrap().remove(&session_key);
+    }
+  }
+
+  let response = match response {
+    Some(response) => response,
+    None => {
+      let token = open_session(
+        &client,
+        &backend_endpoint,
+        backend_username,
+        backend_password,
+      )?;
+      SESSION_TOKENS
+        .lock()
+        .unwrap()
+        .insert(session_key, token.clone());
+      get_credential(&client, &credential_url, &token)?
+    }
+  };
+
+  let response: ValueResponseBody = response.json().map_err(|e| e.to_string())?;
+
+  let value = match response.data.value.clone() {
+    Value::String(string) => serde_json::from_str(&string).unwrap_or(response.data.value),
+    _ => response.data.value,
+  };
+
+  Ok(value)
+}
+
+/// Access token of a session on the backend of the store, reused until it expires
+pub(crate) fn get_session_token(client: &Client, store_code: &str) -> Result<String, String> {
+  let backend_endpoint = get_store_hostname(store_code);
+  let backend_username = get_store_username(store_code);
+  let session_key = (backend_endpoint.clone(), backend_username.clone());
+
+  if let Some(token) = SESSION_TOKENS
+    .lock()
+    .unwrap()
+    .get(&session_key)
+    .filter(|token| !token.is_expired(SystemTime::now()))
+  {
+    return Ok(token.access_token.clone());
+  }
+
+  let token = open_session(
+    client,
+    &backend_endpoint,
+    backend_username,
+    get_store_password(store_code),
+  )?;
+  SESSION_TOKENS
+    .lock()
+    .unwrap()
+    .insert(session_key, token.clone());
+  Ok(token.access_token)
+}
+
+fn open_session(
+  client: &Client,
+  backend_endpoint: &str,
+  email: String,
+  password: String,
+) -> Result<SessionToken, String> {
+  let session_body = SessionBody {
+    session: Session { email, password },
+  };
+
+  let response: SessionResponseBody = client
+    .post(&format!("{}/sessions", backend_endpoint))
+    .json(&session_body)
+    .send()
+    .map_err(|e| e.to_string())?
+    .json()
+    .map_err(|e| e.to_string())?;
+
+  Ok(SessionToken::new(response.access_token))
+}
+
+fn get_credential(
+  client: &Client,
+  credential_url: &str,
+  token: &SessionToken,
+) -> Result<Response, String> {
+  client
+    .get(credential_url)
+    .header(AUTHORIZATION, token.access_token.as_str())
+    .send()
+    .map_err(|e| e.to_string())
+}
+
+/// `exp` claim of a JWT token, in the base64url encoded payload
+fn get_jwt_expiration(access_token: &str) -> Option<SystemTime> {
+  let payload = access_token.split('.').nth(1)?;
+  let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+  let claims: Value = serde_json::from_slice(&payload).ok()?;
+  let expiration = claims.get("exp")?.as_u64()?;
+
+  Some(UNIX_EPOCH + Duration::from_secs(expiration))
+}
+
+/// Drop the sessions, the next ones being opened with the current configuration
+pub(crate) fn clear_sessions() {
+  SESSION_TOKENS.lock().unwrap().clear();
+}
+
+#[test]
+pub fn test_session_token_expiration() {
+  let payload = base64::encode_config(
+    r#"{"sub":"worker@media.io","exp":1700000000}"#,
+    base64::URL_SAFE_NO_PAD,
+  );
+  let token = SessionToken::new(format!("eyJhbGciOiJIUzI1NiJ9.{}.signature", payload));
+  let expiration = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+  assert_eq!(Some(expiration), token.expiration);
+  assert!(!token.is_expired(expiration - Duration::from_secs(120)));
+  assert!(token.is_expired(expiration - Duration::from_secs(30)));
+
+  let token = SessionToken::new("fake_access_token".to_string());
+  assert_eq!(None, token.expiration);
+  assert!(!token.is_expired(SystemTime::now()));
+}
@@ -0,0 +1,28 @@
+//! Credential store of the environment variables of the worker
+
+use super::ParameterStore;
+use crate::config::get_environment_store_prefix;
+use serde_json::Value;
+use std::env::var;
+
+/// Store of the environment variables of the worker, for the local runs and the CI
+#[derive(Debug, Default)]
+pub struct EnvironmentStore;
+
+impl ParameterStore for EnvironmentStore {
+  /// Value of the environment variable, parsed as JSON if possible
+  fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+    request_value(credential_key)
+  }
+}
+
+fn request_value(credential_key: &str) -> Result<Value, String> {
+  let variable = match get_environment_store_prefix() {
+    Some(prefix) => format!("{}{}", prefix, credential_key),
+    None => credential_key.to_string(),
+  };
+
+  var(&variable)
+    .map_err(|error| error.to_string())
+    .map(|value| serde_json::from_str(&value).unwrap_or(Value::String(value)))
+}
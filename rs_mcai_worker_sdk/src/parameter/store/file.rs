@@ -0,0 +1,108 @@
+//! Credential store of the secrets mounted as files, like the Docker and Kubernetes secrets
+//!
+//! The value of a credential is the content of the `<FILE_STORE_PATH>/<key>` file, without its trailing line break.
+//! The values are cached, and read again once the file is modified, like when a Kubernetes secret is updated.
+
+use super::ParameterStore;
+use crate::config::get_file_store_path;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+#[derive(Clone)]
+struct CachedSecret {
+  modified: Option<SystemTime>,
+  length: u64,
+  value: Value,
+}
+
+/// Values of the secret files already read
+static SECRET_FILES: RwLock<BTreeMap<PathBuf, CachedSecret>> = RwLock::new(BTreeMap::new());
+
+#[derive(Debug, Default)]
+pub struct FileStore;
+
+impl ParameterStore for FileStore {
+  fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+    let path = get_secret_path(Path::new(&get_file_store_path()), credential_key)?;
+    read_secret(&path)
+  }
+}
+
+/// The key is a relative path within the directory of the secrets
+fn get_secret_path(directory: &Path, credential_key: &str) -> Result<PathBuf, String> {
+  let key = Path::new(credential_key);
+  let is_relative = key
+    .components()
+    .all(|component| matches!(component, Component::Normal(_)));
+
+  if credential_key.is_empty() || !is_relative {
+    return Err(format!("Invalid secret file key: {:?}", credential_key));
+  }
+  Ok(directory.join(key))
+}
+
+/// Value of the secret file, parsed as JSON if possible
+fn read_secret(path: &Path) -> Result<Value, String> {
+  let metadata = fs::metadata(path)
+    .map_err(|error| format!("Unable to read secret file {:?}: {}", path, error))?;
+  let modified = metadata.modified().ok();
+  let length = metadata.len();
+
+  if let Some(cached) = SECRET_FILES.read().unwrap().get(path) {
+    if cached.modified == modified && cached.length == length {
+      return Ok(cached.value.clone());
+    }
+  }
+
+  let content = fs::read_to_string(path)
+    .map_err(|error| format!("Unable to read secret file {:?}: {}", path, error))?;
+  let content = content.trim_end_matches(&['\n', '\r'][..]).to_string();
+  let value = serde_json::from_str(&content).unwrap_or(Value::String(content));
+
+  SECRET_FILES.write().unwrap().insert(
+    path.to_path_buf(),
+    CachedSecret {
+      modified,
+      length,
+      value: value.clone(),
+    },
+  );
+  Ok(value)
+}
+
+#[test]
+pub fn test_get_secret_path() {
+  let directory = Path::new("/run/secrets");
+  assert_eq!(
+    Ok(PathBuf::from("/run/secrets/s3/secret_key")),
+    get_secret_path(directory, "s3/secret_key")
+  );
+  assert!(get_secret_path(directory, "").is_err());
+  assert!(get_secret_path(directory, "/etc/passwd").is_err());
+  assert!(get_secret_path(directory, "../passwd").is_err());
+  assert!(get_secret_path(directory, "./secret_key").is_err());
+}
+
+#[test]
+pub fn test_read_secret() {
+  let directory = std::env::temp_dir().join(format!("mcai_file_store_{}", std::process::id()));
+  fs::create_dir_all(&directory).unwrap();
+  let path = directory.join("secret_key");
+
+  fs::write(&path, "first_value\n").unwrap();
+  assert_eq!(
+    Ok(Value::String("first_value".to_string())),
+    read_secret(&path)
+  );
+
+  // a change of size is detected even within the resolution of the modification time
+  fs::write(&path, "{\"key\": \"second_value\"}").unwrap();
+  assert_eq!(Ok(json!({"key": "second_value"})), read_secret(&path));
+
+  assert!(read_secret(&directory.join("missing")).is_err());
+  fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,282 @@
+//! Credential store of Google Secret Manager
+//!
+//! The key of a credential is the name of the secret, with its version after a `@` (the latest one by default)
+//! and the field of a JSON secret to extract after a `#`, like `s3-ingest@3#secret_key`.
+//! A full resource name, like `projects/media/secrets/s3-ingest/versions/3`, is used as is.
+//!
+//! The worker authenticates with the workload identity of its service account on GKE:
+//! its access token is given by the metadata server, and reused until its expiration.
+
+use super::ParameterStore;
+use crate::config::{
+  get_gcp_metadata_hostname, get_gcp_secret_manager_endpoint, get_gcp_secret_manager_project,
+};
+use reqwest::blocking::Client;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Access token of the service account, given by the metadata server
+static ACCESS_TOKEN: Mutex<Option<AccessToken>> = Mutex::new(None);
+
+#[derive(Clone, Debug)]
+struct AccessToken {
+  token: String,
+  expiration: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct GcpSecretManagerStore;
+
+impl ParameterStore for GcpSecretManagerStore {
+  fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+    request_value(credential_key)
+  }
+}
+
+fn request_value(credential_key: &str) -> Result<Value, String> {
+  let client = Client::builder()
+    .timeout(Duration::from_secs(30))
+    .build()
+    .map_err(|e| e.to_string())?;
+
+  let (name, field) = split_field(credential_key)?;
+  let resource_name = if name.starts_with("projects/") {
+    get_resource_name(name, None)?
+  } else {
+    let project = match get_gcp_secret_manager_project() {
+      Some(project) => project,
+      None => get_metadata(&client, "project/project-id")?,
+    };
+    get_resource_name(name, Some(&project))?
+  };
+
+  let token = get_access_token(&client)?;
+  let url = format!(
+    "{}/v1/{}:access",
+    get_gcp_secret_manager_endpoint(),
+    resource_name
+  );
+  let response = client
+    .get(&url)
+    .bearer_auth(token)
+    .send()
+    .map_err(|e| e.to_string())?;
+
+  if !response.status().is_success() {
+    return Err(format!(
+      "Unable to access the GCP secret {}: {}",
+      resource_name,
+      response.status()
+    ));
+  }
+
+  let response: Value = response.json().map_err(|e| e.to_string())?;
+  get_secret_value(&response, field)
+}
+
+/// Secret and field to extract, from a `secret#field` key
+fn split_field(credential_key: &str) -> Result<(&str, Option<&str>), String> {
+  let (name, field) = match credential_key.split_once('#') {
+    Some((name, field)) => (name, Some(field)),
+    None => (credential_key, None),
+  };
+
+  if name.is_empty() || field == Some("") {
+    return Err(format!(
+      "Invalid GCP Secret Manager key: {:?}",
+      credential_key
+    ));
+  }
+  Ok((name, field))
+}
+
+/// `projects/<project>/secrets/<secret>/versions/<version>` of the secret
+fn get_resource_name(name: &str, project: Option<&str>) -> Result<String, String> {
+  if let Some(path) = name.strip_prefix("projects/") {
+    let segments: Vec<&str> = path.split('/').collect();
+    return match segments.as_slice() {
+      [project, "secrets", secret] if are_valid_segments(&[project, secret]) => {
+        Ok(format!("{}/versions/latest", name))
+      }
+      [project, "secrets", secret, "versions", version]
+        if are_valid_segments(&[project, secret, version]) =>
+      {
+        Ok(name.to_string())
+      }
+      _ => Err(format!("Invalid GCP secret resource name: {:?}", name)),
+    };
+  }
+
+  let (secret, version) = match name.split_once('@') {
+    Some((secret, version)) => (secret, version),
+    None => (name, "latest"),
+  };
+  if !are_valid_segments(&[secret, version]) {
+    return Err(format!("Invalid GCP secret name: {:?}", name));
+  }
+
+  let project =
+    project.ok_or_else(|| "No project configured for GCP Secret Manager".to_string())?;
+  if !are_valid_segments(&[project]) {
+    return Err(format!("Invalid GCP project: {:?}", project));
+  }
+  Ok(format!(
+    "projects/{}/secrets/{}/versions/{}",
+    project, secret, version
+  ))
+}
+
+/// Segments of the resource name, which can not change the path nor the query of the request
+fn are_valid_segments(segments: &[&str]) -> bool {
+  segments.iter().all(|segment| {
+    !matches!(*segment, "" | "." | "..") && !segment.contains(['/', '\\', '?', '#', '%'])
+  })
+}
+
+/// The payload is base64 encoded, it is parsed as JSON if possible
+fn get_secret_value(response: &Value, field: Option<&str>) -> Result<Value, String> {
+  let data = response
+    .pointer("/payload/data")
+    .and_then(Value::as_str)
+    .ok_or_else(|| "Missing payload in the GCP Secret Manager response".to_string())?;
+  let data = base64::decode(data).map_err(|error| error.to_string())?;
+  let secret = String::from_utf8(data).map_err(|error| error.to_string())?;
+  let value = serde_json::from_str(&secret).unwrap_or(Value::String(secret));
+
+  match field {
+    Some(field) => match value.get(field).cloned() {
+      Some(Value::String(string)) => {
+        Ok(serde_json::from_str(&string).unwrap_or(Value::String(string)))
+      }
+      Some(value) => Ok(value),
+      None => Err(format!("Missing field {:?} in the GCP secret", field)),
+    },
+    None => Ok(value),
+  }
+}
+
+fn get_metadata(client: &Client, path: &str) -> Result<String, String> {
+  client
+    .get(&format!(
+      "{}/computeMetadata/v1/{}",
+      get_gcp_metadata_hostname(),
+      path
+    ))
+    .header("Metadata-Flavor", "Google")
+    .send()
+    .and_then(|response| response.error_for_status())
+    .map_err(|e| format!("Unable to query the GCP metadata server: {}", e))?
+    .text()
+    .map_err(|e| e.to_string())
+}
+
+/// The token is requested again 1 minute before its expiration
+fn get_access_token(client: &Client) -> Result<String, String> {
+  let mut access_token = ACCESS_TOKEN.lock().unwrap();
+  if let Some(access_token) = access_token.as_ref() {
+    if Instant::now() + Duration::from_secs(60) < access_token.expiration {
+      return Ok(access_token.token.clone());
+    }
+  }
+
+  let response: Value = serde_json::from_str(&get_metadata(
+    client,
+    "instance/service-accounts/default/token",
+  )?)
+  .map_err(|e| e.to_string())?;
+  let token = parse_access_token(&response, Instant::now())?;
+  let value = token.token.clone();
+  *access_token = Some(token);
+  Ok(value)
+}
+
+fn parse_access_token(response: &Value, now: Instant) -> Result<AccessToken, String> {
+  let token = response
+    .get("access_token")
+    .and_then(Value::as_str)
+    .ok_or_else(|| "Missing access token in the GCP metadata response".to_string())?;
+  let expires_in = response
+    .get("expires_in")
+    .and_then(Value::as_u64)
+    .unwrap_or_default();
+
+  Ok(AccessToken {
+    token: token.to_string(),
+    expiration: now + Duration::from_secs(expires_in),
+  })
+}
+
+/// Drop the access token, the next one being requested with the current configuration
+pub(crate) fn clear_token() {
+  ACCESS_TOKEN.lock().unwrap().take();
+}
+
+#[test]
+pub fn test_get_resource_name() {
+  assert_eq!(
+    Ok("projects/media/secrets/s3-ingest/versions/latest".to_string()),
+    get_resource_name("s3-ingest", Some("media"))
+  );
+  assert_eq!(
+    Ok("projects/media/secrets/s3-ingest/versions/3".to_string()),
+    get_resource_name("s3-ingest@3", Some("media"))
+  );
+  assert_eq!(
+    Ok("projects/other/secrets/s3-ingest/versions/latest".to_string()),
+    get_resource_name("projects/other/secrets/s3-ingest", None)
+  );
+  assert_eq!(
+    Ok("projects/other/secrets/s3-ingest/versions/2".to_string()),
+    get_resource_name("projects/other/secrets/s3-ingest/versions/2", None)
+  );
+  assert!(get_resource_name("projects/other/s3-ingest", None).is_err());
+  assert!(get_resource_name("s3-ingest@", Some("media")).is_err());
+  assert!(get_resource_name("s3-ingest", None).is_err());
+  assert!(get_resource_name("s3-ingest@../../other", Some("media")).is_err());
+  assert!(get_resource_name("s3-ingest@..", Some("media")).is_err());
+  assert!(get_resource_name("s3-ingest@1?alt=json", Some("media")).is_err());
+  assert!(get_resource_name("s3-ingest", Some("media/secrets/other")).is_err());
+  assert!(get_resource_name("projects/../secrets/s3-ingest", None).is_err());
+  assert!(get_resource_name("projects/other/secrets/../versions/2", None).is_err());
+  assert!(get_resource_name("projects/other/secrets/s3-ingest/versions/%2e%2e", None).is_err());
+}
+
+#[test]
+pub fn test_get_secret_value() {
+  let response = json!({
+    "name": "projects/media/secrets/s3-ingest/versions/3",
+    "payload": {"data": base64::encode("{\"secret_key\": \"abcd\", \"port\": 443}")}
+  });
+
+  assert_eq!(
+    Ok(json!("abcd")),
+    get_secret_value(&response, Some("secret_key"))
+  );
+  assert_eq!(Ok(json!(443)), get_secret_value(&response, Some("port")));
+  assert!(get_secret_value(&response, Some("missing")).is_err());
+
+  let response = json!({"payload": {"data": base64::encode("plain secret")}});
+  assert_eq!(Ok(json!("plain secret")), get_secret_value(&response, None));
+  assert!(get_secret_value(&json!({}), None).is_err());
+
+  assert_eq!(
+    Ok(("s3-ingest@3", Some("key"))),
+    split_field("s3-ingest@3#key")
+  );
+  assert!(split_field("s3-ingest#").is_err());
+}
+
+#[test]
+pub fn test_parse_access_token() {
+  let now = Instant::now();
+  let token = parse_access_token(
+    &json!({"access_token": "ya29.token", "expires_in": 3599, "token_type": "Bearer"}),
+    now,
+  )
+  .unwrap();
+
+  assert_eq!("ya29.token", token.token);
+  assert_eq!(now + Duration::from_secs(3599), token.expiration);
+  assert!(parse_access_token(&json!({}), now).is_err());
+}
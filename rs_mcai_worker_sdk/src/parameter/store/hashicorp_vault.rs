@@ -0,0 +1,276 @@
+//! Credential store of a HashiCorp Vault KV v2 secrets engine
+//!
+//! The key of a credential is the path of the secret in the engine, with the field to extract after a `#`,
+//! like `s3/ingest#secret_key`. Without field, the value is the object of all the fields of the secret.
+//!
+//! The worker authenticates with a token, or with an AppRole. The token is reused between the requests,
+//! renewed before the end of its lease, and obtained again once it can no longer be renewed.
+
+use super::ParameterStore;
+use crate::config::{
+  get_hashicorp_vault_hostname, get_hashicorp_vault_mount, get_hashicorp_vault_role_id,
+  get_hashicorp_vault_secret_id, get_hashicorp_vault_token,
+};
+use reqwest::blocking::Client;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const TOKEN_HEADER: &str = "X-Vault-Token";
+
+/// Token of the worker, obtained from the AppRole login
+static APPROLE_TOKEN: Mutex<Option<VaultToken>> = Mutex::new(None);
+
+#[derive(Clone, Debug)]
+struct VaultToken {
+  client_token: String,
+  renewable: bool,
+  /// End of the lease, none for a token without expiration
+  expiration: Option<Instant>,
+  lease_duration: Duration,
+}
+
+impl VaultToken {
+  fn from_auth(auth: &Value) -> Result<Self, String> {
+    let client_token = auth
+      .get("client_token")
+      .and_then(Value::as_str)
+      .ok_or_else(|| "Missing client token in the Vault response".to_string())?;
+    let lease_duration = Duration::from_secs(
+      auth
+        .get("lease_duration")
+        .and_then(Value::as_u64)
+        .unwrap_or_default(),
+    );
+
+    Ok(VaultToken {
+      client_token: client_token.to_string(),
+      renewable: auth
+        .get("renewable")
+        .and_then(Value::as_bool)
+        .unwrap_or(false),
+      expiration: if lease_duration.as_secs() > 0 {
+        Some(Instant::now() + lease_duration)
+      } else {
+        None
+      },
+      lease_duration,
+    })
+  }
+
+  fn is_expired(&self, now: Instant) -> bool {
+    self
+      .expiration
+      .map(|expiration| now >= expiration)
+      .unwrap_or(false)
+  }
+
+  /// The lease is renewed once two thirds of it have elapsed
+  fn needs_renewal(&self, now: Instant) -> bool {
+    match self.expiration {
+      Some(expiration) if self.renewable => {
+        expiration.saturating_duration_since(now) < self.lease_duration / 3
+      }
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct HashicorpVaultStore;
+
+impl ParameterStore for HashicorpVaultStore {
+  fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+    request_value(credential_key)
+  }
+}
+
+fn request_value(credential_key: &str) -> Result<Value, String> {
+  let (path, field) = parse_key(credential_key)?;
+  let hostname = get_hashicorp_vault_hostname();
+  let client = Client::builder().build().map_err(|e| e.to_string())?;
+  let token = get_token(&client, &hostname)?;
+
+  let url = format!(
+    "{}/v1/{}/data/{}",
+    hostname,
+    get_hashicorp_vault_mount(),
+    path
+  );
+  let response = client
+    .get(&url)
+    .header(TOKEN_HEADER, token.as_str())
+    .send()
+    .map_err(|e| e.to_string())?;
+
+  if !response.status().is_success() {
+    return Err(format!(
+      "Unable to read the Vault secret {}: {}",
+      path,
+      response.status()
+    ));
+  }
+
+  let response: Value = response.json().map_err(|e| e.to_string())?;
+  get_secret_value(&response, field)
+}
+
+/// Path of the secret and field to extract, from a `path#field` key.
+/// The path can not leave the mount of the secrets, with `..` or empty segments, nor add a query.
+fn parse_key(credential_key: &str) -> Result<(&str, Option<&str>), String> {
+  let (path, field) = match credential_key.split_once('#') {
+    Some((path, field)) => (path, Some(field)),
+    None => (credential_key, None),
+  };
+  let path = path.trim_matches('/');
+
+  let invalid_path = path
+    .split('/')
+    .any(|segment| matches!(segment, "" | "." | ".."))
+    || path.contains(['?', '%', '\\']);
+  if invalid_path || field == Some("") {
+    return Err(format!("Invalid Vault secret key: {:?}", credential_key));
+  }
+  Ok((path, field))
+}
+
+/// Fields of a KV v2 secret are in `data.data`, a field holding a JSON string is parsed
+fn get_secret_value(response: &Value, field: Option<&str>) -> Result<Value, String> {
+  let data = response
+    .pointer("/data/data")
+    .ok_or_else(|| "Missing secret data in the Vault response".to_string())?;
+
+  let value = match field {
+    Some(field) => data
+      .get(field)
+      .cloned()
+      .ok_or_else(|| format!("Missing field {:?} in the Vault secret", field))?,
+    None => data.clone(),
+  };
+
+  Ok(match value {
+    Value::String(string) => serde_json::from_str(&string).unwrap_or(Value::String(string)),
+    value => value,
+  })
+}
+
+fn get_token(client: &Client, hostname: &str) -> Result<String, String> {
+  if let Some(token) = get_hashicorp_vault_token() {
+    return Ok(token);
+  }
+
+  let mut approle_token = APPROLE_TOKEN.lock().unwrap();
+  let now = Instant::now();
+
+  let token = match approle_token.take() {
+    Some(token) if token.needs_renewal(now) => match renew_token(client, hostname, &token) {
+      Ok(token) => token,
+      Err(error) => {
+        warn!("Unable to renew the Vault token, login again: {}", error);
+        login_approle(client, hostname)?
+      }
+    },
+    Some(token) if !token.is_expired(now) => token,
+    _ => login_approle(client, hostname)?,
+  };
+
+  let client_token = token.client_token.clone();
+  *approle_token = Some(token);
+  Ok(client_token)
+}
+
+fn login_approle(client: &Client, hostname: &str) -> Result<VaultToken, String> {
+  let role_id = get_hashicorp_vault_role_id()
+    .ok_or_else(|| "No Vault token nor AppRole configured".to_string())?;
+  let secret_id = get_hashicorp_vault_secret_id().unwrap_or_default();
+
+  let response: Value = client
+    .post(&format!("{}/v1/auth/approle/login", hostname))
+    .json(&json!({"role_id": role_id, "secret_id": secret_id}))
+    .send()
+    .and_then(|response| response.error_for_status())
+    .map_err(|e| format!("Unable to login to Vault: {}", e))?
+    .json()
+    .map_err(|e| e.to_string())?;
+
+  VaultToken::from_auth(response.get("auth").unwrap_or(&Value::Null))
+}
+
+fn renew_token(client: &Client, hostname: &str, token: &VaultToken) -> Result<VaultToken, String> {
+  let response: Value = client
+    .post(&format!("{}/v1/auth/token/renew-self", hostname))
+    .header(TOKEN_HEADER, token.client_token.as_str())
+    .send()
+    .and_then(|response| response.error_for_status())
+    .map_err(|e| e.to_string())?
+    .json()
+    .map_err(|e| e.to_string())?;
+
+  VaultToken::from_auth(response.get("auth").unwrap_or(&Value::Null))
+}
+
+/// Drop the AppRole token, the next one being requested with the current configuration
+pub(crate) fn clear_token() {
+  APPROLE_TOKEN.lock().unwrap().take();
+}
+
+#[test]
+pub fn test_parse_key() {
+  assert_eq!(
+    Ok(("s3/ingest", Some("secret_key"))),
+    parse_key("s3/ingest#secret_key")
+  );
+  assert_eq!(Ok(("s3/ingest", None)), parse_key("/s3/ingest"));
+  assert!(parse_key("").is_err());
+  assert!(parse_key("s3/ingest#").is_err());
+  assert!(parse_key("s3/../../sys/seal#key").is_err());
+  assert!(parse_key("s3/./ingest").is_err());
+  assert!(parse_key("s3//ingest").is_err());
+  assert!(parse_key("s3/ingest?version=1").is_err());
+  assert!(parse_key("s3/%2e%2e/ingest").is_err());
+}
+
+#[test]
+pub fn test_get_secret_value() {
+  let response = json!({
+    "data": {
+      "data": {"access_key": "AKIA", "options": "{\"region\": \"eu-west-1\"}"},
+      "metadata": {"version": 2}
+    }
+  });
+
+  assert_eq!(
+    Ok(json!("AKIA")),
+    get_secret_value(&response, Some("access_key"))
+  );
+  assert_eq!(
+    Ok(json!({"region": "eu-west-1"})),
+    get_secret_value(&response, Some("options"))
+  );
+  assert_eq!(
+    Ok(response["data"]["data"].clone()),
+    get_secret_value(&response, None)
+  );
+  assert!(get_secret_value(&response, Some("missing")).is_err());
+  assert!(get_secret_value(&json!({}), None).is_err());
+}
+
+#[test]
+pub fn test_token_lease() {
+  let token = VaultToken::from_auth(&json!({
+    "client_token": "s.token",
+    "lease_duration": 3600,
+    "renewable": true
+  }))
+  .unwrap();
+  let now = Instant::now();
+
+  assert!(!token.needs_renewal(now));
+  assert!(token.needs_renewal(now + Duration::from_secs(2500)));
+  assert!(!token.is_expired(now + Duration::from_secs(2500)));
+  assert!(token.is_expired(now + Duration::from_secs(3600)));
+
+  let token = VaultToken::from_auth(&json!({"client_token": "root", "lease_duration": 0})).unwrap();
+  assert!(!token.needs_renewal(now + Duration::from_secs(3600)));
+  assert!(!token.is_expired(now + Duration::from_secs(3600)));
+}
@@ -0,0 +1,138 @@
+//! Credential stores resolving the parameters with a `store`
+//!
+//! The store of a parameter is selected by its code, case insensitive: `ENVIRONMENT` (or `ENV`), `FILE`,
+//! `HASHICORP_VAULT`, `AWS_SECRETS_MANAGER`, `GCP_SECRET_MANAGER`, any other code being an MCAI backend
+//! configured with `<STORE_CODE>_HOSTNAME` (`BACKEND` by default). A parameter referencing any other store is rejected.
+//! Custom stores, like a corporate KMS, are registered for the whole process with their code,
+//! and take precedence over the built-in stores:
+//!
+//! ```ignore
+//! mcai_worker_sdk::parameter::store::register_store("KMS", KmsStore::new(endpoint));
+//! ```
+
+mod aws_secrets_manager;
+mod backend;
+mod environment;
+mod file;
+mod gcp_secret_manager;
+mod hashicorp_vault;
+
+pub use aws_secrets_manager::AwsSecretsManagerStore;
+pub(crate) use backend::get_session_token;
+pub use backend::BackendStore;
+pub use environment::EnvironmentStore;
+pub use file::FileStore;
+pub use gcp_secret_manager::GcpSecretManagerStore;
+pub use hashicorp_vault::HashicorpVaultStore;
+
+use super::secret::register_secret_value;
+use crate::config::is_store_configured;
+use crate::telemetry::JobSpan;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+
+/// Custom stores, by uppercase code
+static STORES: RwLock<BTreeMap<String, Arc<dyn ParameterStore>>> = RwLock::new(BTreeMap::new());
+
+pub trait ParameterStore: Debug + Send + Sync {
+  /// Value of the credential, a JSON string being parsed
+  fn request_value(&self, credential_key: &str) -> Result<Value, String>;
+}
+
+pub fn register_store<S: ParameterStore + 'static>(store_code: &str, store: S) {
+  STORES
+    .write()
+    .unwrap()
+    .insert(store_code.to_uppercase(), Arc::new(store));
+}
+
+/// Unregister all the custom stores
+pub fn clear_stores() {
+  STORES.write().unwrap().clear();
+}
+
+/// Drop the sessions and tokens of the built-in stores, their credentials being read again on the next request
+pub(crate) fn clear_sessions() {
+  backend::clear_sessions();
+  hashicorp_vault::clear_token();
+  aws_secrets_manager::clear_credentials();
+  gcp_secret_manager::clear_token();
+}
+
+pub fn get_store(store_code: &str) -> Result<Arc<dyn ParameterStore>, String> {
+  let code = store_code.to_uppercase();
+  if let Some(store) = STORES.read().unwrap().get(&code) {
+    return Ok(store.clone());
+  }
+
+  match code.as_str() {
+    "ENV" | "ENVIRONMENT" => Ok(Arc::new(EnvironmentStore)),
+    "FILE" => Ok(Arc::new(FileStore)),
+    "HASHICORP_VAULT" => Ok(Arc::new(HashicorpVaultStore)),
+    "AWS_SECRETS_MANAGER" => Ok(Arc::new(AwsSecretsManagerStore)),
+    "GCP_SECRET_MANAGER" => Ok(Arc::new(GcpSecretManagerStore)),
+    _ if is_store_configured(store_code) => Ok(Arc::new(BackendStore::new(store_code))),
+    _ => Err(format!(
+      "Unknown credential store {}, neither registered nor configured with {}_HOSTNAME",
+      store_code, store_code
+    )),
+  }
+}
+
+/// Value of the credential, masked in the logs and the responses of the jobs in progress
+pub fn request_value(credential_key: &str, store_code: &str) -> Result<Value, String> {
+  let span =
+    JobSpan::new(None, "resolve_credential").with_attribute("credential.store", store_code);
+  let result = get_store(store_code).and_then(|store| store.request_value(credential_key));
+
+  match &result {
+    Ok(value) => register_secret_value(value),
+    Err(error) => span.set_error(error),
+  }
+  result
+}
+
+#[test]
+pub fn test_get_store() {
+  let get_store = |store_code| format!("{:?}", get_store(store_code).unwrap());
+
+  assert_eq!("EnvironmentStore", get_store("env"));
+  assert_eq!("EnvironmentStore", get_store("ENVIRONMENT"));
+  assert_eq!("FileStore", get_store("file"));
+  assert_eq!("HashicorpVaultStore", get_store("HASHICORP_VAULT"));
+  assert_eq!(
+    "BackendStore { store_code: \"BACKEND\" }",
+    get_store("BACKEND")
+  );
+}
+
+#[test]
+pub fn test_get_unknown_store() {
+  assert_eq!(
+    Some(
+      "Unknown credential store MISSING_STORE, neither registered nor configured with MISSING_STORE_HOSTNAME"
+        .to_string()
+    ),
+    get_store("MISSING_STORE").err()
+  );
+}
+
+#[test]
+pub fn test_register_store() {
+  #[derive(Debug)]
+  struct StaticStore;
+
+  impl ParameterStore for StaticStore {
+    fn request_value(&self, credential_key: &str) -> Result<Value, String> {
+      Ok(Value::String(format!("{}_value", credential_key)))
+    }
+  }
+
+  register_store("static_test", StaticStore);
+  assert_eq!(
+    Ok(Value::String("key_value".to_string())),
+    request_value("key", "STATIC_TEST")
+  );
+}
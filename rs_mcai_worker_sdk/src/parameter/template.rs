@@ -0,0 +1,269 @@
+//! Placeholders expanded in the template parameters of the orders
+//!
+//! A field declared as `Template<T>` in the worker parameters is marked as `template` in the schema of the worker:
+//!
+//! ```ignore
+//! #[derive(Debug, Deserialize, JsonSchema)]
+//! pub struct WorkerParameters {
+//!   source_path: String,
+//!   destination_path: Template<String>,
+//! }
+//! ```
+//!
+//! Before deserialization, the string values of the template parameters
+//! (including the strings nested in objects and arrays) expand:
+//! - `{job_id}` to the identifier of the job,
+//! - `{date}` to the current UTC date, like `2021-03-25`,
+//! - `{env:NAME}` to the `NAME` environment variable of the worker, if listed in `PARAMETER_TEMPLATE_VARIABLES`.
+//!
+//! Any other text between braces is kept as is. The other parameters, and the parameters with a store, are not expanded.
+
+use super::has_schema_extension;
+use crate::config::get_parameter_template_variables;
+use chrono::{DateTime, Utc};
+use schemars::{
+  gen::SchemaGenerator,
+  schema::{RootSchema, Schema},
+  JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::ops::Deref;
+
+/// Extension of the schema of a template parameter
+const TEMPLATE_EXTENSION: &str = "template";
+
+/// Value of a parameter whose placeholders are expanded
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Template<T>(T);
+
+impl<T> Template<T> {
+  pub fn new(value: T) -> Self {
+    Template(value)
+  }
+
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> Deref for Template<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T: JsonSchema> JsonSchema for Template<T> {
+  fn is_referenceable() -> bool {
+    false
+  }
+
+  fn schema_name() -> String {
+    format!("Template_{}", T::schema_name())
+  }
+
+  fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<T>().into_object();
+    schema
+      .extensions
+      .insert(TEMPLATE_EXTENSION.to_string(), Value::Bool(true));
+    schema.into()
+  }
+}
+
+/// Parameters declared as `Template` in the schema of the parameters
+pub(crate) fn get_template_parameters(schema: &RootSchema) -> BTreeSet<&str> {
+  schema
+    .schema
+    .object
+    .iter()
+    .flat_map(|validation| validation.properties.iter())
+    .filter(|(_, property)| has_schema_extension(property, TEMPLATE_EXTENSION))
+    .map(|(name, _)| name.as_str())
+    .collect()
+}
+
+/// Values of the placeholders, the same for all the parameters of the job
+pub(crate) struct ParameterTemplate {
+  job_id: u64,
+  date: String,
+  allowed_variables: Vec<String>,
+}
+
+impl ParameterTemplate {
+  pub(crate) fn new(job_id: u64) -> Self {
+    Self::at(job_id, Utc::now())
+  }
+
+  fn at(job_id: u64, now: DateTime<Utc>) -> Self {
+    ParameterTemplate {
+      job_id,
+      date: now.format("%Y-%m-%d").to_string(),
+      allowed_variables: get_parameter_template_variables(),
+    }
+  }
+
+  pub(crate) fn expand(&self, value: Value) -> Result<Value, String> {
+    Ok(match value {
+      Value::String(string) => Value::String(self.expand_string(&string)?),
+      Value::Array(values) => Value::Array(
+        values
+          .into_iter()
+          .map(|value| self.expand(value))
+          .collect::<Result<_, _>>()?,
+      ),
+      Value::Object(object) => Value::Object(
+        object
+          .into_iter()
+          .map(|(key, value)| self.expand(value).map(|value| (key, value)))
+          .collect::<Result<_, _>>()?,
+      ),
+      value => value,
+    })
+  }
+
+  fn expand_string(&self, template: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+      expanded.push_str(&rest[..start]);
+      let after = &rest[start + 1..];
+
+      let placeholder = match after.find('}') {
+        Some(end) => self
+          .get_placeholder_value(&after[..end])?
+          .map(|value| (end, value)),
+        None => None,
+      };
+
+      match placeholder {
+        Some((end, value)) => {
+          expanded.push_str(&value);
+          rest = &after[end + 1..];
+        }
+        None => {
+          expanded.push('{');
+          rest = after;
+        }
+      }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+  }
+
+  fn get_placeholder_value(&self, placeholder: &str) -> Result<Option<String>, String> {
+    if let Some(variable) = placeholder.strip_prefix("env:") {
+      return self.get_variable(variable).map(Some);
+    }
+
+    Ok(match placeholder {
+      "job_id" => Some(self.job_id.to_string()),
+      "date" => Some(self.date.clone()),
+      _ => None,
+    })
+  }
+
+  /// No variable is readable unless listed in `PARAMETER_TEMPLATE_VARIABLES`
+  fn get_variable(&self, variable: &str) -> Result<String, String> {
+    if !self
+      .allowed_variables
+      .iter()
+      .any(|allowed| allowed == variable)
+    {
+      return Err(format!(
+        "Environment variable {} is not allowed in the parameters",
+        variable
+      ));
+    }
+
+    std::env::var(variable).map_err(|_| format!("Missing environment variable {}", variable))
+  }
+}
+
+#[test]
+pub fn test_expand_string() {
+  use chrono::TimeZone;
+
+  std::env::set_var("TEMPLATE_TEST_OUTPUT_ROOT", "/mnt/output");
+  let template = ParameterTemplate {
+    allowed_variables: vec![
+      "TEMPLATE_TEST_OUTPUT_ROOT".to_string(),
+      "TEMPLATE_TEST_MISSING".to_string(),
+    ],
+    ..ParameterTemplate::at(123, Utc.with_ymd_and_hms(2021, 3, 25, 10, 30, 0).unwrap())
+  };
+
+  assert_eq!(
+    Ok("/mnt/output/2021-03-25/123.mp4".to_string()),
+    template.expand_string("{env:TEMPLATE_TEST_OUTPUT_ROOT}/{date}/{job_id}.mp4")
+  );
+  assert_eq!(
+    Ok("{\"key\": \"{unknown}\"} {job_id".to_string()),
+    template.expand_string("{\"key\": \"{unknown}\"} {job_id")
+  );
+  assert_eq!(
+    Err("Missing environment variable TEMPLATE_TEST_MISSING".to_string()),
+    template.expand_string("{env:TEMPLATE_TEST_MISSING}")
+  );
+}
+
+#[test]
+pub fn test_expand_value() {
+  let template = ParameterTemplate {
+    job_id: 456,
+    date: "2021-03-25".to_string(),
+    allowed_variables: vec!["OUTPUT_ROOT".to_string()],
+  };
+
+  assert_eq!(
+    Ok(json!({"paths": ["/out/456/a", "/out/456/b"], "count": 2})),
+    template.expand(json!({"paths": ["/out/{job_id}/a", "/out/{job_id}/b"], "count": 2}))
+  );
+  assert_eq!(
+    Err("Environment variable HOME is not allowed in the parameters".to_string()),
+    template.expand(json!("{env:HOME}/output"))
+  );
+}
+
+#[test]
+pub fn test_environment_denied_by_default() {
+  std::env::set_var("TEMPLATE_TEST_DENIED", "value");
+  let template = ParameterTemplate {
+    job_id: 456,
+    date: "2021-03-25".to_string(),
+    allowed_variables: vec![],
+  };
+
+  assert_eq!(
+    Err("Environment variable TEMPLATE_TEST_DENIED is not allowed in the parameters".to_string()),
+    template.expand_string("{env:TEMPLATE_TEST_DENIED}")
+  );
+}
+
+#[test]
+pub fn test_template_parameters() {
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct Parameters {
+    source_path: String,
+    destination_path: Template<String>,
+    log_path: Option<Template<String>>,
+  }
+
+  let schema = crate::worker::parameter_schema_for::<Parameters>();
+  assert_eq!(
+    vec!["destination_path", "log_path"],
+    get_template_parameters(&schema)
+      .into_iter()
+      .collect::<Vec<&str>>()
+  );
+
+  let parameters: Parameters =
+    serde_json::from_value(json!({"source_path": "/in", "destination_path": "/out"})).unwrap();
+  assert_eq!("/out", parameters.destination_path.as_str());
+}
@@ -0,0 +1,188 @@
+//! Display hints of the parameters, for the forms of the StepFlow frontend
+//!
+//! The hints of the parameters are given by `MessageEvent::get_parameter_hints`:
+//!
+//! ```ignore
+//! fn get_parameter_hints(&self) -> Vec<ParameterHint> {
+//!   vec![
+//!     ParameterHint::new("threshold")
+//!       .with_label("Detection threshold")
+//!       .in_section("Detection")
+//!       .with_widget(Widget::Slider),
+//!     ParameterHint::new("mode")
+//!       .with_enum_label("Fast", "Fast, lower accuracy")
+//!       .with_enum_label("Accurate", "Accurate, slower"),
+//!   ]
+//! }
+//! ```
+//!
+//! In the worker description, the label is the `title` of the parameter and the other hints are in its `ui` object:
+//!
+//! ```json
+//! "threshold": {"type": "number", "title": "Detection threshold", "ui": {"section": "Detection", "widget": "slider"}}
+//! ```
+
+use schemars::schema::{RootSchema, Schema};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Extension of the schema of a parameter with its display hints
+const UI_EXTENSION: &str = "ui";
+
+/// Input of a parameter in a form
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Widget {
+  Checkbox,
+  ColorPicker,
+  FilePicker,
+  Password,
+  Select,
+  Slider,
+  TextArea,
+  TextInput,
+}
+
+/// Hints of a parameter in the worker description, besides its label
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UiHints {
+  /// Section of the form grouping the parameter with others
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub section: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub widget: Option<Widget>,
+  /// Labels of the values of an enumeration
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub enum_labels: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterHint {
+  parameter: String,
+  label: Option<String>,
+  hints: UiHints,
+}
+
+impl ParameterHint {
+  pub fn new(parameter: &str) -> Self {
+    ParameterHint {
+      parameter: parameter.to_string(),
+      label: None,
+      hints: UiHints::default(),
+    }
+  }
+
+  /// Human readable label, instead of the identifier of the parameter
+  pub fn with_label(mut self, label: &str) -> Self {
+    self.label = Some(label.to_string());
+    self
+  }
+
+  pub fn in_section(mut self, section: &str) -> Self {
+    self.hints.section = Some(section.to_string());
+    self
+  }
+
+  pub fn with_widget(mut self, widget: Widget) -> Self {
+    self.hints.widget = Some(widget);
+    self
+  }
+
+  pub fn with_enum_label(mut self, value: &str, label: &str) -> Self {
+    self
+      .hints
+      .enum_labels
+      .insert(value.to_string(), label.to_string());
+    self
+  }
+}
+
+/// Add the display hints to the schema of the parameters of a worker
+pub(crate) fn apply_hints(schema: &mut RootSchema, hints: &[ParameterHint]) {
+  let properties = &mut schema.schema.object().properties;
+  for hint in hints {
+    let property = match properties.get_mut(&hint.parameter) {
+      Some(Schema::Object(property)) => property,
+      _ => {
+        warn!(
+          "Parameter {:?} with display hints missing from the parameters of the worker",
+          hint.parameter
+        );
+        continue;
+      }
+    };
+
+    if let Some(label) = &hint.label {
+      property.metadata().title = Some(label.clone());
+    }
+    if hint.hints != UiHints::default() {
+      match serde_json::to_value(&hint.hints) {
+        Ok(value) => {
+          property.extensions.insert(UI_EXTENSION.to_string(), value);
+        }
+        Err(error) => warn!(
+          "Unable to describe the display hints of parameter {:?}: {}",
+          hint.parameter, error
+        ),
+      }
+    }
+  }
+}
+
+#[test]
+pub fn test_apply_hints() {
+  use schemars::JsonSchema;
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Parameters {
+    source_path: String,
+    #[schemars(range(min = 0, max = 1))]
+    threshold: f64,
+    mode: Mode,
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  enum Mode {
+    Fast,
+    Accurate,
+  }
+
+  let mut schema = crate::worker::parameter_schema_for::<Parameters>();
+  apply_hints(
+    &mut schema,
+    &[
+      ParameterHint::new("source_path")
+        .with_label("Source file")
+        .with_widget(Widget::FilePicker),
+      ParameterHint::new("threshold")
+        .with_label("Detection threshold")
+        .in_section("Detection")
+        .with_widget(Widget::Slider),
+      ParameterHint::new("mode")
+        .in_section("Detection")
+        .with_enum_label("Fast", "Fast, lower accuracy"),
+      ParameterHint::new("missing").with_label("Missing"),
+    ],
+  );
+
+  let description = serde_json::to_value(&schema).unwrap();
+  assert_eq!(
+    json!({"type": "string", "title": "Source file", "ui": {"widget": "file_picker"}}),
+    description["properties"]["source_path"]
+  );
+  assert_eq!(
+    json!("Detection threshold"),
+    description["properties"]["threshold"]["title"]
+  );
+  assert_eq!(
+    json!({"section": "Detection", "widget": "slider"}),
+    description["properties"]["threshold"]["ui"]
+  );
+  assert_eq!(
+    json!({"section": "Detection", "enum_labels": {"Fast": "Fast, lower accuracy"}}),
+    description["properties"]["mode"]["ui"]
+  );
+  assert_eq!(None, description["properties"].get("missing"));
+}
@@ -0,0 +1,618 @@
+//! Validation of the job parameters against the schema of the worker parameters
+//!
+//! Every violation is reported with the JSON pointer of the invalid value, like:
+//!
+//! ```json
+//! [
+//!   {"path": "/threshold", "message": "1.5 is greater than the maximum 1"},
+//!   {"path": "/segments/0", "message": "missing required property \"end\""}
+//! ]
+//! ```
+//!
+//! The credential fields are not validated, their value is resolved after.
+
+use super::ObjectParameter;
+use regex::Regex;
+use schemars::schema::{
+  InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec, SubschemaValidation,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+pub const VALIDATION_ERRORS_PARAMETER: &str = "validation_errors";
+
+/// Job result parameter listing the parameters of the order unknown to the worker
+pub const UNKNOWN_PARAMETERS_PARAMETER: &str = "unknown_parameters";
+
+/// Value of a parameter not matching its schema
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Violation {
+  /// JSON pointer of the value in the parameters
+  pub path: String,
+  pub message: String,
+}
+
+impl ObjectParameter for Violation {}
+
+impl Violation {
+  fn new(path: &str, message: String) -> Self {
+    Violation {
+      path: path.to_string(),
+      message,
+    }
+  }
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    if self.path.is_empty() {
+      write!(formatter, "{}", self.message)
+    } else {
+      write!(formatter, "{}: {}", self.path, self.message)
+    }
+  }
+}
+
+/// List every violation of the schema by the parameters
+pub fn validate(schema: &RootSchema, parameters: &Value) -> Vec<Violation> {
+  let mut violations = vec![];
+  Validator { root: schema }.validate(&schema.schema, parameters, "", &mut violations);
+  violations
+}
+
+/// Names of the parameters declared in the schema, including the fields of flattened structs,
+/// none if the schema accepts any parameter
+pub(crate) fn get_declared_parameters(schema: &RootSchema) -> Option<BTreeSet<&str>> {
+  let mut names = BTreeSet::new();
+  collect_properties(&schema.schema, &mut names)?;
+  Some(names)
+}
+
+fn collect_properties<'a>(schema: &'a SchemaObject, names: &mut BTreeSet<&'a str>) -> Option<()> {
+  let validation = schema.object.as_ref()?;
+  match validation.additional_properties.as_deref() {
+    None | Some(Schema::Bool(false)) => {}
+    Some(_) => return None,
+  }
+  names.extend(validation.properties.keys().map(String::as_str));
+
+  if let Some(subschemas) = &schema.subschemas {
+    if subschemas.any_of.is_some() || subschemas.one_of.is_some() {
+      return None;
+    }
+    for subschema in subschemas.all_of.iter().flatten() {
+      match subschema {
+        Schema::Object(subschema) => collect_properties(subschema, names)?,
+        Schema::Bool(_) => return None,
+      }
+    }
+  }
+  Some(())
+}
+
+struct Validator<'a> {
+  root: &'a RootSchema,
+}
+
+impl<'a> Validator<'a> {
+  fn validate_schema(
+    &self,
+    schema: &Schema,
+    value: &Value,
+    path: &str,
+    violations: &mut Vec<Violation>,
+  ) {
+    match schema {
+      Schema::Bool(true) => {}
+      Schema::Bool(false) => {
+        violations.push(Violation::new(path, "no value is allowed".to_string()))
+      }
+      Schema::Object(schema) => self.validate(schema, value, path, violations),
+    }
+  }
+
+  fn validate(
+    &self,
+    schema: &SchemaObject,
+    value: &Value,
+    path: &str,
+    violations: &mut Vec<Violation>,
+  ) {
+    if schema.format.as_deref() == Some("credential") {
+      return;
+    }
+
+    if let Some(reference) = &schema.reference {
+      match self.resolve(reference) {
+        Some(schema) => self.validate(schema, value, path, violations),
+        None => warn!("Unable to resolve the schema reference {}", reference),
+      }
+      return;
+    }
+
+    if let Some(instance_type) = &schema.instance_type {
+      if !matches_instance_type(instance_type, value) {
+        violations.push(Violation::new(
+          path,
+          format!(
+            "{} is not of type {}",
+            value,
+            get_instance_type_names(instance_type)
+          ),
+        ));
+        return;
+      }
+    }
+
+    if let Some(enum_values) = &schema.enum_values {
+      if !enum_values.contains(value) {
+        violations.push(Violation::new(
+          path,
+          format!(
+            "{} is not one of {}",
+            value,
+            Value::from(enum_values.clone())
+          ),
+        ));
+      }
+    }
+
+    if let Some(const_value) = &schema.const_value {
+      if const_value != value {
+        violations.push(Violation::new(
+          path,
+          format!("{} is not equal to {}", value, const_value),
+        ));
+      }
+    }
+
+    if let Some(subschemas) = &schema.subschemas {
+      self.validate_subschemas(subschemas, value, path, violations);
+    }
+
+    match value {
+      Value::Object(object) => self.validate_object(schema, object, path, violations),
+      Value::Array(items) => self.validate_array(schema, items, path, violations),
+      Value::Number(number) => validate_number(schema, number.as_f64(), path, violations),
+      Value::String(string) => validate_string(schema, string, path, violations),
+      Value::Null | Value::Bool(_) => {}
+    }
+  }
+
+  fn validate_subschemas(
+    &self,
+    subschemas: &SubschemaValidation,
+    value: &Value,
+    path: &str,
+    violations: &mut Vec<Violation>,
+  ) {
+    for schema in subschemas.all_of.iter().flatten() {
+      self.validate_schema(schema, value, path, violations);
+    }
+
+    if let Some(any_of) = &subschemas.any_of {
+      if self.count_valid(any_of, value, path) == 0 {
+        violations.push(Violation::new(
+          path,
+          format!("{} does not match any of the allowed schemas", value),
+        ));
+      }
+    }
+
+    if let Some(one_of) = &subschemas.one_of {
+      let valid = self.count_valid(one_of, value, path);
+      if valid != 1 {
+        violations.push(Violation::new(
+          path,
+          format!(
+            "{} matches {} of the schemas instead of exactly one",
+            value, valid
+          ),
+        ));
+      }
+    }
+
+    if let Some(not) = &subschemas.not {
+      let mut not_violations = vec![];
+      self.validate_schema(not, value, path, &mut not_violations);
+      if not_violations.is_empty() {
+        violations.push(Violation::new(
+          path,
+          format!("{} matches a forbidden schema", value),
+        ));
+      }
+    }
+  }
+
+  fn count_valid(&self, schemas: &[Schema], value: &Value, path: &str) -> usize {
+    schemas
+      .iter()
+      .filter(|schema| {
+        let mut schema_violations = vec![];
+        self.validate_schema(schema, value, path, &mut schema_violations);
+        schema_violations.is_empty()
+      })
+      .count()
+  }
+
+  fn validate_object(
+    &self,
+    schema: &SchemaObject,
+    object: &serde_json::Map<String, Value>,
+    path: &str,
+    violations: &mut Vec<Violation>,
+  ) {
+    let validation = match &schema.object {
+      Some(validation) => validation,
+      None => return,
+    };
+
+    for required in &validation.required {
+      if !object.contains_key(required) {
+        violations.push(Violation::new(
+          path,
+          format!("missing required property {:?}", required),
+        ));
+      }
+    }
+
+    for (name, value) in object {
+      let property_path = format!("{}/{}", path, escape_pointer(name));
+      match validation.properties.get(name) {
+        Some(property) => self.validate_schema(property, value, &property_path, violations),
+        None => {
+          if let Some(additional_properties) = &validation.additional_properties {
+            if let Schema::Bool(false) = additional_properties.as_ref() {
+              violations.push(Violation::new(
+                &property_path,
+                format!("unknown property {:?}", name),
+              ));
+            } else {
+              self.validate_schema(additional_properties, value, &property_path, violations);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn validate_array(
+    &self,
+    schema: &SchemaObject,
+    items: &[Value],
+    path: &str,
+    violations: &mut Vec<Violation>,
+  ) {
+    let validation = match &schema.array {
+      Some(validation) => validation,
+      None => return,
+    };
+
+    if let Some(min_items) = validation.min_items {
+      if items.len() < min_items as usize {
+        violations.push(Violation::new(
+          path,
+          format!("has {} items, fewer than {}", items.len(), min_items),
+        ));
+      }
+    }
+    if let Some(max_items) = validation.max_items {
+      if items.len() > max_items as usize {
+        violations.push(Violation::new(
+          path,
+          format!("has {} items, more than {}", items.len(), max_items),
+        ));
+      }
+    }
+
+    for (index, item) in items.iter().enumerate() {
+      let item_path = format!("{}/{}", path, index);
+      let item_schema = match &validation.items {
+        Some(SingleOrVec::Single(schema)) => Some(schema.as_ref()),
+        Some(SingleOrVec::Vec(schemas)) => schemas
+          .get(index)
+          .or(validation.additional_items.as_deref()),
+        None => None,
+      };
+      if let Some(item_schema) = item_schema {
+        self.validate_schema(item_schema, item, &item_path, violations);
+      }
+    }
+  }
+
+  fn resolve(&self, reference: &str) -> Option<&'a SchemaObject> {
+    let name = reference.strip_prefix("#/definitions/")?;
+    match self.root.definitions.get(name)? {
+      Schema::Object(schema) => Some(schema),
+      Schema::Bool(_) => None,
+    }
+  }
+}
+
+fn validate_number(
+  schema: &SchemaObject,
+  number: Option<f64>,
+  path: &str,
+  violations: &mut Vec<Violation>,
+) {
+  let (validation, number) = match (&schema.number, number) {
+    (Some(validation), Some(number)) => (validation, number),
+    _ => return,
+  };
+
+  if let Some(minimum) = validation.minimum {
+    if number < minimum {
+      violations.push(Violation::new(
+        path,
+        format!("{} is less than the minimum {}", number, minimum),
+      ));
+    }
+  }
+  if let Some(maximum) = validation.maximum {
+    if number > maximum {
+      violations.push(Violation::new(
+        path,
+        format!("{} is greater than the maximum {}", number, maximum),
+      ));
+    }
+  }
+  if let Some(minimum) = validation.exclusive_minimum {
+    if number <= minimum {
+      violations.push(Violation::new(
+        path,
+        format!("{} is not greater than {}", number, minimum),
+      ));
+    }
+  }
+  if let Some(maximum) = validation.exclusive_maximum {
+    if number >= maximum {
+      violations.push(Violation::new(
+        path,
+        format!("{} is not less than {}", number, maximum),
+      ));
+    }
+  }
+  if let Some(multiple_of) = validation.multiple_of {
+    if multiple_of > 0.0 && !is_multiple_of(number, multiple_of) {
+      violations.push(Violation::new(
+        path,
+        format!("{} is not a multiple of {}", number, multiple_of),
+      ));
+    }
+  }
+}
+
+/// The quotient is compared within the rounding error of the floating-point division,
+/// like `0.3 / 0.1` giving `2.9999999999999996`
+fn is_multiple_of(number: f64, multiple_of: f64) -> bool {
+  let quotient = number / multiple_of;
+  let tolerance = (quotient.abs() * f64::EPSILON * 4.0).max(1e-9);
+  (quotient - quotient.round()).abs() <= tolerance
+}
+
+fn validate_string(
+  schema: &SchemaObject,
+  string: &str,
+  path: &str,
+  violations: &mut Vec<Violation>,
+) {
+  let validation = match &schema.string {
+    Some(validation) => validation,
+    None => return,
+  };
+
+  let length = string.chars().count();
+  if let Some(min_length) = validation.min_length {
+    if length < min_length as usize {
+      violations.push(Violation::new(
+        path,
+        format!("{:?} is shorter than {} characters", string, min_length),
+      ));
+    }
+  }
+  if let Some(max_length) = validation.max_length {
+    if length > max_length as usize {
+      violations.push(Violation::new(
+        path,
+        format!("{:?} is longer than {} characters", string, max_length),
+      ));
+    }
+  }
+  if let Some(pattern) = &validation.pattern {
+    match Regex::new(pattern) {
+      Ok(regex) if !regex.is_match(string) => violations.push(Violation::new(
+        path,
+        format!("{:?} does not match the pattern {:?}", string, pattern),
+      )),
+      Ok(_) => {}
+      Err(error) => warn!("Invalid pattern {:?} in the schema: {}", pattern, error),
+    }
+  }
+}
+
+fn matches_instance_type(instance_type: &SingleOrVec<InstanceType>, value: &Value) -> bool {
+  match instance_type {
+    SingleOrVec::Single(instance_type) => is_instance_of(instance_type, value),
+    SingleOrVec::Vec(instance_types) => instance_types
+      .iter()
+      .any(|instance_type| is_instance_of(instance_type, value)),
+  }
+}
+
+fn is_instance_of(instance_type: &InstanceType, value: &Value) -> bool {
+  match (instance_type, value) {
+    (InstanceType::Null, Value::Null)
+    | (InstanceType::Boolean, Value::Bool(_))
+    | (InstanceType::Object, Value::Object(_))
+    | (InstanceType::Array, Value::Array(_))
+    | (InstanceType::Number, Value::Number(_))
+    | (InstanceType::String, Value::String(_)) => true,
+    (InstanceType::Integer, Value::Number(number)) => {
+      number.is_i64() || number.is_u64() || number.as_f64().is_some_and(|n| n.fract() == 0.0)
+    }
+    _ => false,
+  }
+}
+
+fn get_instance_type_names(instance_type: &SingleOrVec<InstanceType>) -> String {
+  let names = match instance_type {
+    SingleOrVec::Single(instance_type) => vec![get_instance_type_name(instance_type)],
+    SingleOrVec::Vec(instance_types) => instance_types.iter().map(get_instance_type_name).collect(),
+  };
+  names.join(" or ")
+}
+
+fn get_instance_type_name(instance_type: &InstanceType) -> &'static str {
+  match instance_type {
+    InstanceType::Null => "null",
+    InstanceType::Boolean => "boolean",
+    InstanceType::Object => "object",
+    InstanceType::Array => "array",
+    InstanceType::Number => "number",
+    InstanceType::String => "string",
+    InstanceType::Integer => "integer",
+  }
+}
+
+/// Escape a property name in a JSON pointer
+fn escape_pointer(name: &str) -> String {
+  name.replace('~', "~0").replace('/', "~1")
+}
+
+#[test]
+pub fn test_validate() {
+  use schemars::JsonSchema;
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Segment {
+    start: u64,
+    end: u64,
+    #[schemars(length(min = 1))]
+    label: String,
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Parameters {
+    source_path: String,
+    #[schemars(range(min = 0, max = 1))]
+    threshold: f64,
+    mode: Option<Mode>,
+    segments: Vec<Segment>,
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  enum Mode {
+    Fast,
+    Accurate,
+  }
+
+  let schema = crate::worker::parameter_schema_for::<Parameters>();
+
+  let parameters = json!({
+    "source_path": "/path/to/file",
+    "threshold": 0.5,
+    "mode": "Fast",
+    "segments": [{"start": 0, "end": 1000, "label": "intro"}]
+  });
+  assert_eq!(Vec::<Violation>::new(), validate(&schema, &parameters));
+
+  let parameters = json!({
+    "threshold": 1.5,
+    "mode": "Slow",
+    "segments": [{"start": -1, "label": ""}]
+  });
+  let violations = validate(&schema, &parameters);
+  let paths: Vec<&str> = violations
+    .iter()
+    .map(|violation| violation.path.as_str())
+    .collect();
+  assert_eq!(
+    vec![
+      "",
+      "/mode",
+      "/segments/0",
+      "/segments/0/label",
+      "/segments/0/start",
+      "/threshold"
+    ],
+    paths
+  );
+  assert_eq!(
+    "missing required property \"source_path\"",
+    violations[0].message
+  );
+  assert_eq!("1.5 is greater than the maximum 1", violations[5].message);
+}
+
+#[test]
+pub fn test_validate_recursive_schema() {
+  use schemars::JsonSchema;
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Node {
+    name: String,
+    children: Vec<Node>,
+  }
+
+  let schema = crate::worker::parameter_schema_for::<Node>();
+
+  let parameters = json!({"name": "root", "children": [{"name": "leaf", "children": []}]});
+  assert!(validate(&schema, &parameters).is_empty());
+
+  let parameters = json!({"name": "root", "children": [{"name": 3, "children": []}]});
+  let violations = validate(&schema, &parameters);
+  assert_eq!(1, violations.len());
+  assert_eq!("/children/0/name", violations[0].path);
+}
+
+#[test]
+pub fn test_get_declared_parameters() {
+  use schemars::JsonSchema;
+  use std::collections::HashMap;
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Output {
+    destination_path: String,
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct Parameters {
+    source_path: String,
+    #[serde(flatten)]
+    output: Output,
+  }
+
+  let schema = crate::worker::parameter_schema_for::<Parameters>();
+  assert_eq!(
+    Some(vec!["destination_path", "source_path"]),
+    get_declared_parameters(&schema).map(|names| names.into_iter().collect())
+  );
+
+  let schema = crate::worker::parameter_schema_for::<HashMap<String, String>>();
+  assert_eq!(None, get_declared_parameters(&schema));
+}
+
+#[test]
+pub fn test_validate_multiple_of() {
+  let schema: RootSchema = serde_json::from_value(json!({
+    "type": "object",
+    "properties": {"step": {"type": "number", "multipleOf": 0.1}}
+  }))
+  .unwrap();
+
+  for step in [json!(0.3), json!(0.7), json!(3), json!(1234567.8)] {
+    assert!(validate(&schema, &json!({ "step": step })).is_empty());
+  }
+
+  let violations = validate(&schema, &json!({"step": 0.35}));
+  assert_eq!(1, violations.len());
+  assert_eq!("0.35 is not a multiple of 0.1", violations[0].message);
+}
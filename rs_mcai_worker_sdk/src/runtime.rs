@@ -0,0 +1,154 @@
+//! Runtime driving the asynchronous internals of the SDK
+//!
+//! By default the SDK does not rely on the runtime of the application: the jobs run on threads of the worker,
+//! and `process_async` is driven by a runtime created for each job.
+//! An application already running an asynchronous runtime provides it with `set_executor`
+//! (or `WorkerBuilder::with_executor`), the `tokio_runtime` feature provides one for Tokio:
+//!
+//! ```ignore
+//! mcai_worker_sdk::runtime::set_executor(TokioExecutor::current());
+//! ```
+//!
+//! The executor then drives the AMQP connection and `process_async`,
+//! and runs the orders with a timeout and the slots of the re-runs scheduler on its blocking threads.
+//! The other jobs still run on the job pool of the worker,
+//! or on the thread consuming the orders when `MAX_CONCURRENT_JOBS` is `1`.
+
+use crate::{MessageError, Result};
+use futures_util::future::{BoxFuture, FutureExt, LocalBoxFuture};
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+/// Executor set by the application, the SDK one is used if none
+static EXECUTOR: RwLock<Option<Arc<dyn Executor>>> = RwLock::new(None);
+
+pub trait Executor: Debug + Send + Sync {
+  /// Run the future in the background
+  fn spawn(&self, future: BoxFuture<'static, ()>);
+
+  /// Run a blocking task, like a job processing, out of the asynchronous tasks
+  fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>);
+
+  /// Block the current thread until the future is completed,
+  /// never called from an asynchronous task of the runtime
+  fn block_on(&self, future: LocalBoxFuture<'_, ()>) -> Result<()>;
+}
+
+/// Executor of the SDK, running each task on its own thread
+#[derive(Debug, Default)]
+pub struct ThreadExecutor {}
+
+impl Executor for ThreadExecutor {
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    thread::spawn(move || futures_executor::block_on(future));
+  }
+
+  fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+    thread::spawn(task);
+  }
+
+  fn block_on(&self, future: LocalBoxFuture<'_, ()>) -> Result<()> {
+    tokio::runtime::Builder::new()
+      .basic_scheduler()
+      .enable_all()
+      .build()
+      .map(|mut runtime| runtime.block_on(future))
+      .map_err(|error| MessageError::RuntimeError(format!("Unable to start runtime: {}", error)))
+  }
+}
+
+/// Executor of an existing Tokio runtime
+#[cfg(feature = "tokio_runtime")]
+#[derive(Clone, Debug)]
+pub struct TokioExecutor {
+  handle: tokio_1::runtime::Handle,
+}
+
+#[cfg(feature = "tokio_runtime")]
+impl TokioExecutor {
+  pub fn new(handle: tokio_1::runtime::Handle) -> Self {
+    TokioExecutor { handle }
+  }
+
+  /// Executor of the runtime of the current task, panics if called out of a Tokio runtime
+  pub fn current() -> Self {
+    Self::new(tokio_1::runtime::Handle::current())
+  }
+}
+
+#[cfg(feature = "tokio_runtime")]
+impl Executor for TokioExecutor {
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    self.handle.spawn(future);
+  }
+
+  fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+    self.handle.spawn_blocking(task);
+  }
+
+  fn block_on(&self, future: LocalBoxFuture<'_, ()>) -> Result<()> {
+    self.handle.block_on(future);
+    Ok(())
+  }
+}
+
+/// Executor of the AMQP connection, only set when the application provides one
+#[derive(Debug)]
+pub(crate) struct AmqpExecutor {
+  executor: Arc<dyn Executor>,
+}
+
+impl lapin::executor::Executor for AmqpExecutor {
+  fn spawn(&self, future: std::pin::Pin<Box<dyn Future<Output = ()> + Send>>) -> lapin::Result<()> {
+    self.executor.spawn(future);
+    Ok(())
+  }
+}
+
+/// Replace the executor of the SDK for the whole process
+pub fn set_executor<E: Executor + 'static>(executor: E) {
+  set_shared_executor(Arc::new(executor));
+}
+
+pub(crate) fn set_shared_executor(executor: Arc<dyn Executor>) {
+  *EXECUTOR.write().unwrap() = Some(executor);
+}
+
+pub fn get_executor() -> Arc<dyn Executor> {
+  EXECUTOR
+    .read()
+    .unwrap()
+    .clone()
+    .unwrap_or_else(|| Arc::new(ThreadExecutor::default()))
+}
+
+pub(crate) fn get_amqp_executor() -> Option<AmqpExecutor> {
+  EXECUTOR
+    .read()
+    .unwrap()
+    .clone()
+    .map(|executor| AmqpExecutor { executor })
+}
+
+pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+  get_executor().spawn(future.boxed());
+}
+
+pub fn spawn_blocking<F: FnOnce() + Send + 'static>(task: F) {
+  get_executor().spawn_blocking(Box::new(task));
+}
+
+/// Block the current thread until the future is completed, it must not be called from an asynchronous task
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+  let mut output = None;
+  get_executor().block_on(
+    async {
+      output = Some(future.await);
+    }
+    .boxed_local(),
+  )?;
+
+  output.ok_or_else(|| MessageError::RuntimeError("The future has not been completed".to_string()))
+}
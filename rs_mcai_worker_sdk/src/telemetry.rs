@@ -0,0 +1,262 @@
+//! OpenTelemetry spans of the processing of the orders (`otel` feature)
+//!
+//! Once `OTEL_EXPORTER_OTLP_ENDPOINT` is set, the reception of each order, the resolution of its credentials,
+//! its processing and the publication of its responses are exported as spans over OTLP/HTTP (protobuf).
+//! The span of an order continues the trace of its `trace_context` when present.
+//! Without the feature, the spans are not recorded.
+
+#[cfg(feature = "otel")]
+use crate::config::{get_otlp_endpoint, get_otlp_service_name};
+#[cfg(feature = "otel")]
+use opentelemetry::{
+  global,
+  propagation::TextMapPropagator,
+  trace::{Span, Status, TraceContextExt, Tracer},
+  Context, ContextGuard, KeyValue,
+};
+#[cfg(feature = "otel")]
+use opentelemetry_http::{Bytes, HttpClient, HttpError, Request, Response};
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig, WithHttpConfig};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+#[cfg(feature = "otel")]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "otel")]
+use std::sync::{OnceLock, RwLock};
+
+#[cfg(feature = "otel")]
+const TRACER_NAME: &str = "mcai_worker_sdk";
+
+/// Client of the OTLP endpoint, the blocking client of the SDK
+#[cfg(feature = "otel")]
+#[derive(Debug, Default)]
+struct OtlpClient {
+  // created on the exporting thread, outside of the runtime of the worker
+  client: OnceLock<reqwest::blocking::Client>,
+}
+
+#[cfg(feature = "otel")]
+#[async_trait::async_trait]
+impl HttpClient for OtlpClient {
+  async fn send_bytes(&self, request: Request<Bytes>) -> Result<Response<Bytes>, HttpError> {
+    let method = reqwest::Method::from_bytes(request.method().as_str().as_bytes())?;
+    let mut builder = self
+      .client
+      .get_or_init(reqwest::blocking::Client::new)
+      .request(method, &request.uri().to_string());
+    for (name, value) in request.headers() {
+      builder = builder.header(name.as_str(), value.as_bytes());
+    }
+
+    let response = builder.body(request.into_body().to_vec()).send()?;
+    let status = response.status().as_u16();
+    let body = response.bytes()?.to_vec();
+
+    Ok(Response::builder().status(status).body(Bytes::from(body))?)
+  }
+}
+
+/// Context of the span of each order in progress, parent of the spans of its job
+#[cfg(feature = "otel")]
+static ORDER_CONTEXTS: RwLock<BTreeMap<u64, Context>> = RwLock::new(BTreeMap::new());
+
+/// Export the spans if an endpoint is configured, until the shutdown of the worker,
+/// the `service.version` being unset without a single version of the workers
+#[cfg(feature = "otel")]
+pub(crate) fn init(worker_name: &str, worker_version: Option<&str>, instance_id: &str) {
+  let endpoint = match get_otlp_endpoint() {
+    Some(endpoint) => endpoint,
+    None => return,
+  };
+
+  let exporter = match SpanExporter::builder()
+    .with_http()
+    .with_http_client(OtlpClient::default())
+    .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+    .build()
+  {
+    Ok(exporter) => exporter,
+    Err(error) => {
+      error!("Unable to export the spans to {}: {}", endpoint, error);
+      return;
+    }
+  };
+
+  let mut attributes = vec![KeyValue::new(
+    "service.instance.id",
+    instance_id.to_string(),
+  )];
+  if let Some(worker_version) = worker_version {
+    attributes.push(KeyValue::new("service.version", worker_version.to_string()));
+  }
+  let resource = Resource::builder()
+    .with_service_name(get_otlp_service_name().unwrap_or_else(|| worker_name.to_string()))
+    .with_attributes(attributes)
+    .build();
+
+  let provider = SdkTracerProvider::builder()
+    .with_batch_exporter(exporter)
+    .with_resource(resource)
+    .build();
+  global::set_tracer_provider(provider.clone());
+  info!("Spans exported to {}", endpoint);
+
+  // the pending spans are exported before exiting
+  crate::worker::shutdown::register(move || {
+    if let Err(error) = provider.shutdown() {
+      warn!("Unable to export the pending spans: {}", error);
+    }
+  });
+}
+
+#[cfg(not(feature = "otel"))]
+pub(crate) fn init(_worker_name: &str, _worker_version: Option<&str>, _instance_id: &str) {}
+
+/// Span of an order, from its reception to the publication of its result
+pub(crate) struct OrderSpan {
+  #[cfg(feature = "otel")]
+  job_id: Option<u64>,
+}
+
+impl OrderSpan {
+  /// Child of the span of the `traceparent` of the order, if any
+  #[cfg(feature = "otel")]
+  pub(crate) fn new(job_id: Option<u64>, traceparent: Option<&str>) -> Self {
+    let job_id = match job_id {
+      Some(job_id) => job_id,
+      None => return OrderSpan { job_id: None },
+    };
+
+    let parent = match traceparent {
+      Some(traceparent) => {
+        let carrier: HashMap<String, String> =
+          vec![("traceparent".to_string(), traceparent.to_string())]
+            .into_iter()
+            .collect();
+        TraceContextPropagator::new().extract(&carrier)
+      }
+      None => Context::new(),
+    };
+
+    let mut span = global::tracer(TRACER_NAME).start_with_context("order", &parent);
+    span.set_attribute(KeyValue::new("job_id", job_id as i64));
+    ORDER_CONTEXTS
+      .write()
+      .unwrap()
+      .insert(job_id, parent.with_span(span));
+
+    OrderSpan {
+      job_id: Some(job_id),
+    }
+  }
+
+  #[cfg(not(feature = "otel"))]
+  pub(crate) fn new(_job_id: Option<u64>, _traceparent: Option<&str>) -> Self {
+    OrderSpan {}
+  }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for OrderSpan {
+  fn drop(&mut self) {
+    if let Some(context) = self
+      .job_id
+      .and_then(|job_id| ORDER_CONTEXTS.write().unwrap().remove(&job_id))
+    {
+      context.span().end();
+    }
+  }
+}
+
+/// Span of a step of an order, current span of the thread until dropped
+pub(crate) struct JobSpan {
+  #[cfg(feature = "otel")]
+  context: Context,
+  #[cfg(feature = "otel")]
+  _guard: ContextGuard,
+}
+
+impl JobSpan {
+  /// Child of the span of the order of the job if known, of the current span otherwise
+  #[cfg(feature = "otel")]
+  pub(crate) fn new(job_id: Option<u64>, name: &'static str) -> Self {
+    let parent = job_id
+      .and_then(|job_id| ORDER_CONTEXTS.read().unwrap().get(&job_id).cloned())
+      .unwrap_or_else(Context::current);
+
+    let span = global::tracer(TRACER_NAME).start_with_context(name, &parent);
+    let context = parent.with_span(span);
+    JobSpan {
+      _guard: context.clone().attach(),
+      context,
+    }
+  }
+
+  #[cfg(not(feature = "otel"))]
+  pub(crate) fn new(_job_id: Option<u64>, _name: &'static str) -> Self {
+    JobSpan {}
+  }
+
+  #[cfg(feature = "otel")]
+  pub(crate) fn with_attribute(self, key: &'static str, value: &str) -> Self {
+    self
+      .context
+      .span()
+      .set_attribute(KeyValue::new(key, value.to_string()));
+    self
+  }
+
+  #[cfg(not(feature = "otel"))]
+  pub(crate) fn with_attribute(self, _key: &'static str, _value: &str) -> Self {
+    self
+  }
+
+  #[cfg(feature = "otel")]
+  pub(crate) fn set_error(&self, message: &str) {
+    self
+      .context
+      .span()
+      .set_status(Status::error(message.to_string()));
+  }
+
+  #[cfg(not(feature = "otel"))]
+  pub(crate) fn set_error(&self, _message: &str) {}
+}
+
+#[cfg(feature = "otel")]
+impl Drop for JobSpan {
+  fn drop(&mut self) {
+    self.context.span().end();
+  }
+}
+
+#[test]
+#[cfg(feature = "otel")]
+pub fn test_order_span() {
+  let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+  let order_span = OrderSpan::new(Some(9789), Some(traceparent));
+
+  let order_context = ORDER_CONTEXTS.read().unwrap().get(&9789).cloned().unwrap();
+  // without tracer provider the spans are not recorded, the remote parent is propagated
+  assert_eq!(
+    "4bf92f3577b34da6a3ce929d0e0e4736",
+    order_context.span().span_context().trace_id().to_string()
+  );
+
+  {
+    let job_span = JobSpan::new(Some(9789), "process");
+    assert_eq!(
+      "4bf92f3577b34da6a3ce929d0e0e4736",
+      Context::current()
+        .span()
+        .span_context()
+        .trace_id()
+        .to_string()
+    );
+    job_span.set_error("failed");
+  }
+
+  drop(order_span);
+  assert!(ORDER_CONTEXTS.read().unwrap().get(&9789).is_none());
+}
@@ -0,0 +1,68 @@
+//! Description of a live worker, published on request of the orchestrators
+//!
+//! The description is built from the worker implementation, like with `DESCRIBE=1` at startup,
+//! and cached for `DESCRIBE_CACHE_TTL` seconds to serve the orchestrators querying it often.
+//!
+//! At startup, the description is printed in the `DESCRIBE_FORMAT`: `json` (pretty, by default), `json-compact`, `yaml`,
+//! or `schema-only` for the JSON Schema of the parameters only.
+
+use crate::config::get_describe_cache_ttl;
+use crate::worker::WorkerConfiguration;
+use crate::MessageEvent;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Serialized descriptions, by worker instance
+static DESCRIPTIONS: Mutex<BTreeMap<String, (Instant, String)>> = Mutex::new(BTreeMap::new());
+
+pub fn get_description<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
+  worker_configuration: &WorkerConfiguration,
+  message_event: &Arc<RwLock<ME>>,
+) -> String {
+  let instance_id = worker_configuration.get_instance_id();
+  let ttl = Duration::from_secs(get_describe_cache_ttl());
+
+  let mut descriptions = DESCRIPTIONS.lock().unwrap();
+  if let Some((built, description)) = descriptions.get(&instance_id) {
+    if built.elapsed() < ttl {
+      return description.clone();
+    }
+  }
+
+  // a worker locked by a job in progress is described by its startup configuration
+  let live_configuration = message_event.try_read().ok().and_then(|message_event| {
+    WorkerConfiguration::new(
+      &worker_configuration.get_queue_name(),
+      &*message_event,
+      &instance_id,
+    )
+    .ok()
+    .map(|configuration| configuration.with_labels(worker_configuration.get_labels().clone()))
+  });
+  let description = json!(live_configuration.as_ref().unwrap_or(worker_configuration)).to_string();
+
+  descriptions.insert(instance_id, (Instant::now(), description.clone()));
+  description
+}
+
+/// Description printed at startup, in one of the `DESCRIBE_FORMAT`s
+pub fn format_description(
+  worker_configuration: &WorkerConfiguration,
+  format: &str,
+) -> Result<String, String> {
+  match format {
+    "json" => serde_json::to_string_pretty(worker_configuration).map_err(|e| e.to_string()),
+    "json-compact" => serde_json::to_string(worker_configuration).map_err(|e| e.to_string()),
+    "yaml" => serde_yaml::to_string(worker_configuration).map_err(|e| e.to_string()),
+    "schema-only" => {
+      serde_json::to_string_pretty(worker_configuration.get_parameters()).map_err(|e| e.to_string())
+    }
+    _ => Err(format!(
+      "Unknown describe format {:?}, expected json, json-compact, yaml or schema-only",
+      format
+    )),
+  }
+}
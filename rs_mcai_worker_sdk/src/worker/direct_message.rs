@@ -0,0 +1,325 @@
+use crate::config::{get_describe_response_queue, get_rerun_lookahead};
+use crate::job::RunningJobs;
+use crate::message::{read_message_event, write_message_event};
+use crate::parameter::store;
+use crate::worker::{
+  description, processing_window, readiness::WorkerReadiness, reload,
+  self_test::SelfTestDiagnostic, shutdown, system_information, WorkerConfiguration,
+};
+use crate::MessageEvent;
+use lapin::{
+  message::Delivery,
+  options::{BasicAckOptions, BasicCancelOptions, BasicPublishOptions, BasicQosOptions},
+  BasicProperties, Channel, Promise,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::{thread, time::Duration};
+
+/// Orders received on the direct messaging queue of the worker
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DirectMessage {
+  Status,
+  StopJob {
+    job_id: u64,
+  },
+  /// Stop consuming jobs, publish the status once the jobs in progress are completed
+  Drain {
+    #[serde(default)]
+    exit: bool,
+  },
+  /// Run the worker self-test, publish its diagnostic on the status queue
+  SelfTest,
+  /// Publish the description of the worker, like with `DESCRIBE=1` at startup
+  Describe,
+  /// Apply new values of the reloadable configuration, without restarting the worker
+  ReloadConfiguration {
+    #[serde(default)]
+    values: BTreeMap<String, String>,
+  },
+}
+
+impl DirectMessage {
+  /// Any message which is not a known order is considered as a status request
+  pub fn new(data: &[u8]) -> Self {
+    serde_json::from_slice(data).unwrap_or(DirectMessage::Status)
+  }
+}
+
+pub fn process_direct_message<
+  P: DeserializeOwned + JsonSchema,
+  ME: 'static + MessageEvent<P> + Send + Sync,
+>(
+  message: Delivery,
+  channel: &Arc<Channel>,
+  worker_configuration: &WorkerConfiguration,
+  running_jobs: &RunningJobs,
+  readiness: &WorkerReadiness,
+  message_event: &Arc<RwLock<ME>>,
+) -> Promise<()> {
+  match DirectMessage::new(&message.data) {
+    DirectMessage::Describe => {
+      let description = description::get_description(worker_configuration, message_event);
+      publish_description(channel, &message, description);
+
+      channel.basic_ack(
+        message.delivery_tag,
+        BasicAckOptions::default(), /*not requeue*/
+      )
+    }
+    DirectMessage::ReloadConfiguration { values } => {
+      reload_configuration(
+        channel,
+        worker_configuration,
+        readiness,
+        &values,
+        message_event,
+      );
+
+      channel.basic_ack(
+        message.delivery_tag,
+        BasicAckOptions::default(), /*not requeue*/
+      )
+    }
+    DirectMessage::Status => system_information::send_real_time_information(
+      message,
+      channel,
+      worker_configuration,
+      readiness,
+      running_jobs,
+    ),
+    DirectMessage::StopJob { job_id } => {
+      if running_jobs.cancel(job_id) {
+        info!(target: &job_id.to_string(), "Job cancellation requested");
+      } else {
+        warn!(target: &job_id.to_string(), "Cannot cancel job: not in progress");
+      }
+
+      channel.basic_ack(
+        message.delivery_tag,
+        BasicAckOptions::default(), /*not requeue*/
+      )
+    }
+    DirectMessage::Drain { exit } => {
+      drain(
+        channel.clone(),
+        worker_configuration.clone(),
+        running_jobs.clone(),
+        readiness.clone(),
+        exit,
+      );
+
+      channel.basic_ack(
+        message.delivery_tag,
+        BasicAckOptions::default(), /*not requeue*/
+      )
+    }
+    DirectMessage::SelfTest => {
+      let status_channel = channel.clone();
+      let worker_configuration = worker_configuration.clone();
+      let message_event = message_event.clone();
+
+      // the status requests are still answered during the self-test
+      thread::spawn(move || {
+        let diagnostic =
+          SelfTestDiagnostic::run(&*read_message_event(&message_event), &worker_configuration);
+        info!("Self-test passed: {}", diagnostic.passed);
+
+        let serialized = serde_json::to_string(&diagnostic).unwrap();
+        if let Err(error) = status_channel
+          .basic_publish(
+            "",
+            "worker_status_response",
+            BasicPublishOptions::default(),
+            serialized.as_bytes().to_vec(),
+            BasicProperties::default(),
+          )
+          .wait()
+        {
+          error!("Unable to publish the self-test diagnostic: {:?}", error);
+        }
+      });
+
+      channel.basic_ack(
+        message.delivery_tag,
+        BasicAckOptions::default(), /*not requeue*/
+      )
+    }
+  }
+}
+
+/// Publish the description on the `reply_to` queue of the request, or on the configured queue
+fn publish_description(channel: &Channel, message: &Delivery, description: String) {
+  let queue_name = message
+    .properties
+    .reply_to()
+    .as_ref()
+    .map(|reply_to| reply_to.to_string())
+    .unwrap_or_else(get_describe_response_queue);
+
+  let mut properties = BasicProperties::default();
+  if let Some(correlation_id) = message.properties.correlation_id() {
+    properties = properties.with_correlation_id(correlation_id.clone());
+  }
+
+  if let Err(error) = channel
+    .basic_publish(
+      "",
+      &queue_name,
+      BasicPublishOptions::default(),
+      description.into_bytes(),
+      properties,
+    )
+    .wait()
+  {
+    error!("Unable to publish the worker description: {:?}", error);
+  }
+}
+
+fn reload_configuration<
+  P: DeserializeOwned + JsonSchema,
+  ME: 'static + MessageEvent<P> + Send + Sync,
+>(
+  channel: &Arc<Channel>,
+  worker_configuration: &WorkerConfiguration,
+  readiness: &WorkerReadiness,
+  values: &BTreeMap<String, String>,
+  message_event: &Arc<RwLock<ME>>,
+) {
+  let reload = reload::reload(values);
+  if !reload.ignored.is_empty() {
+    warn!(
+      "Configuration not reloadable, restart the worker to apply: {}",
+      reload.ignored.join(", ")
+    );
+  }
+  info!("Configuration reloaded: {}", reload.applied.join(", "));
+
+  // Media processing handles a single job, the re-runs scheduler keeps its number of slots
+  if reload.has_max_concurrent_jobs() {
+    if cfg!(feature = "media") || get_rerun_lookahead() > 0 {
+      warn!(
+        "The maximum number of concurrent jobs cannot be reloaded, restart the worker to apply"
+      );
+    } else {
+      let max_concurrent_jobs = reload::get_reloaded_max_concurrent_jobs(1);
+      match channel
+        .basic_qos(max_concurrent_jobs, BasicQosOptions::default())
+        .wait()
+      {
+        Ok(()) => {
+          info!("Max concurrent jobs: {}", max_concurrent_jobs);
+          // the prefetch applies to the consumers started afterwards
+          if readiness.is_ready() && !readiness.is_draining() && !readiness.is_waiting() {
+            reload::request_consumer_restart();
+            processing_window::cancel_consumer(channel, &worker_configuration.get_consumer_tag());
+          }
+        }
+        Err(error) => error!(
+          "Unable to apply the maximum number of concurrent jobs: {:?}",
+          error
+        ),
+      }
+    }
+  }
+
+  // the hook waits for the jobs in progress, the status requests are still answered,
+  // then the stores authenticate again with the configuration re-read by the worker
+  let message_event = message_event.clone();
+  thread::spawn(move || {
+    write_message_event(&message_event).on_config_reload();
+    store::clear_sessions();
+  });
+}
+
+fn drain(
+  channel: Arc<Channel>,
+  worker_configuration: WorkerConfiguration,
+  running_jobs: RunningJobs,
+  readiness: WorkerReadiness,
+  exit: bool,
+) {
+  if !readiness.is_draining() {
+    info!("Drain the worker, stop consuming jobs");
+    readiness.set_draining();
+
+    if let Err(error) = channel
+      .basic_cancel(
+        &worker_configuration.get_consumer_tag(),
+        BasicCancelOptions::default(),
+      )
+      .wait()
+    {
+      error!("Unable to stop consuming jobs: {:?}", error);
+    }
+  }
+
+  thread::spawn(move || {
+    while running_jobs.count() > 0 {
+      thread::sleep(Duration::from_millis(100));
+    }
+
+    info!("Worker drained");
+    if !system_information::publish_status(
+      &channel,
+      &worker_configuration,
+      &readiness,
+      &running_jobs,
+    ) {
+      error!("Unable to publish the drained status");
+    }
+
+    if exit {
+      info!("Exit drained worker");
+      shutdown::exit(0);
+    }
+  });
+}
+
+#[test]
+pub fn test_direct_message() {
+  assert_eq!(DirectMessage::Status, DirectMessage::new(b""));
+  assert_eq!(
+    DirectMessage::Status,
+    DirectMessage::new(br#"{"type": "status"}"#)
+  );
+  assert_eq!(
+    DirectMessage::StopJob { job_id: 123 },
+    DirectMessage::new(br#"{"type": "stop_job", "job_id": 123}"#)
+  );
+  assert_eq!(
+    DirectMessage::Drain { exit: false },
+    DirectMessage::new(br#"{"type": "drain"}"#)
+  );
+  assert_eq!(
+    DirectMessage::SelfTest,
+    DirectMessage::new(br#"{"type": "self_test"}"#)
+  );
+  assert_eq!(
+    DirectMessage::Drain { exit: true },
+    DirectMessage::new(br#"{"type": "drain", "exit": true}"#)
+  );
+  assert_eq!(
+    DirectMessage::Describe,
+    DirectMessage::new(br#"{"type": "describe"}"#)
+  );
+  assert_eq!(
+    DirectMessage::ReloadConfiguration {
+      values: BTreeMap::new()
+    },
+    DirectMessage::new(br#"{"type": "reload_configuration"}"#)
+  );
+  assert_eq!(
+    DirectMessage::ReloadConfiguration {
+      values: vec![("HTTP_CLIENT_RATE_LIMIT".to_string(), "2.5".to_string())]
+        .into_iter()
+        .collect()
+    },
+    DirectMessage::new(
+      br#"{"type": "reload_configuration", "values": {"HTTP_CLIENT_RATE_LIMIT": "2.5"}}"#
+    )
+  );
+}
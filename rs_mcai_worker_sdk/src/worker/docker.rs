@@ -1,7 +1,8 @@
 use std::fs;
+use std::sync::Mutex;
 use uuid::Uuid;
 
-static mut INSTANCE_UUID: Option<String> = None;
+static INSTANCE_UUID: Mutex<Option<String>> = Mutex::new(None);
 
 /// Retrieve the identifier of this instance.
 ///
@@ -9,37 +10,68 @@ static mut INSTANCE_UUID: Option<String> = None;
 /// Else an UUID is generated to provide an unique identifier.
 ///
 pub fn get_instance_id(filename: &str) -> String {
+  get_cgroup_container_id(filename).unwrap_or_else(get_instance_uuid)
+}
+
+/// Container ID of the process, from its cgroups (v1 or v2 with systemd) or the mounts of the container (v2),
+/// like `/proc/self/cgroup` and `/proc/self/mountinfo`
+pub fn get_container_id(cgroup_filename: &str, mountinfo_filename: &str) -> Option<String> {
+  get_cgroup_container_id(cgroup_filename).or_else(|| {
+    fs::read_to_string(mountinfo_filename)
+      .ok()
+      .and_then(|content| parse_mountinfo_container_id(&content))
+  })
+}
+
+fn get_cgroup_container_id(filename: &str) -> Option<String> {
   fs::read_to_string(filename)
-    .map(|content| parse_docker_container_id(&content))
-    .unwrap_or_else(|_| None)
-    .unwrap_or_else(|| unsafe {
-      if let Some(instance_uuid) = &INSTANCE_UUID {
-        instance_uuid.to_string()
-      } else {
-        let identifier = format!("{:?}", Uuid::new_v4());
-        INSTANCE_UUID = Some(identifier.clone());
-        identifier
-      }
-    })
+    .ok()
+    .and_then(|content| parse_docker_container_id(&content))
 }
 
+/// Identifier generated once for the process
+pub(crate) fn get_instance_uuid() -> String {
+  INSTANCE_UUID
+    .lock()
+    .unwrap()
+    .get_or_insert_with(|| format!("{:?}", Uuid::new_v4()))
+    .clone()
+}
+
+/// The container ID is in the path of a cgroup, like `/docker/<id>`, `/kubepods/.../<id>`
+/// or `/system.slice/docker-<id>.scope`
 fn parse_docker_container_id(content: &str) -> Option<String> {
-  let lines: Vec<&str> = content.split('\n').collect();
-  if lines.is_empty() {
-    return None;
-  }
-  let items: Vec<&str> = lines[0].split(':').collect();
-  if items.len() != 3 {
-    return None;
-  }
+  content
+    .lines()
+    .filter_map(|line| line.splitn(3, ':').nth(2))
+    .flat_map(|path| path.split('/'))
+    .find_map(parse_container_id)
+}
+
+/// With cgroup v2, the container ID is in the path of its files mounted from the host,
+/// like `/var/lib/docker/containers/<id>/hostname`
+fn parse_mountinfo_container_id(content: &str) -> Option<String> {
+  content
+    .lines()
+    .flat_map(|line| line.split_whitespace())
+    .filter_map(|path| path.split("/containers/").nth(1))
+    .filter_map(|path| path.split('/').next())
+    .find_map(parse_container_id)
+}
+
+fn parse_container_id(segment: &str) -> Option<String> {
+  let identifier = segment.trim_end_matches(".scope");
+  let identifier = identifier.rsplit(['-', ':']).next().unwrap_or(identifier);
 
-  let long_identifier: Vec<&str> = items[2].split("/docker/").collect();
-  if long_identifier.len() != 2 {
-    return None;
+  if identifier.len() == 64
+    && identifier
+      .chars()
+      .all(|character| character.is_ascii_hexdigit())
+  {
+    Some(identifier[..12].to_string())
+  } else {
+    None
   }
-  let mut identifier = long_identifier[1].to_string();
-  identifier.truncate(12);
-  Some(identifier)
 }
 
 #[test]
@@ -52,8 +84,53 @@ fn test_get_instance_id() {
   let str_uuid = get_instance_id("/tmp/file_not_exists");
   let parsed_uuid = Uuid::parse_str(&str_uuid);
   assert!(parsed_uuid.is_ok());
+  assert_eq!(get_instance_uuid(), get_instance_uuid());
+  assert_eq!(
+    get_container_id("./tests/cgroup.sample", "/tmp/file_not_exists"),
+    Some("da9002cb1553".to_string())
+  );
+  assert_eq!(
+    get_container_id("/tmp/file_not_exists", "/tmp/file_not_exists"),
+    None
+  );
 
   assert_eq!(parse_docker_container_id(""), None);
   assert_eq!(parse_docker_container_id("\n"), None);
   assert_eq!(parse_docker_container_id("a:b:c\n"), None);
+  assert_eq!(parse_docker_container_id("0::/\n"), None);
+}
+
+#[test]
+fn test_parse_container_id() {
+  let identifier = "da9002cb15536736ac8ef168903ad6f7bddc9a1c544ce2952df2568f2dccc243";
+
+  assert_eq!(
+    Some("da9002cb1553".to_string()),
+    parse_docker_container_id(&format!("0::/system.slice/docker-{}.scope\n", identifier))
+  );
+  assert_eq!(
+    Some("da9002cb1553".to_string()),
+    parse_docker_container_id(&format!(
+      "11:memory:/kubepods/burstable/pod6c1c3c0e-43bb-4b6f-a4b2-5ab0cd3c6f3b/{}\n",
+      identifier
+    ))
+  );
+  assert_eq!(
+    Some("da9002cb1553".to_string()),
+    parse_docker_container_id(&format!(
+      "0::/kubepods.slice/kubepods-besteffort.slice/cri-containerd-{}.scope\n",
+      identifier
+    ))
+  );
+  assert_eq!(
+    Some("da9002cb1553".to_string()),
+    parse_mountinfo_container_id(&format!(
+      "1226 1207 259:1 /var/lib/docker/containers/{}/hostname /etc/hostname rw,relatime - ext4 /dev/root rw\n",
+      identifier
+    ))
+  );
+  assert_eq!(
+    None,
+    parse_mountinfo_container_id("1207 1 0:52 / / rw,relatime - overlay overlay rw\n")
+  );
 }
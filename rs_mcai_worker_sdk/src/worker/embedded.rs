@@ -0,0 +1,308 @@
+//! Library-mode embedding of the worker
+//!
+//! Instead of `start_worker` owning the process, the worker runs on its own thread
+//! and is controlled through a `WorkerHandle`:
+//!
+//! ```ignore
+//! let handle = Worker::builder()
+//!   .with_message_event(WorkerEvent {})
+//!   .build()?
+//!   .spawn()?;
+//!
+//! // ...
+//! handle.stop();
+//! handle.join()?;
+//! ```
+
+use crate::config::{
+  clamp_max_concurrent_jobs, get_amqp_queue, get_effective_max_concurrent_jobs, SdkConfig,
+};
+use crate::job::RunningJobs;
+use crate::message::middleware::{self, Middleware};
+use crate::runtime::{self, Executor};
+use crate::worker::{
+  instance,
+  readiness::{ReadinessState, WorkerReadiness},
+  registry::{JobConsumer, WorkerConsumer},
+  WorkerConfiguration,
+};
+use crate::{MessageError, MessageEvent, Result};
+use futures::channel::oneshot;
+use futures_util::future::{self, FutureExt, Shared};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc, Mutex, RwLock,
+};
+use std::thread::{self, JoinHandle};
+
+/// Stop request of a worker, never triggered when its sender is dropped
+#[derive(Clone)]
+pub(crate) struct StopSignal {
+  stopped: Arc<AtomicBool>,
+  receiver: Shared<oneshot::Receiver<()>>,
+}
+
+impl Default for StopSignal {
+  fn default() -> Self {
+    Self::new().1
+  }
+}
+
+impl StopSignal {
+  fn new() -> (oneshot::Sender<()>, Self) {
+    let (sender, receiver) = oneshot::channel();
+    let signal = StopSignal {
+      stopped: Arc::new(AtomicBool::new(false)),
+      receiver: receiver.shared(),
+    };
+    (sender, signal)
+  }
+
+  pub(crate) fn is_stopped(&self) -> bool {
+    self.stopped.load(Ordering::SeqCst)
+  }
+
+  /// Resolved once the stop is requested
+  pub(crate) async fn wait(&self) {
+    if self.receiver.clone().await.is_err() {
+      future::pending::<()>().await;
+    }
+  }
+}
+
+pub struct Worker<P, ME> {
+  message_event: ME,
+  worker_configuration: WorkerConfiguration,
+  max_concurrent_jobs: u16,
+  _parameters: PhantomData<fn() -> P>,
+}
+
+pub struct WorkerBuilder<P, ME> {
+  message_event: Option<ME>,
+  queue_name: Option<String>,
+  instance_id: Option<String>,
+  labels: BTreeMap<String, String>,
+  max_concurrent_jobs: Option<u16>,
+  config: Option<SdkConfig>,
+  executor: Option<Arc<dyn Executor>>,
+  middlewares: Vec<Arc<dyn Middleware>>,
+  _parameters: PhantomData<fn() -> P>,
+}
+
+impl<P: 'static + DeserializeOwned + JsonSchema, ME: 'static + MessageEvent<P> + Send + Sync>
+  Worker<P, ME>
+{
+  pub fn builder() -> WorkerBuilder<P, ME> {
+    WorkerBuilder {
+      message_event: None,
+      queue_name: None,
+      instance_id: None,
+      labels: BTreeMap::new(),
+      max_concurrent_jobs: None,
+      config: None,
+      executor: None,
+      middlewares: vec![],
+      _parameters: PhantomData,
+    }
+  }
+
+  pub fn get_worker_configuration(&self) -> &WorkerConfiguration {
+    &self.worker_configuration
+  }
+
+  /// Initialize the worker and consume the job orders on a dedicated thread
+  ///
+  /// The host application keeps the control of the process: it is not exited on an
+  /// initialization failure, the worker stops and the error is available in the readiness.
+  pub fn spawn(self) -> Result<WorkerHandle> {
+    let message_event = Arc::new(RwLock::new(self.message_event));
+    let running_jobs = RunningJobs::default();
+    let readiness = WorkerReadiness::default();
+    let (stop_sender, stop) = StopSignal::new();
+
+    let handle = WorkerHandle {
+      worker_configuration: self.worker_configuration.clone(),
+      running_jobs: running_jobs.clone(),
+      readiness: readiness.clone(),
+      stopped: stop.stopped.clone(),
+      stop_sender: Arc::new(Mutex::new(Some(stop_sender))),
+      thread: Arc::new(Mutex::new(None)),
+    };
+
+    let init_message_event = message_event.clone();
+    let init_readiness = readiness.clone();
+    let init_handle = handle.clone();
+    thread::Builder::new()
+      .name("mcai_worker_init".to_string())
+      .spawn(move || {
+        if let Err(error) = crate::init_worker(&init_message_event, &init_readiness) {
+          error!("{:?}", error);
+          init_handle.stop();
+        }
+      })
+      .map_err(|error| MessageError::RuntimeError(error.to_string()))?;
+
+    let worker: Arc<dyn JobConsumer> = Arc::new(WorkerConsumer::new(
+      message_event,
+      self.worker_configuration,
+      running_jobs,
+      readiness,
+      self.max_concurrent_jobs,
+    ));
+    let thread = thread::Builder::new()
+      .name("mcai_worker".to_string())
+      .spawn(move || {
+        crate::run_amqp_worker(std::slice::from_ref(&worker), &stop);
+        worker.shutdown();
+      })
+      .map_err(|error| MessageError::RuntimeError(error.to_string()))?;
+
+    *handle.thread.lock().unwrap() = Some(thread);
+    Ok(handle)
+  }
+}
+
+impl<P: 'static + DeserializeOwned + JsonSchema, ME: 'static + MessageEvent<P> + Send + Sync>
+  WorkerBuilder<P, ME>
+{
+  pub fn with_message_event(mut self, message_event: ME) -> Self {
+    self.message_event = Some(message_event);
+    self
+  }
+
+  /// Queue of the job orders (default: `AMQP_QUEUE`)
+  pub fn with_queue_name(mut self, queue_name: &str) -> Self {
+    self.queue_name = Some(queue_name.to_string());
+    self
+  }
+
+  /// Identifier of the worker instance (default: the container identifier)
+  pub fn with_instance_id(mut self, instance_id: &str) -> Self {
+    self.instance_id = Some(instance_id.to_string());
+    self
+  }
+
+  /// Free-form label of the worker, taking precedence over the `WORKER_LABELS`
+  pub fn with_label(mut self, key: &str, value: &str) -> Self {
+    self.labels.insert(key.to_string(), value.to_string());
+    self
+  }
+
+  /// Number of jobs processed concurrently (default: `MAX_CONCURRENT_JOBS`, always 1 with the `media` feature)
+  pub fn with_max_concurrent_jobs(mut self, max_concurrent_jobs: u16) -> Self {
+    self.max_concurrent_jobs = Some(max_concurrent_jobs.max(1));
+    self
+  }
+
+  /// Configuration overriding the environment variables, applied to the whole process on build
+  pub fn with_config(mut self, config: SdkConfig) -> Self {
+    self.config = Some(config);
+    self
+  }
+
+  /// Runtime of the application running the asynchronous internals, applied to the whole process on build
+  pub fn with_executor<E: Executor + 'static>(mut self, executor: E) -> Self {
+    self.executor = Some(Arc::new(executor));
+    self
+  }
+
+  /// Hooks around the processing of the orders, registered for the whole process on build
+  pub fn with_middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+    self.middlewares.push(Arc::new(middleware));
+    self
+  }
+
+  pub fn build(self) -> Result<Worker<P, ME>> {
+    if let Some(config) = &self.config {
+      config.apply();
+    }
+
+    if let Some(executor) = &self.executor {
+      runtime::set_shared_executor(executor.clone());
+    }
+
+    for middleware in &self.middlewares {
+      middleware::register_shared_middleware(middleware.clone());
+    }
+
+    let message_event = self.message_event.ok_or_else(|| {
+      MessageError::RuntimeError("Missing message event to build the worker".to_string())
+    })?;
+
+    let queue_name = self.queue_name.unwrap_or_else(get_amqp_queue);
+    let instance_id = self.instance_id.unwrap_or_else(instance::get_instance_id);
+    let worker_configuration =
+      WorkerConfiguration::new(&queue_name, &message_event, &instance_id)?.with_labels(self.labels);
+
+    let max_concurrent_jobs = self
+      .max_concurrent_jobs
+      .map(clamp_max_concurrent_jobs)
+      .unwrap_or_else(get_effective_max_concurrent_jobs);
+
+    Ok(Worker {
+      message_event,
+      worker_configuration,
+      max_concurrent_jobs,
+      _parameters: PhantomData,
+    })
+  }
+}
+
+/// Control of a spawned worker, it can be cloned to be shared with other services
+#[derive(Clone)]
+pub struct WorkerHandle {
+  worker_configuration: WorkerConfiguration,
+  running_jobs: RunningJobs,
+  readiness: WorkerReadiness,
+  stopped: Arc<AtomicBool>,
+  stop_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+  thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl WorkerHandle {
+  /// Stop consuming the job orders and close the connection,
+  /// a job in progress is completed before the worker stops
+  pub fn stop(&self) {
+    self.stopped.store(true, Ordering::SeqCst);
+    if let Some(sender) = self.stop_sender.lock().unwrap().take() {
+      let _ = sender.send(());
+    }
+  }
+
+  /// Block until the worker is stopped, only the first call of the clones waits for it
+  pub fn join(self) -> Result<()> {
+    let thread = self.thread.lock().unwrap().take();
+
+    match thread {
+      Some(thread) => thread
+        .join()
+        .map_err(|_| MessageError::RuntimeError("The worker thread panicked".to_string())),
+      None => Ok(()),
+    }
+  }
+
+  pub fn is_stopped(&self) -> bool {
+    self.stopped.load(Ordering::SeqCst)
+  }
+
+  pub fn is_ready(&self) -> bool {
+    self.readiness.is_ready()
+  }
+
+  pub fn get_readiness(&self) -> ReadinessState {
+    self.readiness.get_state()
+  }
+
+  /// Number of jobs in progress
+  pub fn get_running_jobs_count(&self) -> usize {
+    self.running_jobs.count()
+  }
+
+  pub fn get_worker_configuration(&self) -> &WorkerConfiguration {
+    &self.worker_configuration
+  }
+}
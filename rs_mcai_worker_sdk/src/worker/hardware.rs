@@ -0,0 +1,174 @@
+//! Hardware capabilities of the worker instance, advertised in the worker description
+//!
+//! The orchestrators route the heavy jobs to the capable instances with the number of processors,
+//! the total memory and the GPUs: the NVIDIA devices listed by `nvidia-smi`, and the VAAPI render nodes in `/dev/dri`.
+//! The hardware is detected once per process.
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use sysinfo::{RefreshKind, SystemExt};
+
+static HARDWARE_CAPABILITIES: Mutex<Option<HardwareCapabilities>> = Mutex::new(None);
+
+const DRI_DEVICES_PATH: &str = "/dev/dri";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HardwareCapabilities {
+  pub cpu_cores: usize,
+  /// Total memory in bytes
+  pub total_memory: u64,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub gpus: Vec<GpuDevice>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GpuDevice {
+  pub kind: GpuKind,
+  /// Model of an NVIDIA device, path of a VAAPI render node
+  pub name: String,
+  /// Memory in bytes, if known
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub memory: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuKind {
+  Nvidia,
+  Vaapi,
+}
+
+impl HardwareCapabilities {
+  /// Capabilities of the host, detected on the first call
+  pub fn get() -> Self {
+    HARDWARE_CAPABILITIES
+      .lock()
+      .unwrap()
+      .get_or_insert_with(HardwareCapabilities::detect)
+      .clone()
+  }
+
+  fn detect() -> Self {
+    let system = sysinfo::System::new_with_specifics(RefreshKind::new().with_cpu().with_memory());
+
+    let mut gpus = detect_nvidia_devices();
+    gpus.extend(find_render_nodes(Path::new(DRI_DEVICES_PATH)));
+
+    HardwareCapabilities {
+      cpu_cores: system.get_processors().len(),
+      total_memory: system.get_total_memory() * 1024,
+      gpus,
+    }
+  }
+
+  pub fn has_gpu(&self) -> bool {
+    !self.gpus.is_empty()
+  }
+}
+
+/// No NVIDIA device without the `nvidia-smi` tool
+fn detect_nvidia_devices() -> Vec<GpuDevice> {
+  Command::new("nvidia-smi")
+    .args([
+      "--query-gpu=name,memory.total",
+      "--format=csv,noheader,nounits",
+    ])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .map(|output| parse_nvidia_devices(&String::from_utf8_lossy(&output.stdout)))
+    .unwrap_or_default()
+}
+
+/// One `name, memory in MiB` line per device
+fn parse_nvidia_devices(output: &str) -> Vec<GpuDevice> {
+  output
+    .lines()
+    .filter_map(|line| {
+      let (name, memory) = line.rsplit_once(',').unwrap_or((line, ""));
+      let name = name.trim();
+      if name.is_empty() {
+        return None;
+      }
+
+      Some(GpuDevice {
+        kind: GpuKind::Nvidia,
+        name: name.to_string(),
+        memory: memory
+          .trim()
+          .parse::<u64>()
+          .ok()
+          .map(|memory| memory * 1024 * 1024),
+      })
+    })
+    .collect()
+}
+
+fn find_render_nodes(dri_path: &Path) -> Vec<GpuDevice> {
+  let mut render_nodes: Vec<String> = std::fs::read_dir(dri_path)
+    .map(|entries| {
+      entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("renderD"))
+        .map(|entry| entry.path().to_string_lossy().to_string())
+        .collect()
+    })
+    .unwrap_or_default();
+  render_nodes.sort();
+
+  render_nodes
+    .into_iter()
+    .map(|name| GpuDevice {
+      kind: GpuKind::Vaapi,
+      name,
+      memory: None,
+    })
+    .collect()
+}
+
+#[test]
+pub fn test_parse_nvidia_devices() {
+  let output = "Tesla T4, 15360\nNVIDIA A100-SXM4-40GB, 40960\n\n";
+
+  assert_eq!(
+    vec![
+      GpuDevice {
+        kind: GpuKind::Nvidia,
+        name: "Tesla T4".to_string(),
+        memory: Some(15360 * 1024 * 1024),
+      },
+      GpuDevice {
+        kind: GpuKind::Nvidia,
+        name: "NVIDIA A100-SXM4-40GB".to_string(),
+        memory: Some(40960 * 1024 * 1024),
+      },
+    ],
+    parse_nvidia_devices(output)
+  );
+  assert_eq!(None, parse_nvidia_devices("Tesla T4, [N/A]")[0].memory);
+}
+
+#[test]
+pub fn test_find_render_nodes() {
+  let dri_path = std::env::temp_dir().join(format!("mcai_dri_{}", std::process::id()));
+  std::fs::create_dir_all(&dri_path).unwrap();
+  for device in &["card0", "renderD129", "renderD128"] {
+    std::fs::write(dri_path.join(device), "").unwrap();
+  }
+
+  let names: Vec<String> = find_render_nodes(&dri_path)
+    .into_iter()
+    .map(|device| device.name)
+    .collect();
+  assert_eq!(
+    vec![
+      dri_path.join("renderD128").to_string_lossy().to_string(),
+      dri_path.join("renderD129").to_string_lossy().to_string(),
+    ],
+    names
+  );
+  assert!(find_render_nodes(&dri_path.join("missing")).is_empty());
+
+  std::fs::remove_dir_all(&dri_path).unwrap();
+}
@@ -0,0 +1,160 @@
+//! Periodic heartbeat of the worker instance, for external liveness tracking
+//!
+//! When `HEARTBEAT_EXCHANGE` is set, a heartbeat is published on this topic exchange every
+//! `HEARTBEAT_INTERVAL` seconds, with the queue of the worker as routing key, as long as the connection is alive.
+//! The heartbeats are best effort: a failed publish is only logged.
+
+use crate::config::{get_heartbeat_exchange, get_heartbeat_interval, get_workspace_root};
+use crate::job::RunningJobs;
+use crate::message::admission::get_available_disk_space;
+use crate::worker::{readiness::WorkerReadiness, WorkerConfiguration};
+use crate::McaiChannel;
+use chrono::{DateTime, Utc};
+use lapin::{options::BasicPublishOptions, BasicProperties};
+use std::{thread, time::Duration};
+use sysinfo::{ProcessorExt, RefreshKind, System, SystemExt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeartbeatStatus {
+  Initializing,
+  Idle,
+  Processing,
+  /// Out of the processing windows
+  Waiting,
+  Draining,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Heartbeat {
+  pub datetime: DateTime<Utc>,
+  pub docker_container_id: String,
+  pub instance_name: String,
+  pub worker_name: String,
+  pub worker_version: String,
+  pub queue_name: String,
+  pub status: HeartbeatStatus,
+  /// Usage of all the processors since the previous heartbeat, in percent
+  pub cpu_usage: f32,
+  /// Memory in bytes
+  pub total_memory: u64,
+  pub used_memory: u64,
+  /// Available space in bytes of the workspace disk, if known
+  pub disk_free: Option<u64>,
+  pub running_jobs: usize,
+  /// Jobs processed since the start of the worker, completed or not
+  pub processed_jobs: u64,
+}
+
+impl HeartbeatStatus {
+  fn new(readiness: &WorkerReadiness, running_jobs: usize) -> Self {
+    if readiness.is_draining() {
+      HeartbeatStatus::Draining
+    } else if !readiness.is_ready() {
+      HeartbeatStatus::Initializing
+    } else if running_jobs > 0 {
+      HeartbeatStatus::Processing
+    } else if readiness.is_waiting() {
+      HeartbeatStatus::Waiting
+    } else {
+      HeartbeatStatus::Idle
+    }
+  }
+}
+
+impl Heartbeat {
+  fn new(
+    system: &System,
+    worker_configuration: &WorkerConfiguration,
+    readiness: &WorkerReadiness,
+    running_jobs: &RunningJobs,
+  ) -> Self {
+    let running_jobs_count = running_jobs.count();
+
+    Heartbeat {
+      datetime: Utc::now(),
+      docker_container_id: worker_configuration.get_instance_id(),
+      instance_name: worker_configuration.get_instance_name(),
+      worker_name: worker_configuration.get_worker_name(),
+      worker_version: worker_configuration.get_worker_version(),
+      queue_name: worker_configuration.get_queue_name(),
+      status: HeartbeatStatus::new(readiness, running_jobs_count),
+      cpu_usage: system.get_global_processor_info().get_cpu_usage(),
+      total_memory: system.get_total_memory() * 1024,
+      used_memory: system.get_used_memory() * 1024,
+      disk_free: get_available_disk_space(&get_workspace_root()),
+      running_jobs: running_jobs_count,
+      processed_jobs: running_jobs.get_processed_jobs(),
+    }
+  }
+}
+
+/// Publish the heartbeats in the background if an exchange is configured
+pub(crate) fn start(
+  channel: McaiChannel,
+  worker_configuration: WorkerConfiguration,
+  readiness: WorkerReadiness,
+  running_jobs: RunningJobs,
+) {
+  let exchange = match get_heartbeat_exchange() {
+    Some(exchange) => exchange,
+    None => return,
+  };
+  let interval = Duration::from_secs(get_heartbeat_interval());
+  let routing_key = worker_configuration.get_queue_name();
+
+  thread::spawn(move || {
+    // the CPU usage is measured between two refreshes
+    let mut system = System::new_with_specifics(RefreshKind::new().with_cpu().with_memory());
+
+    while channel.status().connected() {
+      system.refresh_cpu();
+      system.refresh_memory();
+
+      let heartbeat = Heartbeat::new(&system, &worker_configuration, &readiness, &running_jobs);
+      let payload = json!(heartbeat).to_string();
+      if let Err(error) = channel
+        .basic_publish(
+          &exchange,
+          &routing_key,
+          BasicPublishOptions::default(),
+          payload.into_bytes(),
+          BasicProperties::default(),
+        )
+        .wait()
+      {
+        warn!("Unable to publish the heartbeat: {:?}", error);
+      }
+
+      thread::sleep(interval);
+    }
+  });
+}
+
+#[test]
+pub fn test_heartbeat_status() {
+  let readiness = WorkerReadiness::default();
+  assert_eq!(
+    HeartbeatStatus::Initializing,
+    HeartbeatStatus::new(&readiness, 0)
+  );
+
+  readiness.set_ready();
+  assert_eq!(HeartbeatStatus::Idle, HeartbeatStatus::new(&readiness, 0));
+  assert_eq!(
+    HeartbeatStatus::Processing,
+    HeartbeatStatus::new(&readiness, 2)
+  );
+
+  readiness.set_waiting(true);
+  assert_eq!(
+    HeartbeatStatus::Waiting,
+    HeartbeatStatus::new(&readiness, 0)
+  );
+
+  readiness.set_draining();
+  assert_eq!(
+    HeartbeatStatus::Draining,
+    HeartbeatStatus::new(&readiness, 1)
+  );
+}
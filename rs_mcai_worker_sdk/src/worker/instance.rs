@@ -0,0 +1,51 @@
+//! Identifier of the worker instance
+//!
+//! The identifier names the direct messaging queue of the instance and is set in its responses.
+//! It is the first available of:
+//! 1. the `INSTANCE_ID` variable,
+//! 2. the name of the Kubernetes pod, from the `POD_NAME` variable set with the downward API (`metadata.name`),
+//! 3. the UID of the pod, from the `POD_UID` variable (`metadata.uid`),
+//! 4. the hostname, in a Kubernetes pod (detected with `KUBERNETES_SERVICE_HOST`) where it is the name of the pod,
+//! 5. the Docker container ID, from `/proc/self/cgroup` or, with cgroup v2, from `/proc/self/mountinfo`,
+//! 6. an UUID generated for the process.
+//!
+//! The `INSTANCE_NAME` variable sets a human-readable name of the instance, the identifier by default,
+//! used as tag of its AMQP consumers and in its log lines.
+
+use crate::config::{
+  get_instance_id_override, get_instance_name_override, get_pod_name, get_pod_uid,
+  is_kubernetes_pod,
+};
+use crate::worker::docker;
+use std::fs;
+
+/// Identifier of this instance
+pub fn get_instance_id() -> String {
+  get_instance_id_override()
+    .or_else(get_pod_id)
+    .or_else(|| docker::get_container_id("/proc/self/cgroup", "/proc/self/mountinfo"))
+    .unwrap_or_else(docker::get_instance_uuid)
+}
+
+/// Name of this instance, or its identifier if not named
+pub fn get_instance_name(instance_id: &str) -> String {
+  get_instance_name_override().unwrap_or_else(|| instance_id.to_string())
+}
+
+fn get_pod_id() -> Option<String> {
+  get_pod_name().or_else(get_pod_uid).or_else(|| {
+    if is_kubernetes_pod() {
+      get_hostname()
+    } else {
+      None
+    }
+  })
+}
+
+fn get_hostname() -> Option<String> {
+  fs::read_to_string("/proc/sys/kernel/hostname")
+    .ok()
+    .or_else(|| std::env::var("HOSTNAME").ok())
+    .map(|hostname| hostname.trim().to_string())
+    .filter(|hostname| !hostname.is_empty())
+}
@@ -0,0 +1,174 @@
+//! Parameters of the worker instance, distinct from the parameters of the jobs
+//!
+//! The worker declares them with `MessageEvent::get_instance_parameters_schema` (model path, device index, license key...).
+//! Each property is read at startup from the configuration variable of its uppercased name prefixed with `INSTANCE_PARAMETER_`
+//! (`model_path` from `INSTANCE_PARAMETER_MODEL_PATH`),
+//! parsed according to its type, and the parameters are given to `MessageEvent::init_with_parameters`.
+
+use crate::config::{get_instance_parameter, get_instance_parameter_key};
+use crate::{MessageError, Result};
+use schemars::schema::{InstanceType, RootSchema, Schema, SingleOrVec};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstanceParameters {
+  values: Map<String, Value>,
+}
+
+impl InstanceParameters {
+  /// Values of the parameters of the schema set in the configuration
+  pub(crate) fn resolve(schema: Option<&RootSchema>) -> Result<Self> {
+    match schema {
+      Some(schema) => Self::resolve_with(schema, get_instance_parameter),
+      None => Ok(Self::default()),
+    }
+  }
+
+  fn resolve_with<F: Fn(&str) -> Option<String>>(
+    schema: &RootSchema,
+    get_value: F,
+  ) -> Result<Self> {
+    let object = match &schema.schema.object {
+      Some(object) => object,
+      None => return Ok(Self::default()),
+    };
+
+    let mut values = Map::new();
+    let mut errors = vec![];
+
+    for (identifier, property) in &object.properties {
+      let key = get_instance_parameter_key(identifier);
+      match get_value(identifier) {
+        Some(value) => match parse_value(property, &value) {
+          Ok(value) => {
+            values.insert(identifier.clone(), value);
+          }
+          Err(error) => errors.push(format!("{} ({}): {}", identifier, key, error)),
+        },
+        None if object.required.contains(identifier) => {
+          errors.push(format!("{} ({}): missing value", identifier, key))
+        }
+        None => {}
+      }
+    }
+
+    if errors.is_empty() {
+      Ok(InstanceParameters { values })
+    } else {
+      Err(MessageError::ParameterValueError(format!(
+        "Invalid instance parameters: {}",
+        errors.join(", ")
+      )))
+    }
+  }
+
+  pub fn get_values(&self) -> &Map<String, Value> {
+    &self.values
+  }
+
+  /// Typed parameters, the type of the schema of `get_instance_parameters_schema`
+  pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+    serde_json::from_value(Value::Object(self.values.clone())).map_err(|error| {
+      MessageError::ParameterValueError(format!("Invalid instance parameters: {}", error))
+    })
+  }
+}
+
+/// The values of the non-string types are JSON, like `2`, `true` or `["en", "fr"]`
+fn parse_value(property: &Schema, value: &str) -> std::result::Result<Value, String> {
+  let instance_type = match property {
+    Schema::Object(schema) => match &schema.instance_type {
+      Some(SingleOrVec::Single(instance_type)) => Some(**instance_type),
+      Some(SingleOrVec::Vec(instance_types)) => instance_types
+        .iter()
+        .find(|instance_type| **instance_type != InstanceType::Null)
+        .cloned(),
+      None => None,
+    },
+    Schema::Bool(_) => None,
+  };
+
+  match instance_type {
+    Some(InstanceType::String) => Ok(Value::String(value.to_string())),
+    Some(InstanceType::Boolean) => match value {
+      "true" | "1" | "True" | "TRUE" => Ok(Value::Bool(true)),
+      "false" | "0" | "False" | "FALSE" => Ok(Value::Bool(false)),
+      _ => Err(format!("expected a boolean, got {:?}", value)),
+    },
+    Some(InstanceType::Integer) => value
+      .parse::<i64>()
+      .map(Value::from)
+      .map_err(|_| format!("expected an integer, got {:?}", value)),
+    Some(InstanceType::Number) => value
+      .parse::<f64>()
+      .map(Value::from)
+      .map_err(|_| format!("expected a number, got {:?}", value)),
+    _ => Ok(serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))),
+  }
+}
+
+#[test]
+pub fn test_resolve_instance_parameters() {
+  use schemars::JsonSchema;
+
+  #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+  struct Parameters {
+    model_path: String,
+    device_index: u32,
+    half_precision: Option<bool>,
+    languages: Vec<String>,
+    threshold: Option<f64>,
+  }
+
+  let schema = schemars::schema_for!(Parameters);
+  let parameters = InstanceParameters::resolve_with(&schema, |identifier| match identifier {
+    "model_path" => Some("/models/large.bin".to_string()),
+    "device_index" => Some("1".to_string()),
+    "half_precision" => Some("true".to_string()),
+    "languages" => Some(r#"["en", "fr"]"#.to_string()),
+    _ => None,
+  })
+  .unwrap();
+
+  assert_eq!(
+    Parameters {
+      model_path: "/models/large.bin".to_string(),
+      device_index: 1,
+      half_precision: Some(true),
+      languages: vec!["en".to_string(), "fr".to_string()],
+      threshold: None,
+    },
+    parameters.deserialize::<Parameters>().unwrap()
+  );
+
+  let result = InstanceParameters::resolve_with(&schema, |identifier| match identifier {
+    "device_index" => Some("first".to_string()),
+    _ => None,
+  });
+  assert_eq!(
+    Err(MessageError::ParameterValueError(
+      "Invalid instance parameters: device_index (INSTANCE_PARAMETER_DEVICE_INDEX): expected an integer, got \"first\", \
+       languages (INSTANCE_PARAMETER_LANGUAGES): missing value, model_path (INSTANCE_PARAMETER_MODEL_PATH): missing value"
+        .to_string()
+    )),
+    result
+  );
+
+  assert_eq!(
+    Ok(InstanceParameters::default()),
+    InstanceParameters::resolve(None)
+  );
+
+  #[derive(Deserialize, JsonSchema)]
+  struct PathParameters {
+    #[allow(dead_code)]
+    path: Option<String>,
+  }
+
+  // the unrelated `PATH` variable is not read
+  std::env::set_var("INSTANCE_PARAMETER_PATH", "/models");
+  let parameters =
+    InstanceParameters::resolve(Some(&schemars::schema_for!(PathParameters))).unwrap();
+  assert_eq!(Some(&json!("/models")), parameters.get_values().get("path"));
+}
@@ -0,0 +1,128 @@
+//! Threads processing the jobs of a worker
+//!
+//! The pool has as many threads as the maximum number of concurrent jobs, resized when it is reloaded.
+//! The orders received while every thread is busy wait for a thread to be available.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+pub(crate) struct JobPool {
+  sender: Mutex<mpsc::Sender<Task>>,
+  receiver: Arc<Mutex<mpsc::Receiver<Task>>>,
+  size: Arc<AtomicUsize>,
+  threads: Arc<AtomicUsize>,
+}
+
+impl JobPool {
+  /// Pool without thread until resized
+  pub(crate) fn new() -> Self {
+    let (sender, receiver) = mpsc::channel();
+
+    JobPool {
+      sender: Mutex::new(sender),
+      receiver: Arc::new(Mutex::new(receiver)),
+      size: Arc::new(AtomicUsize::new(0)),
+      threads: Arc::new(AtomicUsize::new(0)),
+    }
+  }
+
+  /// Start the missing threads, the extra ones stop once their job is processed
+  pub(crate) fn resize(&self, size: u16) {
+    let size = size as usize;
+    self.size.store(size, Ordering::SeqCst);
+
+    while self.threads.load(Ordering::SeqCst) < size {
+      self.threads.fetch_add(1, Ordering::SeqCst);
+      self.spawn_thread();
+    }
+  }
+
+  #[cfg(test)]
+  fn get_threads(&self) -> usize {
+    self.threads.load(Ordering::SeqCst)
+  }
+
+  /// Process the task on a thread of the pool, once one is available
+  pub(crate) fn execute<F: FnOnce() + Send + 'static>(&self, task: F) {
+    if self.sender.lock().unwrap().send(Box::new(task)).is_err() {
+      error!("Unable to process the job, the job threads are stopped");
+    }
+  }
+
+  fn spawn_thread(&self) {
+    let receiver = self.receiver.clone();
+    let size = self.size.clone();
+    let threads = self.threads.clone();
+
+    thread::spawn(move || loop {
+      let current_threads = threads.load(Ordering::SeqCst);
+      if current_threads > size.load(Ordering::SeqCst) {
+        if threads
+          .compare_exchange(
+            current_threads,
+            current_threads - 1,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+          )
+          .is_ok()
+        {
+          return;
+        }
+        continue;
+      }
+
+      let task = receiver.lock().unwrap().recv();
+      match task {
+        Ok(task) => {
+          // the thread remains in the pool whatever the outcome of the job
+          if catch_unwind(AssertUnwindSafe(task)).is_err() {
+            error!("The processing of a job panicked");
+          }
+        }
+        Err(_) => {
+          threads.fetch_sub(1, Ordering::SeqCst);
+          return;
+        }
+      }
+    });
+  }
+}
+
+#[test]
+pub fn test_job_pool() {
+  use std::time::Duration;
+
+  let job_pool = JobPool::new();
+  assert_eq!(0, job_pool.get_threads());
+
+  job_pool.resize(3);
+  assert_eq!(3, job_pool.get_threads());
+
+  let (sender, receiver) = mpsc::channel();
+  for index in 0..10 {
+    let sender = sender.clone();
+    job_pool.execute(move || sender.send(index).unwrap());
+  }
+  job_pool.execute(|| panic!("job panicked"));
+
+  let mut processed: Vec<i32> = (0..10)
+    .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+    .collect();
+  processed.sort_unstable();
+  assert_eq!((0..10).collect::<Vec<i32>>(), processed);
+
+  // the extra threads stop once they get a job
+  job_pool.resize(1);
+  for _ in 0..2 {
+    job_pool.execute(|| {});
+  }
+  let stopped = (0..50).any(|_| {
+    thread::sleep(Duration::from_millis(20));
+    job_pool.get_threads() == 1
+  });
+  assert!(stopped);
+}
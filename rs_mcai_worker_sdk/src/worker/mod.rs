@@ -1,28 +1,63 @@
 //! Module to manage the worker
 
+use schemars::gen::SchemaSettings;
 use schemars::schema::RootSchema;
 use schemars::schema_for;
 use schemars::JsonSchema;
 use semver::Version;
 use serde::Deserialize;
 
+use crate::config::get_worker_labels;
+use crate::job::{JobProgression, JobResult};
+use crate::parameter::deprecation::{apply_deprecations, register_deprecated_parameters};
+use crate::parameter::secret::register_secret_parameters;
+use crate::parameter::ui_hint::apply_hints;
 #[cfg(feature = "media")]
 use crate::{
   message::{DESTINATION_PATH_PARAMETER, SOURCE_PATH_PARAMETER},
   MessageError,
 };
 use crate::{MessageEvent, Result};
+use hardware::HardwareCapabilities;
 use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
+pub mod description;
+pub mod direct_message;
 pub mod docker;
+pub mod embedded;
+pub mod hardware;
+pub mod heartbeat;
+pub mod instance;
+pub mod instance_parameters;
+pub(crate) mod job_pool;
+pub mod processing_window;
+pub mod readiness;
+pub mod registration;
+pub mod registry;
+pub mod reload;
+pub mod self_test;
+pub(crate) mod shutdown;
 pub mod system_information;
+pub mod watchdog;
 
 pub mod built_info {
   include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
+/// Schema of the parameters, with the nested objects (like the items of an `array_of_objects`) described in place
+pub(crate) fn parameter_schema_for<P: JsonSchema>() -> RootSchema {
+  SchemaSettings::draft07()
+    .with(|settings| settings.inline_subschemas = true)
+    .into_generator()
+    .into_root_schema_for::<P>()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ParameterType {
+  #[serde(rename = "array_of_objects")]
+  ArrayOfObjects,
   #[serde(rename = "array_of_strings")]
   ArrayOfStrings,
   #[serde(rename = "boolean")]
@@ -49,6 +84,9 @@ pub struct Parameter {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WorkerConfiguration {
   instance_id: String,
+  /// Human-readable name of the instance, its identifier if not named
+  #[serde(default)]
+  instance_name: String,
   queue_name: String,
   label: String,
   short_description: String,
@@ -56,6 +94,48 @@ pub struct WorkerConfiguration {
   version: Version,
   sdk_version: Version,
   parameters: RootSchema,
+  /// Parameters of each job action
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  actions: BTreeMap<String, RootSchema>,
+  messages: MessageSchemas,
+  /// Parameters of the instance, read from the configuration at startup
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  instance_parameters: Option<RootSchema>,
+  /// MIME types of the accepted sources, any if empty
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  input_content_types: Vec<String>,
+  /// MIME types of the produced files
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  output_content_types: Vec<String>,
+  /// Processors, memory and GPUs of the instance
+  #[serde(default)]
+  hardware: HardwareCapabilities,
+  /// Free-form labels for the filtering of the fleet, like `team=ingest` or `gpu=true`
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  labels: BTreeMap<String, String>,
+  #[serde(skip)]
+  start_instant: Option<Instant>,
+}
+
+/// JSON Schemas of the messages published by the worker
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MessageSchemas {
+  pub progression: RootSchema,
+  pub completed: RootSchema,
+  pub error: RootSchema,
+  /// Typed output of the worker, added to the job results
+  pub output: Option<RootSchema>,
+}
+
+impl MessageSchemas {
+  fn new(output: Option<RootSchema>) -> Self {
+    MessageSchemas {
+      progression: schema_for!(JobProgression),
+      completed: schema_for!(JobResult),
+      error: schema_for!(JobResult),
+      output,
+    }
+  }
 }
 
 impl WorkerConfiguration {
@@ -67,10 +147,21 @@ impl WorkerConfiguration {
     let sdk_version =
       Version::parse(built_info::PKG_VERSION).unwrap_or_else(|_| Version::new(0, 0, 0));
 
-    let parameters = WorkerConfiguration::get_parameter_schema::<P>()?;
+    let mut parameters = WorkerConfiguration::get_parameter_schema::<P>()?;
+    let actions = message_event.get_actions().get_schemas();
+    apply_deprecations(&mut parameters, &message_event.get_parameter_deprecations());
+    apply_hints(&mut parameters, &message_event.get_parameter_hints());
+
+    register_secret_parameters(&parameters);
+    register_deprecated_parameters(&parameters);
+    for action in actions.values() {
+      register_secret_parameters(action);
+      register_deprecated_parameters(action);
+    }
 
     Ok(WorkerConfiguration {
       instance_id: instance_id.to_string(),
+      instance_name: instance::get_instance_name(instance_id),
       queue_name: queue_name.to_string(),
       label: message_event.get_name(),
       sdk_version,
@@ -78,12 +169,20 @@ impl WorkerConfiguration {
       short_description: message_event.get_short_description(),
       description: message_event.get_description(),
       parameters,
+      actions,
+      messages: MessageSchemas::new(message_event.get_output_schema()),
+      instance_parameters: message_event.get_instance_parameters_schema(),
+      input_content_types: message_event.get_input_content_types(),
+      output_content_types: message_event.get_output_content_types(),
+      hardware: HardwareCapabilities::get(),
+      labels: get_worker_labels(),
+      start_instant: Some(Instant::now()),
     })
   }
 
   #[cfg(feature = "media")]
   fn get_parameter_schema<P: JsonSchema>() -> Result<RootSchema> {
-    let mut parameters: RootSchema = schema_for!(P);
+    let mut parameters: RootSchema = parameter_schema_for::<P>();
     if !parameters
       .schema
       .object()
@@ -111,13 +210,17 @@ impl WorkerConfiguration {
 
   #[cfg(not(feature = "media"))]
   fn get_parameter_schema<P: JsonSchema>() -> Result<RootSchema> {
-    Ok(schema_for!(P))
+    Ok(parameter_schema_for::<P>())
   }
 
   pub fn get_instance_id(&self) -> String {
     self.instance_id.clone()
   }
 
+  pub fn get_instance_name(&self) -> String {
+    self.instance_name.clone()
+  }
+
   pub fn get_queue_name(&self) -> String {
     self.queue_name.clone()
   }
@@ -138,7 +241,59 @@ impl WorkerConfiguration {
     "file".to_string()
   }
 
+  /// JSON Schema of the parameters of the jobs
+  pub fn get_parameters(&self) -> &RootSchema {
+    &self.parameters
+  }
+
+  /// Time since the worker configuration was built, at the start of the worker
+  pub fn get_uptime(&self) -> Duration {
+    self
+      .start_instant
+      .map(|start_instant| start_instant.elapsed())
+      .unwrap_or_default()
+  }
+
+  pub fn get_instance_parameters(&self) -> Option<&RootSchema> {
+    self.instance_parameters.as_ref()
+  }
+
+  pub fn get_input_content_types(&self) -> &[String] {
+    &self.input_content_types
+  }
+
+  pub fn get_output_content_types(&self) -> &[String] {
+    &self.output_content_types
+  }
+
+  pub fn get_hardware(&self) -> &HardwareCapabilities {
+    &self.hardware
+  }
+
+  /// Add the labels, replacing the values of the labels already set
+  pub fn with_labels(mut self, labels: BTreeMap<String, String>) -> Self {
+    self.labels.extend(labels);
+    self
+  }
+
+  pub fn get_labels(&self) -> &BTreeMap<String, String> {
+    &self.labels
+  }
+
+  pub fn get_message_schemas(&self) -> &MessageSchemas {
+    &self.messages
+  }
+
   pub fn get_direct_messaging_queue_name(&self) -> String {
     format!("direct_messaging_{}", self.instance_id)
   }
+
+  /// Tag of the consumer of the job orders, listed by the broker
+  pub fn get_consumer_tag(&self) -> String {
+    self.instance_name.clone()
+  }
+
+  pub fn get_direct_messaging_consumer_tag(&self) -> String {
+    format!("status_{}", self.instance_name)
+  }
 }
@@ -0,0 +1,229 @@
+//! Time-of-day windows in which the job orders are consumed
+//!
+//! A window is a cron expression (`minute hour day-of-month month day-of-week`) matching the minutes,
+//! in the local time of the worker, in which new jobs are started.
+//! For instance `* 0-17,23 * * *` excludes the broadcast prime time, from 18:00 to 23:00.
+//! Out of the windows, the job consumer is cancelled: the jobs in progress are completed,
+//! the worker stays connected and reports it is waiting.
+
+use crate::worker::readiness::WorkerReadiness;
+use crate::{McaiChannel, MessageError, Result};
+use chrono::{Datelike, Local, Timelike};
+use futures::channel::oneshot;
+use lapin::options::BasicCancelOptions;
+use std::thread;
+use std::time::Duration;
+
+/// Interval between two checks of the windows
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessingWindow {
+  minutes: u64,
+  hours: u64,
+  days_of_month: u64,
+  months: u64,
+  days_of_week: u64,
+  /// When both days are restricted, either of them matches like in cron
+  restricted_days: bool,
+}
+
+impl ProcessingWindow {
+  pub fn new(expression: &str) -> Result<Self> {
+    let invalid = |reason: String| {
+      MessageError::RuntimeError(format!(
+        "Invalid processing window {:?}: {}",
+        expression, reason
+      ))
+    };
+
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    if fields.len() != 5 {
+      return Err(invalid(format!(
+        "5 fields expected, {} found",
+        fields.len()
+      )));
+    }
+
+    let days_of_week = parse_field(fields[4], 0, 7).map_err(invalid)?;
+    // Sunday is either 0 or 7
+    let days_of_week = if days_of_week & (1 << 7) != 0 {
+      days_of_week | 1
+    } else {
+      days_of_week
+    };
+
+    Ok(ProcessingWindow {
+      minutes: parse_field(fields[0], 0, 59).map_err(invalid)?,
+      hours: parse_field(fields[1], 0, 23).map_err(invalid)?,
+      days_of_month: parse_field(fields[2], 1, 31).map_err(invalid)?,
+      months: parse_field(fields[3], 1, 12).map_err(invalid)?,
+      days_of_week,
+      restricted_days: fields[2] != "*" && fields[4] != "*",
+    })
+  }
+
+  pub fn contains<T: Datelike + Timelike>(&self, datetime: &T) -> bool {
+    let day_of_month = self.days_of_month & (1 << datetime.day()) != 0;
+    let day_of_week = self.days_of_week & (1 << datetime.weekday().num_days_from_sunday()) != 0;
+    let day = if self.restricted_days {
+      day_of_month || day_of_week
+    } else {
+      day_of_month && day_of_week
+    };
+
+    day
+      && self.minutes & (1 << datetime.minute()) != 0
+      && self.hours & (1 << datetime.hour()) != 0
+      && self.months & (1 << datetime.month()) != 0
+  }
+}
+
+/// Windows in which the job orders are consumed, none matching if empty
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessingWindows {
+  windows: Vec<ProcessingWindow>,
+}
+
+impl ProcessingWindows {
+  /// Windows separated by `;`
+  pub fn new(expressions: &str) -> Result<Self> {
+    let windows = expressions
+      .split(';')
+      .map(str::trim)
+      .filter(|expression| !expression.is_empty())
+      .map(ProcessingWindow::new)
+      .collect::<Result<Vec<ProcessingWindow>>>()?;
+
+    Ok(ProcessingWindows { windows })
+  }
+
+  pub fn is_open<T: Datelike + Timelike>(&self, datetime: &T) -> bool {
+    self.windows.iter().any(|window| window.contains(datetime))
+  }
+}
+
+/// Pause the consumption of the job orders out of the windows, while the channel is connected
+pub(crate) fn start_watcher(
+  windows: ProcessingWindows,
+  channel: McaiChannel,
+  readiness: WorkerReadiness,
+  consumer_tag: String,
+) {
+  readiness.set_waiting(!windows.is_open(&Local::now()));
+
+  thread::spawn(move || {
+    while channel.status().connected() {
+      thread::sleep(CHECK_INTERVAL);
+
+      let open = windows.is_open(&Local::now());
+      if open != readiness.is_waiting() {
+        continue;
+      }
+
+      readiness.set_waiting(!open);
+      if open {
+        info!("Processing window opened, resume consuming jobs");
+      } else {
+        info!("Out of the processing windows, pause consuming jobs");
+        cancel_consumer(&channel, &consumer_tag);
+      }
+    }
+  });
+}
+
+pub(crate) fn cancel_consumer(channel: &McaiChannel, consumer_tag: &str) {
+  if let Err(error) = channel
+    .basic_cancel(consumer_tag, BasicCancelOptions::default())
+    .wait()
+  {
+    warn!("Unable to cancel the job consumer: {:?}", error);
+  }
+}
+
+/// Resolved once a window is opened or the channel is disconnected
+pub(crate) fn wait_opening(
+  channel: McaiChannel,
+  readiness: WorkerReadiness,
+) -> oneshot::Receiver<()> {
+  let (sender, receiver) = oneshot::channel();
+
+  thread::spawn(move || {
+    while readiness.is_waiting() && channel.status().connected() {
+      thread::sleep(CHECK_INTERVAL);
+    }
+    let _ = sender.send(());
+  });
+
+  receiver
+}
+
+/// Values matched by a cron field, as bits
+fn parse_field(field: &str, min: u32, max: u32) -> std::result::Result<u64, String> {
+  let parse_value = |value: &str| {
+    value
+      .parse::<u32>()
+      .map_err(|_| format!("invalid value {:?}", value))
+  };
+
+  let mut values = 0;
+  for part in field.split(',') {
+    let (range, step) = match part.split_once('/') {
+      Some((range, step)) => (range, parse_value(step)?),
+      None => (part, 1),
+    };
+
+    let (start, end) = if range == "*" {
+      (min, max)
+    } else if let Some((start, end)) = range.split_once('-') {
+      (parse_value(start)?, parse_value(end)?)
+    } else {
+      let start = parse_value(range)?;
+      (start, if step > 1 { max } else { start })
+    };
+
+    if step == 0 || start < min || end > max || start > end {
+      return Err(format!("{:?} out of the range {}-{}", part, min, max));
+    }
+
+    for value in (start..=end).step_by(step as usize) {
+      values |= 1 << value;
+    }
+  }
+
+  Ok(values)
+}
+
+#[test]
+pub fn test_processing_windows() {
+  let datetime = |day, hour, minute| {
+    chrono::NaiveDate::from_ymd_opt(2021, 3, day)
+      .and_then(|date| date.and_hms_opt(hour, minute, 0))
+      .unwrap()
+  };
+
+  let windows = ProcessingWindows::new("* 0-17,23 * * *").unwrap();
+  assert!(windows.is_open(&datetime(12, 17, 59)));
+  assert!(!windows.is_open(&datetime(12, 18, 0)));
+  assert!(!windows.is_open(&datetime(12, 22, 59)));
+  assert!(windows.is_open(&datetime(12, 23, 0)));
+
+  // the week-end, and the nights of the working days
+  let windows = ProcessingWindows::new("* * * * 6,7; */30 0-5 * * 1-5").unwrap();
+  assert!(windows.is_open(&datetime(14, 15, 10)));
+  assert!(windows.is_open(&datetime(12, 4, 30)));
+  assert!(!windows.is_open(&datetime(12, 4, 31)));
+  assert!(!windows.is_open(&datetime(12, 15, 10)));
+
+  // days of the month or of the week, like in cron
+  let windows = ProcessingWindows::new("* * 1 * 1").unwrap();
+  assert!(windows.is_open(&datetime(1, 12, 0)));
+  assert!(windows.is_open(&datetime(8, 12, 0)));
+  assert!(!windows.is_open(&datetime(9, 12, 0)));
+
+  assert!(!ProcessingWindows::default().is_open(&Local::now()));
+  assert!(ProcessingWindows::new("* 0-17 * *").is_err());
+  assert!(ProcessingWindows::new("* 18-24 * * *").is_err());
+  assert!(ProcessingWindows::new("*/0 * * * *").is_err());
+  assert!(ProcessingWindows::new("* night * * *").is_err());
+}
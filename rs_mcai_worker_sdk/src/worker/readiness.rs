@@ -0,0 +1,109 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Initialization state of the worker, reported on the status requests
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ReadinessState {
+  pub ready: bool,
+  pub init_attempts: u32,
+  pub init_error: Option<String>,
+  /// New jobs are no longer consumed
+  pub draining: bool,
+  /// Out of the processing windows, new jobs are consumed once a window is opened
+  pub waiting: bool,
+}
+
+/// Readiness of the worker, the job queue is consumed once the worker is ready
+#[derive(Clone, Debug, Default)]
+pub struct WorkerReadiness {
+  state: Arc<(Mutex<ReadinessState>, Condvar)>,
+}
+
+impl WorkerReadiness {
+  pub fn get_state(&self) -> ReadinessState {
+    self.state.0.lock().unwrap().clone()
+  }
+
+  pub fn is_ready(&self) -> bool {
+    self.state.0.lock().unwrap().ready
+  }
+
+  pub fn set_init_error(&self, error: &str) {
+    let mut state = self.state.0.lock().unwrap();
+    state.init_attempts += 1;
+    state.init_error = Some(error.to_string());
+  }
+
+  pub fn set_ready(&self) {
+    let (state, condition) = &*self.state;
+    let mut state = state.lock().unwrap();
+    state.init_attempts += 1;
+    state.init_error = None;
+    state.ready = true;
+    condition.notify_all();
+  }
+
+  pub fn is_draining(&self) -> bool {
+    self.state.0.lock().unwrap().draining
+  }
+
+  pub fn set_draining(&self) {
+    self.state.0.lock().unwrap().draining = true;
+  }
+
+  pub fn is_waiting(&self) -> bool {
+    self.state.0.lock().unwrap().waiting
+  }
+
+  pub fn set_waiting(&self, waiting: bool) {
+    self.state.0.lock().unwrap().waiting = waiting;
+  }
+
+  /// Block until the worker is ready
+  pub fn wait(&self) {
+    let (state, condition) = &*self.state;
+    let _ready = condition
+      .wait_while(state.lock().unwrap(), |state| !state.ready)
+      .unwrap();
+  }
+}
+
+#[test]
+pub fn test_worker_readiness() {
+  let readiness = WorkerReadiness::default();
+  assert!(!readiness.is_ready());
+
+  readiness.set_init_error("Model not available");
+  assert_eq!(
+    ReadinessState {
+      ready: false,
+      init_attempts: 1,
+      init_error: Some("Model not available".to_string()),
+      draining: false,
+      waiting: false,
+    },
+    readiness.get_state()
+  );
+
+  let waiting_readiness = readiness.clone();
+  let waiting = std::thread::spawn(move || waiting_readiness.wait());
+
+  readiness.set_ready();
+  waiting.join().unwrap();
+
+  assert_eq!(
+    ReadinessState {
+      ready: true,
+      init_attempts: 2,
+      init_error: None,
+      draining: false,
+      waiting: false,
+    },
+    readiness.get_state()
+  );
+
+  readiness.set_waiting(true);
+  assert!(readiness.is_waiting());
+
+  readiness.set_draining();
+  assert!(readiness.is_draining());
+}
@@ -0,0 +1,89 @@
+//! Registration of the worker in the catalog of the backend
+//!
+//! When `WORKER_REGISTRATION_URL` is set, the worker configuration (its description) is posted
+//! to this endpoint on startup, and the worker is deregistered with a `DELETE` request on
+//! `<WORKER_REGISTRATION_URL>/<instance_id>?queue_name=<queue_name>` on shutdown.
+//! The requests are authenticated with a session of the `BACKEND` store once `BACKEND_USERNAME` is set.
+//! A failed registration is only logged, the worker still consumes its jobs.
+
+use crate::config::{get_store_username, get_worker_registration_url};
+use crate::parameter::store::get_session_token;
+use crate::worker::{shutdown, WorkerConfiguration};
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::AUTHORIZATION;
+
+const REGISTRATION_STORE: &str = "BACKEND";
+
+/// Register the worker, and its deregistration on shutdown, if an endpoint is configured
+pub(crate) fn register_worker(worker_configuration: &WorkerConfiguration) {
+  if get_worker_registration_url().is_none() {
+    return;
+  }
+
+  match register(worker_configuration) {
+    Ok(()) => {
+      info!(
+        "Worker registered on queue {:?}",
+        worker_configuration.get_queue_name()
+      );
+
+      let worker_configuration = worker_configuration.clone();
+      shutdown::register(move || {
+        if let Err(error) = deregister(&worker_configuration) {
+          warn!("Unable to deregister the worker: {}", error);
+        }
+      });
+    }
+    Err(error) => error!("Unable to register the worker: {}", error),
+  }
+}
+
+/// Post the worker configuration to the registration endpoint
+pub fn register(worker_configuration: &WorkerConfiguration) -> Result<(), String> {
+  let registration_url = get_registration_url()?;
+  let client = Client::builder().build().map_err(|e| e.to_string())?;
+
+  send(authenticate(
+    &client,
+    client.post(&registration_url).json(worker_configuration),
+  )?)
+}
+
+/// Remove the worker instance from the catalog
+pub fn deregister(worker_configuration: &WorkerConfiguration) -> Result<(), String> {
+  let registration_url = get_registration_url()?;
+  let client = Client::builder().build().map_err(|e| e.to_string())?;
+
+  let deregistration_url = format!(
+    "{}/{}",
+    registration_url.trim_end_matches('/'),
+    worker_configuration.get_instance_id()
+  );
+  let request = client
+    .delete(&deregistration_url)
+    .query(&[("queue_name", worker_configuration.get_queue_name())]);
+
+  send(authenticate(&client, request)?)
+}
+
+fn get_registration_url() -> Result<String, String> {
+  get_worker_registration_url().ok_or_else(|| "WORKER_REGISTRATION_URL is not set".to_string())
+}
+
+fn authenticate(client: &Client, request: RequestBuilder) -> Result<RequestBuilder, String> {
+  if get_store_username(REGISTRATION_STORE).is_empty() {
+    return Ok(request);
+  }
+
+  let access_token = get_session_token(client, REGISTRATION_STORE)?;
+  Ok(request.header(AUTHORIZATION, access_token))
+}
+
+fn send(request: RequestBuilder) -> Result<(), String> {
+  let response = request.send().map_err(|e| e.to_string())?;
+  if response.status().is_success() {
+    Ok(())
+  } else {
+    Err(format!("status {}", response.status()))
+  }
+}
@@ -0,0 +1,230 @@
+//! Hosting of several workers in a single process
+//!
+//! Each registered worker consumes the job orders of its own queue and answers the direct messages
+//! on its own instance, the AMQP connection and the logger are shared:
+//!
+//! ```ignore
+//! WorkerRegistry::new()
+//!   .register(ThumbnailEvent {}, "job_thumbnail")?
+//!   .register(ChecksumEvent {}, "job_checksum")?
+//!   .start();
+//! ```
+
+use crate::config::get_effective_max_concurrent_jobs;
+use crate::job::RunningJobs;
+use crate::worker::{
+  embedded::StopSignal, instance, readiness::WorkerReadiness, shutdown, WorkerConfiguration,
+};
+use crate::{MessageError, MessageEvent, Result};
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use lapin::Connection;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+/// Worker consuming its job orders on a connection shared with other workers
+pub(crate) trait JobConsumer: Send + Sync {
+  fn get_worker_configuration(&self) -> &WorkerConfiguration;
+
+  fn get_running_jobs(&self) -> &RunningJobs;
+
+  fn init(&self) -> Result<()>;
+
+  /// Call the shutdown hook of the worker
+  fn shutdown(&self);
+
+  /// Consume the job orders until the connection is lost
+  fn consume<'a>(&'a self, conn: &'a Connection) -> LocalBoxFuture<'a, ()>;
+}
+
+pub(crate) struct WorkerConsumer<P, ME> {
+  message_event: Arc<RwLock<ME>>,
+  worker_configuration: WorkerConfiguration,
+  running_jobs: RunningJobs,
+  readiness: WorkerReadiness,
+  max_concurrent_jobs: u16,
+  _parameters: PhantomData<fn() -> P>,
+}
+
+impl<P, ME> WorkerConsumer<P, ME> {
+  pub(crate) fn new(
+    message_event: Arc<RwLock<ME>>,
+    worker_configuration: WorkerConfiguration,
+    running_jobs: RunningJobs,
+    readiness: WorkerReadiness,
+    max_concurrent_jobs: u16,
+  ) -> Self {
+    WorkerConsumer {
+      message_event,
+      worker_configuration,
+      running_jobs,
+      readiness,
+      max_concurrent_jobs,
+      _parameters: PhantomData,
+    }
+  }
+}
+
+impl<P: 'static + DeserializeOwned + JsonSchema, ME: 'static + MessageEvent<P> + Send + Sync>
+  JobConsumer for WorkerConsumer<P, ME>
+{
+  fn get_worker_configuration(&self) -> &WorkerConfiguration {
+    &self.worker_configuration
+  }
+
+  fn get_running_jobs(&self) -> &RunningJobs {
+    &self.running_jobs
+  }
+
+  fn init(&self) -> Result<()> {
+    crate::init_worker(&self.message_event, &self.readiness)
+  }
+
+  fn shutdown(&self) {
+    shutdown::shutdown_message_event(&self.message_event);
+  }
+
+  fn consume<'a>(&'a self, conn: &'a Connection) -> LocalBoxFuture<'a, ()> {
+    crate::consume_jobs(
+      conn,
+      &self.message_event,
+      &self.worker_configuration,
+      &self.running_jobs,
+      &self.readiness,
+      self.max_concurrent_jobs,
+    )
+    .boxed_local()
+  }
+}
+
+pub struct WorkerRegistry {
+  instance_id: String,
+  workers: Vec<Arc<dyn JobConsumer>>,
+}
+
+impl Default for WorkerRegistry {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl WorkerRegistry {
+  pub fn new() -> Self {
+    WorkerRegistry {
+      instance_id: instance::get_instance_id(),
+      workers: vec![],
+    }
+  }
+
+  /// Host the worker, consuming the job orders of the queue
+  pub fn register<
+    P: 'static + DeserializeOwned + JsonSchema,
+    ME: 'static + MessageEvent<P> + Send + Sync,
+  >(
+    mut self,
+    message_event: ME,
+    queue_name: &str,
+  ) -> Result<Self> {
+    if self
+      .workers
+      .iter()
+      .any(|worker| worker.get_worker_configuration().get_queue_name() == queue_name)
+    {
+      return Err(MessageError::RuntimeError(format!(
+        "A worker is already registered on queue {}",
+        queue_name
+      )));
+    }
+
+    // each worker answers the direct messages on its own queue
+    let instance_id = format!("{}_{}", self.instance_id, queue_name);
+    let worker_configuration = WorkerConfiguration::new(queue_name, &message_event, &instance_id)?;
+
+    let max_concurrent_jobs = get_effective_max_concurrent_jobs();
+
+    self.workers.push(Arc::new(WorkerConsumer::new(
+      Arc::new(RwLock::new(message_event)),
+      worker_configuration,
+      RunningJobs::default(),
+      WorkerReadiness::default(),
+      max_concurrent_jobs,
+    )));
+    Ok(self)
+  }
+
+  pub fn get_worker_configurations(&self) -> Vec<WorkerConfiguration> {
+    self
+      .workers
+      .iter()
+      .map(|worker| worker.get_worker_configuration().clone())
+      .collect()
+  }
+
+  /// Start the registered workers, the process is exited if one of them fails to initialize
+  pub fn start(self) {
+    let queue_names: Vec<String> = self
+      .workers
+      .iter()
+      .map(|worker| worker.get_worker_configuration().get_queue_name())
+      .collect();
+    crate::logger::init(
+      &crate::worker::instance::get_instance_name(&self.instance_id),
+      &queue_names.join(","),
+    );
+    crate::exit_on_invalid_configuration();
+
+    if self.workers.is_empty() {
+      error!("No worker registered");
+      return;
+    }
+
+    shutdown::handle_signals();
+
+    let worker_names: Vec<String> = self
+      .workers
+      .iter()
+      .map(|worker| worker.get_worker_configuration().get_worker_name())
+      .collect();
+    // the workers share the service version only if they have the same version
+    let mut worker_versions: Vec<String> = self
+      .workers
+      .iter()
+      .map(|worker| worker.get_worker_configuration().get_worker_version())
+      .collect();
+    worker_versions.sort();
+    worker_versions.dedup();
+    let worker_version = match worker_versions.as_slice() {
+      [worker_version] => Some(worker_version.as_str()),
+      _ => None,
+    };
+    crate::telemetry::init(&worker_names.join(","), worker_version, &self.instance_id);
+
+    for worker in &self.workers {
+      let shutdown_worker = worker.clone();
+      shutdown::register(move || shutdown_worker.shutdown());
+      crate::worker::registration::register_worker(worker.get_worker_configuration());
+
+      let worker_configuration = worker.get_worker_configuration();
+      info!(
+        "Worker: {}, version: {} on queue {:?} (MCAI Worker SDK {})",
+        worker_configuration.get_worker_name(),
+        worker_configuration.get_worker_version(),
+        worker_configuration.get_queue_name(),
+        worker_configuration.get_sdk_version(),
+      );
+
+      // status requests are answered during the initialization, jobs are consumed once initialized
+      let init_worker = worker.clone();
+      thread::spawn(move || {
+        if let Err(message) = init_worker.init() {
+          error!("{:?}", message);
+          shutdown::exit(1);
+        }
+      });
+    }
+
+    crate::run_amqp_worker(&self.workers, &StopSignal::default());
+  }
+}
@@ -0,0 +1,124 @@
+//! Reload of the runtime configuration, ordered with the `reload_configuration` direct message
+//!
+//! Only the non-secret settings read again by the SDK on use can be set by the message:
+//! the HTTP client settings and the maximum number of concurrent jobs.
+//! The hostnames and credentials of the parameter stores are never taken from the message,
+//! the sessions of the stores are dropped instead for their configuration to be read again on the next request.
+
+use crate::config::{get_max_concurrent_jobs, SdkConfig};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+const RELOADABLE_KEYS: &[&str] = &[
+  "MAX_CONCURRENT_JOBS",
+  "HTTP_CLIENT_TIMEOUT",
+  "HTTP_CLIENT_MAX_RETRIES",
+  "HTTP_CLIENT_RETRY_DELAY",
+  "HTTP_CLIENT_RATE_LIMIT",
+];
+
+/// Maximum number of concurrent jobs set by the last reload, 0 if never reloaded
+static RELOADED_MAX_CONCURRENT_JOBS: AtomicU16 = AtomicU16::new(0);
+
+/// Set once the job consumer is cancelled, to be started again with the reloaded prefetch
+static CONSUMER_RESTART: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigurationReload {
+  pub applied: Vec<String>,
+  pub ignored: Vec<String>,
+}
+
+impl ConfigurationReload {
+  pub fn has_max_concurrent_jobs(&self) -> bool {
+    self.applied.iter().any(|key| key == "MAX_CONCURRENT_JOBS")
+  }
+}
+
+/// The AMQP connection is established once and the stores are configured by the worker only
+fn is_reloadable(key: &str) -> bool {
+  RELOADABLE_KEYS.contains(&key)
+}
+
+/// Apply the reloadable values on the configuration of the process, the others are ignored
+pub(crate) fn reload(values: &BTreeMap<String, String>) -> ConfigurationReload {
+  let mut reload = ConfigurationReload::default();
+  let mut config = SdkConfig::new();
+
+  for (key, value) in values {
+    if is_reloadable(key) {
+      config = config.with_value(key, value);
+      reload.applied.push(key.clone());
+    } else {
+      reload.ignored.push(key.clone());
+    }
+  }
+  config.apply();
+
+  if reload.has_max_concurrent_jobs() {
+    RELOADED_MAX_CONCURRENT_JOBS.store(get_max_concurrent_jobs(), Ordering::SeqCst);
+  }
+
+  reload
+}
+
+/// The job consumer is cancelled, to be started again with the current prefetch
+pub(crate) fn request_consumer_restart() {
+  CONSUMER_RESTART.store(true, Ordering::SeqCst);
+}
+
+/// Whether the job consumer has been cancelled to be started again, reset once read
+pub(crate) fn take_consumer_restart() -> bool {
+  CONSUMER_RESTART.swap(false, Ordering::SeqCst)
+}
+
+/// Maximum number of concurrent jobs, the one set at startup until reloaded
+pub(crate) fn get_reloaded_max_concurrent_jobs(max_concurrent_jobs: u16) -> u16 {
+  match RELOADED_MAX_CONCURRENT_JOBS.load(Ordering::SeqCst) {
+    0 => max_concurrent_jobs,
+    reloaded => reloaded,
+  }
+}
+
+#[test]
+pub fn test_is_reloadable() {
+  assert!(is_reloadable("HTTP_CLIENT_RATE_LIMIT"));
+  assert!(is_reloadable("MAX_CONCURRENT_JOBS"));
+  assert!(!is_reloadable("BACKEND_HOSTNAME"));
+  assert!(!is_reloadable("MEDIA_ASSETS_PASSWORD"));
+  assert!(!is_reloadable("AMQP_HOSTNAME"));
+  assert!(!is_reloadable("AMQP_QUEUE"));
+  assert!(!is_reloadable("_HOSTNAME"));
+  assert!(!is_reloadable("JOB_TIMEOUT"));
+}
+
+#[test]
+pub fn test_reload() {
+  let values: BTreeMap<String, String> = vec![
+    ("HTTP_CLIENT_TIMEOUT", "120"),
+    ("RELOAD_TEST_HOSTNAME", "http://attacker:4000/api"),
+    ("AMQP_QUEUE", "job_reloaded"),
+  ]
+  .into_iter()
+  .map(|(key, value)| (key.to_string(), value.to_string()))
+  .collect();
+
+  let reload = reload(&values);
+  assert_eq!(
+    ConfigurationReload {
+      applied: vec!["HTTP_CLIENT_TIMEOUT".to_string()],
+      ignored: vec!["AMQP_QUEUE".to_string(), "RELOAD_TEST_HOSTNAME".to_string()],
+    },
+    reload
+  );
+  assert!(!reload.has_max_concurrent_jobs());
+  assert_eq!(Some(120), crate::config::get_http_client_timeout());
+  assert!(!crate::config::is_store_configured("RELOAD_TEST"));
+  assert_eq!(3, get_reloaded_max_concurrent_jobs(3));
+
+  // the requests of the other tests are sent without timeout
+  SdkConfig::new()
+    .with_value("HTTP_CLIENT_TIMEOUT", "")
+    .apply();
+  assert_eq!(None, crate::config::get_http_client_timeout());
+}
@@ -0,0 +1,84 @@
+use crate::worker::WorkerConfiguration;
+use crate::MessageEvent;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Instant;
+
+/// Result of a check of the worker self-test
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SelfTestCheck {
+  pub name: String,
+  pub passed: bool,
+  pub message: Option<String>,
+}
+
+impl SelfTestCheck {
+  pub fn new(name: &str, result: std::result::Result<(), String>) -> Self {
+    SelfTestCheck {
+      name: name.to_string(),
+      passed: result.is_ok(),
+      message: result.err(),
+    }
+  }
+}
+
+/// Diagnostic document of the worker self-test
+#[derive(Clone, Debug, Serialize)]
+pub struct SelfTestDiagnostic {
+  pub docker_container_id: String,
+  pub worker_name: String,
+  pub worker_version: String,
+  pub sdk_version: String,
+  pub passed: bool,
+  /// Duration of the self-test in seconds
+  pub duration: f64,
+  pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestDiagnostic {
+  /// Run the self-test of the worker, a panic is reported as a failed check
+  pub fn run<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
+    message_event: &ME,
+    worker_configuration: &WorkerConfiguration,
+  ) -> Self {
+    let start = Instant::now();
+
+    let checks =
+      catch_unwind(AssertUnwindSafe(|| message_event.self_test())).unwrap_or_else(|error| {
+        let message = error
+          .downcast_ref::<&str>()
+          .map(|message| message.to_string())
+          .or_else(|| error.downcast_ref::<String>().cloned())
+          .unwrap_or_else(|| "unknown panic".to_string());
+        vec![SelfTestCheck::new(
+          "self_test",
+          Err(format!("Self-test panicked: {}", message)),
+        )]
+      });
+
+    Self::new(worker_configuration, checks, start.elapsed().as_secs_f64())
+  }
+
+  pub fn new(
+    worker_configuration: &WorkerConfiguration,
+    checks: Vec<SelfTestCheck>,
+    duration: f64,
+  ) -> Self {
+    SelfTestDiagnostic {
+      docker_container_id: worker_configuration.get_instance_id(),
+      worker_name: worker_configuration.get_worker_name(),
+      worker_version: worker_configuration.get_worker_version(),
+      sdk_version: worker_configuration.get_sdk_version(),
+      passed: checks.iter().all(|check| check.passed),
+      duration,
+      checks,
+    }
+  }
+
+  pub(crate) fn with_first_check(mut self, check: SelfTestCheck) -> Self {
+    self.passed = self.passed && check.passed;
+    self.checks.insert(0, check);
+    self
+  }
+}
@@ -0,0 +1,146 @@
+//! Termination of the worker process
+//!
+//! The shutdown hooks of the workers (`MessageEvent::on_shutdown`) are called once when the process
+//! terminates: on a termination signal (`SIGTERM`, `SIGINT`) once `HANDLE_TERMINATION_SIGNALS` is enabled,
+//! a fatal error, or a drain with exit.
+//!
+//! The handlers previously installed for the termination signals are called after the hooks,
+//! then the process exits with the code `128 + signal`, like if it was terminated by the signal.
+
+use crate::{config::get_handle_termination_signals, MessageEvent};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::process;
+use std::sync::{
+  atomic::{AtomicI32, Ordering},
+  Arc, Mutex, Once, RwLock,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type ShutdownHook = Box<dyn FnOnce() + Send>;
+
+static SHUTDOWN_HOOKS: Mutex<Vec<ShutdownHook>> = Mutex::new(Vec::new());
+/// Termination signal received, 0 until then
+static TERMINATION_SIGNAL: AtomicI32 = AtomicI32::new(0);
+static SIGNAL_HANDLERS: Once = Once::new();
+#[cfg(unix)]
+static PREVIOUS_SIGNAL_HANDLERS: Mutex<Vec<(libc::c_int, libc::sighandler_t)>> =
+  Mutex::new(Vec::new());
+
+/// Maximum duration to wait for the jobs in progress to release the worker
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) fn register<F: 'static + FnOnce() + Send>(hook: F) {
+  SHUTDOWN_HOOKS.lock().unwrap().push(Box::new(hook));
+}
+
+/// Run the registered hooks, each one is called once
+pub(crate) fn run_hooks() {
+  let hooks: Vec<ShutdownHook> = SHUTDOWN_HOOKS.lock().unwrap().drain(..).collect();
+  for hook in hooks {
+    hook();
+  }
+}
+
+/// Exit the process once the hooks are run
+pub(crate) fn exit(code: i32) -> ! {
+  run_hooks();
+  process::exit(code)
+}
+
+/// Call the shutdown hook of the worker, skipped if a job does not complete in time
+pub(crate) fn shutdown_message_event<P: DeserializeOwned + JsonSchema, ME: MessageEvent<P>>(
+  message_event: &Arc<RwLock<ME>>,
+) {
+  let started = Instant::now();
+  loop {
+    if let Ok(mut message_event) = message_event.try_write() {
+      info!("Shutdown the worker");
+      message_event.on_shutdown();
+      return;
+    }
+
+    if started.elapsed() > SHUTDOWN_TIMEOUT {
+      warn!("Worker still processing a job, its shutdown hook is not called");
+      return;
+    }
+    thread::sleep(Duration::from_millis(100));
+  }
+}
+
+#[cfg(unix)]
+extern "C" fn on_termination_signal(signal: libc::c_int) {
+  TERMINATION_SIGNAL.store(signal, Ordering::SeqCst);
+}
+
+/// Exit the process on a termination signal once the hooks are run, if `HANDLE_TERMINATION_SIGNALS` is enabled
+pub(crate) fn handle_signals() {
+  if !get_handle_termination_signals() {
+    return;
+  }
+
+  SIGNAL_HANDLERS.call_once(|| {
+    #[cfg(unix)]
+    install_signal_handlers();
+
+    thread::spawn(|| loop {
+      let signal = TERMINATION_SIGNAL.load(Ordering::SeqCst);
+      if signal != 0 {
+        info!("Termination requested by the signal {}", signal);
+        run_hooks();
+        #[cfg(unix)]
+        call_previous_signal_handler(signal);
+        process::exit(128 + signal);
+      }
+      thread::sleep(Duration::from_millis(100));
+    });
+  });
+}
+
+/// The signals ignored by the process (e.g. with `nohup`) remain ignored
+#[cfg(unix)]
+fn install_signal_handlers() {
+  let handler = on_termination_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+  let mut previous_handlers = PREVIOUS_SIGNAL_HANDLERS.lock().unwrap();
+
+  for signal in [libc::SIGTERM, libc::SIGINT] {
+    let previous_handler = unsafe { libc::signal(signal, handler) };
+    if previous_handler == libc::SIG_IGN {
+      unsafe { libc::signal(signal, libc::SIG_IGN) };
+    } else if previous_handler != libc::SIG_ERR {
+      previous_handlers.push((signal, previous_handler));
+    }
+  }
+}
+
+/// Restore and call the handler installed before the SDK one, if any
+#[cfg(unix)]
+fn call_previous_signal_handler(signal: libc::c_int) {
+  let previous_handler = PREVIOUS_SIGNAL_HANDLERS
+    .lock()
+    .unwrap()
+    .iter()
+    .find(|(handled_signal, _)| *handled_signal == signal)
+    .map(|(_, previous_handler)| *previous_handler);
+
+  if let Some(previous_handler) = previous_handler {
+    unsafe { libc::signal(signal, previous_handler) };
+    if previous_handler != libc::SIG_DFL {
+      let previous_handler: extern "C" fn(libc::c_int) =
+        unsafe { std::mem::transmute(previous_handler) };
+      previous_handler(signal);
+    }
+  }
+}
+
+#[test]
+pub fn test_shutdown_hooks() {
+  let calls = Arc::new(Mutex::new(0));
+  let hook_calls = calls.clone();
+  register(move || *hook_calls.lock().unwrap() += 1);
+
+  run_hooks();
+  run_hooks();
+  assert_eq!(1, *calls.lock().unwrap());
+}
@@ -1,23 +1,47 @@
-use crate::worker::WorkerConfiguration;
+use crate::job::{JobError, RunningJob, RunningJobs};
+use crate::worker::{
+  readiness::{ReadinessState, WorkerReadiness},
+  WorkerConfiguration,
+};
 use lapin::{
   message::Delivery,
   options::{BasicAckOptions, BasicPublishOptions, BasicRejectOptions},
   BasicProperties, Channel, Promise,
 };
+use std::collections::BTreeMap;
 use sysinfo::SystemExt;
 
 #[derive(Debug, Serialize)]
 struct SystemInformation {
   docker_container_id: String,
+  instance_name: String,
+  worker_name: String,
+  worker_version: String,
+  queue_name: String,
+  /// Duration since the start of the worker in seconds
+  uptime: u64,
   total_memory: u64,
   used_memory: u64,
   total_swap: u64,
   used_swap: u64,
   number_of_processors: usize,
+  #[serde(flatten)]
+  readiness: ReadinessState,
+  running_jobs: usize,
+  jobs: Vec<RunningJob>,
+  last_error: Option<JobError>,
+  /// Draining and without job in progress
+  drained: bool,
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  labels: BTreeMap<String, String>,
 }
 
 impl SystemInformation {
-  fn new(worker_configuration: &WorkerConfiguration) -> Self {
+  fn new(
+    worker_configuration: &WorkerConfiguration,
+    readiness: &WorkerReadiness,
+    running_jobs: &RunningJobs,
+  ) -> Self {
     let mut system = sysinfo::System::new_all();
     system.refresh_all();
 
@@ -27,14 +51,31 @@ impl SystemInformation {
     let total_swap = system.get_total_swap();
     let used_swap = system.get_used_swap();
     let number_of_processors = system.get_processors().len();
+    let readiness = readiness.get_state();
+    let jobs = running_jobs.get_running_jobs();
+    let last_error = running_jobs.get_last_error();
+    let running_jobs = jobs.len();
+    let drained = readiness.draining && running_jobs == 0;
+    let labels = worker_configuration.get_labels().clone();
 
     SystemInformation {
       docker_container_id,
+      instance_name: worker_configuration.get_instance_name(),
+      worker_name: worker_configuration.get_worker_name(),
+      worker_version: worker_configuration.get_worker_version(),
+      queue_name: worker_configuration.get_queue_name(),
+      uptime: worker_configuration.get_uptime().as_secs(),
       total_memory,
       used_memory,
       total_swap,
       used_swap,
       number_of_processors,
+      readiness,
+      running_jobs,
+      jobs,
+      last_error,
+      drained,
+      labels,
     }
   }
 }
@@ -43,22 +84,10 @@ pub fn send_real_time_information(
   message: Delivery,
   channel: &Channel,
   worker_configuration: &WorkerConfiguration,
+  readiness: &WorkerReadiness,
+  running_jobs: &RunningJobs,
 ) -> Promise<()> {
-  let information = SystemInformation::new(worker_configuration);
-  let serialized = serde_json::to_string(&information).unwrap();
-
-  let result = channel
-    .basic_publish(
-      "",
-      "worker_status_response",
-      BasicPublishOptions::default(),
-      serialized.as_bytes().to_vec(),
-      BasicProperties::default(),
-    )
-    .wait()
-    .is_ok();
-
-  if result {
+  if publish_status(channel, worker_configuration, readiness, running_jobs) {
     channel.basic_ack(
       message.delivery_tag,
       BasicAckOptions::default(), /*not requeue*/
@@ -70,3 +99,25 @@ pub fn send_real_time_information(
     )
   }
 }
+
+/// Publish the system information on the status queue, returns whether it succeeded
+pub fn publish_status(
+  channel: &Channel,
+  worker_configuration: &WorkerConfiguration,
+  readiness: &WorkerReadiness,
+  running_jobs: &RunningJobs,
+) -> bool {
+  let information = SystemInformation::new(worker_configuration, readiness, running_jobs);
+  let serialized = serde_json::to_string(&information).unwrap();
+
+  channel
+    .basic_publish(
+      "",
+      "worker_status_response",
+      BasicPublishOptions::default(),
+      serialized.as_bytes().to_vec(),
+      BasicProperties::default(),
+    )
+    .wait()
+    .is_ok()
+}
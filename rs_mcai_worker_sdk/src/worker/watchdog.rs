@@ -0,0 +1,189 @@
+//! Detection of hung jobs
+//!
+//! Jobs signal they are alive with heartbeats: progressions, checkpoints, processed frames
+//! or explicit calls to `JobContext::heartbeat`. A job without heartbeat for longer than
+//! the `WATCHDOG_TIMEOUT` is declared stuck.
+
+use crate::job::RunningJobs;
+use crate::parameter::ParameterValue;
+use crate::worker::shutdown;
+use crate::McaiChannel;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{process, thread};
+
+static HEARTBEATS: Mutex<BTreeMap<u64, Heartbeat>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone, Debug)]
+struct Heartbeat {
+  started: Instant,
+  last: Instant,
+  count: u64,
+  progression: Option<u8>,
+  stuck: bool,
+}
+
+/// Diagnostics of a job declared stuck, published with its error
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StuckJobDiagnostic {
+  pub job_id: u64,
+  /// Duration since the last heartbeat in seconds
+  pub idle_duration: f64,
+  /// Duration since the job started in seconds
+  pub running_duration: f64,
+  pub heartbeats: u64,
+  pub last_progression: Option<u8>,
+}
+
+impl ParameterValue for StuckJobDiagnostic {
+  fn get_type_as_string() -> String {
+    "stuck_job_diagnostic".to_string()
+  }
+}
+
+pub(crate) fn start(job_id: u64) {
+  let now = Instant::now();
+  HEARTBEATS.lock().unwrap().insert(
+    job_id,
+    Heartbeat {
+      started: now,
+      last: now,
+      count: 0,
+      progression: None,
+      stuck: false,
+    },
+  );
+}
+
+pub(crate) fn finish(job_id: u64) {
+  HEARTBEATS.lock().unwrap().remove(&job_id);
+}
+
+/// Heartbeats of jobs not in progress are ignored
+pub(crate) fn beat(job_id: u64) {
+  if let Some(heartbeat) = HEARTBEATS.lock().unwrap().get_mut(&job_id) {
+    heartbeat.last = Instant::now();
+    heartbeat.count += 1;
+    heartbeat.stuck = false;
+  }
+}
+
+pub(crate) fn beat_progression(job_id: u64, progression: u8) {
+  if let Some(heartbeat) = HEARTBEATS.lock().unwrap().get_mut(&job_id) {
+    heartbeat.last = Instant::now();
+    heartbeat.count += 1;
+    heartbeat.progression = Some(progression);
+    heartbeat.stuck = false;
+  }
+}
+
+/// Last progression of a job in progress
+pub(crate) fn get_progression(job_id: u64) -> Option<u8> {
+  HEARTBEATS
+    .lock()
+    .unwrap()
+    .get(&job_id)
+    .and_then(|heartbeat| heartbeat.progression)
+}
+
+/// Jobs without heartbeat for longer than the timeout, reported once until their next heartbeat
+pub(crate) fn get_stuck_jobs(timeout: Duration) -> Vec<StuckJobDiagnostic> {
+  let now = Instant::now();
+  HEARTBEATS
+    .lock()
+    .unwrap()
+    .iter_mut()
+    .filter(|(_job_id, heartbeat)| !heartbeat.stuck && now.duration_since(heartbeat.last) > timeout)
+    .map(|(job_id, heartbeat)| {
+      heartbeat.stuck = true;
+      StuckJobDiagnostic {
+        job_id: *job_id,
+        idle_duration: now.duration_since(heartbeat.last).as_secs_f64(),
+        running_duration: now.duration_since(heartbeat.started).as_secs_f64(),
+        heartbeats: heartbeat.count,
+        last_progression: heartbeat.progression,
+      }
+    })
+    .collect()
+}
+
+/// Check the heartbeats while the channel is connected
+pub fn start_watchdog(
+  channel: McaiChannel,
+  running_jobs: Vec<RunningJobs>,
+  timeout: Duration,
+  restart: bool,
+) {
+  thread::spawn(move || {
+    let interval = (timeout / 10).clamp(Duration::from_millis(100), Duration::from_secs(10));
+
+    while channel.status().connected() {
+      thread::sleep(interval);
+
+      for diagnostic in get_stuck_jobs(timeout) {
+        error!(target: &diagnostic.job_id.to_string(), "Job stuck, no heartbeat for {:.0} seconds: {:?}", diagnostic.idle_duration, diagnostic);
+
+        // let the job abort if it polls its context, whatever the worker running it
+        for running_jobs in &running_jobs {
+          running_jobs.cancel(diagnostic.job_id);
+        }
+        crate::message::publish_stuck_job_error(&channel, &diagnostic);
+
+        if restart {
+          restart_process();
+        }
+      }
+    }
+  });
+}
+
+/// Replace the process by a new instance of the worker, the unacknowledged orders are requeued
+fn restart_process() {
+  warn!("Restarting the worker");
+  shutdown::run_hooks();
+
+  #[cfg(unix)]
+  if let Ok(executable) = std::env::current_exe() {
+    use std::os::unix::process::CommandExt;
+
+    let error = process::Command::new(executable)
+      .args(std::env::args_os().skip(1))
+      .exec();
+    error!("Unable to restart the worker: {}", error);
+  }
+
+  process::exit(1);
+}
+
+#[test]
+pub fn test_watchdog_heartbeats() {
+  let job_id = 7_531_598;
+  let timeout = Duration::from_millis(50);
+
+  beat(job_id);
+  assert!(!HEARTBEATS.lock().unwrap().contains_key(&job_id));
+
+  start(job_id);
+  beat_progression(job_id, 20);
+  assert!(get_stuck_jobs(timeout)
+    .iter()
+    .all(|diagnostic| diagnostic.job_id != job_id));
+
+  thread::sleep(Duration::from_millis(100));
+  let diagnostic = get_stuck_jobs(timeout)
+    .into_iter()
+    .find(|diagnostic| diagnostic.job_id == job_id)
+    .unwrap();
+  assert_eq!(1, diagnostic.heartbeats);
+  assert_eq!(Some(20), diagnostic.last_progression);
+  assert!(diagnostic.idle_duration >= 0.05);
+
+  // reported once until the next heartbeat
+  assert!(get_stuck_jobs(timeout)
+    .iter()
+    .all(|diagnostic| diagnostic.job_id != job_id));
+
+  finish(job_id);
+  assert!(!HEARTBEATS.lock().unwrap().contains_key(&job_id));
+}
@@ -0,0 +1,113 @@
+extern crate mcai_worker_sdk;
+
+use mcai_worker_sdk::job::{command::Command, JobContext};
+use mcai_worker_sdk::{MessageError, ParametersContainer};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+fn get_error_message(error: MessageError) -> String {
+  match error {
+    MessageError::ProcessingError(job_result) => {
+      job_result.get_parameter::<String>("message").unwrap()
+    }
+    error => panic!("unexpected error: {:?}", error),
+  }
+}
+
+#[test]
+pub fn test_command_output() {
+  let progressions = Rc::new(RefCell::new(vec![]));
+  let parsed_progressions = progressions.clone();
+
+  let output = Command::new("sh")
+    .with_args([
+      "-c",
+      "echo progress=50; echo warning >&2; echo progress=100",
+    ])
+    .with_progression(move |line| {
+      let progression = line.strip_prefix("progress=")?.parse().ok()?;
+      parsed_progressions.borrow_mut().push(progression);
+      Some(progression)
+    })
+    .run(None, &JobContext::new(123))
+    .unwrap();
+
+  assert_eq!("progress=50\nprogress=100\n", output.stdout);
+  assert_eq!("warning\n", output.stderr);
+  assert_eq!(vec![50, 100], *progressions.borrow());
+}
+
+#[test]
+pub fn test_command_exit_code() {
+  let error = Command::new("sh")
+    .with_args(["-c", "echo first error >&2; echo last error >&2; exit 3"])
+    .run(None, &JobContext::new(123))
+    .unwrap_err();
+  assert_eq!(
+    "sh exited with code 3: first error\nlast error",
+    get_error_message(error)
+  );
+
+  let error = Command::new("missing_program_for_test")
+    .run(None, &JobContext::new(123))
+    .unwrap_err();
+  assert!(get_error_message(error).starts_with("Unable to run missing_program_for_test"));
+}
+
+#[test]
+pub fn test_command_accepted_exit_codes() {
+  let output = Command::new("sh")
+    .with_args(["-c", "exit 1"])
+    .with_accepted_exit_codes(&[1])
+    .run(None, &JobContext::new(123))
+    .unwrap();
+  assert_eq!(Some(1), output.exit_code);
+
+  let error = Command::new("sh")
+    .with_args(["-c", "exit 2"])
+    .with_accepted_exit_codes(&[1])
+    .run(None, &JobContext::new(123))
+    .unwrap_err();
+  assert_eq!("sh exited with code 2: ", get_error_message(error));
+}
+
+#[test]
+pub fn test_command_max_output_size() {
+  let output = Command::new("sh")
+    .with_args(["-c", "for line in 1 2 3 4 5; do echo line $line; done"])
+    .with_max_output_size(14)
+    .run(None, &JobContext::new(123))
+    .unwrap();
+  assert_eq!("line 4\nline 5\n", output.stdout);
+  assert_eq!(Some(0), output.exit_code);
+}
+
+#[test]
+pub fn test_command_timeout() {
+  let started = Instant::now();
+  let error = Command::new("sleep")
+    .with_arg("10")
+    .with_timeout(Duration::from_millis(200))
+    .run(None, &JobContext::new(123))
+    .unwrap_err();
+
+  assert_eq!("sleep timed out after 0 seconds", get_error_message(error));
+  assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+pub fn test_command_cancellation() {
+  let context = JobContext::new(123);
+  let cancelled_context = context.clone();
+  std::thread::spawn(move || {
+    std::thread::sleep(Duration::from_millis(200));
+    cancelled_context.cancel();
+  });
+
+  let started = Instant::now();
+  let result = Command::new("sleep").with_arg("10").run(None, &context);
+
+  assert!(matches!(result, Err(MessageError::ProcessingError(_))));
+  assert!(started.elapsed() < Duration::from_secs(5));
+}
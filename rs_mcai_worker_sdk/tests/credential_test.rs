@@ -1,8 +1,29 @@
 extern crate mcai_worker_sdk;
 
 use mcai_worker_sdk::{
-  job::*, parameter::media_segment::MediaSegment, MessageError, ParametersContainer,
+  job::*,
+  parameter::{media_segment::MediaSegment, Credential},
+  MessageError, ParameterValue, ParametersContainer,
 };
+use serde_derive::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct S3Configuration {
+  bucket: String,
+  secret_key: Credential<String>,
+}
+
+impl ParameterValue for S3Configuration {
+  fn get_type_as_string() -> String {
+    "s3_configuration".to_string()
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkerParameters {
+  destination: S3Configuration,
+  source: S3Configuration,
+}
 
 #[test]
 fn test_string_credential_request_value() {
@@ -425,6 +446,56 @@ fn test_string_credential_request_value_invalid_credential() {
   );
 }
 
+#[test]
+fn test_credential_request_value_reuses_session() {
+  std::env::set_var("REUSED_BACKEND_HOSTNAME", mockito::server_url());
+  std::env::set_var("REUSED_BACKEND_USERNAME", "reused_session@media.io");
+  use mockito::{mock, Matcher};
+
+  let session = mock("POST", "/sessions")
+    .match_body(Matcher::Regex("reused_session@media.io".to_string()))
+    .with_header("content-type", "application/json")
+    .with_body(r#"{"access_token": "reused_access_token"}"#)
+    .expect(1)
+    .create();
+
+  let credential = mock("GET", "/credentials/REUSED_CREDENTIAL_KEY")
+    .match_header("authorization", "reused_access_token")
+    .with_header("content-type", "application/json")
+    .with_body(
+      r#"{"data": {
+        "id": 667,
+        "key": "REUSED_CREDENTIAL_KEY",
+        "value": "REUSED_CREDENTIAL_VALUE",
+        "inserted_at": "today"
+      }}"#,
+    )
+    .expect(2)
+    .create();
+
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"test_credential",
+        "type":"string",
+        "store":"REUSED_BACKEND",
+        "value":"REUSED_CREDENTIAL_KEY"
+      }
+    ]
+  }"#;
+
+  for _ in 0..2 {
+    let job = Job::new(message).unwrap();
+    assert_eq!(
+      job.get_parameter::<String>("test_credential"),
+      Ok("REUSED_CREDENTIAL_VALUE".to_string())
+    );
+  }
+
+  session.assert();
+  credential.assert();
+}
+
 #[test]
 fn test_string_credential_request_value_without_store() {
   std::env::set_var("BACKEND_HOSTNAME", mockito::server_url());
@@ -541,18 +612,182 @@ fn test_string_credential_request_value_with_invalid_store() {
 
   let job = Job::new(message).unwrap();
 
-  #[cfg(target_os = "linux")]
-  let code = 111;
-
-  #[cfg(target_os = "macos")]
-  let code = 61;
-
-  let part_1 = "error sending request for url (http://127.0.0.1:4000/api/sessions): ";
-  let part_2 = "error trying to connect: tcp connect error: Connection refused (os error";
-  let error_message = format!(r#""{}{} {})""#, part_1, part_2, code);
+  let error_message = r#""Unknown credential store UNKNOWN, neither registered nor configured with UNKNOWN_HOSTNAME""#;
 
   assert_eq!(
     job.get_parameter::<String>("test_credential"),
-    Err(MessageError::ParameterValueError(error_message))
+    Err(MessageError::ParameterValueError(error_message.to_string()))
   );
 }
+
+#[test]
+fn test_credential_fields_request_value() {
+  std::env::set_var("TEST_S3_SECRET_KEY", "s3_secret");
+
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"destination",
+        "type":"s3_configuration",
+        "store":"env",
+        "value": {"bucket": "archive", "secret_key": "TEST_S3_SECRET_KEY"}
+      },
+      { "id":"source",
+        "type":"s3_configuration",
+        "value": {"bucket": "ingest", "secret_key": {"store": "env", "key": "TEST_S3_SECRET_KEY"}}
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  let destination = job.get_parameter::<S3Configuration>("destination").unwrap();
+  assert_eq!("archive", destination.bucket);
+  assert_eq!("s3_secret", destination.secret_key.as_str());
+
+  let parameters: WorkerParameters = job.get_parameters().unwrap();
+  assert_eq!("archive", parameters.destination.bucket);
+  assert_eq!("s3_secret", parameters.destination.secret_key.as_str());
+  assert_eq!("ingest", parameters.source.bucket);
+  assert_eq!("s3_secret", parameters.source.secret_key.as_str());
+}
+
+#[test]
+fn test_missing_credential_field() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"destination",
+        "type":"s3_configuration",
+        "store":"env",
+        "value": {"bucket": "archive", "secret_key": "TEST_S3_MISSING_SECRET_KEY"}
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  let result = job.get_parameter::<S3Configuration>("destination");
+  assert!(matches!(result, Err(MessageError::ParameterValueError(_))));
+}
+
+#[test]
+fn test_hashicorp_vault_credential_request_value() {
+  std::env::set_var("HASHICORP_VAULT_HOSTNAME", mockito::server_url());
+  std::env::set_var("HASHICORP_VAULT_TOKEN", "s.test_token");
+  use mockito::mock;
+
+  let _m = mock("GET", "/v1/secret/data/s3/ingest")
+    .match_header("X-Vault-Token", "s.test_token")
+    .with_header("content-type", "application/json")
+    .with_body(
+      r#"{"data": {
+        "data": {"secret_key": "TEST_VAULT_SECRET_KEY"},
+        "metadata": {"version": 1}
+      }}"#,
+    )
+    .create();
+
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"test_credential",
+        "type":"string",
+        "store":"HASHICORP_VAULT",
+        "value":"s3/ingest#secret_key"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  let credential = job.get_parameter::<String>("test_credential").unwrap();
+
+  assert_eq!("TEST_VAULT_SECRET_KEY".to_string(), credential);
+}
+
+#[test]
+fn test_aws_secrets_manager_credential_request_value() {
+  std::env::set_var("AWS_SECRETS_MANAGER_ENDPOINT", mockito::server_url());
+  std::env::set_var("AWS_SECRETS_MANAGER_REGION", "eu-west-1");
+  std::env::set_var("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE");
+  std::env::set_var("AWS_SECRET_ACCESS_KEY", "TEST_AWS_SECRET_ACCESS_KEY");
+  use mockito::{mock, Matcher};
+
+  let _m = mock("POST", "/")
+    .match_header("x-amz-target", "secretsmanager.GetSecretValue")
+    .match_header(
+      "authorization",
+      Matcher::Regex("^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/".to_string()),
+    )
+    .match_body(Matcher::Json(
+      serde_json::json!({"SecretId": "prod/s3-ingest"}),
+    ))
+    .with_header("content-type", "application/x-amz-json-1.1")
+    .with_body(
+      r#"{"Name": "prod/s3-ingest", "SecretString": "{\"secret_key\": \"TEST_AWS_SECRET\"}"}"#,
+    )
+    .create();
+
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"test_credential",
+        "type":"string",
+        "store":"AWS_SECRETS_MANAGER",
+        "value":"prod/s3-ingest#secret_key"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  let credential = job.get_parameter::<String>("test_credential").unwrap();
+
+  assert_eq!("TEST_AWS_SECRET".to_string(), credential);
+}
+
+#[test]
+fn test_gcp_secret_manager_credential_request_value() {
+  std::env::set_var("GCP_SECRET_MANAGER_ENDPOINT", mockito::server_url());
+  std::env::set_var("GCP_METADATA_HOSTNAME", mockito::server_url());
+  std::env::set_var("GCP_SECRET_MANAGER_PROJECT", "media");
+  use mockito::mock;
+
+  let _m = mock(
+    "GET",
+    "/computeMetadata/v1/instance/service-accounts/default/token",
+  )
+  .match_header("Metadata-Flavor", "Google")
+  .with_body(r#"{"access_token": "ya29.test_token", "expires_in": 3599, "token_type": "Bearer"}"#)
+  .create();
+
+  let _m = mock(
+    "GET",
+    "/v1/projects/media/secrets/s3-ingest/versions/latest:access",
+  )
+  .match_header("authorization", "Bearer ya29.test_token")
+  .with_header("content-type", "application/json")
+  .with_body(
+    // {"secret_key": "TEST_GCP_SECRET"}
+    r#"{"payload": {"data": "eyJzZWNyZXRfa2V5IjogIlRFU1RfR0NQX1NFQ1JFVCJ9"}}"#,
+  )
+  .create();
+
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"test_credential",
+        "type":"string",
+        "store":"GCP_SECRET_MANAGER",
+        "value":"s3-ingest#secret_key"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  let credential = job.get_parameter::<String>("test_credential").unwrap();
+
+  assert_eq!("TEST_GCP_SECRET".to_string(), credential);
+}
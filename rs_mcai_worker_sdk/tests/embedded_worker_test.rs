@@ -0,0 +1,123 @@
+extern crate mcai_worker_sdk;
+#[macro_use]
+extern crate serde_derive;
+
+use mcai_worker_sdk::{MessageError, MessageEvent, SdkConfig, Worker};
+use schemars::JsonSchema;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
+
+#[derive(Debug, Default)]
+struct CustomEvent {
+  shutdown: Arc<AtomicBool>,
+}
+
+#[derive(JsonSchema, Deserialize)]
+struct CustomParameters {
+  #[cfg(feature = "media")]
+  source_path: String,
+  #[cfg(feature = "media")]
+  destination_path: String,
+}
+
+impl MessageEvent<CustomParameters> for CustomEvent {
+  fn get_name(&self) -> String {
+    "custom".to_string()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> semver::Version {
+    semver::Version::new(1, 2, 3)
+  }
+  fn on_shutdown(&mut self) {
+    self.shutdown.store(true, Ordering::SeqCst);
+  }
+}
+
+#[test]
+pub fn test_embedded_worker_build() {
+  let result = Worker::<CustomParameters, CustomEvent>::builder().build();
+  assert!(matches!(result, Err(MessageError::RuntimeError(_))));
+
+  let worker = Worker::builder()
+    .with_message_event(CustomEvent::default())
+    .with_queue_name("job_embedded")
+    .with_instance_id("embedded_instance")
+    .build()
+    .unwrap();
+
+  let worker_configuration = worker.get_worker_configuration();
+  assert_eq!("job_embedded", worker_configuration.get_queue_name());
+  assert_eq!("embedded_instance", worker_configuration.get_instance_id());
+  assert_eq!("1.2.3", worker_configuration.get_worker_version());
+}
+
+#[test]
+pub fn test_embedded_worker_labels() {
+  let worker = Worker::builder()
+    .with_message_event(CustomEvent::default())
+    .with_config(SdkConfig::new().with_value("WORKER_LABELS", "team=ingest, region=eu,gpu"))
+    .with_label("region", "us")
+    .build()
+    .unwrap();
+
+  let worker_configuration = worker.get_worker_configuration();
+  let labels: Vec<(&str, &str)> = worker_configuration
+    .get_labels()
+    .iter()
+    .map(|(key, value)| (key.as_str(), value.as_str()))
+    .collect();
+  assert_eq!(
+    vec![("gpu", ""), ("region", "us"), ("team", "ingest")],
+    labels
+  );
+
+  let description = serde_json::to_value(worker_configuration).unwrap();
+  assert_eq!(
+    serde_json::json!({"gpu": "", "region": "us", "team": "ingest"}),
+    description["labels"]
+  );
+}
+
+#[test]
+pub fn test_embedded_worker_stop() {
+  // no broker is reachable, the worker keeps trying to connect until it is stopped
+  let config = SdkConfig::new()
+    .with_amqp_hostname("127.0.0.1:1")
+    .with_amqp_tls(false);
+
+  let message_event = CustomEvent::default();
+  let shutdown = message_event.shutdown.clone();
+
+  let handle = Worker::builder()
+    .with_message_event(message_event)
+    .with_config(config)
+    .with_instance_id("embedded_instance")
+    .build()
+    .unwrap()
+    .spawn()
+    .unwrap();
+
+  let status_handle = handle.clone();
+  for _ in 0..100 {
+    if status_handle.is_ready() {
+      break;
+    }
+    std::thread::sleep(std::time::Duration::from_millis(10));
+  }
+  assert!(status_handle.is_ready());
+  assert_eq!(0, status_handle.get_running_jobs_count());
+  assert!(!status_handle.is_stopped());
+
+  handle.stop();
+  assert!(handle.join().is_ok());
+  assert!(status_handle.is_stopped());
+  assert!(status_handle.join().is_ok());
+  assert!(shutdown.load(Ordering::SeqCst));
+}
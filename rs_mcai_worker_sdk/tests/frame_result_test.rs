@@ -0,0 +1,92 @@
+extern crate mcai_worker_sdk;
+#[macro_use]
+extern crate serde_json;
+
+use mcai_worker_sdk::job::{Aggregation, FrameResultCollector, JobResult};
+use mcai_worker_sdk::parameter::frame_result::{FrameResult, Histogram};
+use mcai_worker_sdk::parameter::{FrameAggregates, FrameResults};
+use mcai_worker_sdk::ParametersContainer;
+use std::sync::{mpsc, Arc, Mutex};
+
+#[test]
+pub fn test_frame_result_collector() {
+  let (sender, receiver) = mpsc::channel();
+  let sender = Arc::new(Mutex::new(sender));
+
+  let mut frame_results = FrameResultCollector::new()
+    .with_aggregation(
+      "loudness",
+      Aggregation::new("loudness").with_histogram(-40.0, 0.0, 4),
+    )
+    .with_aggregation("peak", Aggregation::new("/levels/peak").for_stream(1))
+    .with_sink(move |frame_result| {
+      sender.lock().unwrap().send(frame_result.pts).unwrap();
+    });
+
+  frame_results.push(FrameResult::new(0, 0, &json!({"loudness": -23.0})));
+  frame_results.push(FrameResult::new(0, 40, &json!({"loudness": -50.0})));
+  frame_results.push(FrameResult::new(
+    1,
+    40,
+    &json!({"loudness": -5.0, "levels": {"peak": -1.5}}),
+  ));
+  frame_results.push(FrameResult::new(0, 80, &json!({"silence": true})));
+
+  assert_eq!(
+    vec![0, 40, 40, 80],
+    receiver.try_iter().collect::<Vec<i64>>()
+  );
+  assert_eq!(4, frame_results.get_frames().len());
+
+  let aggregates = frame_results.get_aggregates();
+  let loudness = aggregates.get("loudness").unwrap();
+  assert_eq!(3, loudness.count);
+  assert_eq!(-50.0, loudness.min);
+  assert_eq!(-5.0, loudness.max);
+  assert_eq!(-26.0, loudness.mean);
+  assert_eq!(
+    Some(Histogram {
+      min: -40.0,
+      max: 0.0,
+      bins: vec![1, 1, 0, 1],
+    }),
+    loudness.histogram
+  );
+
+  let peak = aggregates.get("peak").unwrap();
+  assert_eq!(1, peak.count);
+  assert_eq!(-1.5, peak.mean);
+  assert_eq!(None, peak.histogram);
+
+  let job_result = frame_results.attach(JobResult::new(123)).unwrap();
+  let frames: FrameResults = job_result.get_parameter("frame_results").unwrap();
+  assert_eq!(frame_results.get_frames(), &frames);
+  let job_aggregates: FrameAggregates = job_result.get_parameter("frame_aggregates").unwrap();
+  assert_eq!(aggregates, job_aggregates);
+}
+
+#[test]
+pub fn test_frame_result_collector_without_frames() {
+  let mut frame_results = FrameResultCollector::new()
+    .with_aggregation("score", Aggregation::new("score"))
+    .without_frames();
+
+  frame_results.push(FrameResult::new(0, 0, &json!({"score": 0.5})));
+  frame_results.push(FrameResult::new(0, 40, &json!({"score": 1.0})));
+  assert!(frame_results.get_frames().is_empty());
+
+  let job_result = frame_results.attach(JobResult::new(123)).unwrap();
+  assert!(job_result
+    .get_parameter::<FrameResults>("frame_results")
+    .is_err());
+  let aggregates: FrameAggregates = job_result.get_parameter("frame_aggregates").unwrap();
+  assert_eq!(0.75, aggregates.get("score").unwrap().mean);
+
+  // an aggregation without value
+  let frame_results =
+    FrameResultCollector::new().with_aggregation("score", Aggregation::new("score"));
+  assert_eq!(
+    0,
+    frame_results.get_aggregates().get("score").unwrap().count
+  );
+}
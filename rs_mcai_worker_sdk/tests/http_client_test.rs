@@ -0,0 +1,61 @@
+extern crate mcai_worker_sdk;
+
+use mcai_worker_sdk::job::JobContext;
+use mcai_worker_sdk::{MessageError, SdkConfig};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Respond to each connection with the next status
+fn start_server(statuses: Vec<&'static str>) -> String {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = listener.local_addr().unwrap();
+
+  thread::spawn(move || {
+    for status in statuses {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut buffer = [0; 1024];
+      let _ = stream.read(&mut buffer);
+      let response = format!(
+        "HTTP/1.1 {}\r\nRetry-After: 0\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok",
+        status
+      );
+      stream.write_all(response.as_bytes()).unwrap();
+    }
+  });
+
+  format!("http://{}/", address)
+}
+
+#[test]
+pub fn test_http_client_retry() {
+  SdkConfig::new()
+    .with_value("HTTP_CLIENT_MAX_RETRIES", "2")
+    .with_value("HTTP_CLIENT_RETRY_DELAY", "10")
+    .with_value("HTTP_CLIENT_RATE_LIMIT", "50")
+    .apply();
+
+  let http_client = JobContext::new(123).get_http_client().unwrap();
+
+  let url = start_server(vec![
+    "503 Service Unavailable",
+    "429 Too Many Requests",
+    "200 OK",
+  ]);
+  let response = http_client.get(&url).unwrap();
+  assert_eq!(200, response.status().as_u16());
+
+  let url = start_server(vec!["404 Not Found"]);
+  let response = http_client.get(&url).unwrap();
+  assert_eq!(404, response.status().as_u16());
+
+  let url = start_server(vec![
+    "502 Bad Gateway",
+    "502 Bad Gateway",
+    "502 Bad Gateway",
+  ]);
+  match http_client.get(&url) {
+    Err(MessageError::Transient(job_result)) => assert_eq!(123, job_result.get_job_id()),
+    _ => panic!("the retries should be exhausted"),
+  }
+}
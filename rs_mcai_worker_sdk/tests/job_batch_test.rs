@@ -0,0 +1,111 @@
+extern crate mcai_worker_sdk;
+
+use mcai_worker_sdk::job::*;
+use mcai_worker_sdk::{MessageError, ParametersContainer};
+
+#[test]
+fn test_new_job_batch() {
+  let message = r#"{
+    "batch_id": 42,
+    "jobs": [
+      { "job_id": 123,
+        "parameters": [
+          { "id":"string_parameter",
+            "type":"string",
+            "value":"first_value" }
+        ]
+      },
+      { "job_id": 124,
+        "parameters": [
+          { "id":"string_parameter",
+            "type":"string",
+            "value":"second_value" }
+        ]
+      }
+    ]
+  }"#;
+
+  let result = JobBatch::new(message);
+  assert!(result.is_ok());
+  let batch = result.unwrap();
+  assert_eq!(42, batch.batch_id);
+  assert_eq!(2, batch.jobs.len());
+  assert_eq!(123, batch.jobs[0].job_id);
+  assert_eq!(124, batch.jobs[1].job_id);
+}
+
+#[test]
+fn test_new_job_batch_invalid_message() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": []
+  }"#;
+
+  let result = JobBatch::new(message);
+  assert!(result.is_err());
+}
+
+#[test]
+fn test_job_batch_aggregate() {
+  let batch = JobBatch {
+    batch_id: 42,
+    jobs: vec![],
+  };
+
+  let job_results = vec![
+    JobResult::new(123).with_status(JobStatus::Completed),
+    JobResult::new(124).with_status(JobStatus::Completed),
+  ];
+  let job_result = batch.aggregate(job_results.clone()).unwrap();
+  assert_eq!(42, job_result.get_job_id());
+  assert_eq!(&JobStatus::Completed, job_result.get_status());
+
+  let results: Vec<JobResult> = job_result.get_parameter("job_results").unwrap();
+  assert_eq!(job_results, results);
+
+  let job_results = vec![
+    JobResult::new(123).with_status(JobStatus::Completed),
+    JobResult::new(124)
+      .with_status(JobStatus::Error)
+      .with_message("failure"),
+  ];
+  let job_result = batch.aggregate(job_results).unwrap();
+  assert_eq!(&JobStatus::Error, job_result.get_status());
+
+  let job_results = vec![
+    JobResult::new(123).skipped("Already processed"),
+    JobResult::new(124)
+      .with_status(JobStatus::Completed)
+      .with_warning("Subtitles track missing"),
+  ];
+  let job_result = batch.aggregate(job_results).unwrap();
+  assert_eq!(&JobStatus::CompletedWithWarnings, job_result.get_status());
+
+  let job_results = vec![
+    JobResult::new(123).skipped("Already processed"),
+    JobResult::new(124).skipped("Already processed"),
+  ];
+  let job_result = batch.aggregate(job_results).unwrap();
+  assert_eq!(&JobStatus::Skipped, job_result.get_status());
+}
+
+#[test]
+fn test_job_batch_message_error() {
+  let batch = JobBatch {
+    batch_id: 42,
+    jobs: vec![Job::new(
+      r#"{
+        "job_id": 123,
+        "parameters": [
+          { "id":"requirements",
+            "type":"requirements",
+            "value": { "paths": ["/path/to/nowhere"] } }
+        ]
+      }"#,
+    )
+    .unwrap()],
+  };
+
+  let result = batch.check_requirements();
+  assert!(matches!(result, Err(MessageError::RequirementsError(_))));
+}
@@ -2,11 +2,12 @@ extern crate mcai_worker_sdk;
 
 use mcai_worker_sdk::{
   job::*,
-  parameter::{media_segment::MediaSegment, MediaSegments},
+  parameter::{chapter::Chapter, media_segment::MediaSegment, Chapters, MediaSegments},
   MessageError, ParameterValue, ParametersContainer,
 };
 use reqwest::blocking::Client;
 use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
@@ -396,6 +397,39 @@ fn job_result_with_setters() {
   assert_eq!(Ok(json_object), json_param);
 }
 
+#[test]
+fn job_result_with_warnings() {
+  let job_result = JobResult::new(123)
+    .with_warning("Subtitles track missing")
+    .with_status(JobStatus::Completed);
+  assert_eq!(job_result.get_status(), &JobStatus::CompletedWithWarnings);
+  assert!(job_result.get_status().is_successful());
+
+  let job_result = job_result.with_warning("Audio track truncated");
+  assert_eq!(
+    vec!["Subtitles track missing", "Audio track truncated"],
+    job_result.get_warnings()
+  );
+  assert_eq!(
+    json!("array_of_strings"),
+    json!(job_result)["parameters"][0]["type"]
+  );
+  assert_eq!(
+    json!("completed_with_warnings"),
+    json!(job_result)["status"]
+  );
+
+  let job_result = JobResult::new(123).with_status(JobStatus::Completed);
+  assert!(job_result.get_warnings().is_empty());
+
+  let job_result = JobResult::new(123).skipped("Already processed");
+  assert_eq!(job_result.get_status(), &JobStatus::Skipped);
+  assert_eq!(
+    Ok("Already processed".to_string()),
+    job_result.get_parameter::<String>("message")
+  );
+}
+
 #[test]
 fn job_result_with_error() {
   let job_id = 123;
@@ -438,3 +472,101 @@ fn job_result_with_media_segments() {
   let json_param = job_result.get_parameter::<MediaSegments>("segments");
   assert_eq!(Ok(segments), json_param);
 }
+
+#[test]
+fn job_result_with_chapters() {
+  let job_id = 123;
+  let mut job_result = JobResult::new(job_id);
+  let chapters = vec![
+    Chapter::new(1, 0, 10_000, Some("Opening".to_string())),
+    Chapter::new(2, 10_000, 25_000, None),
+  ];
+  job_result = job_result.with_json("chapters", &chapters).unwrap();
+
+  let json_param = job_result.get_parameter::<Chapters>("chapters");
+  assert_eq!(Ok(chapters), json_param);
+}
+
+#[test]
+fn job_result_with_json_payload() {
+  let payload = serde_json::json!({
+    "faces": [{"x": 10, "y": 20}],
+    "count": 1
+  });
+  let job_result = JobResult::new(123)
+    .with_json("detections", &payload)
+    .unwrap();
+
+  assert_eq!(
+    Ok(payload.clone()),
+    job_result.get_parameter::<serde_json::Value>("detections")
+  );
+
+  let serialized = serde_json::to_value(&job_result).unwrap();
+  assert_eq!("json", serialized["parameters"][0]["type"]);
+  assert_eq!(payload, serialized["parameters"][0]["value"]);
+}
+
+#[test]
+fn job_result_with_artifacts() {
+  let mut artifacts = vec![JobArtifact::new(
+    "thumbnail",
+    "/data/thumbnail.jpg",
+    "image/jpeg",
+    None,
+  )];
+  let job_result = JobResult::new(123)
+    .with_artifact(
+      "proxy",
+      "https://storage.example.com/proxy.mp4",
+      "video/mp4",
+      Some("sha256:9f86d081884c7d65"),
+    )
+    .with_artifacts(&mut artifacts);
+
+  assert_eq!(2, job_result.get_artifacts().len());
+  assert_eq!("proxy", job_result.get_artifacts()[0].name);
+  assert_eq!("image/jpeg", job_result.get_artifacts()[1].content_type);
+
+  let json = serde_json::to_value(&job_result).unwrap();
+  assert_eq!(
+    serde_json::json!({
+      "name": "proxy",
+      "location": "https://storage.example.com/proxy.mp4",
+      "content_type": "video/mp4",
+      "checksum": "sha256:9f86d081884c7d65"
+    }),
+    json["artifacts"][0]
+  );
+
+  let parsed: JobResult = serde_json::from_value(json).unwrap();
+  assert_eq!(job_result, parsed);
+
+  // no artifacts in the completed message of a job without files
+  let json = serde_json::to_value(JobResult::new(123)).unwrap();
+  assert!(json.get("artifacts").is_none());
+}
+
+#[test]
+fn job_result_origin() {
+  let json = serde_json::to_value(JobResult::new(123)).unwrap();
+  assert_eq!(json!(env!("CARGO_PKG_VERSION")), json["sdk_version"]);
+  // the worker is only known while the order of the job is processed
+  assert!(json.get("worker_name").is_none());
+  assert!(json.get("worker_version").is_none());
+
+  let parsed: JobResult = serde_json::from_value(json!({
+    "destination_paths": [],
+    "execution_duration": 0.0,
+    "job_id": 123,
+    "parameters": [],
+    "sdk_version": "2.0.0",
+    "status": "completed",
+    "worker_name": "transcoder",
+    "worker_version": "1.4.0",
+  }))
+  .unwrap();
+  assert_eq!("2.0.0", parsed.get_origin().get_sdk_version());
+  assert_eq!(Some("transcoder"), parsed.get_origin().get_worker_name());
+  assert_eq!(Some("1.4.0"), parsed.get_origin().get_worker_version());
+}
@@ -8,6 +8,8 @@ use assert_matches::assert_matches;
 use crate::mcai_worker_sdk::ParametersContainer;
 use mcai_worker_sdk::job::*;
 use mcai_worker_sdk::parameter::media_segment::MediaSegment;
+use mcai_worker_sdk::parameter::validation::Violation;
+use mcai_worker_sdk::parameter::Template;
 use mcai_worker_sdk::MessageError;
 
 use std::collections::HashMap;
@@ -233,6 +235,161 @@ fn test_check_invalid_requirements() {
   }
 }
 
+fn get_requirements_job(requirements: &str) -> Job {
+  let message = format!(
+    r#"{{
+    "job_id": 123,
+    "parameters": [
+      {{ "id":"requirements",
+        "type":"requirements",
+        "value": {}
+      }}
+    ]
+  }}"#,
+    requirements
+  );
+  Job::new(&message).unwrap()
+}
+
+#[test]
+fn test_check_file_requirements() {
+  let path = std::env::temp_dir().join("mcai_test_check_file_requirements");
+  std::fs::write(&path, "content").unwrap();
+
+  let job = get_requirements_job(&format!(
+    r#"{{"files": [{{"path": {:?}, "min_size": 7, "readable": true}}]}}"#,
+    path
+  ));
+  assert!(job.check_requirements().is_ok());
+
+  let job = get_requirements_job(&format!(
+    r#"{{"files": [{{"path": {:?}, "min_size": 1024}}]}}"#,
+    path
+  ));
+  assert_eq!(
+    MessageError::RequirementsError(format!(
+      "Warning: Required file {:?} has 7 bytes, less than 1024",
+      path.to_str().unwrap()
+    )),
+    job.check_requirements().unwrap_err()
+  );
+
+  let job = get_requirements_job(r#"{"files": [{"path": "nonexistent_file"}]}"#);
+  assert_matches!(
+    job.check_requirements(),
+    Err(MessageError::RequirementsError(_))
+  );
+
+  std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_check_result_requirements() {
+  let get_job = |requirements: &str| {
+    let message = format!(
+      r#"{{
+      "job_id": 123,
+      "parameters": [
+        {{ "id": "requirements", "type": "requirements", "value": {} }},
+        {{ "id": "probe", "type": "json", "value": {{"duration": 12.5, "streams": [{{"codec": "h264"}}], "audio": null}} }}
+      ]
+    }}"#,
+      requirements
+    );
+    Job::new(&message).unwrap()
+  };
+
+  let job = get_job(
+    r#"{"results": [{"key": "probe.duration"}, {"key": "probe.streams.0.codec", "value": "h264"}]}"#,
+  );
+  assert!(job.check_requirements().is_ok());
+
+  for key in &[
+    "probe.width",
+    "probe.audio",
+    "probe.streams.1",
+    "upload.url",
+  ] {
+    let job = get_job(&format!(r#"{{"results": [{{"key": {:?}}}]}}"#, key));
+    assert_eq!(
+      MessageError::RequirementsError(format!("Warning: Required result {} is not available", key)),
+      job.check_requirements().unwrap_err()
+    );
+  }
+
+  let job = get_job(r#"{"results": [{"key": "probe.streams.0.codec", "value": "hevc"}]}"#);
+  assert_eq!(
+    MessageError::RequirementsError(
+      "Warning: Required result probe.streams.0.codec is \"h264\", expected \"hevc\"".to_string()
+    ),
+    job.check_requirements().unwrap_err()
+  );
+}
+
+#[test]
+fn test_check_disk_space_requirements() {
+  let path = std::env::temp_dir();
+
+  let job = get_requirements_job(&format!(
+    r#"{{"free_disk_space": [{{"path": {:?}, "bytes": 1}}]}}"#,
+    path
+  ));
+  assert!(job.check_requirements().is_ok());
+
+  let job = get_requirements_job(&format!(
+    r#"{{"free_disk_space": [{{"path": {:?}, "bytes": {}}}]}}"#,
+    path,
+    u64::MAX
+  ));
+  if let Err(error) = job.check_requirements() {
+    assert_matches!(error, MessageError::RequirementsError(_));
+  }
+}
+
+#[test]
+fn test_check_url_requirements() {
+  use std::io::{Read, Write};
+  use std::net::TcpListener;
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = listener.local_addr().unwrap();
+  std::thread::spawn(move || {
+    for status in &["200 OK", "404 Not Found"] {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut buffer = [0; 1024];
+      let _ = stream.read(&mut buffer);
+      let response = format!(
+        "HTTP/1.1 {}\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        status
+      );
+      stream.write_all(response.as_bytes()).unwrap();
+    }
+  });
+  let url = format!("http://{}/source.mxf", address);
+
+  let job = get_requirements_job(&format!(r#"{{"urls": [{:?}]}}"#, url));
+  assert!(job.check_requirements().is_ok());
+  assert_eq!(
+    MessageError::RequirementsError(format!(
+      "Warning: Required URL {} answered with status 404 Not Found",
+      url
+    )),
+    job.check_requirements().unwrap_err()
+  );
+
+  // the server is closed
+  assert_matches!(
+    job.check_requirements(),
+    Err(MessageError::RequirementsError(_))
+  );
+
+  let job = get_requirements_job(r#"{"urls": ["ftp://server/source.mxf"]}"#);
+  assert_matches!(
+    job.check_requirements(),
+    Err(MessageError::ParameterValueError(_))
+  );
+}
+
 #[test]
 fn test_get_job_parameters() {
   let message = r#"{
@@ -265,6 +422,395 @@ fn test_get_job_parameters() {
   assert_eq!(None, job_parameters.other);
 }
 
+#[test]
+fn test_get_job_parameters_with_array_of_objects() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      {
+        "id":"segments",
+        "type":"array_of_objects",
+        "value":[{"start": 0, "end": 1000, "label": "intro"}]
+      },
+      {
+        "id":"markers",
+        "type":"array_of_objects",
+        "value":"[{\"start\": 2000, \"end\": 3000, \"label\": \"credits\"}]"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  #[derive(Debug, JsonSchema, Deserialize, PartialEq)]
+  struct Segment {
+    start: u64,
+    end: u64,
+    label: String,
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  struct WorkerJobParameters {
+    segments: Vec<Segment>,
+    markers: Vec<Segment>,
+  }
+
+  let job_parameters = job.get_parameters::<WorkerJobParameters>().unwrap();
+  assert_eq!(
+    vec![Segment {
+      start: 0,
+      end: 1000,
+      label: "intro".to_string(),
+    }],
+    job_parameters.segments
+  );
+  assert_eq!(
+    vec![Segment {
+      start: 2000,
+      end: 3000,
+      label: "credits".to_string(),
+    }],
+    job_parameters.markers
+  );
+}
+
+#[test]
+fn test_get_job_parameters_with_references() {
+  use mockito::mock;
+
+  std::env::set_var("PARAMETER_REFERENCE_ALLOWED_URLS", mockito::server_url());
+  let _m = mock("GET", "/configs/big_config.json")
+    .with_header("content-type", "application/json")
+    .with_body(r#"{"model": "large", "layers": [1, 2, 3]}"#)
+    .create();
+  let _m = mock("GET", "/configs/missing.json")
+    .with_status(404)
+    .create();
+
+  let message = format!(
+    r#"{{
+    "job_id": 123,
+    "parameters": [
+      {{
+        "id":"config",
+        "type":"json",
+        "value":{{"@ref": "{}/configs/big_config.json"}}
+      }},
+      {{
+        "id":"missing_config",
+        "type":"json",
+        "value":{{"@ref": "{}/configs/missing.json"}}
+      }}
+    ]
+  }}"#,
+    mockito::server_url(),
+    mockito::server_url()
+  );
+
+  let job = Job::new(&message).unwrap();
+
+  #[derive(Deserialize)]
+  struct Config {
+    model: String,
+    layers: Vec<u32>,
+  }
+
+  #[derive(Deserialize)]
+  struct WorkerJobParameters {
+    config: Config,
+  }
+
+  let config = job.get_parameter::<serde_json::Value>("config").unwrap();
+  assert_eq!(
+    serde_json::json!({"model": "large", "layers": [1, 2, 3]}),
+    config
+  );
+
+  assert_eq!(
+    job.get_parameter::<serde_json::Value>("missing_config"),
+    Err(MessageError::ParameterValueError(format!(
+      "Cannot download parameter reference {}/configs/missing.json: status 404 Not Found",
+      mockito::server_url()
+    )))
+  );
+
+  let job = Job {
+    parameters: job.parameters[..1].to_vec(),
+    ..job
+  };
+  let job_parameters = job.get_parameters::<WorkerJobParameters>().unwrap();
+  assert_eq!("large", job_parameters.config.model);
+  assert_eq!(vec![1, 2, 3], job_parameters.config.layers);
+}
+
+#[test]
+fn test_get_job_parameters_with_templates() {
+  std::env::set_var("TEMPLATE_OUTPUT_ROOT", "/mnt/output");
+  std::env::set_var("TEMPLATE_DENIED_ROOT", "/mnt/denied");
+  std::env::set_var(
+    "PARAMETER_TEMPLATE_VARIABLES",
+    "TEMPLATE_OUTPUT_ROOT,TEMPLATE_MISSING_ROOT",
+  );
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      {
+        "id":"destination_path",
+        "type":"string",
+        "value":"{env:TEMPLATE_OUTPUT_ROOT}/{job_id}/output.mp4"
+      },
+      {
+        "id":"title",
+        "type":"string",
+        "value":"{date} {env:TEMPLATE_DENIED_ROOT}"
+      },
+      {
+        "id":"missing_path",
+        "type":"string",
+        "value":"{env:TEMPLATE_MISSING_ROOT}/output.mp4"
+      },
+      {
+        "id":"denied_path",
+        "type":"string",
+        "value":"{env:TEMPLATE_DENIED_ROOT}/output.mp4"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct WorkerJobParameters {
+    destination_path: Template<String>,
+    title: String,
+    missing_path: Option<Template<String>>,
+    denied_path: Option<Template<String>>,
+  }
+
+  let without_parameters = |ids: &[&str]| Job {
+    parameters: job
+      .parameters
+      .iter()
+      .filter(|parameter| !ids.contains(&parameter.id.as_str()))
+      .cloned()
+      .collect(),
+    ..job.clone()
+  };
+
+  assert_eq!(
+    without_parameters(&["denied_path"])
+      .get_validated_parameters::<WorkerJobParameters>()
+      .err(),
+    Some(MessageError::ParameterValueError(
+      "Cannot expand parameter 'missing_path': Missing environment variable TEMPLATE_MISSING_ROOT"
+        .to_string()
+    ))
+  );
+  assert_eq!(
+    without_parameters(&["missing_path"])
+      .get_validated_parameters::<WorkerJobParameters>()
+      .err(),
+    Some(MessageError::ParameterValueError(
+      "Cannot expand parameter 'denied_path': Environment variable TEMPLATE_DENIED_ROOT is not allowed in the parameters"
+        .to_string()
+    ))
+  );
+
+  let job = without_parameters(&["missing_path", "denied_path"]);
+  let job_parameters = job
+    .get_validated_parameters::<WorkerJobParameters>()
+    .unwrap();
+  assert_eq!(
+    "/mnt/output/123/output.mp4".to_string(),
+    *job_parameters.destination_path
+  );
+  // the parameters not declared as templates are kept as is
+  assert_eq!(
+    "{date} {env:TEMPLATE_DENIED_ROOT}".to_string(),
+    job_parameters.title
+  );
+}
+
+#[test]
+fn test_get_unknown_job_parameters() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"source_path", "type":"string", "value":"/path/to/source" },
+      { "id":"destiantion_path", "type":"string", "value":"/path/to/destination" },
+      { "id":"sdk_timeout", "type":"integer", "value":60 },
+      { "id":"log_level", "type":"string", "value":"debug" }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct WorkerJobParameters {
+    source_path: String,
+    destination_path: Option<String>,
+  }
+
+  let schema = schemars::schema_for!(WorkerJobParameters);
+  assert_eq!(
+    vec!["destiantion_path".to_string()],
+    job.get_unknown_parameters(&schema)
+  );
+}
+
+#[test]
+fn test_get_job_parameters_with_defaults() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      {
+        "id":"source_path",
+        "type":"string",
+        "value":"/path/to/file"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  fn default_threshold() -> f64 {
+    0.5
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  struct WorkerJobParameters {
+    source_path: String,
+    #[schemars(default = "default_threshold")]
+    threshold: f64,
+  }
+
+  assert!(job.get_parameters::<WorkerJobParameters>().is_err());
+
+  let job_parameters = job
+    .get_parameters_with_defaults::<WorkerJobParameters>()
+    .unwrap();
+  assert_eq!("/path/to/file", job_parameters.source_path);
+  assert_eq!(0.5, job_parameters.threshold);
+}
+
+#[test]
+fn test_get_validated_job_parameters() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      {
+        "id":"source_path",
+        "type":"string",
+        "value":"/path/to/file"
+      },
+      {
+        "id":"threshold",
+        "type":"float",
+        "value":1.5
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  fn default_ratio() -> f64 {
+    0.5
+  }
+
+  #[derive(Debug, JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct WorkerJobParameters {
+    source_path: String,
+    #[schemars(range(max = 1))]
+    threshold: f64,
+    #[schemars(default = "default_ratio")]
+    ratio: f64,
+  }
+
+  // the defaults are applied without validation
+  let job_parameters = job
+    .get_parameters_with_defaults::<WorkerJobParameters>()
+    .unwrap();
+  assert_eq!(1.5, job_parameters.threshold);
+  assert_eq!(0.5, job_parameters.ratio);
+
+  assert_matches!(
+    job.get_validated_parameters::<WorkerJobParameters>(),
+    Err(MessageError::ProcessingError(_))
+  );
+}
+
+#[test]
+fn test_get_job_parameters_with_invalid_values() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      {
+        "id":"threshold",
+        "type":"float",
+        "value":1.5
+      },
+      {
+        "id":"segments",
+        "type":"array_of_objects",
+        "value":[{"start": 0, "end": "1000"}]
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct Segment {
+    start: u64,
+    end: u64,
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct WorkerJobParameters {
+    source_path: String,
+    #[schemars(range(max = 1))]
+    threshold: f64,
+    segments: Vec<Segment>,
+  }
+
+  let job_result = match job.get_validated_parameters::<WorkerJobParameters>() {
+    Err(MessageError::ProcessingError(job_result)) => job_result,
+    _ => panic!("The parameters should be invalid"),
+  };
+  assert_eq!(123, job_result.get_job_id());
+  assert_eq!(&JobStatus::Error, job_result.get_status());
+
+  let violations = job_result
+    .get_parameter::<Vec<Violation>>("validation_errors")
+    .unwrap();
+  assert_eq!(
+    vec![
+      Violation {
+        path: "".to_string(),
+        message: "missing required property \"source_path\"".to_string(),
+      },
+      Violation {
+        path: "/segments/0/end".to_string(),
+        message: "\"1000\" is not of type integer".to_string(),
+      },
+      Violation {
+        path: "/threshold".to_string(),
+        message: "1.5 is greater than the maximum 1".to_string(),
+      },
+    ],
+    violations
+  );
+  assert_eq!(
+    "Invalid parameters: missing required property \"source_path\", /segments/0/end: \"1000\" is not of type integer, /threshold: 1.5 is greater than the maximum 1",
+    job_result.get_parameter::<String>("message").unwrap()
+  );
+}
+
 #[test]
 fn test_get_missing_job_parameters() {
   let message = r#"{
@@ -466,6 +1012,35 @@ fn test_get_job_parameters_with_environment_credential_4() {
   std::env::remove_var("credential_key_4");
 }
 
+#[test]
+fn test_get_job_parameters_with_environment_credential_5() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      {
+        "id":"key",
+        "type":"string",
+        "value": "credential_key_5",
+        "store": "ENVIRONMENT"
+      }
+    ]
+  }"#;
+
+  let job = Job::new(message).unwrap();
+
+  #[derive(JsonSchema, Deserialize, Debug)]
+  struct WorkerJobParameters {
+    key: String,
+  }
+
+  std::env::set_var("credential_key_5", "credential_value_5");
+
+  let job_parameters = job.get_parameters::<WorkerJobParameters>().unwrap();
+  assert_eq!("credential_value_5", &job_parameters.key);
+
+  std::env::remove_var("credential_key_5");
+}
+
 #[test]
 fn test_get_job_parameters_with_unsupported_integer_credential_type() {
   let message = r#"{
@@ -497,3 +1072,28 @@ fn test_get_job_parameters_with_unsupported_integer_credential_type() {
   assert!(job_parameters.is_err());
   assert_eq!(expected, job_parameters.unwrap_err());
 }
+
+#[test]
+fn test_check_worker_version_requirements() {
+  let version = semver::Version::new(1, 4, 2);
+
+  let job = get_requirements_job(r#"{}"#);
+  assert!(job.check_worker_version(&version).is_ok());
+
+  let job = get_requirements_job(r#"{"worker_version": ">=1.2, <2"}"#);
+  assert!(job.check_worker_version(&version).is_ok());
+
+  let job = get_requirements_job(r#"{"worker_version": "^2.0"}"#);
+  assert_eq!(
+    job.check_worker_version(&version),
+    Err(MessageError::RequirementsError(
+      "Warning: Worker version 1.4.2 does not match the required version ^2.0".to_string()
+    ))
+  );
+
+  let job = get_requirements_job(r#"{"worker_version": "latest"}"#);
+  assert_matches!(
+    job.check_worker_version(&version),
+    Err(MessageError::ParameterValueError(_))
+  );
+}
@@ -0,0 +1,353 @@
+#![allow(clippy::result_large_err)]
+
+extern crate mcai_worker_sdk;
+#[macro_use]
+extern crate serde_derive;
+
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use mcai_worker_sdk::{
+  job::{
+    ExecutionMetrics, JobActions, JobContext, JobResult, JobStatus, RunningJobs,
+    EXECUTION_METRICS_PARAMETER,
+  },
+  message::parse_and_process_message,
+  worker::WorkerConfiguration,
+  McaiChannel, MessageError, MessageEvent, ParametersContainer, Result, Version,
+};
+use schemars::JsonSchema;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WorkerParameters {
+  delay: u64,
+}
+
+#[derive(Debug)]
+struct AsyncWorker {}
+
+impl MessageEvent<WorkerParameters> for AsyncWorker {
+  fn get_name(&self) -> String {
+    "async worker".to_string()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> Version {
+    Version::new(1, 2, 3)
+  }
+
+  fn process_async<'a>(
+    &'a self,
+    _channel: Option<McaiChannel>,
+    parameters: WorkerParameters,
+    job_result: JobResult,
+    _context: JobContext,
+  ) -> LocalBoxFuture<'a, Result<JobResult>> {
+    async move {
+      tokio::time::delay_for(std::time::Duration::from_millis(parameters.delay)).await;
+      Ok(job_result.with_status(JobStatus::Completed))
+    }
+    .boxed_local()
+  }
+}
+
+#[derive(Debug)]
+struct PanicWorker {}
+
+impl MessageEvent<WorkerParameters> for PanicWorker {
+  fn get_name(&self) -> String {
+    "panic worker".to_string()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> Version {
+    Version::new(1, 2, 3)
+  }
+
+  fn process(
+    &self,
+    _channel: Option<McaiChannel>,
+    parameters: WorkerParameters,
+    _job_result: JobResult,
+    _context: JobContext,
+  ) -> Result<JobResult> {
+    panic!("unexpected delay: {}", parameters.delay);
+  }
+}
+
+#[derive(Debug, Default)]
+struct WorkspaceWorker {
+  workspace_path: Mutex<Option<PathBuf>>,
+}
+
+impl MessageEvent<WorkerParameters> for WorkspaceWorker {
+  fn get_name(&self) -> String {
+    "workspace worker".to_string()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> Version {
+    Version::new(1, 2, 3)
+  }
+
+  fn process(
+    &self,
+    _channel: Option<McaiChannel>,
+    _parameters: WorkerParameters,
+    job_result: JobResult,
+    context: JobContext,
+  ) -> Result<JobResult> {
+    let workspace = context.get_workspace().unwrap();
+    std::fs::write(workspace.get_path().join("segment.ts"), b"data").unwrap();
+    *self.workspace_path.lock().unwrap() = Some(workspace.get_path().to_path_buf());
+
+    Err(MessageError::ProcessingError(
+      job_result.with_status(JobStatus::Error),
+    ))
+  }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ThumbnailParameters {
+  width: u64,
+}
+
+#[derive(Debug)]
+struct ActionWorker {}
+
+impl ActionWorker {
+  fn process_thumbnail(
+    &self,
+    _channel: Option<McaiChannel>,
+    parameters: ThumbnailParameters,
+    job_result: JobResult,
+    _context: JobContext,
+  ) -> Result<JobResult> {
+    Ok(
+      job_result
+        .with_status(JobStatus::Completed)
+        .with_message(&format!("thumbnail of {} pixels", parameters.width)),
+    )
+  }
+}
+
+impl MessageEvent<WorkerParameters> for ActionWorker {
+  fn get_name(&self) -> String {
+    "action worker".to_string()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> Version {
+    Version::new(1, 2, 3)
+  }
+
+  fn get_actions(&self) -> JobActions<Self> {
+    JobActions::new().with_action("thumbnail", ActionWorker::process_thumbnail)
+  }
+
+  fn process(
+    &self,
+    _channel: Option<McaiChannel>,
+    parameters: WorkerParameters,
+    job_result: JobResult,
+    _context: JobContext,
+  ) -> Result<JobResult> {
+    Ok(
+      job_result
+        .with_status(JobStatus::Completed)
+        .with_message(&format!("delay of {}", parameters.delay)),
+    )
+  }
+}
+
+fn ignore_progression(_channel: Option<McaiChannel>, _job_id: u64, _progression: u8) -> Result<()> {
+  Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+fn test_process_async() {
+  let message = r#"{
+    "job_id": 123,
+    "parameters": [
+      { "id":"delay",
+        "type":"integer",
+        "value": 10 }
+    ]
+  }"#;
+
+  let message_event = Arc::new(RwLock::new(AsyncWorker {}));
+
+  let result = parse_and_process_message(
+    message_event,
+    message,
+    None,
+    None,
+    &RunningJobs::default(),
+    ignore_progression,
+  );
+
+  assert!(result.is_ok());
+  let job_result = result.unwrap();
+  assert_eq!(123, job_result.get_job_id());
+  assert_eq!(&JobStatus::Completed, job_result.get_status());
+
+  let metrics: ExecutionMetrics = job_result
+    .get_parameter(EXECUTION_METRICS_PARAMETER)
+    .unwrap();
+  assert!(metrics.wall_clock_time >= 0.01);
+  assert_eq!(None, metrics.frames_processed);
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+fn test_process_panic() {
+  let message = r#"{
+    "job_id": 456,
+    "parameters": [
+      { "id":"delay",
+        "type":"integer",
+        "value": 10 }
+    ]
+  }"#;
+
+  let message_event = Arc::new(RwLock::new(PanicWorker {}));
+
+  for _ in 0..2 {
+    let result = parse_and_process_message(
+      message_event.clone(),
+      message,
+      None,
+      None,
+      &RunningJobs::default(),
+      ignore_progression,
+    );
+
+    match result {
+      Err(MessageError::ProcessingError(job_result)) => {
+        assert_eq!(456, job_result.get_job_id());
+        assert_eq!(&JobStatus::Error, job_result.get_status());
+        assert_eq!(
+          Some("Worker panicked: unexpected delay: 10".to_string()),
+          job_result.get_parameter::<String>("message").ok()
+        );
+      }
+      _ => panic!("the panic should be converted to a processing error"),
+    }
+  }
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+fn test_process_workspace_cleanup() {
+  let message = r#"{
+    "job_id": 789,
+    "parameters": [
+      { "id":"delay",
+        "type":"integer",
+        "value": 10 }
+    ]
+  }"#;
+
+  let message_event = Arc::new(RwLock::new(WorkspaceWorker::default()));
+
+  let result = parse_and_process_message(
+    message_event.clone(),
+    message,
+    None,
+    None,
+    &RunningJobs::default(),
+    ignore_progression,
+  );
+  assert!(result.is_err());
+
+  let workspace_path = message_event
+    .read()
+    .unwrap()
+    .workspace_path
+    .lock()
+    .unwrap()
+    .clone()
+    .unwrap();
+  assert!(!workspace_path.exists());
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+fn test_process_action() {
+  let message_event = Arc::new(RwLock::new(ActionWorker {}));
+  let process = |message: &str| {
+    parse_and_process_message(
+      message_event.clone(),
+      message,
+      None,
+      None,
+      &RunningJobs::default(),
+      ignore_progression,
+    )
+  };
+
+  let job_result = process(
+    r#"{
+    "job_id": 123,
+    "action": "thumbnail",
+    "parameters": [{ "id": "width", "type": "integer", "value": 320 }]
+  }"#,
+  )
+  .unwrap();
+  assert_eq!(
+    "thumbnail of 320 pixels",
+    job_result.get_parameter::<String>("message").unwrap()
+  );
+
+  let job_result = process(
+    r#"{
+    "job_id": 123,
+    "parameters": [{ "id": "delay", "type": "integer", "value": 10 }]
+  }"#,
+  )
+  .unwrap();
+  assert_eq!(
+    "delay of 10",
+    job_result.get_parameter::<String>("message").unwrap()
+  );
+
+  let result = process(
+    r#"{
+    "job_id": 123,
+    "action": "thumbnail",
+    "parameters": [{ "id": "delay", "type": "integer", "value": 10 }]
+  }"#,
+  );
+  assert!(matches!(result, Err(MessageError::ProcessingError(_))));
+
+  let result = process(
+    r#"{
+    "job_id": 123,
+    "action": "transcode",
+    "parameters": []
+  }"#,
+  );
+  assert!(matches!(result, Err(MessageError::ParameterValueError(_))));
+
+  let worker_configuration =
+    WorkerConfiguration::new("job_queue", &*message_event.read().unwrap(), "instance").unwrap();
+  let description = serde_json::to_value(&worker_configuration).unwrap();
+  assert!(description["actions"]["thumbnail"]["properties"]["width"].is_object());
+}
@@ -0,0 +1,112 @@
+#![allow(clippy::result_large_err)]
+
+extern crate mcai_worker_sdk;
+#[macro_use]
+extern crate serde_derive;
+
+use mcai_worker_sdk::{
+  job::{Job, JobContext, JobResult, JobStatus, RunningJobs},
+  message::{middleware, parse_and_process_message},
+  McaiChannel, MessageError, MessageEvent, ParametersContainer, Result, Version,
+};
+use schemars::JsonSchema;
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WorkerParameters {}
+
+#[derive(Debug)]
+struct Worker {}
+
+impl MessageEvent<WorkerParameters> for Worker {
+  fn get_name(&self) -> String {
+    "worker".to_string()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> Version {
+    Version::new(1, 2, 3)
+  }
+
+  fn process(
+    &self,
+    _channel: Option<McaiChannel>,
+    _parameters: WorkerParameters,
+    job_result: JobResult,
+    _context: JobContext,
+  ) -> Result<JobResult> {
+    Ok(job_result.with_status(JobStatus::Completed))
+  }
+}
+
+#[derive(Debug, Default)]
+struct AuthorizationMiddleware {
+  processed_jobs: Arc<Mutex<Vec<u64>>>,
+}
+
+impl middleware::Middleware for AuthorizationMiddleware {
+  fn before_process(&self, job: &Job) -> Result<()> {
+    if job.job_id == 666 {
+      return Err(MessageError::ParameterValueError(
+        "Unauthorized job".to_string(),
+      ));
+    }
+    Ok(())
+  }
+
+  fn after_process(&self, job: &Job, result: Result<JobResult>) -> Result<JobResult> {
+    self.processed_jobs.lock().unwrap().push(job.job_id);
+    result.map(|job_result| job_result.with_message("checked"))
+  }
+}
+
+fn ignore_progression(_channel: Option<McaiChannel>, _job_id: u64, _progression: u8) -> Result<()> {
+  Ok(())
+}
+
+fn process(job_id: u64) -> Result<JobResult> {
+  let message = format!(r#"{{"job_id": {}, "parameters": []}}"#, job_id);
+
+  parse_and_process_message(
+    Arc::new(RwLock::new(Worker {})),
+    &message,
+    None,
+    None,
+    &RunningJobs::default(),
+    ignore_progression,
+  )
+}
+
+#[test]
+fn test_middleware() {
+  let job_result = process(123).unwrap();
+  assert!(job_result.get_parameter::<String>("message").is_err());
+
+  let processed_jobs = Arc::new(Mutex::new(vec![]));
+  middleware::register_middleware(AuthorizationMiddleware {
+    processed_jobs: processed_jobs.clone(),
+  });
+
+  let job_result = process(123).unwrap();
+  assert_eq!(&JobStatus::Completed, job_result.get_status());
+  assert_eq!(
+    "checked".to_string(),
+    job_result.get_parameter::<String>("message").unwrap()
+  );
+
+  let result = process(666);
+  assert_eq!(
+    Err(MessageError::ParameterValueError(
+      "Unauthorized job".to_string()
+    )),
+    result
+  );
+  assert_eq!(vec![123], *processed_jobs.lock().unwrap());
+
+  middleware::clear_middlewares();
+  assert!(process(666).is_ok());
+}
@@ -1,7 +1,20 @@
 extern crate mcai_worker_sdk;
 
-use mcai_worker_sdk::{parameter::MediaSegments, MessageError, ParameterValue, Requirement};
-use serde_json::{Number, Value};
+use mcai_worker_sdk::{
+  parameter::{ByteSize, Chapters, Duration, MediaSegments},
+  MessageError, ObjectParameter, ParameterValue, Requirement,
+};
+use serde::Deserialize;
+use serde_json::{json, Number, Value};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Segment {
+  start: u64,
+  end: u64,
+  label: String,
+}
+
+impl ObjectParameter for Segment {}
 
 #[test]
 fn test_parameter_value_types_as_string() {
@@ -13,6 +26,10 @@ fn test_parameter_value_types_as_string() {
     "array_of_strings".to_string(),
     Vec::<String>::get_type_as_string()
   );
+  assert_eq!(
+    "array_of_objects".to_string(),
+    Vec::<Segment>::get_type_as_string()
+  );
   assert_eq!(
     "requirements".to_string(),
     Requirement::get_type_as_string()
@@ -21,6 +38,29 @@ fn test_parameter_value_types_as_string() {
     "array_of_media_segments".to_string(),
     MediaSegments::get_type_as_string()
   );
+  assert_eq!(
+    "array_of_chapters".to_string(),
+    Chapters::get_type_as_string()
+  );
+  assert_eq!("duration".to_string(), Duration::get_type_as_string());
+  assert_eq!("byte_size".to_string(), ByteSize::get_type_as_string());
+}
+
+#[test]
+fn test_parameter_value_duration_and_byte_size() {
+  assert_eq!(
+    Duration::from_millis(90_000),
+    Duration::parse_value(json!("00:01:30"), &None).unwrap()
+  );
+  assert_eq!(
+    Duration::from_millis(90_000),
+    Duration::parse_value(json!(90_000), &None).unwrap()
+  );
+  assert_eq!(
+    ByteSize::new(500_000_000),
+    ByteSize::parse_value(json!("500MB"), &None).unwrap()
+  );
+  assert!(ByteSize::parse_value(json!("500 apples"), &None).is_err());
 }
 
 #[test]
@@ -216,3 +256,34 @@ fn test_parameter_value_bool() {
     result.unwrap_err()
   );
 }
+
+#[test]
+fn test_parameter_value_array_of_objects() {
+  let expected = vec![
+    Segment {
+      start: 0,
+      end: 1000,
+      label: "intro".to_string(),
+    },
+    Segment {
+      start: 1000,
+      end: 5000,
+      label: "credits".to_string(),
+    },
+  ];
+
+  let json_value = json!([
+    {"start": 0, "end": 1000, "label": "intro"},
+    {"start": 1000, "end": 5000, "label": "credits"}
+  ]);
+  let result = Vec::<Segment>::parse_value(json_value.clone(), &None);
+  assert_eq!(expected, result.unwrap());
+
+  let json_value = Value::String(json_value.to_string());
+  let result = Vec::<Segment>::parse_value(json_value, &None);
+  assert_eq!(expected, result.unwrap());
+
+  let json_value = json!([{"start": 0, "label": "intro"}]);
+  let result = Vec::<Segment>::parse_value(json_value, &None);
+  assert!(result.is_err());
+}
@@ -0,0 +1,56 @@
+extern crate mcai_worker_sdk;
+
+use futures_util::future::{BoxFuture, LocalBoxFuture};
+use mcai_worker_sdk::runtime::{self, Executor, ThreadExecutor};
+use mcai_worker_sdk::Result;
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  mpsc, Arc,
+};
+
+/// Count the calls before delegating them to the SDK executor
+#[derive(Debug, Default)]
+struct CountingExecutor {
+  calls: Arc<AtomicUsize>,
+  executor: ThreadExecutor,
+}
+
+impl Executor for CountingExecutor {
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    self.calls.fetch_add(1, Ordering::SeqCst);
+    self.executor.spawn(future)
+  }
+
+  fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+    self.calls.fetch_add(1, Ordering::SeqCst);
+    self.executor.spawn_blocking(task)
+  }
+
+  fn block_on(&self, future: LocalBoxFuture<'_, ()>) -> Result<()> {
+    self.calls.fetch_add(1, Ordering::SeqCst);
+    self.executor.block_on(future)
+  }
+}
+
+#[test]
+pub fn test_runtime_executor() {
+  assert_eq!(42, runtime::block_on(async { 42 }).unwrap());
+
+  let calls = Arc::new(AtomicUsize::new(0));
+  runtime::set_executor(CountingExecutor {
+    calls: calls.clone(),
+    executor: ThreadExecutor::default(),
+  });
+
+  assert_eq!("done", runtime::block_on(async { "done" }).unwrap());
+
+  let (sender, receiver) = mpsc::channel();
+  let blocking_sender = sender.clone();
+  runtime::spawn_blocking(move || blocking_sender.send("blocking").unwrap());
+  runtime::spawn(async move { sender.send("future").unwrap() });
+
+  let mut results = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+  results.sort();
+  assert_eq!(vec!["blocking", "future"], results);
+  assert_eq!(3, calls.load(Ordering::SeqCst));
+}
@@ -3,6 +3,8 @@ extern crate mcai_worker_sdk;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(not(feature = "media"))]
+use mcai_worker_sdk::worker::self_test::{SelfTestCheck, SelfTestDiagnostic};
 use mcai_worker_sdk::worker::WorkerConfiguration;
 #[cfg(feature = "media")]
 use mcai_worker_sdk::MessageError;
@@ -29,6 +31,19 @@ pub fn test_worker_configuration_new() {
   #[derive(JsonSchema, Deserialize)]
   struct CustomParameters {}
 
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct CustomOutput {
+    score: f64,
+  }
+
+  #[derive(JsonSchema)]
+  #[allow(dead_code)]
+  struct CustomInstanceParameters {
+    model_path: String,
+    device_index: Option<u32>,
+  }
+
   impl MessageEvent<CustomParameters> for CustomEvent {
     fn get_name(&self) -> String {
       "worker name".to_string()
@@ -42,6 +57,18 @@ pub fn test_worker_configuration_new() {
     fn get_version(&self) -> semver::Version {
       semver::Version::new(1, 2, 3)
     }
+    fn get_output_schema(&self) -> Option<schemars::schema::RootSchema> {
+      Some(schemars::schema_for!(CustomOutput))
+    }
+    fn get_instance_parameters_schema(&self) -> Option<schemars::schema::RootSchema> {
+      Some(schemars::schema_for!(CustomInstanceParameters))
+    }
+    fn get_input_content_types(&self) -> Vec<String> {
+      vec!["video/mp4".to_string(), "audio/*".to_string()]
+    }
+    fn get_output_content_types(&self) -> Vec<String> {
+      vec!["application/json".to_string()]
+    }
   }
 
   let message_event = CustomEvent {};
@@ -53,6 +80,12 @@ pub fn test_worker_configuration_new() {
 
   assert_eq!(queue_name, worker_configuration.get_queue_name());
   assert_eq!(instance_id, worker_configuration.get_instance_id());
+  assert_eq!(instance_id, worker_configuration.get_instance_name());
+  assert_eq!(instance_id, worker_configuration.get_consumer_tag());
+  assert_eq!(
+    format!("status_{}", instance_id),
+    worker_configuration.get_direct_messaging_consumer_tag()
+  );
   assert_eq!(
     default_consumer_mode,
     worker_configuration.get_consumer_mode()
@@ -73,6 +106,200 @@ pub fn test_worker_configuration_new() {
     "1.2.3".to_string().to_string(),
     worker_configuration.get_worker_version()
   );
+  assert!(worker_configuration.get_hardware().cpu_cores > 0);
+  assert!(worker_configuration.get_hardware().total_memory > 0);
+  assert_eq!(
+    &["video/mp4".to_string(), "audio/*".to_string()],
+    worker_configuration.get_input_content_types()
+  );
+  let description = serde_json::to_value(&worker_configuration).unwrap();
+  assert_eq!(
+    serde_json::json!(["application/json"]),
+    description["output_content_types"]
+  );
+  assert_eq!(
+    serde_json::json!(["model_path"]),
+    description["instance_parameters"]["required"]
+  );
+  assert!(description["parameters"]["properties"]["model_path"].is_null());
+  assert!(worker_configuration.get_instance_parameters().is_some());
+
+  let message_schemas = worker_configuration.get_message_schemas();
+  let progression = serde_json::to_value(&message_schemas.progression).unwrap();
+  assert!(progression["properties"]["progression"].is_object());
+  assert!(progression["properties"]["docker_container_id"].is_object());
+  let completed = serde_json::to_value(&message_schemas.completed).unwrap();
+  assert!(completed["properties"]["job_id"].is_object());
+  assert!(completed["properties"]["start_instant"].is_null());
+  let output = serde_json::to_value(&message_schemas.output).unwrap();
+  assert!(output["properties"]["score"].is_object());
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+pub fn test_worker_configuration_array_of_objects() {
+  #[derive(Debug)]
+  struct CustomEvent {}
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct Segment {
+    start: u64,
+    end: u64,
+    label: String,
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct CustomParameters {
+    segments: Vec<Segment>,
+  }
+
+  impl MessageEvent<CustomParameters> for CustomEvent {
+    fn get_name(&self) -> String {
+      "worker name".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+  }
+
+  let worker_configuration =
+    WorkerConfiguration::new("queue_name", &CustomEvent {}, "instance_id").unwrap();
+
+  let description = serde_json::to_value(&worker_configuration).unwrap();
+  let segments = &description["parameters"]["properties"]["segments"];
+  assert_eq!("array", segments["type"]);
+  assert_eq!("object", segments["items"]["type"]);
+  assert!(segments["items"]["properties"]["start"].is_object());
+  assert!(segments["items"]["properties"]["label"].is_object());
+  assert!(description["parameters"]["definitions"].is_null());
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+pub fn test_worker_configuration_secret_parameters() {
+  use mcai_worker_sdk::job::Job;
+  use mcai_worker_sdk::parameter::Secret;
+
+  #[derive(Debug)]
+  struct CustomEvent {}
+
+  fn default_api_token() -> Secret<String> {
+    Secret::new("default_api_token".to_string())
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct CustomParameters {
+    source_path: String,
+    #[schemars(default = "default_api_token")]
+    api_token: Secret<String>,
+  }
+
+  impl MessageEvent<CustomParameters> for CustomEvent {
+    fn get_name(&self) -> String {
+      "worker name".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+  }
+
+  let worker_configuration =
+    WorkerConfiguration::new("queue_name", &CustomEvent {}, "instance_id").unwrap();
+
+  let description = serde_json::to_value(&worker_configuration).unwrap();
+  let api_token = &description["parameters"]["properties"]["api_token"];
+  assert_eq!(true, api_token["secret"]);
+  assert_eq!(true, api_token["writeOnly"]);
+  assert_eq!("***", api_token["default"]);
+
+  let job = Job::new(
+    r#"{"job_id": 123, "parameters": [
+      {"id": "source_path", "type": "string", "value": "/path/to/file"},
+      {"id": "api_token", "type": "string", "value": "eyJhbGciOi"}
+    ]}"#,
+  )
+  .unwrap();
+  let dump = format!("{:?}", job);
+  assert!(dump.contains("/path/to/file"));
+  assert!(!dump.contains("eyJhbGciOi"));
+  assert!(dump.contains("***"));
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+pub fn test_self_test_diagnostic() {
+  #[derive(Debug)]
+  struct CustomEvent {
+    panic: bool,
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  struct CustomParameters {}
+
+  impl MessageEvent<CustomParameters> for CustomEvent {
+    fn get_name(&self) -> String {
+      "worker name".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+    fn self_test(&self) -> Vec<SelfTestCheck> {
+      if self.panic {
+        panic!("no GPU");
+      }
+      vec![
+        SelfTestCheck::new("model", Ok(())),
+        SelfTestCheck::new("gpu", Err("no GPU".to_string())),
+      ]
+    }
+  }
+
+  let message_event = CustomEvent { panic: false };
+  let worker_configuration =
+    WorkerConfiguration::new("queue_name", &message_event, "instance_id").unwrap();
+
+  let diagnostic = SelfTestDiagnostic::run(&message_event, &worker_configuration);
+  assert!(!diagnostic.passed);
+  assert_eq!("instance_id", diagnostic.docker_container_id);
+  assert_eq!("1.2.3", diagnostic.worker_version);
+  assert_eq!(2, diagnostic.checks.len());
+  assert!(diagnostic.checks[0].passed);
+  assert_eq!(None, diagnostic.checks[0].message);
+  assert_eq!(Some("no GPU".to_string()), diagnostic.checks[1].message);
+
+  let diagnostic = SelfTestDiagnostic::run(&CustomEvent { panic: true }, &worker_configuration);
+  assert!(!diagnostic.passed);
+  assert_eq!(
+    vec![SelfTestCheck::new(
+      "self_test",
+      Err("Self-test panicked: no GPU".to_string())
+    )],
+    diagnostic.checks
+  );
+
+  let diagnostic = SelfTestDiagnostic::new(&worker_configuration, vec![], 0.0);
+  assert!(diagnostic.passed);
 }
 
 #[test]
@@ -304,3 +531,188 @@ pub fn test_media_worker_configuration_new_missing_parameters() {
   assert!(result.is_err());
   assert_eq!(expected, result.unwrap_err());
 }
+
+#[test]
+#[cfg(not(feature = "media"))]
+pub fn test_worker_description() {
+  use mcai_worker_sdk::worker::description::get_description;
+  use std::sync::{Arc, RwLock};
+
+  #[derive(Debug)]
+  struct VersionedEvent {
+    version: u64,
+  }
+
+  #[derive(JsonSchema, Deserialize)]
+  struct VersionedParameters {}
+
+  impl MessageEvent<VersionedParameters> for VersionedEvent {
+    fn get_name(&self) -> String {
+      "versioned worker".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, self.version, 0)
+    }
+  }
+
+  let message_event = Arc::new(RwLock::new(VersionedEvent { version: 1 }));
+  let worker_configuration = WorkerConfiguration::new(
+    "versioned_queue",
+    &*message_event.read().unwrap(),
+    "description_instance",
+  )
+  .unwrap();
+
+  let description = get_description(&worker_configuration, &message_event);
+  let description: serde_json::Value = serde_json::from_str(&description).unwrap();
+  assert_eq!("description_instance", description["instance_id"]);
+  assert_eq!("versioned_queue", description["queue_name"]);
+  assert_eq!("1.1.0", description["version"]);
+
+  // the description is cached
+  message_event.write().unwrap().version = 2;
+  let description = get_description(&worker_configuration, &message_event);
+  let description: serde_json::Value = serde_json::from_str(&description).unwrap();
+  assert_eq!("1.1.0", description["version"]);
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+pub fn test_worker_description_formats() {
+  use mcai_worker_sdk::worker::description::format_description;
+
+  #[derive(Debug)]
+  struct CustomEvent {}
+
+  #[derive(JsonSchema, Deserialize)]
+  #[allow(dead_code)]
+  struct CustomParameters {
+    source_path: String,
+  }
+
+  impl MessageEvent<CustomParameters> for CustomEvent {
+    fn get_name(&self) -> String {
+      "worker name".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+  }
+
+  let worker_configuration =
+    WorkerConfiguration::new("formats_queue", &CustomEvent {}, "formats_instance").unwrap();
+
+  let description = format_description(&worker_configuration, "json").unwrap();
+  assert!(description.contains("\n  \"queue_name\": \"formats_queue\""));
+
+  let description = format_description(&worker_configuration, "json-compact").unwrap();
+  assert!(!description.contains('\n'));
+  assert!(!description.contains("content_types"));
+  assert!(!description.contains("instance_parameters"));
+  assert!(description.contains("\"queue_name\":\"formats_queue\""));
+
+  let description = format_description(&worker_configuration, "yaml").unwrap();
+  assert!(description.contains("\nqueue_name: formats_queue\n"));
+
+  let description = format_description(&worker_configuration, "schema-only").unwrap();
+  let schema: serde_json::Value = serde_json::from_str(&description).unwrap();
+  assert_eq!("CustomParameters", schema["title"]);
+  assert_eq!("string", schema["properties"]["source_path"]["type"]);
+  assert!(schema.get("queue_name").is_none());
+
+  assert_eq!(
+    Err(
+      "Unknown describe format \"xml\", expected json, json-compact, yaml or schema-only"
+        .to_string()
+    ),
+    format_description(&worker_configuration, "xml")
+  );
+}
+
+#[test]
+#[cfg(not(feature = "media"))]
+pub fn test_worker_registration() {
+  use mcai_worker_sdk::worker::registration::{deregister, register};
+  use mcai_worker_sdk::SdkConfig;
+  use mockito::{mock, Matcher};
+
+  #[derive(Debug)]
+  struct CustomEvent {}
+
+  #[derive(JsonSchema, Deserialize)]
+  struct CustomParameters {}
+
+  impl MessageEvent<CustomParameters> for CustomEvent {
+    fn get_name(&self) -> String {
+      "worker name".to_string()
+    }
+    fn get_short_description(&self) -> String {
+      "short description".to_string()
+    }
+    fn get_description(&self) -> String {
+      "long description".to_string()
+    }
+    fn get_version(&self) -> semver::Version {
+      semver::Version::new(1, 2, 3)
+    }
+  }
+
+  let worker_configuration = WorkerConfiguration::new(
+    "registration_queue",
+    &CustomEvent {},
+    "registration_instance",
+  )
+  .unwrap();
+
+  assert_eq!(
+    Err("WORKER_REGISTRATION_URL is not set".to_string()),
+    register(&worker_configuration)
+  );
+
+  SdkConfig::new()
+    .with_value(
+      "WORKER_REGISTRATION_URL",
+      &format!("{}/workers", mockito::server_url()),
+    )
+    .apply();
+
+  let registration = mock("POST", "/workers")
+    .match_body(Matcher::PartialJson(serde_json::json!({
+      "instance_id": "registration_instance",
+      "queue_name": "registration_queue",
+      "version": "1.2.3"
+    })))
+    .with_status(201)
+    .create();
+  assert_eq!(Ok(()), register(&worker_configuration));
+  registration.assert();
+  drop(registration);
+
+  let deregistration = mock("DELETE", "/workers/registration_instance")
+    .match_query(Matcher::UrlEncoded(
+      "queue_name".to_string(),
+      "registration_queue".to_string(),
+    ))
+    .with_status(204)
+    .create();
+  assert_eq!(Ok(()), deregister(&worker_configuration));
+  deregistration.assert();
+
+  let _unavailable = mock("POST", "/workers").with_status(503).create();
+  assert_eq!(
+    Err("status 503 Service Unavailable".to_string()),
+    register(&worker_configuration)
+  );
+}
@@ -0,0 +1,72 @@
+extern crate mcai_worker_sdk;
+#[macro_use]
+extern crate serde_derive;
+
+use mcai_worker_sdk::{MessageError, MessageEvent, WorkerRegistry};
+use schemars::JsonSchema;
+
+#[derive(Debug)]
+struct CustomEvent {
+  name: String,
+}
+
+#[derive(JsonSchema, Deserialize)]
+struct CustomParameters {
+  #[cfg(feature = "media")]
+  source_path: String,
+  #[cfg(feature = "media")]
+  destination_path: String,
+}
+
+impl MessageEvent<CustomParameters> for CustomEvent {
+  fn get_name(&self) -> String {
+    self.name.clone()
+  }
+  fn get_short_description(&self) -> String {
+    "short description".to_string()
+  }
+  fn get_description(&self) -> String {
+    "long description".to_string()
+  }
+  fn get_version(&self) -> semver::Version {
+    semver::Version::new(1, 2, 3)
+  }
+}
+
+#[test]
+pub fn test_worker_registry() {
+  let registry = WorkerRegistry::new()
+    .register(
+      CustomEvent {
+        name: "thumbnail".to_string(),
+      },
+      "job_thumbnail",
+    )
+    .unwrap()
+    .register(
+      CustomEvent {
+        name: "checksum".to_string(),
+      },
+      "job_checksum",
+    )
+    .unwrap();
+
+  let worker_configurations = registry.get_worker_configurations();
+  assert_eq!(2, worker_configurations.len());
+  assert_eq!("thumbnail", worker_configurations[0].get_worker_name());
+  assert_eq!("job_thumbnail", worker_configurations[0].get_queue_name());
+  assert_eq!("checksum", worker_configurations[1].get_worker_name());
+  assert_eq!("job_checksum", worker_configurations[1].get_queue_name());
+  assert_ne!(
+    worker_configurations[0].get_direct_messaging_queue_name(),
+    worker_configurations[1].get_direct_messaging_queue_name()
+  );
+
+  let result = registry.register(
+    CustomEvent {
+      name: "other".to_string(),
+    },
+    "job_checksum",
+  );
+  assert!(matches!(result, Err(MessageError::RuntimeError(_))));
+}